@@ -8,164 +8,511 @@
 //
 //==============================================================================
 use crate::errors::*;
+use crate::labview::{
+	LStrHandle, LvArray, LvArrayHandle, string_to_lstr, string_to_new_lstr, write_lv_array,
+};
+use crate::reference_types::REF_HIERARCHICAL;
+use crate::utils::{lv_node_id, with_timeout};
 use opcua::{
 	client::Session,
 	types::{
-		BrowseDescription, BrowseDirection, BrowseResultMask, NodeClassMask, NodeId,
-		ReferenceTypeId,
+		AttributeId, BrowseDescription, BrowseDirection, BrowseResultMask, NodeClass,
+		NodeClassMask, NodeId, ObjectId, ReadValueId, ReferenceDescription, ReferenceTypeId,
+		StatusCode, TimestampsToReturn, Variant,
 	},
 };
-use std::{
-	sync::Arc,
-	{ffi::CString, os::raw::c_int},
-};
+use std::{collections::HashSet, os::raw::c_int, sync::Arc};
 use tokio::runtime::Runtime;
 
 #[cfg(target_arch = "x86_64")]
 #[repr(C)]
-pub struct Node {
-	dim_size: c_int,
-	node_attribute: [NodeAttribute; 1000], // Placeholder, adjust size as needed
+pub struct NodeAttribute {
+	class: c_int,
+	display_name: LStrHandle,
+	node_uid: LStrHandle,
 }
-// Type alias for double pointer
 
-#[cfg(target_arch = "x86_64")]
-#[repr(C)]
-struct NodeAttribute {
+#[cfg(target_arch = "x86")]
+#[repr(C, packed(1))]
+pub struct NodeAttribute {
 	class: c_int,
 	display_name: LStrHandle,
 	node_uid: LStrHandle,
 }
 
+type NodeHdl = LvArrayHandle<NodeAttribute>;
+
+// Element of lvBrowserRefs, NodeAttribute with the reference
 #[cfg(target_arch = "x86_64")]
 #[repr(C)]
-struct LStr {
-	cnt: i32,
-	str: [u8; 0],
+pub struct NodeReference {
+	class: c_int,
+	display_name: LStrHandle,
+	node_uid: LStrHandle,
+	ref_type: LStrHandle,
+	is_forward: u8,
 }
 
 #[cfg(target_arch = "x86")]
 #[repr(C, packed(1))]
-pub struct Node {
-	dim_size: c_int,
-	node_attribute: [NodeAttribute; 1000], // Placeholder, adjust size as needed
-}
-// Type alias for double pointer
-#[cfg(target_arch = "x86")]
-#[repr(C, packed(1))]
-struct NodeAttribute {
+pub struct NodeReference {
 	class: c_int,
 	display_name: LStrHandle,
 	node_uid: LStrHandle,
+	ref_type: LStrHandle,
+	is_forward: u8,
 }
+
+type NodeRefHdl = LvArrayHandle<NodeReference>;
+
+// Layout of the LabVIEW array of clusters, checked when building for either target
 #[cfg(target_arch = "x86")]
-#[repr(C, packed(1))]
-struct LStr {
-	cnt: i32,
-	str: [u8; 0],
+const _: () = assert!(
+	std::mem::offset_of!(LvArray<NodeAttribute>, elt) == 4
+		&& std::mem::size_of::<NodeAttribute>() == 12
+);
+#[cfg(target_arch = "x86_64")]
+const _: () = assert!(
+	std::mem::offset_of!(LvArray<NodeAttribute>, elt) == 8
+		&& std::mem::size_of::<NodeAttribute>() == 24
+);
+#[cfg(target_arch = "x86")]
+const _: () = assert!(
+	std::mem::offset_of!(LvArray<NodeReference>, elt) == 4
+		&& std::mem::size_of::<NodeReference>() == 17
+);
+#[cfg(target_arch = "x86_64")]
+const _: () = assert!(
+	std::mem::offset_of!(LvArray<NodeReference>, elt) == 8
+		&& std::mem::size_of::<NodeReference>() == 40
+);
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lvBrowser(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	id_u32: u32,
+	id_str: *const i8,
+	ns: u16,
+	id_type: u32,
+	nodes: NodeHdl,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_NULL_POINTER);
+		check_null!(session_in, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node = match lv_node_id(id_u32, id_str, ns, id_type) {
+				Ok(node) => node,
+				Err(err) => return err,
+			};
+			//
+			//let node = NodeId::new(0, id_u32).into(); //so works so far
+			browse_to_lv(rt, session, hierarchical_desc(node), 0, nodes)
+		}
+	})
 }
 
-type NodeHdl = *mut *mut Node;
-type LStrHandle = *mut *mut LStr;
+//==============================================================================
+// Same as lvBrowser, but gives up after timeout_ms (0 - session default)
+// with ERR_TIMEOUT
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lvBrowserEx(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	id_u32: u32,
+	id_str: *const i8,
+	ns: u16,
+	id_type: u32,
+	timeout_ms: u32,
+	nodes: NodeHdl,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_NULL_POINTER);
+		check_null!(session_in, ERR_NULL_POINTER);
 
-unsafe extern "C" {
-	//#[link_name = "DSSetHandleSize"]
-	fn DSSetHandleSize(nodes: NodeHdl, size: usize);
-	fn DSNewHandle(size: usize) -> LStrHandle;
-	#[link_name = "MoveBlock"]
-	fn MoveBlockChar(src: *const i8, destination: *mut u8, size: usize);
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node = match lv_node_id(id_u32, id_str, ns, id_type) {
+				Ok(node) => node,
+				Err(err) => return err,
+			};
+			browse_to_lv(rt, session, hierarchical_desc(node), timeout_ms, nodes)
+		}
+	})
 }
 
+//==============================================================================
+// Same as lvBrowser, but follows only ref_type_id references (see
+// reference_types.rs, 0 - hierarchical), include_subtypes != 0 also
+// follows its subtypes. is_forward = 0 browses the inverse references.
+// timeout_ms 0 - session default, ERR_TIMEOUT if it passed
+//
 #[unsafe(no_mangle)]
-pub extern "C" fn lvBrowser(
+pub extern "C" fn lvBrowserFiltered(
 	rt_ptr: *mut Runtime,
 	session_in: *mut Arc<Session>,
 	id_u32: u32,
 	id_str: *const i8,
 	ns: u16,
 	id_type: u32,
+	ref_type_id: u32,
+	include_subtypes: u8,
+	is_forward: u8,
+	timeout_ms: u32,
 	nodes: NodeHdl,
 ) -> i32 {
-	check_null!(rt_ptr, ERR_NULL_POINTER);
-	check_null!(session_in, ERR_NULL_POINTER);
-
-	unsafe {
-		let rt = &mut *rt_ptr;
-		let session = &mut *session_in;
-		let node: NodeId;
-		match id_type {
-			1 => node = NodeId::new(0, id_u32).into(), //so works so far
-			2 => node = NodeId::new(ns, cstr_to_string!(id_str)).into(),
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_NULL_POINTER);
+		check_null!(session_in, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node = match lv_node_id(id_u32, id_str, ns, id_type) {
+				Ok(node) => node,
+				Err(err) => return err,
+			};
+			let ref_type_id = match ref_type_id {
+				0 => REF_HIERARCHICAL,
+				id => id,
+			};
+			let desc = BrowseDescription {
+				browse_direction: match is_forward {
+					0 => BrowseDirection::Inverse,
+					_ => BrowseDirection::Forward,
+				},
+				reference_type_id: NodeId::new(0, ref_type_id),
+				include_subtypes: include_subtypes != 0,
+				..hierarchical_desc(node)
+			};
+			browse_to_lv(rt, session, desc, timeout_ms, nodes)
+		}
+	})
+}
+
+//==============================================================================
+// Same as lvBrowser with the references chosen by ref_type: 0 -
+// HierarchicalReferences, 1 - Organizes, 2 - HasComponent, 3 - HasProperty,
+// 4 - HasTypeDefinition, 5 - the node id ref_type_str ("i=45",
+// "ns=2;i=5001"). include_subtypes != 0 also follows its subtypes,
+// direction 0 - forward, 1 - inverse (e.g. the parents), 2 - both.
+// node_class_mask of NodeClass bits (1 - Object, 2 - Variable, 4 - Method...),
+// 0 - all. ref_type 0, include_subtypes 1, direction 0 and node_class_mask 0
+// browse as lvBrowser. Each element has the reference type as node id
+// ("i=47" - HasComponent) and is_forward of the reference.
+// timeout_ms 0 - session default, ERR_TIMEOUT if it passed
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lvBrowserRefs(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	id_u32: u32,
+	id_str: *const i8,
+	ns: u16,
+	id_type: u32,
+	ref_type: u32,
+	ref_type_str: *const i8,
+	include_subtypes: u8,
+	direction: u32,
+	node_class_mask: u32,
+	timeout_ms: u32,
+	refs: NodeRefHdl,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(refs, ERR_NULL_POINTER);
+
+		let node = match lv_node_id(id_u32, id_str, ns, id_type) {
+			Ok(node) => node,
+			Err(err) => return err,
+		};
+		let reference_type_id = match ref_type {
+			0 => ReferenceTypeId::HierarchicalReferences.into(),
+			1 => ReferenceTypeId::Organizes.into(),
+			2 => ReferenceTypeId::HasComponent.into(),
+			3 => ReferenceTypeId::HasProperty.into(),
+			4 => ReferenceTypeId::HasTypeDefinition.into(),
+			5 => match lv_node_id(0, ref_type_str, 0, 5) {
+				Ok(id) => id,
+				Err(err) => return err,
+			},
 			_ => return ERR_INVALID_TYPE,
+		};
+		let browse_direction = match direction {
+			0 => BrowseDirection::Forward,
+			1 => BrowseDirection::Inverse,
+			2 => BrowseDirection::Both,
+			_ => return ERR_INVALID_ARGUMENT,
+		};
+		let desc = BrowseDescription {
+			browse_direction,
+			reference_type_id,
+			include_subtypes: include_subtypes != 0,
+			node_class_mask,
+			..hierarchical_desc(node)
+		};
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let r = rt.block_on(with_timeout(timeout_ms, browse_all(session, &[desc])));
+			let found = match r {
+				Ok(Ok(found)) => found,
+				Ok(Err(status)) => {
+					set_last_error_detail(format!("Browse failed: {status}"));
+					return ERR_BROWSE_ERROR;
+				}
+				Err(err) => return err,
+			};
+
+			let n = found.len() as i32;
+			let elements = found.iter().map(|r| NodeReference {
+				class: r.node_class as u32 as c_int,
+				display_name: string_to_new_lstr(&r.browse_name.to_string()),
+				node_uid: string_to_new_lstr(&r.node_id.node_id.identifier.to_string()),
+				ref_type: string_to_new_lstr(&r.reference_type_id.to_string()),
+				is_forward: r.is_forward as u8,
+			});
+			let err = write_lv_array(refs, elements);
+			if err != 0 {
+				set_last_error_detail(format!("LabVIEW memory error {err}"));
+				return ERR_BROWSE_ERROR;
+			}
+			n
 		}
-		//
-		//let node = NodeId::new(0, id_u32).into(); //so works so far
-		let r = rt.block_on(async { session.browse(&[hierarchical_desc(node)], 1000, None).await });
-		match r {
-			Ok(result) => {
-				let it = &result[0];
-				let refs = it.references.clone().unwrap_or_default();
-				let n = refs.len() as i32;
-
-				unsafe {
-					//let n = count;
-					// Assuming sizeof(Node) is equivalent to the size of the struct in Rust
-					let ret_size = std::mem::size_of::<NodeAttribute>() * n as usize
-						+ std::mem::size_of::<NodeHdl>();
-					DSSetHandleSize(nodes, ret_size);
-
-					(**nodes).dim_size = n;
-
-					for i in 0..n as usize {
-						let name = refs[i].browse_name.to_string();
-
-						let name_cnt = name.len();
-						let node_id_s = refs[i].node_id.node_id.identifier.to_string();
-
-						//(**nodes).node_attribute[i].id = i as c_int;
-						(**nodes).node_attribute[i].class = refs[i].node_class as u32 as c_int;
-
-						(**nodes).node_attribute[i].display_name =
-							DSNewHandle(name.len() + std::mem::size_of::<c_int>());
-						(**nodes).node_attribute[i].node_uid =
-							DSNewHandle(node_id_s.len() + std::mem::size_of::<c_int>());
-
-						(**((**nodes).node_attribute[i].display_name)).cnt = name.len() as i32;
-						(**((**nodes).node_attribute[i].node_uid)).cnt = node_id_s.len() as i32;
-
-						let c_headers = match CString::new(name) {
-							Ok(cs) => cs,
-							Err(_) => return -1, // failed to convert to C string
-						};
-						MoveBlockChar(
-							c_headers.as_ptr(), //seems to be OK, but 4 bytes shift
-							(**((**nodes).node_attribute[i].display_name))
-								.str
-								.as_mut_ptr(),
-							name_cnt,
-						);
-
-						let c_headers = match CString::new(node_id_s.to_string()) {
-							Ok(cs) => cs,
-							Err(_) => return -1, // failed to convert to C string
-						};
-						MoveBlockChar(
-							c_headers.as_ptr(), //seems to be OK, but 4 bytes shift
-							(**((**nodes).node_attribute[i].node_uid)).str.as_mut_ptr(),
-							node_id_s.len(),
-						);
-					}
+	})
+}
+
+// Browse one node, the references are written to nodes,
+// returns their count, ERR_BROWSE_ERROR or ERR_TIMEOUT
+fn browse_to_lv(
+	rt: &Runtime,
+	session: &Session,
+	desc: BrowseDescription,
+	timeout_ms: u32,
+	nodes: NodeHdl,
+) -> i32 {
+	let r = rt.block_on(with_timeout(
+		timeout_ms,
+		session.browse(&[desc], 1000, None),
+	));
+	match r {
+		Ok(Ok(result)) => {
+			let refs = result[0].references.clone().unwrap_or_default();
+			refs_to_lv(&refs, nodes)
+		}
+		Ok(Err(_)) => ERR_BROWSE_ERROR,
+		Err(err) => err,
+	}
+}
+
+// Write the references to nodes (resized), returns their count
+fn refs_to_lv(refs: &[ReferenceDescription], nodes: NodeHdl) -> i32 {
+	let n = refs.len() as i32;
+
+	// In the codepage of LabVIEW (lv_set_string_encoding)
+	let attributes = refs.iter().map(|r| unsafe {
+		NodeAttribute {
+			class: r.node_class as u32 as c_int,
+			display_name: string_to_new_lstr(&r.browse_name.to_string()),
+			// "i=", "s=", "g=" or "b=" (base64), lvBrowser id_type 5 takes it back
+			node_uid: string_to_new_lstr(&r.node_id.node_id.identifier.to_string()),
+		}
+	});
+	let err = unsafe { write_lv_array(nodes, attributes) };
+	if err != 0 {
+		set_last_error_detail(format!("LabVIEW memory error {err}"));
+		return ERR_BROWSE_ERROR;
+	}
+	n
+}
+
+//==============================================================================
+// All DataType nodes of the server, found under the DataTypes folder
+// (i=90) and the subtypes of the types. include_built_in = 0 skips the
+// types of namespace 0, i.e. returns only the custom types of the server.
+// Returns their count or ERR_BROWSE_ERROR
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_browse_data_types(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	include_built_in: u8,
+	nodes: NodeHdl,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(nodes, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			match rt.block_on(data_type_refs(session, include_built_in != 0)) {
+				Ok(refs) => refs_to_lv(&refs, nodes),
+				Err(status) => {
+					set_last_error_detail(format!("Browse of the data types failed: {status}"));
+					ERR_BROWSE_ERROR
 				}
-				return n as i32;
 			}
+		}
+	})
+}
 
-			Err(_) => {
-				return ERR_BROWSE_ERROR;
+// Nodes browsed per request, below the usual MaxNodesPerBrowse of servers
+const BROWSE_CHUNK_SIZE: usize = 100;
+
+// Breadth-first walk of the hierarchical references from the DataTypes
+// folder, through folders and data types
+async fn data_type_refs(
+	session: &Session,
+	include_built_in: bool,
+) -> Result<Vec<ReferenceDescription>, StatusCode> {
+	let mut visited = HashSet::new();
+	let mut data_types = Vec::new();
+	let mut level = vec![NodeId::from(ObjectId::DataTypesFolder)];
+	while !level.is_empty() {
+		let mut next_level = Vec::new();
+		for chunk in level.chunks(BROWSE_CHUNK_SIZE) {
+			let descs: Vec<BrowseDescription> = chunk
+				.iter()
+				.map(|node_id| BrowseDescription {
+					node_class_mask: (NodeClassMask::OBJECT | NodeClassMask::DATA_TYPE).bits(),
+					..hierarchical_desc(node_id.clone())
+				})
+				.collect();
+			for r in browse_all(session, &descs).await? {
+				let node_id = r.node_id.node_id.clone();
+				if !visited.insert(node_id.clone()) {
+					continue;
+				}
+				if r.node_class == NodeClass::DataType
+					&& (include_built_in || node_id.namespace != 0)
+				{
+					data_types.push(r);
+				}
+				next_level.push(node_id);
+			}
+		}
+		level = next_level;
+	}
+	Ok(data_types)
+}
+
+// References of the nodes, following the continuation points
+async fn browse_all(
+	session: &Session,
+	descs: &[BrowseDescription],
+) -> Result<Vec<ReferenceDescription>, StatusCode> {
+	let mut refs = Vec::new();
+	let mut results = session.browse(descs, 0, None).await?;
+	loop {
+		let mut continuation_points = Vec::new();
+		for result in results {
+			refs.extend(result.references.unwrap_or_default());
+			if !result.continuation_point.is_null() {
+				continuation_points.push(result.continuation_point);
 			}
 		}
+		if continuation_points.is_empty() {
+			return Ok(refs);
+		}
+		results = session.browse_next(false, &continuation_points).await?;
 	}
 }
 
+//==============================================================================
+// NodeClass of the node (1 - Object, 2 - Variable, 4 - Method, 8 - ObjectType,
+// 16 - VariableType, 32 - ReferenceType, 64 - DataType, 128 - View) and the
+// target of its HasTypeDefinition reference (ns 0 and empty string if it has
+// none, e.g. methods and types). Any of the outputs may be null
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_node_class_and_type_def(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const i8,
+	node_class_out: *mut u32,
+	type_def_ns_out: *mut u16,
+	type_def_str_out: crate::labview::LStrHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+			let (node_class, type_def) =
+				match rt.block_on(node_class_and_type_def(session, node_id)) {
+					Ok(result) => result,
+					Err(status) => return status.bits() as i32,
+				};
+
+			if !node_class_out.is_null() {
+				*node_class_out = node_class;
+			}
+			let (type_def_ns, type_def_str) = match type_def {
+				Some(type_def) => (type_def.namespace, type_def.identifier.to_string()),
+				None => (0, String::new()),
+			};
+			if !type_def_ns_out.is_null() {
+				*type_def_ns_out = type_def_ns;
+			}
+			if !type_def_str_out.is_null() {
+				string_to_lstr(&type_def_str, type_def_str_out);
+			}
+		}
+		NO_ERR
+	})
+}
+
+// Read NodeClass, then browse the HasTypeDefinition reference
+async fn node_class_and_type_def(
+	session: &Session,
+	node_id: NodeId,
+) -> Result<(u32, Option<NodeId>), StatusCode> {
+	let read = session
+		.read(
+			&[ReadValueId::new(node_id.clone(), AttributeId::NodeClass)],
+			TimestampsToReturn::Neither,
+			0.0,
+		)
+		.await?;
+	let node_class = match read.first() {
+		Some(dv) if dv.status().is_bad() => return Err(dv.status()),
+		Some(dv) => match &dv.value {
+			Some(Variant::Int32(node_class)) => *node_class as u32,
+			_ => return Err(StatusCode::BadUnexpectedError),
+		},
+		None => return Err(StatusCode::BadUnexpectedError),
+	};
+
+	let desc = BrowseDescription {
+		reference_type_id: ReferenceTypeId::HasTypeDefinition.into(),
+		include_subtypes: false,
+		node_class_mask: (NodeClassMask::OBJECT_TYPE | NodeClassMask::VARIABLE_TYPE).bits(),
+		..hierarchical_desc(node_id)
+	};
+	let browse = session.browse(&[desc], 1, None).await?;
+	let type_def = browse
+		.into_iter()
+		.next()
+		.and_then(|result| result.references)
+		.and_then(|refs| refs.into_iter().next())
+		.map(|r| r.node_id.node_id);
+	Ok((node_class, type_def))
+}
+
 fn hierarchical_desc(node_id: NodeId) -> BrowseDescription {
 	BrowseDescription {
 		node_id,
@@ -176,3 +523,122 @@ fn hierarchical_desc(node_id: NodeId) -> BrowseDescription {
 		result_mask: BrowseResultMask::All as u32,
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::labview::{
+		DSDisposeHandle, DSDisposeHandleLStr, lstr_to_string, lv_array_as_slice, new_lv_array,
+	};
+	use opcua::types::{ExpandedNodeId, QualifiedName};
+
+	// Offsets LabVIEW has in the array of clusters: dimension size, then
+	// the clusters, packed on x86 and aligned to the 8-byte handles on x64
+	#[cfg(target_arch = "x86")]
+	mod layout {
+		pub const HEADER: usize = 4;
+		pub const ATTRIBUTE_SIZE: usize = 12;
+		pub const REFERENCE_SIZE: usize = 17;
+		pub const HANDLES: [usize; 3] = [4, 8, 12]; // display_name, node_uid, ref_type
+		pub const IS_FORWARD: usize = 16;
+	}
+	#[cfg(target_arch = "x86_64")]
+	mod layout {
+		pub const HEADER: usize = 8;
+		pub const ATTRIBUTE_SIZE: usize = 24;
+		pub const REFERENCE_SIZE: usize = 40;
+		pub const HANDLES: [usize; 3] = [8, 16, 24];
+		pub const IS_FORWARD: usize = 32;
+	}
+
+	// Reads the array as LabVIEW does, by the offsets of the layout
+	unsafe fn read_at<T: Copy>(handle: *mut *mut u8, offset: usize) -> T {
+		unsafe { ((*handle).add(offset) as *const T).read_unaligned() }
+	}
+
+	fn reference(ns: u16, name: &str, class: NodeClass) -> ReferenceDescription {
+		ReferenceDescription {
+			reference_type_id: ReferenceTypeId::HasComponent.into(),
+			is_forward: true,
+			node_id: ExpandedNodeId::from(NodeId::new(ns, name.to_string())),
+			browse_name: QualifiedName::new(ns, name),
+			node_class: class,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn browse_result_in_the_labview_layout() {
+		let refs = [
+			reference(2, "Tank1", NodeClass::Object),
+			reference(2, "Level", NodeClass::Variable),
+			reference(3, "Füllstand", NodeClass::Variable),
+		];
+		unsafe {
+			// Empty array of the LabVIEW diagram
+			let nodes: NodeHdl = new_lv_array(std::iter::empty());
+			assert!(!nodes.is_null());
+			for refs in [&refs[..], &refs[..1]] {
+				assert_eq!(refs_to_lv(refs, nodes), refs.len() as i32);
+				let bytes = nodes as *mut *mut u8;
+				assert_eq!(read_at::<i32>(bytes, 0), refs.len() as i32);
+				for (i, r) in refs.iter().enumerate() {
+					let elt = layout::HEADER + i * layout::ATTRIBUTE_SIZE;
+					assert_eq!(read_at::<c_int>(bytes, elt), r.node_class as c_int);
+					let display_name = read_at::<LStrHandle>(bytes, elt + layout::HANDLES[0]);
+					let node_uid = read_at::<LStrHandle>(bytes, elt + layout::HANDLES[1]);
+					assert_eq!(lstr_to_string(display_name), r.browse_name.to_string());
+					assert_eq!(
+						lstr_to_string(node_uid),
+						format!("s={}", r.browse_name.name)
+					);
+				}
+				// Each call writes new strings
+				for node in lv_array_as_slice(nodes) {
+					DSDisposeHandleLStr(node.display_name);
+					DSDisposeHandleLStr(node.node_uid);
+				}
+			}
+			DSDisposeHandle(nodes);
+		}
+	}
+
+	#[test]
+	fn references_in_the_labview_layout() {
+		let mut inverse = reference(0, "Objects", NodeClass::Object);
+		inverse.is_forward = false;
+		unsafe {
+			let refs: NodeRefHdl = new_lv_array(
+				[reference(2, "Tank1", NodeClass::Object), inverse]
+					.iter()
+					.map(|r| NodeReference {
+						class: r.node_class as u32 as c_int,
+						display_name: string_to_new_lstr(&r.browse_name.to_string()),
+						node_uid: string_to_new_lstr(&r.node_id.node_id.identifier.to_string()),
+						ref_type: string_to_new_lstr(&r.reference_type_id.to_string()),
+						is_forward: r.is_forward as u8,
+					}),
+			);
+			assert!(!refs.is_null());
+			let bytes = refs as *mut *mut u8;
+			assert_eq!(read_at::<i32>(bytes, 0), 2);
+			for (i, (name, is_forward)) in
+				[("2:Tank1", 1u8), ("Objects", 0)].into_iter().enumerate()
+			{
+				let elt = layout::HEADER + i * layout::REFERENCE_SIZE;
+				assert_eq!(read_at::<c_int>(bytes, elt), NodeClass::Object as c_int);
+				let [display_name, _, ref_type] =
+					layout::HANDLES.map(|offset| read_at::<LStrHandle>(bytes, elt + offset));
+				assert_eq!(lstr_to_string(display_name), name);
+				assert_eq!(lstr_to_string(ref_type), "i=47");
+				assert_eq!(read_at::<u8>(bytes, elt + layout::IS_FORWARD), is_forward);
+			}
+			for r in lv_array_as_slice(refs) {
+				for s in [r.display_name, r.node_uid, r.ref_type] {
+					DSDisposeHandleLStr(s);
+				}
+			}
+			DSDisposeHandle(refs);
+		}
+	}
+}
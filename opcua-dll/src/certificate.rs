@@ -0,0 +1,450 @@
+//==============================================================================
+//
+// Title:		Own application instance certificate
+// Purpose:		Check the expiry of the self-signed certificate (365 days by
+//				create_sample_keypair) and renew it for the same application
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+use crate::errors::*;
+
+use libc::c_char;
+use opcua::{
+	client::{Client, ClientBuilder, Session},
+	crypto::{CertificateStore, SignatureAlgorithm, X509, X509Data},
+};
+use std::{
+	path::{Path, PathBuf},
+	sync::{Arc, LazyLock, Mutex, Weak},
+};
+
+// Used when the renewal is called with key_size 0 or duration_days 0
+const DEFAULT_KEY_SIZE: u32 = 2048;
+const DEFAULT_DURATION_DAYS: u32 = 365;
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+// Sessions created by the lv_connect_* functions, to find the ones which
+// use the certificate being renewed
+static SESSIONS: LazyLock<Mutex<Vec<Weak<Session>>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+pub fn register_session(session: &Arc<Session>) {
+	let mut sessions = SESSIONS.lock().unwrap();
+	sessions.retain(|s| s.strong_count() > 0);
+	sessions.push(Arc::downgrade(session));
+}
+
+// Same file, also if one path is relative
+fn same_path(a: &Path, b: &Path) -> bool {
+	match (a.canonicalize(), b.canonicalize()) {
+		(Ok(a), Ok(b)) => a == b,
+		_ => a == b,
+	}
+}
+
+// Number of connected sessions using the certificate of the store
+fn sessions_using(store: &CertificateStore) -> usize {
+	let cert_path = store.own_certificate_path();
+	SESSIONS
+		.lock()
+		.unwrap()
+		.iter()
+		.filter_map(Weak::upgrade)
+		.filter(|s| s.is_connected())
+		.filter(|s| {
+			same_path(
+				&s.certificate_store().read().own_certificate_path(),
+				&cert_path,
+			)
+		})
+		.count()
+}
+
+// Number of connected sessions created by the client (they share its
+// certificate store)
+pub fn sessions_of_client(client: &Client) -> usize {
+	SESSIONS
+		.lock()
+		.unwrap()
+		.iter()
+		.filter_map(Weak::upgrade)
+		.filter(|s| s.is_connected())
+		.filter(|s| Arc::ptr_eq(s.certificate_store(), client.certificate_store()))
+		.count()
+}
+
+fn backup_stamp() -> String {
+	chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string()
+}
+
+// Copy the file to <file>.<UTC time>.bak, Err is the return code
+fn backup_file(path: &Path, stamp: &str) -> Result<PathBuf, i32> {
+	let mut backup = path.as_os_str().to_owned();
+	backup.push(format!(".{stamp}.bak"));
+	let backup = PathBuf::from(backup);
+	match std::fs::copy(path, &backup) {
+		Ok(_) => Ok(backup),
+		Err(e) => {
+			set_last_error_detail(format!("Backup of {}: {e}", path.display()));
+			Err(ERR_IO)
+		}
+	}
+}
+
+// Own certificate of the pki_dir, Err is the return code
+fn read_own_cert(store: &CertificateStore) -> Result<X509, i32> {
+	store.read_own_cert().map_err(|e| {
+		set_last_error_detail(e);
+		ERR_IO
+	})
+}
+
+// Subject of a new certificate (or request) for the application of the
+// old certificate: same alternate names, empty common_name keeps the old
+// one, the other empty fields are left out
+fn x509_data_from(
+	old_cert: &X509,
+	common_name_str: *const c_char,
+	organization_str: *const c_char,
+	organizational_unit_str: *const c_char,
+	country_str: *const c_char,
+	state_str: *const c_char,
+) -> Result<X509Data, i32> {
+	let Some(alt_host_names) = old_cert.alternate_names() else {
+		set_last_error_detail("The old certificate has no alternate names (application URI)");
+		return Err(ERR_INVALID_ARGUMENT);
+	};
+	let common_name = match cstr_to_string!(common_name_str) {
+		// "CN=<name>"
+		name if name.is_empty() => old_cert
+			.common_name()
+			.map(|cn| cn.trim_start_matches("CN=").to_string())
+			.unwrap_or_default(),
+		name => name,
+	};
+	Ok(X509Data {
+		key_size: DEFAULT_KEY_SIZE,
+		common_name,
+		organization: cstr_to_string!(organization_str),
+		organizational_unit: cstr_to_string!(organizational_unit_str),
+		country: cstr_to_string!(country_str),
+		state: cstr_to_string!(state_str),
+		alt_host_names,
+		certificate_duration_days: DEFAULT_DURATION_DAYS,
+		serial_number: None,
+		signature_algorithm: SignatureAlgorithm::default(),
+	})
+}
+
+// ERR_CERT_IN_USE if connected sessions use the certificate of the store
+fn check_not_in_use(store: &CertificateStore) -> Result<(), i32> {
+	match sessions_using(store) {
+		0 => Ok(()),
+		in_use => {
+			set_last_error_detail(format!(
+				"The certificate is used by {in_use} connected session(s)"
+			));
+			Err(ERR_CERT_IN_USE)
+		}
+	}
+}
+
+//==============================================================================
+// Days until the own certificate expires (not_after), negative if expired.
+// The certificate is taken from the client if client_ptr isn't null
+// (see lvClientBuilder), otherwise from the pki_dir (e.g. "./pki").
+// ERR_IO if the certificate can't be read
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_check_certificate_expiry(
+	pki_dir_str: *const c_char,
+	client_ptr: *mut Client,
+	days_out: *mut f64,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(days_out, ERR_NULL_POINTER);
+
+		let cert = if client_ptr.is_null() {
+			check_null!(pki_dir_str, ERR_NULL_POINTER);
+			CertificateStore::new(Path::new(&cstr_to_string!(pki_dir_str))).read_own_cert()
+		} else {
+			let client = unsafe { &*client_ptr };
+			client.certificate_store().read().read_own_cert()
+		};
+		let cert = match cert {
+			Ok(cert) => cert,
+			Err(e) => {
+				set_last_error_detail(e);
+				return ERR_IO;
+			}
+		};
+		let not_after = match cert.not_after() {
+			Ok(not_after) => not_after,
+			Err(_) => {
+				set_last_error_detail("The certificate has no valid not_after");
+				return ERR_IO;
+			}
+		};
+		let remaining = not_after - chrono::Utc::now();
+		unsafe { *days_out = remaining.num_seconds() as f64 / SECONDS_PER_DAY };
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Create a new keypair in pki_dir for the same application: the alternate
+// names (application URI, host names) are taken from the old certificate.
+// Empty common_name keeps the old one, the other empty subject fields are
+// left out, key_size 0 - 2048 (else 2048, 3072 or 4096, ERR_INVALID_ARGUMENT
+// for other sizes), duration_days 0 - 365.
+// The old cert and key are kept as <file>.<UTC time>.bak.
+// ERR_CERT_IN_USE if a connected session uses the certificate, unless
+// force != 0 (the session uses the new one after the next reconnect,
+// the server must trust it)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_renew_certificate(
+	pki_dir_str: *const c_char,
+	common_name_str: *const c_char,
+	organization_str: *const c_char,
+	organizational_unit_str: *const c_char,
+	country_str: *const c_char,
+	state_str: *const c_char,
+	key_size: u32,
+	duration_days: u32,
+	force: u8,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(pki_dir_str, ERR_NULL_POINTER);
+		check_null!(common_name_str, ERR_NULL_POINTER);
+		check_null!(organization_str, ERR_NULL_POINTER);
+		check_null!(organizational_unit_str, ERR_NULL_POINTER);
+		check_null!(country_str, ERR_NULL_POINTER);
+		check_null!(state_str, ERR_NULL_POINTER);
+
+		let store = CertificateStore::new(Path::new(&cstr_to_string!(pki_dir_str)));
+		let old_cert = match read_own_cert(&store) {
+			Ok(cert) => cert,
+			Err(err) => return err,
+		};
+		if force == 0 {
+			if let Err(err) = check_not_in_use(&store) {
+				return err;
+			}
+		}
+
+		let mut x509_data = match x509_data_from(
+			&old_cert,
+			common_name_str,
+			organization_str,
+			organizational_unit_str,
+			country_str,
+			state_str,
+		) {
+			Ok(x509_data) => x509_data,
+			Err(err) => return err,
+		};
+		if key_size != 0 {
+			x509_data.key_size = key_size;
+		}
+		if let Err(e) = x509_data.check_key_size() {
+			set_last_error_detail(e);
+			return ERR_INVALID_ARGUMENT;
+		}
+		if duration_days != 0 {
+			x509_data.certificate_duration_days = duration_days;
+		}
+
+		let cert_path = store.own_certificate_path();
+		let pkey_path = store.own_private_key_path();
+		let stamp = backup_stamp();
+		let cert_backup = match backup_file(&cert_path, &stamp) {
+			Ok(backup) => backup,
+			Err(err) => return err,
+		};
+		let pkey_backup = match backup_file(&pkey_path, &stamp) {
+			Ok(backup) => backup,
+			Err(err) => return err,
+		};
+
+		match store.create_and_store_application_instance_cert(&x509_data, true) {
+			Ok(_) => NO_ERR,
+			Err(e) => {
+				// Don't leave a cert without its key
+				let _ = std::fs::copy(&cert_backup, &cert_path);
+				let _ = std::fs::copy(&pkey_backup, &pkey_path);
+				set_last_error_detail(e);
+				ERR_IO
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Certificate signing request (DER, PKCS#10) for the own private key of
+// pki_dir, written to csr_path_str, for a CA or a GDS. Alternate names and
+// subject as with lv_renew_certificate. Install the signed certificate
+// with lv_install_signed_certificate
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_create_csr(
+	pki_dir_str: *const c_char,
+	common_name_str: *const c_char,
+	organization_str: *const c_char,
+	organizational_unit_str: *const c_char,
+	country_str: *const c_char,
+	state_str: *const c_char,
+	csr_path_str: *const c_char,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(pki_dir_str, ERR_NULL_POINTER);
+		check_null!(common_name_str, ERR_NULL_POINTER);
+		check_null!(organization_str, ERR_NULL_POINTER);
+		check_null!(organizational_unit_str, ERR_NULL_POINTER);
+		check_null!(country_str, ERR_NULL_POINTER);
+		check_null!(state_str, ERR_NULL_POINTER);
+		check_null!(csr_path_str, ERR_NULL_POINTER);
+
+		let store = CertificateStore::new(Path::new(&cstr_to_string!(pki_dir_str)));
+		let own_cert = match read_own_cert(&store) {
+			Ok(cert) => cert,
+			Err(err) => return err,
+		};
+		let pkey = match store.read_own_pkey() {
+			Ok(pkey) => pkey,
+			Err(e) => {
+				set_last_error_detail(e);
+				return ERR_IO;
+			}
+		};
+		let x509_data = match x509_data_from(
+			&own_cert,
+			common_name_str,
+			organization_str,
+			organizational_unit_str,
+			country_str,
+			state_str,
+		) {
+			Ok(x509_data) => x509_data,
+			Err(err) => return err,
+		};
+
+		let csr = match X509::create_csr(&pkey, &x509_data) {
+			Ok(csr) => csr,
+			Err(_) => {
+				set_last_error_detail("The subject fields are not valid for a request");
+				return ERR_INVALID_ARGUMENT;
+			}
+		};
+		let csr_path = cstr_to_string!(csr_path_str);
+		if let Err(e) = std::fs::write(&csr_path, csr) {
+			set_last_error_detail(format!("{csr_path}: {e}"));
+			return ERR_IO;
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Install the certificate signed by the CA (.der or .pem) as the own
+// certificate of pki_dir, it must be for the own private key (made from the
+// lv_create_csr request). The old cert is kept as <file>.<UTC time>.bak.
+// ERR_CERT_IN_USE as with lv_renew_certificate, unless force != 0
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_install_signed_certificate(
+	pki_dir_str: *const c_char,
+	cert_path_str: *const c_char,
+	force: u8,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(pki_dir_str, ERR_NULL_POINTER);
+		check_null!(cert_path_str, ERR_NULL_POINTER);
+
+		let store = CertificateStore::new(Path::new(&cstr_to_string!(pki_dir_str)));
+		if force == 0 {
+			if let Err(err) = check_not_in_use(&store) {
+				return err;
+			}
+		}
+
+		let cert_path = store.own_certificate_path();
+		if cert_path.exists() {
+			if let Err(err) = backup_file(&cert_path, &backup_stamp()) {
+				return err;
+			}
+		}
+		match store.install_own_cert(Path::new(&cstr_to_string!(cert_path_str))) {
+			Ok(_) => NO_ERR,
+			Err(e) => {
+				set_last_error_detail(e);
+				ERR_INVALID_ARGUMENT
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Use pki_dir (it must exist) for the certificates of the client instead of
+// the one of its config, e.g. a PKI store per LabVIEW instance on the same
+// machine. The subdirectories of the store (trusted, rejected, issuers, own,
+// private) are created if missing. The client is rebuilt with the new store,
+// its own certificate is read from pki_dir (or created there if the client
+// creates a sample keypair). ERR_CERT_IN_USE while the client has connected
+// sessions
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_client_set_pki_dir(
+	client_ptr: *mut Client,
+	pki_dir_str: *const c_char,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(client_ptr, ERR_INVALID_CLIENT_REF);
+		check_null!(pki_dir_str, ERR_NULL_POINTER);
+
+		let pki_dir = PathBuf::from(cstr_to_string!(pki_dir_str));
+		if !pki_dir.is_dir() {
+			set_last_error_detail(format!("{} is not a directory", pki_dir.display()));
+			return ERR_INVALID_ARGUMENT;
+		}
+		let client = unsafe { &mut *client_ptr };
+		match sessions_of_client(client) {
+			0 => (),
+			in_use => {
+				set_last_error_detail(format!("The client has {in_use} connected session(s)"));
+				return ERR_CERT_IN_USE;
+			}
+		}
+
+		let store = CertificateStore::new(&pki_dir);
+		let own_dirs = [store.own_certificate_path(), store.own_private_key_path()];
+		let created = store.ensure_pki_path().and_then(|_| {
+			own_dirs
+				.iter()
+				.filter_map(|path| path.parent())
+				.try_for_each(|dir| {
+					std::fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))
+				})
+		});
+		if let Err(e) = created {
+			set_last_error_detail(e);
+			return ERR_IO;
+		}
+
+		match ClientBuilder::from(client.config().clone())
+			.pki_dir(pki_dir)
+			.client()
+		{
+			Ok(new_client) => {
+				*client = new_client;
+				NO_ERR
+			}
+			Err(errors) => {
+				set_last_error_detail(errors.join("\n"));
+				ERR_INVALID_CLIENT_CONFIG
+			}
+		}
+	})
+}
@@ -10,6 +10,9 @@
 //==============================================================================
 #![allow(unused_must_use)] //on cleanup unused result #ToDo-fix it
 use crate::errors::*;
+use crate::handles::{HandleKind, from_handle, into_handle};
+use crate::labview::{LStrHandle, PostLVUserEvent, string_to_lstr};
+use crate::utils::lv_node_id;
 
 use opcua::types::StatusCode;
 use tokio::runtime::Runtime;
@@ -18,19 +21,21 @@ use tokio::task::JoinHandle;
 use libc::c_char;
 use opcua::{
 	client::{Client, ClientBuilder, ClientConfig, IdentityToken, Session, SessionEventLoop},
-	core::config::Config,
-	crypto::SecurityPolicy,
+	core::{
+		comms::url::{hostname_port_from_url, url_with_replaced_host_port},
+		config::{Config, ConfigError},
+		constants::DEFAULT_OPC_UA_SERVER_PORT,
+	},
+	crypto::{SecurityPolicy, X509},
 	types::{
-		AttributeId, MessageSecurityMode, NodeId, ReadValueId, TimestampsToReturn, UserTokenPolicy,
-		Variant,
+		AttributeId, ByteString, EndpointDescription, MessageSecurityMode, NodeId, ReadValueId,
+		TimestampsToReturn, UserTokenPolicy, UserTokenType, VariableId, Variant,
 	},
 };
-use std::{
-	fmt::Write,
-	path::PathBuf,
-	sync::Arc,
-	{ffi::CString, os::raw::c_int},
-};
+use std::{ffi::c_void, fmt::Write, path::PathBuf, str::FromStr, sync::Arc, time::Duration};
+
+// lv_reconnect_session checks this often if the old event loop has ended
+const RECONNECT_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 #[macro_use]
 pub mod runtime {
@@ -46,50 +51,176 @@ pub mod runtime {
 
 #[unsafe(no_mangle)]
 pub extern "C" fn lvClientBuilder(client_out: *mut *mut Client) -> i32 {
-	if client_out.is_null() {
-		return ERR_INVALID_CLIENT_REF; // Error: null output pointer
-	}
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		if client_out.is_null() {
+			return ERR_INVALID_CLIENT_REF; // Error: null output pointer
+		}
 
-	// Make the client configuration
-	let client = ClientBuilder::new()
-		.application_name("Simple Client")
-		.application_uri("urn:SimpleClient")
-		.product_uri("urn:SimpleClient")
-		.trust_server_certs(true)
-		.create_sample_keypair(true)
-		.session_retry_limit(3)
-		.client()
-		.unwrap();
-
-	unsafe {
-		// Store the boxed client in the output pointer
-		*client_out = Box::into_raw(Box::new(client));
-	}
+		// Make the client configuration
+		let client = ClientBuilder::new()
+			.application_name("Simple Client")
+			.application_uri("urn:SimpleClient")
+			.product_uri("urn:SimpleClient")
+			.trust_server_certs(true)
+			.create_sample_keypair(true)
+			.session_retry_limit(3)
+			.client()
+			.unwrap();
 
-	0 // Success
+		unsafe {
+			// Store the boxed client in the output pointer
+			*client_out = into_handle(client, HandleKind::Client);
+		}
+
+		0 // Success
+	})
 }
 
+//==============================================================================
+// Same client as lvClientBuilder with reconnect options.
+// transfer_subscriptions != 0: after a reconnect the subscriptions are transferred
+// to the new session (or recreated), 0: they are dropped and must be rebuilt.
+// session_retry_limit: -1 retries forever
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lvClientBuilderEx(
+	transfer_subscriptions: u8,
+	session_retry_limit: i32,
+	client_out: *mut *mut Client,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		if client_out.is_null() {
+			return ERR_INVALID_CLIENT_REF;
+		}
+		if session_retry_limit < -1 {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		let client = match ClientBuilder::new()
+			.application_name("Simple Client")
+			.application_uri("urn:SimpleClient")
+			.product_uri("urn:SimpleClient")
+			.trust_server_certs(true)
+			.create_sample_keypair(true)
+			.session_retry_limit(session_retry_limit)
+			.recreate_subscriptions(transfer_subscriptions != 0)
+			.client()
+		{
+			Ok(client) => client,
+			Err(_) => return ERR_INVALID_ARGUMENT,
+		};
+
+		unsafe {
+			*client_out = into_handle(client, HandleKind::Client);
+		}
+
+		NO_ERR
+	})
+}
+
+// Load and validate the client config file
+fn load_client_config(path: &str) -> Result<ClientConfig, i32> {
+	let config: ClientConfig = ClientConfig::load(&PathBuf::from(path))
+		.map_err(|err| config_error(path, err, ERR_INVALID_CLIENT_CONFIG))?;
+	config.validate().map_err(|errors| {
+		config_error(
+			path,
+			ConfigError::ConfigInvalid(errors),
+			ERR_INVALID_CLIENT_CONFIG,
+		)
+	})?;
+	Ok(config)
+}
+
+//==============================================================================
+// Client from a config file (YAML, e.g. written by lv_save_client_config).
+// ERR_IO or ERR_INVALID_CLIENT_CONFIG with the reason in the last error detail
+//
 #[unsafe(no_mangle)]
 pub extern "C" fn lvClientBuilderFile(
 	config_path_str: *const c_char,
 	client_out: *mut *mut Client,
 ) -> i32 {
-	if client_out.is_null() {
-		return ERR_INVALID_CLIENT_REF; // Error: null output pointer
-	}
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		if client_out.is_null() {
+			return ERR_INVALID_CLIENT_REF; // Error: null output pointer
+		}
 
-	// Make the client configuration
-	//let config_file = "";
-	let config_path_str = cstr_to_string!(config_path_str);
-	//let client = Client::new(ClientConfig::load(&PathBuf::from(config_file)).unwrap());
-	let client = Client::new(ClientConfig::load(&PathBuf::from(config_path_str)).unwrap());
+		let config_path_str = cstr_to_string!(config_path_str);
+		let client = match load_client_config(&config_path_str) {
+			Ok(config) => Client::new(config),
+			Err(err) => return err,
+		};
 
-	unsafe {
-		// Store the boxed client in the output pointer
-		*client_out = Box::into_raw(Box::new(client));
-	}
+		unsafe {
+			// Store the boxed client in the output pointer
+			*client_out = into_handle(client, HandleKind::Client);
+		}
 
-	NO_ERR
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Write the configuration of the client (from any lvClientBuilder function)
+// to a YAML file, which lvClientBuilderFile loads again
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_save_client_config(client_ptr: *mut Client, path: *const c_char) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(client_ptr, ERR_INVALID_CLIENT_REF);
+		check_null!(path, ERR_NULL_POINTER);
+
+		let path = cstr_to_string!(path);
+		let client = unsafe { &*client_ptr };
+		match client.config().save(&PathBuf::from(&path)) {
+			Ok(()) => NO_ERR,
+			Err(err) => config_error(&path, err, ERR_INVALID_CLIENT_CONFIG),
+		}
+	})
+}
+
+//==============================================================================
+// Load and validate a client config file without creating the client.
+// detail_out receives what is wrong (one problem per line, e.g. a PKI dir
+// which isn't a directory, a malformed endpoint URL or an unknown security
+// policy), empty if the file is valid
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_validate_client_config(path: *const c_char, detail_out: LStrHandle) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(path, ERR_NULL_POINTER);
+		check_null!(detail_out, ERR_NULL_POINTER);
+
+		let path = cstr_to_string!(path);
+		let (result, detail) = match load_client_config(&path) {
+			Ok(_) => (NO_ERR, String::new()),
+			Err(err) => (err, last_error_detail()),
+		};
+		let err = unsafe { string_to_lstr(&detail, detail_out) };
+		if err != NO_ERR {
+			return err;
+		}
+		result
+	})
+}
+
+//==============================================================================
+// Failed connect: the code of the endpoint URL diagnosis (bad URL, DNS, TCP,
+// Hello) if it finds the reason, otherwise connect_failed.
+// The reason is left in the last error detail
+//
+pub async fn connect_error(url: &str, status: StatusCode, connect_failed: i32) -> i32 {
+	match crate::client_url::diagnose_endpoint_url(url).await {
+		Err((err, detail)) => {
+			set_last_error_detail(detail);
+			err
+		}
+		Ok(()) => {
+			set_last_error_detail(format!("Connect to {url} failed: {status}"));
+			connect_failed
+		}
+	}
 }
 
 #[unsafe(no_mangle)]
@@ -100,51 +231,56 @@ pub extern "C" fn lv_connect_loop(
 	session_out: *mut *mut Arc<Session>,
 	event_loop_out: *mut *mut Arc<SessionEventLoop>,
 ) -> i32 {
-	if lv_client.is_null() || url.is_null() || session_out.is_null() || event_loop_out.is_null() {
-		return -1;
-	}
-	if rt_ptr.is_null() {
-		return ERR_INVALID_RUNTIME;
-	}
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		if lv_client.is_null() || url.is_null() || session_out.is_null() || event_loop_out.is_null()
+		{
+			return -1;
+		}
+		if rt_ptr.is_null() {
+			return ERR_INVALID_RUNTIME;
+		}
 
-	// Convert C string to Rust string
-	let url_str = unsafe {
-		match std::ffi::CStr::from_ptr(url as *const i8).to_str() {
-			Ok(s) => s.to_string(),
-			Err(_) => return -3,
-		}
-	};
-
-	// Get the client from the pointer (without dropping it)
-	let client = unsafe { &mut *lv_client };
-
-	// Execute the async connection logic
-	unsafe {
-		let rt = &mut *rt_ptr;
-		rt.block_on(async {
-			match client
-				.connect_to_matching_endpoint(
-					(
-						url_str.as_ref(),
-						SecurityPolicy::None.to_str(),
-						MessageSecurityMode::None,
-						UserTokenPolicy::anonymous(),
-					),
-					IdentityToken::Anonymous,
-				)
-				.await
-			{
-				Ok((session, event_loop)) => {
-					// Store the Arc<Session> directly (it's already an Arc)
-					*session_out = Box::into_raw(Box::new(session));
-					// Wrap the EventLoop in an Arc before storing
-					*event_loop_out = Box::into_raw(Box::new(Arc::new(event_loop)));
-					0
-				}
-				Err(_) => -4,
+		// Convert C string to Rust string
+		let url_str = unsafe {
+			match std::ffi::CStr::from_ptr(url as *const i8).to_str() {
+				Ok(s) => s.to_string(),
+				Err(_) => return -3,
 			}
-		})
-	}
+		};
+
+		// Get the client from the pointer (without dropping it)
+		let client = unsafe { &mut *lv_client };
+
+		// Execute the async connection logic
+		unsafe {
+			let rt = &mut *rt_ptr;
+			rt.block_on(async {
+				match client
+					.connect_to_matching_endpoint(
+						(
+							url_str.as_ref(),
+							SecurityPolicy::None.to_str(),
+							MessageSecurityMode::None,
+							UserTokenPolicy::anonymous(),
+						),
+						IdentityToken::Anonymous,
+					)
+					.await
+				{
+					Ok((session, event_loop)) => {
+						crate::client_json::add_raw_structure_loader(&session);
+						crate::certificate::register_session(&session);
+						// Store the Arc<Session> directly (it's already an Arc)
+						*session_out = into_handle(session, HandleKind::Session);
+						// Wrap the EventLoop in an Arc before storing
+						*event_loop_out = into_handle(Arc::new(event_loop), HandleKind::EventLoop);
+						0
+					}
+					Err(e) => connect_error(&url_str, e, -4).await,
+				}
+			})
+		}
+	})
 }
 
 #[unsafe(no_mangle)]
@@ -156,81 +292,556 @@ pub extern "C" fn lv_connect_simple(
 	event_loop_out: *mut *mut Arc<SessionEventLoop>,
 	handle_out: *mut *mut JoinHandle<StatusCode>,
 ) -> i32 {
-	check_runtime!(rt_ptr);
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_runtime!(rt_ptr);
 
-	if lv_client.is_null() || url.is_null() || session_out.is_null() || event_loop_out.is_null() {
-		return ERR_INVALID_CLIENT_REF;
-	}
+		if lv_client.is_null() || url.is_null() || session_out.is_null() || event_loop_out.is_null()
+		{
+			return ERR_INVALID_CLIENT_REF;
+		}
 
-	// Convert C string to Rust string
-	let url_str = unsafe {
-		match std::ffi::CStr::from_ptr(url as *const i8).to_str() {
-			Ok(s) => s.to_string(),
-			Err(_) => return -3,
-		}
-	};
-
-	// Get the client from the pointer (without dropping it)
-	let client = unsafe { &mut *lv_client };
-
-	// Execute the async connection logic
-	unsafe {
-		let rt = &mut *rt_ptr;
-		rt.block_on(async {
-			match client
-				.connect_to_matching_endpoint(
-					(
-						url_str.as_ref(),
-						SecurityPolicy::None.to_str(),
-						MessageSecurityMode::None,
-						UserTokenPolicy::anonymous(),
-					),
-					IdentityToken::Anonymous,
-				)
-				.await
-			{
-				Ok((session, event_loop)) => {
-					let handle = event_loop.spawn(); //Important!
-					session.wait_for_connection().await;
-
-					// Store the Arc<Session> directly (it's already an Arc)
-					let session_c = session.clone();
-					*session_out = Box::into_raw(Box::new(session));
-					*handle_out = Box::into_raw(Box::new(handle));
-
-					let r_v1 = session_c
-						.read(
-							&[NodeId::new(2, "v1").into()],
-							TimestampsToReturn::Both,
-							0.0,
-						)
-						.await;
-
-					match r_v1 {
-						Ok(read_values) => {
-							if let Some(data_value) = read_values.first() {
-								if let Some(variant) = &data_value.value {
-									if let Variant::Int32(i32_value) = variant {
-										return *i32_value; // Successfully extracted i32 OK IT WORKS!
+		// Convert C string to Rust string
+		let url_str = unsafe {
+			match std::ffi::CStr::from_ptr(url as *const i8).to_str() {
+				Ok(s) => s.to_string(),
+				Err(_) => return -3,
+			}
+		};
+
+		// Get the client from the pointer (without dropping it)
+		let client = unsafe { &mut *lv_client };
+
+		// Execute the async connection logic
+		unsafe {
+			let rt = &mut *rt_ptr;
+			rt.block_on(async {
+				match client
+					.connect_to_matching_endpoint(
+						(
+							url_str.as_ref(),
+							SecurityPolicy::None.to_str(),
+							MessageSecurityMode::None,
+							UserTokenPolicy::anonymous(),
+						),
+						IdentityToken::Anonymous,
+					)
+					.await
+				{
+					Ok((session, event_loop)) => {
+						crate::client_json::add_raw_structure_loader(&session);
+						crate::certificate::register_session(&session);
+						let handle = event_loop.spawn(); //Important!
+						session.wait_for_connection().await;
+
+						// Store the Arc<Session> directly (it's already an Arc)
+						let session_c = session.clone();
+						*session_out = into_handle(session, HandleKind::Session);
+						*handle_out = into_handle(handle, HandleKind::JoinHandle);
+
+						let r_v1 = session_c
+							.read(
+								&[NodeId::new(2, "v1").into()],
+								TimestampsToReturn::Both,
+								0.0,
+							)
+							.await;
+
+						match r_v1 {
+							Ok(read_values) => {
+								if let Some(data_value) = read_values.first() {
+									if let Some(variant) = &data_value.value {
+										if let Variant::Int32(i32_value) = variant {
+											return *i32_value; // Successfully extracted i32 OK IT WORKS!
+										} else {
+											return -4; // Error code for variant not being an i32
+										}
 									} else {
-										return -4; // Error code for variant not being an i32
+										return -5; // Error code for no value in DataValue
 									}
 								} else {
-									return -5; // Error code for no value in DataValue
+									return -6; // Error code for no values returned
 								}
-							} else {
-								return -6; // Error code for no values returned
 							}
+							Err(_) => return -7, // Error code for read failure
 						}
-						Err(_) => return -7, // Error code for read failure
 					}
+					Err(e) => connect_error(&url_str, e, -8).await,
 				}
-				Err(_) => -8,
+			})
+		}
+	})
+}
+
+//==============================================================================
+// Connect to the server behind NAT: the endpoints are discovered on
+// discovery_url, the one with security_policy ("None", "Basic256Sha256", ...)
+// and security_mode ("None", "Sign", "SignAndEncrypt") is used, but the
+// session connects to host_port ("host:port", empty - host and port of
+// discovery_url) instead of the advertised (internal) address.
+// relax_hostname_check != 0 accepts a server certificate that doesn't contain
+// this hostname, for this connection only, the other checks are still done
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_connect_with_endpoint_override(
+	rt_ptr: *mut Runtime,
+	lv_client: *mut Client,
+	discovery_url: *const c_char,
+	host_port: *const c_char,
+	security_policy: *const c_char,
+	security_mode: *const c_char,
+	relax_hostname_check: u8,
+	session_out: *mut *mut Arc<Session>,
+	event_loop_out: *mut *mut Arc<SessionEventLoop>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_runtime!(rt_ptr);
+		check_null!(lv_client, ERR_INVALID_CLIENT_REF);
+		check_null!(discovery_url, ERR_NULL_POINTER);
+		check_null!(host_port, ERR_NULL_POINTER);
+		check_null!(security_policy, ERR_NULL_POINTER);
+		check_null!(security_mode, ERR_NULL_POINTER);
+		check_null!(session_out, ERR_NULL_POINTER);
+		check_null!(event_loop_out, ERR_NULL_POINTER);
+
+		let url_str = cstr_to_string!(discovery_url);
+		let host_port = cstr_to_string!(host_port);
+		let policy_str = cstr_to_string!(security_policy);
+		let mode_str = cstr_to_string!(security_mode);
+
+		let policy = match SecurityPolicy::from_str(&policy_str) {
+			Ok(policy) if policy != SecurityPolicy::Unknown => policy,
+			_ => {
+				set_last_error_detail(format!("Unknown security policy '{policy_str}'"));
+				return ERR_INVALID_ARGUMENT;
 			}
+		};
+		let mode = MessageSecurityMode::from(mode_str.as_str());
+		if mode == MessageSecurityMode::Invalid {
+			set_last_error_detail(format!("Unknown security mode '{mode_str}'"));
+			return ERR_INVALID_ARGUMENT;
+		}
+		let override_url = if host_port.is_empty() {
+			url_str.clone()
+		} else {
+			format!("opc.tcp://{host_port}")
+		};
+		let Ok((host, port)) = hostname_port_from_url(&override_url, DEFAULT_OPC_UA_SERVER_PORT)
+		else {
+			set_last_error_detail(format!("'{host_port}' is not a valid host:port"));
+			return ERR_BAD_URL;
+		};
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let client = &mut *lv_client;
+			rt.block_on(async {
+				let endpoints = match client.get_server_endpoints_from_url(url_str.as_str()).await {
+					Ok(endpoints) => endpoints,
+					Err(e) => return connect_error(&url_str, e, e.bits() as i32).await,
+				};
+				let Some(mut endpoint) = endpoints.into_iter().find(|e| {
+					e.security_mode == mode
+						&& SecurityPolicy::from_uri(e.security_policy_uri.as_ref()) == policy
+				}) else {
+					set_last_error_detail(format!(
+						"{url_str} has no endpoint with {policy_str} / {mode_str}"
+					));
+					return StatusCode::BadTcpEndpointUrlInvalid.bits() as i32;
+				};
+				match url_with_replaced_host_port(endpoint.endpoint_url.as_ref(), &host, port) {
+					Ok(endpoint_url) => endpoint.endpoint_url = endpoint_url.into(),
+					Err(e) => {
+						set_last_error_detail(format!(
+							"Can't use {host}:{port} for {}: {e}",
+							endpoint.endpoint_url
+						));
+						return ERR_BAD_URL;
+					}
+				}
+
+				let builder = match client
+					.session_builder()
+					.connect_to_endpoint_directly(endpoint)
+				{
+					Ok(builder) => builder,
+					Err(e) => {
+						set_last_error_detail(e);
+						return ERR_BAD_URL;
+					}
+				};
+				let (session, event_loop) = builder
+					.user_identity_token(IdentityToken::Anonymous)
+					.verify_server_hostname(relax_hostname_check == 0)
+					.build(client.certificate_store().clone());
+				crate::client_json::add_raw_structure_loader(&session);
+				crate::certificate::register_session(&session);
+				*session_out = into_handle(session, HandleKind::Session);
+				*event_loop_out = into_handle(Arc::new(event_loop), HandleKind::EventLoop);
+				NO_ERR
+			})
+		}
+	})
+}
+
+// Rank of the security mode of an endpoint, None if the mode can't be used
+fn security_mode_rank(mode: MessageSecurityMode) -> Option<u8> {
+	match mode {
+		MessageSecurityMode::SignAndEncrypt => Some(2),
+		MessageSecurityMode::Sign => Some(1),
+		MessageSecurityMode::None => Some(0),
+		_ => None,
+	}
+}
+
+// Usable endpoints for the token type, the most secure first
+fn endpoints_by_security(
+	endpoints: Vec<EndpointDescription>,
+	token_type: UserTokenType,
+	has_own_cert: bool,
+) -> Vec<EndpointDescription> {
+	let mut usable: Vec<_> = endpoints
+		.into_iter()
+		.filter(|e| {
+			let policy = SecurityPolicy::from_uri(e.security_policy_uri.as_ref());
+			policy != SecurityPolicy::Unknown && (policy == SecurityPolicy::None || has_own_cert)
+		})
+		.filter(|e| {
+			e.user_identity_tokens
+				.iter()
+				.flatten()
+				.any(|token| token.token_type == token_type)
 		})
+		.filter_map(|e| security_mode_rank(e.security_mode).map(|rank| (rank, e)))
+		.collect();
+	usable.sort_by(|(rank_a, a), (rank_b, b)| {
+		(rank_b, b.security_level).cmp(&(rank_a, a.security_level))
+	});
+	usable.into_iter().map(|(_, e)| e).collect()
+}
+
+//==============================================================================
+// Connect to the most secure endpoint of the server at url: the endpoints
+// (GetEndpoints) are ordered SignAndEncrypt, Sign, None (higher security
+// level first within a mode) and the first one accepting the credentials
+// is used. Null username_str or password_str - anonymous. Sign and
+// SignAndEncrypt need the own certificate of the client (see lvClientBuilder),
+// without it the None endpoints are used.
+// The event loop is spawned as with lv_connect_simple, handle_out takes its
+// handle and event_loop_out is set to null.
+// ERR_NO_SUITABLE_ENDPOINT if no endpoint can be used
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_connect_best_security(
+	rt_ptr: *mut Runtime,
+	lv_client: *mut Client,
+	url: *const c_char,
+	username_str: *const c_char,
+	password_str: *const c_char,
+	session_out: *mut *mut Arc<Session>,
+	event_loop_out: *mut *mut Arc<SessionEventLoop>,
+	handle_out: *mut *mut JoinHandle<StatusCode>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_runtime!(rt_ptr);
+		check_null!(lv_client, ERR_INVALID_CLIENT_REF);
+		check_null!(url, ERR_NULL_POINTER);
+		check_null!(session_out, ERR_NULL_POINTER);
+		check_null!(event_loop_out, ERR_NULL_POINTER);
+		check_null!(handle_out, ERR_NULL_POINTER);
+
+		let url_str = cstr_to_string!(url);
+		let (identity, token_type) = if username_str.is_null() || password_str.is_null() {
+			(IdentityToken::Anonymous, UserTokenType::Anonymous)
+		} else {
+			(
+				IdentityToken::UserName(
+					cstr_to_string!(username_str),
+					cstr_to_string!(password_str),
+				),
+				UserTokenType::UserName,
+			)
+		};
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let client = &mut *lv_client;
+			rt.block_on(async {
+				let endpoints = match client.get_server_endpoints_from_url(url_str.as_str()).await {
+					Ok(endpoints) => endpoints,
+					Err(e) => return connect_error(&url_str, e, e.bits() as i32).await,
+				};
+				let has_own_cert = client.certificate_store().read().read_own_cert().is_ok();
+				let Some(endpoint) = endpoints_by_security(endpoints, token_type, has_own_cert)
+					.into_iter()
+					.next()
+				else {
+					set_last_error_detail(format!(
+						"{url_str} has no endpoint for {token_type:?} users"
+					));
+					return ERR_NO_SUITABLE_ENDPOINT;
+				};
+
+				let (session, event_loop) =
+					match client.connect_to_endpoint_directly(endpoint, identity) {
+						Ok(connection) => connection,
+						Err(e) => {
+							set_last_error_detail(e);
+							return ERR_BAD_URL;
+						}
+					};
+				crate::client_json::add_raw_structure_loader(&session);
+				crate::certificate::register_session(&session);
+				let mut handle = event_loop.spawn();
+				tokio::select! {
+					_ = session.wait_for_connection() => {}
+					status = &mut handle => {
+						let status = status.unwrap_or(StatusCode::BadUnexpectedError);
+						return connect_error(&url_str, status, status.bits() as i32).await;
+					}
+				}
+				*session_out = into_handle(session, HandleKind::Session);
+				*event_loop_out = std::ptr::null_mut();
+				*handle_out = into_handle(handle, HandleKind::JoinHandle);
+				NO_ERR
+			})
+		}
+	})
+}
+
+// As connect_error, the detail says that discovery was skipped and which
+// endpoint was used
+async fn direct_connect_error(url: &str, endpoint: &str, status: StatusCode) -> i32 {
+	match crate::client_url::diagnose_endpoint_url(url).await {
+		Err((err, detail)) => {
+			set_last_error_detail(format!("{endpoint} (discovery skipped): {detail}"));
+			err
+		}
+		Ok(()) => {
+			set_last_error_detail(format!(
+				"Connect to {endpoint} (discovery skipped) failed: {status}"
+			));
+			status.bits() as i32
+		}
 	}
 }
 
+//==============================================================================
+// Connect to an exact endpoint without GetEndpoints: the endpoint description
+// is built from endpoint_url, security_policy ("None", "Basic256Sha256", ...)
+// and security_mode ("None", "Sign", "SignAndEncrypt"). Sign and
+// SignAndEncrypt need the DER server certificate (server_cert_der,
+// server_cert_len bytes), with None it may be null.
+// Null username_str or password_str - anonymous. user_token_policy_id is the
+// policy id of the identity token on the server, null or empty - "anonymous"
+// or "userpass_none" (the defaults of this server).
+// The event loop is spawned as with lv_connect_best_security, handle_out takes
+// its handle and event_loop_out is set to null.
+// The error detail names the endpoint the session was created against
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_connect_to_endpoint(
+	rt_ptr: *mut Runtime,
+	lv_client: *mut Client,
+	endpoint_url: *const c_char,
+	security_policy: *const c_char,
+	security_mode: *const c_char,
+	username_str: *const c_char,
+	password_str: *const c_char,
+	user_token_policy_id: *const c_char,
+	server_cert_der: *const u8,
+	server_cert_len: i32,
+	session_out: *mut *mut Arc<Session>,
+	event_loop_out: *mut *mut Arc<SessionEventLoop>,
+	handle_out: *mut *mut JoinHandle<StatusCode>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_runtime!(rt_ptr);
+		check_null!(lv_client, ERR_INVALID_CLIENT_REF);
+		check_null!(endpoint_url, ERR_NULL_POINTER);
+		check_null!(security_policy, ERR_NULL_POINTER);
+		check_null!(security_mode, ERR_NULL_POINTER);
+		check_null!(session_out, ERR_NULL_POINTER);
+		check_null!(event_loop_out, ERR_NULL_POINTER);
+		check_null!(handle_out, ERR_NULL_POINTER);
+
+		let url_str = cstr_to_string!(endpoint_url);
+		let policy_str = cstr_to_string!(security_policy);
+		let mode_str = cstr_to_string!(security_mode);
+
+		let policy = match SecurityPolicy::from_str(&policy_str) {
+			Ok(policy) if policy != SecurityPolicy::Unknown => policy,
+			_ => {
+				set_last_error_detail(format!("Unknown security policy '{policy_str}'"));
+				return ERR_INVALID_ARGUMENT;
+			}
+		};
+		let mode = MessageSecurityMode::from(mode_str.as_str());
+		if mode == MessageSecurityMode::Invalid {
+			set_last_error_detail(format!("Unknown security mode '{mode_str}'"));
+			return ERR_INVALID_ARGUMENT;
+		}
+		if (policy == SecurityPolicy::None) != (mode == MessageSecurityMode::None) {
+			set_last_error_detail(format!("{policy_str} can't be used with {mode_str}"));
+			return ERR_INVALID_ARGUMENT;
+		}
+		let endpoint_str = format!("{url_str} {policy_str} / {mode_str}");
+
+		let server_cert = if server_cert_der.is_null() || server_cert_len <= 0 {
+			None
+		} else {
+			let der =
+				unsafe { std::slice::from_raw_parts(server_cert_der, server_cert_len as usize) };
+			if let Err(e) = X509::from_der(der) {
+				set_last_error_detail(format!("Server certificate for {endpoint_str}: {e}"));
+				return ERR_INVALID_ARGUMENT;
+			}
+			Some(der.to_vec())
+		};
+		if server_cert.is_none() && mode != MessageSecurityMode::None {
+			set_last_error_detail(format!(
+				"{endpoint_str} needs the server certificate, discovery is skipped"
+			));
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		let (identity, token_type, default_policy_id) =
+			if username_str.is_null() || password_str.is_null() {
+				(
+					IdentityToken::Anonymous,
+					UserTokenType::Anonymous,
+					"anonymous",
+				)
+			} else {
+				(
+					IdentityToken::UserName(
+						cstr_to_string!(username_str),
+						cstr_to_string!(password_str),
+					),
+					UserTokenType::UserName,
+					"userpass_none",
+				)
+			};
+		let policy_id = if user_token_policy_id.is_null() {
+			String::new()
+		} else {
+			cstr_to_string!(user_token_policy_id)
+		};
+		let token_policy = UserTokenPolicy {
+			policy_id: if policy_id.is_empty() {
+				default_policy_id.into()
+			} else {
+				policy_id.into()
+			},
+			token_type,
+			..UserTokenPolicy::anonymous()
+		};
+
+		let mut endpoint =
+			EndpointDescription::from((url_str.as_str(), policy.to_uri(), mode, token_policy));
+		if let Some(der) = server_cert {
+			endpoint.server_certificate = ByteString::from(der);
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let client = &mut *lv_client;
+			rt.block_on(async {
+				let (session, event_loop) = match client
+					.connect_to_endpoint_directly(endpoint, identity)
+				{
+					Ok(connection) => connection,
+					Err(e) => {
+						set_last_error_detail(format!("{endpoint_str} (discovery skipped): {e}"));
+						return ERR_BAD_URL;
+					}
+				};
+				crate::client_json::add_raw_structure_loader(&session);
+				crate::certificate::register_session(&session);
+				let mut handle = event_loop.spawn();
+				tokio::select! {
+					_ = session.wait_for_connection() => {}
+					status = &mut handle => {
+						let status = status.unwrap_or(StatusCode::BadUnexpectedError);
+						return direct_connect_error(&url_str, &endpoint_str, status).await;
+					}
+				}
+				*session_out = into_handle(session, HandleKind::Session);
+				*event_loop_out = std::ptr::null_mut();
+				*handle_out = into_handle(handle, HandleKind::JoinHandle);
+				NO_ERR
+			})
+		}
+	})
+}
+
+// Wait until the session is connected again, a new event loop is started as
+// soon as the old one has ended. Err is the status of the ended new loop
+async fn reconnect(session: &Arc<Session>) -> Result<(), StatusCode> {
+	let mut poll = tokio::time::interval(RECONNECT_POLL_INTERVAL);
+	loop {
+		if let Ok(event_loop) = session.new_event_loop() {
+			let mut handle = event_loop.spawn();
+			return tokio::select! {
+				_ = session.wait_for_connection() => Ok(()),
+				status = &mut handle => Err(status.unwrap_or(StatusCode::BadUnexpectedError)),
+			};
+		}
+		tokio::select! {
+			_ = session.wait_for_connection() => return Ok(()),
+			_ = poll.tick() => {}
+		}
+	}
+}
+
+//==============================================================================
+// Connect the lost session again without a new handle, e.g. after its event
+// loop gave up reconnecting: a new event loop is started (with the
+// reconnect policy of the client config) when the old one has ended, while
+// it is still reconnecting this waits for it. url_str is the URL the
+// session was connected to, for the diagnosis of a failed reconnect.
+// Subscriptions are transferred to the new server session or created again,
+// then their ids may be remapped: get the new id with
+// lv_get_reconnected_subscription_id(), the LabVIEW user events keep firing.
+// timeout_ms 0 - no limit, after ERR_TIMEOUT reconnecting goes on.
+// ERR_INVALID_CLIENT_REF if the session is connected
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_reconnect_session(
+	rt_ptr: *mut Runtime,
+	client_ptr: *mut Client,
+	session_in: *mut Arc<Session>,
+	url_str: *const c_char,
+	timeout_ms: u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_runtime!(rt_ptr);
+		check_null!(client_ptr, ERR_INVALID_CLIENT_REF);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(url_str, ERR_NULL_POINTER);
+
+		let url = cstr_to_string!(url_str);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &*session_in;
+			if session.is_connected() {
+				set_last_error_detail("The session is connected");
+				return ERR_INVALID_CLIENT_REF;
+			}
+			rt.block_on(async {
+				match crate::utils::with_timeout(timeout_ms, reconnect(session)).await {
+					Ok(Ok(())) => {
+						crate::subscription::remap_recreated_subscriptions(session);
+						NO_ERR
+					}
+					Ok(Err(status)) => connect_error(&url, status, status.bits() as i32).await,
+					Err(err) => err,
+				}
+			})
+		}
+	})
+}
+
 // GetNode Atributes to LV String
 
 #[allow(unused)]
@@ -251,38 +862,6 @@ pub fn read_value_ids(attributes: &[AttributeId], id: impl Into<NodeId>) -> Vec<
 		.map(|a| read_value_id(*a, &node_id))
 		.collect()
 }
-// Will be better to move common and LabVIEW-specific stuff into labview.rs (may be later)
-#[cfg(target_arch = "x86_64")]
-#[repr(C)]
-pub struct LStr {
-	cnt: i32,
-	str: [u8; 0],
-}
-#[cfg(target_arch = "x86")]
-#[repr(C, packed(1))]
-pub struct LStr {
-	cnt: i32,
-	str: [u8; 0],
-}
-
-type LStrHandle = *mut *mut LStr;
-
-unsafe extern "C" {
-	// in latest Rust must be unsafe!
-	#[link_name = "NumericArrayResize"]
-	// Use link_name if the function is named differently in the DLL
-	fn string_resize(
-		numeric_type: u32, // This should be u32, based on LabVIEW documentation
-		num_dimensions: i32,
-		data_handle: *mut LStrHandle, // LabVIEW uses UHandle for array resizing.
-		new_size: usize,              // New size of the array
-	) -> c_int;
-}
-
-unsafe extern "C" {
-	#[link_name = "MoveBlock"]
-	fn MoveBlockChar(src: *const i8, destination: *mut u8, size: usize);
-}
 
 #[unsafe(no_mangle)]
 pub extern "C" fn lv_get_node_info(
@@ -292,70 +871,66 @@ pub extern "C" fn lv_get_node_info(
 	id_str: *const i8,
 	ns: u16,
 	id_type: u32,
-	mut lv_str: LStrHandle,
+	lv_str: LStrHandle,
 ) -> i32 {
-	check_runtime!(rt_ptr);
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_runtime!(rt_ptr);
 
-	unsafe {
-		let rt = &mut *rt_ptr;
-		if !session_in.is_null() {
-			// let session = Box::from_raw(session_in); //Very bad idea, crashed after few calls!
-			let session = &mut *session_in;
-			// let id: NodeId = NodeId::new(2, "MyVariable").into(); //Jst for test
-			let id: NodeId;
-			match id_type {
-				1 => id = NodeId::new(0, id_u32).into(), //so works so far
-				2 => id = NodeId::new(ns, cstr_to_string!(id_str)).into(),
-				_ => return ERR_INVALID_TYPE,
-			}
+		unsafe {
+			let rt = &mut *rt_ptr;
+			if !session_in.is_null() {
+				// let session = from_handle(session_in); //Very bad idea, crashed after few calls!
+				let session = &mut *session_in;
+				// let id: NodeId = NodeId::new(2, "MyVariable").into(); //Jst for test
+				let id = match lv_node_id(id_u32, id_str, ns, id_type) {
+					Ok(id) => id,
+					Err(err) => return err,
+				};
 
-			let r = rt.block_on(async {
-				session
-					.read(
-						&read_value_ids(
-							&[
-								AttributeId::Value,
-								AttributeId::DisplayName,
-								AttributeId::BrowseName,
-								//AttributeId::NodeClass, //lot of Attributes available
-								//AttributeId::NodeId,
-								//AttributeId::Historizing,
-								//AttributeId::ArrayDimensions,
-								//AttributeId::Description,
-								//AttributeId::ValueRank,
-								//AttributeId::DataType,
-								//AttributeId::AccessLevel,
-								//AttributeId::UserAccessLevel,
-							],
-							&id,
-						),
-						TimestampsToReturn::Both,
-						0.0,
-					)
-					.await
-					.unwrap()
-			});
+				let r = rt.block_on(async {
+					session
+						.read(
+							&read_value_ids(
+								&[
+									AttributeId::Value,
+									AttributeId::DisplayName,
+									AttributeId::BrowseName,
+									//AttributeId::NodeClass, //lot of Attributes available
+									//AttributeId::NodeId,
+									//AttributeId::Historizing,
+									//AttributeId::ArrayDimensions,
+									//AttributeId::Description,
+									//AttributeId::ValueRank,
+									//AttributeId::DataType,
+									//AttributeId::AccessLevel,
+									//AttributeId::UserAccessLevel,
+								],
+								&id,
+							),
+							TimestampsToReturn::Both,
+							0.0,
+						)
+						.await
+						.unwrap()
+				});
 
-			let mut i = 0;
-			let mut output = String::new();
+				let mut i = 0;
+				let mut output = String::new();
 
-			while i < r.len() {
-				write!(&mut output, "Attribute {}: {:?}\n", i, r[i])
-					.expect("Failed to get attribute");
-				i = i + 1;
+				while i < r.len() {
+					write!(&mut output, "Attribute {}: {:?}\n", i, r[i])
+						.expect("Failed to get attribute");
+					i = i + 1;
+				}
+				// Length prefixed, the values may contain NULs (padded PLC strings)
+				let err = string_to_lstr(&output, lv_str);
+				if err != NO_ERR {
+					return err;
+				}
 			}
-			let len = output.len();
-			string_resize(1, 1, &mut lv_str as *mut LStrHandle, len);
-
-			let c_headers = match CString::new(output) {
-				Ok(cs) => cs,
-				Err(_) => return -1, // failed to convert to C string
-			};
-			MoveBlockChar(c_headers.as_ptr(), (**lv_str).str.as_mut_ptr(), len);
-			(**lv_str).cnt = len as i32;
 		}
-	}
-	return 0;
+		return 0;
+	})
 }
 
 // Update cleanup function to handle Arc types
@@ -366,27 +941,155 @@ pub extern "C" fn lv_cleanup_session(
 	event_loop_in: *mut Arc<SessionEventLoop>,
 	handle_in: *mut JoinHandle<StatusCode>,
 ) -> i32 {
-	check_runtime!(rt_ptr);
-
-	unsafe {
-		let rt = &mut *rt_ptr;
-		if !session_in.is_null() {
-			let session = Box::from_raw(session_in);
-			let handle = Box::from_raw(handle_in);
-			//let session = &mut *session_in; //let try this way, no was better
-			//let handle = &mut *handle_in;
-			//session.disconnect().await;
-			//let result = runtime.block_on(async {
-			rt.block_on(async { session.disconnect().await });
-			rt.block_on(async { handle.await.unwrap() });
-		}
-		if !event_loop_in.is_null() {
-			let _ = Box::from_raw(event_loop_in);
-		}
-		//rt.shutdown_background();
-	}
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_runtime!(rt_ptr);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			if !session_in.is_null() {
+				let session = from_handle(session_in);
+				let handle = from_handle(handle_in);
+				//let session = &mut *session_in; //let try this way, no was better
+				//let handle = &mut *handle_in;
+				//session.disconnect().await;
+				//let result = runtime.block_on(async {
+				rt.block_on(async { session.disconnect().await });
+				rt.block_on(async { handle.await.unwrap() });
+			}
+			if !event_loop_in.is_null() {
+				let _ = from_handle(event_loop_in);
+			}
+			//rt.shutdown_background();
+		}
+
+		return 0;
+	})
+}
+
+//==============================================================================
+// Free the session reference without disconnecting, e.g. after
+// lv_disconnect_session_graceful or when the event loop has ended. The
+// session must not be connected any more, the routing of its subscriptions
+// is removed
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_free_session(session_in: *mut Arc<Session>) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+
+		unsafe {
+			let session = &*session_in;
+			if session.is_connected() {
+				set_last_error_detail("The session is connected, disconnect it first");
+				return ERR_INVALID_CLIENT_REF;
+			}
+			crate::subscription::unregister_session(session);
+			drop(from_handle(session_in));
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Free the client from lvClientBuilder (and the other builders) when all its
+// sessions are closed, else the client leaks after every connection.
+// ERR_INVALID_CLIENT_REF if a session of the client is still connected
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_free_client(client_ptr: *mut Client) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(client_ptr, ERR_INVALID_CLIENT_REF);
+
+		unsafe {
+			let connected = crate::certificate::sessions_of_client(&*client_ptr);
+			if connected > 0 {
+				set_last_error_detail(format!(
+					"The client has {connected} connected session(s), close them first"
+				));
+				return ERR_INVALID_CLIENT_REF;
+			}
+			drop(from_handle(client_ptr));
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Close the session (CloseSession request), waiting at most timeout_ms
+// (0 - no limit) for the server. ERR_TIMEOUT if the server doesn't answer
+// within the budget, the session is usable again after a reconnect.
+// The session reference stays valid, free it with lv_cleanup_session
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_disconnect_session_graceful(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	timeout_ms: u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_runtime!(rt_ptr);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
 
-	return 0;
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &*session_in;
+			rt.block_on(async {
+				match crate::utils::with_timeout(timeout_ms, session.disconnect()).await {
+					Ok(Ok(())) => NO_ERR,
+					Ok(Err(status)) => status.bits() as i32,
+					Err(err) => err,
+				}
+			})
+		}
+	})
+}
+
+//==============================================================================
+// lv_cleanup_session with at most timeout_ms (0 - no limit) for closing the
+// session and the end of its event loop, e.g. if the server is gone.
+// The references are freed in any case, on ERR_TIMEOUT the event loop is
+// aborted
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_cleanup_session_timeout(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	event_loop_in: *mut Arc<SessionEventLoop>,
+	handle_in: *mut JoinHandle<StatusCode>,
+	timeout_ms: u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_runtime!(rt_ptr);
+
+		let mut result = NO_ERR;
+		unsafe {
+			let rt = &mut *rt_ptr;
+			if !session_in.is_null() {
+				let session = from_handle(session_in);
+				let mut handle = (!handle_in.is_null()).then(|| from_handle(handle_in));
+				crate::subscription::unregister_session(&session);
+				result = rt.block_on(async {
+					let cleanup = async {
+						let _ = session.disconnect().await;
+						if let Some(handle) = handle.as_mut() {
+							let _ = handle.as_mut().await;
+						}
+					};
+					match crate::utils::with_timeout(timeout_ms, cleanup).await {
+						Ok(()) => NO_ERR,
+						Err(err) => err,
+					}
+				});
+				if let Some(handle) = handle {
+					handle.abort();
+				}
+			}
+			if !event_loop_in.is_null() {
+				let _ = from_handle(event_loop_in);
+			}
+		}
+		result
+	})
 }
 /*
 
@@ -434,108 +1137,110 @@ pub extern "C" fn lv_subscribe_to_variables_i32var(
 	node_path_array: &LStr1DarrayHdl,
 	subscription_out: *mut u32,
 ) -> i32 {
-	let session = unsafe { &mut *lv_session };
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(lv_session, ERR_INVALID_CLIENT_REF);
+		if rt_ptr.is_null() {
+			return ERR_INVALID_RUNTIME;
+		}
+		let session = unsafe { &mut *lv_session };
 
-	// Wrap both raw pointers in thread-safe containers
-	let safe_refus = user_event_ref as usize;
-	let safe_dataus = data as usize;
+		// Wrap both raw pointers in thread-safe containers
+		let safe_refus = user_event_ref as usize;
+		let safe_dataus = data as usize;
 
-	if rt_ptr.is_null() {
-		return ERR_INVALID_RUNTIME;
-	}
+		unsafe {
+			let rt = &mut *rt_ptr;
 
-	unsafe {
-		let rt = &mut *rt_ptr;
-
-		let subscription_id_res = rt.block_on(async {
-			session
-				.create_subscription(
-					Duration::from_secs(1),
-					10,
-					30,
-					0,
-					0,
-					true,
-					DataChangeCallback::new(move |dv, item| {
-						let user_event_ptr = safe_refus as *mut *mut c_void;
-						let data_ptr = safe_dataus as *mut c_void;
-						// let val = dv.value.as_i32(); //that doesn't work
-						//output_debug_string("--callback--");
-						let val = if let Some(variant) = &dv.value {
-							if let Variant::Int32(i32_value) = variant {
-								// *i32_value; // Successfully extracted i32
-								let i32_ptr = i32_value as *const i32 as *mut c_void;
-								//output_debug_string("callback as i32");
-								PostLVUserEvent(*user_event_ptr, i32_ptr)
+			let subscription_id_res = rt.block_on(async {
+				session
+					.create_subscription(
+						Duration::from_secs(1),
+						10,
+						30,
+						0,
+						0,
+						true,
+						DataChangeCallback::new(move |dv, item| {
+							let user_event_ptr = safe_refus as *mut *mut c_void;
+							let data_ptr = safe_dataus as *mut c_void;
+							// let val = dv.value.as_i32(); //that doesn't work
+							//output_debug_string("--callback--");
+							let val = if let Some(variant) = &dv.value {
+								if let Variant::Int32(i32_value) = variant {
+									// *i32_value; // Successfully extracted i32
+									let i32_ptr = i32_value as *const i32 as *mut c_void;
+									//output_debug_string("callback as i32");
+									PostLVUserEvent(*user_event_ptr, i32_ptr)
+								} else {
+									//output_debug_string("variant not being an i32");
+									-4 // Error code for variant not being an i32
+								}
 							} else {
-								//output_debug_string("variant not being an i32");
-								-4 // Error code for variant not being an i32
-							}
-						} else {
-							-5 // Error code for no value in DataValue
-						};
-					}),
-				)
-				.await
-		});
+								-5 // Error code for no value in DataValue
+							};
+						}),
+					)
+					.await
+			});
 
-		let subscription = {
-			match subscription_id_res {
-				Ok(subscription_id) => {
-					// Create some monitored items
+			let subscription = {
+				match subscription_id_res {
+					Ok(subscription_id) => {
+						// Create some monitored items
 
-					let mut items_to_create_list = Vec::new();
+						let mut items_to_create_list = Vec::new();
 
-					//let td1 = (*(*node_path_array)).node_ru.as_ptr();
-					//let td1 = std::ptr::addr_of!((*node_path_array).node_ru); // Get raw pointer directly
-					//let td1 = std::ptr::addr_of!((*(*node_path_array)).node_ru);
+						//let td1 = (*(*node_path_array)).node_ru.as_ptr();
+						//let td1 = std::ptr::addr_of!((*node_path_array).node_ru); // Get raw pointer directly
+						//let td1 = std::ptr::addr_of!((*(*node_path_array)).node_ru);
 
-					//let td1 = std::ptr::addr_of!((*(*node_path_array)).node_ru); // Get raw pointer directly
-					let td1 = unsafe {
-						std::ptr::read_unaligned(addr_of!((*(*node_path_array)).node_ru))
-					};
+						//let td1 = std::ptr::addr_of!((*(*node_path_array)).node_ru); // Get raw pointer directly
+						let td1 = unsafe {
+							std::ptr::read_unaligned(addr_of!((*(*node_path_array)).node_ru))
+						};
 
-					let dim_size = (*(*node_path_array)).dim_size;
+						let dim_size = (*(*node_path_array)).dim_size;
 
-					for i in 0..dim_size {
-						//let lstr_ptr = *td1.add(i as usize);
-						let lstr_ptr = td1;
-						if lstr_ptr.is_null() {
-							break;
-						}
+						for i in 0..dim_size {
+							//let lstr_ptr = *td1.add(i as usize);
+							let lstr_ptr = td1;
+							if lstr_ptr.is_null() {
+								break;
+							}
 
-						let cnt: usize = (*(*lstr_ptr)).cnt as usize;
+							let cnt: usize = (*(*lstr_ptr)).cnt as usize;
 
-						let str_ptr: *const u8 = (*(*lstr_ptr)).str.as_ptr();
+							let str_ptr: *const u8 = (*(*lstr_ptr)).str.as_ptr();
 
-						// Create a slice from the raw pointer and length
-						let slice = slice::from_raw_parts(str_ptr, cnt);
-						let name_str: &str = str::from_utf8(slice).unwrap();
-						items_to_create_list.push(name_str);
-					}
+							// Create a slice from the raw pointer and length
+							let slice = slice::from_raw_parts(str_ptr, cnt);
+							let name_str: &str = str::from_utf8(slice).unwrap();
+							items_to_create_list.push(name_str);
+						}
 
-					let items_to_create: Vec<MonitoredItemCreateRequest> = items_to_create_list // ! v1 hard coded !
-						.iter()
-						.map(|v| NodeId::new(ns, *v).into())
-						.collect();
+						let items_to_create: Vec<MonitoredItemCreateRequest> = items_to_create_list // ! v1 hard coded !
+							.iter()
+							.map(|v| NodeId::new(ns, *v).into())
+							.collect();
 
-					let _ = rt.block_on(async {
-						session
-							.create_monitored_items(
-								subscription_id,
-								TimestampsToReturn::Both,
-								items_to_create,
-							)
-							.await
-					});
+						let _ = rt.block_on(async {
+							session
+								.create_monitored_items(
+									subscription_id,
+									TimestampsToReturn::Both,
+									items_to_create,
+								)
+								.await
+						});
 
-					*subscription_out = subscription_id;
+						*subscription_out = subscription_id;
+					}
+					Err(_) => return -7, // Error code for read failure
 				}
-				Err(_) => return -7, // Error code for read failure
-			}
-		};
-	}
-	return 0;
+			};
+		}
+		NO_ERR
+	})
 }
 */
 
@@ -545,17 +1250,200 @@ pub extern "C" fn lv_delete_subscription(
 	lv_session: *mut Arc<Session>,
 	sub_id: u32,
 ) -> i32 {
-	let session = unsafe { &mut *lv_session };
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		let session = unsafe { &mut *lv_session };
 
-	if rt_ptr.is_null() {
-		return -2;
+		if rt_ptr.is_null() {
+			return -2;
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			rt.block_on(async {
+				session.delete_subscription(sub_id).await.unwrap();
+			});
+		}
+		crate::subscription::unregister_subscription(session, sub_id);
+		return 0;
+	})
+}
+
+//==============================================================================
+// Read Server_ServerStatus_State to check the session is really alive
+// (the connection state doesn't notice a stale TCP connection).
+// Returns 1 - alive, 0 - timeout, negative - error
+//
+pub async fn keepalive_ping(session: &Session, timeout: Duration) -> i32 {
+	let state_id: NodeId = VariableId::Server_ServerStatus_State.into();
+	let r = tokio::time::timeout(
+		timeout,
+		session.read(&[state_id.into()], TimestampsToReturn::Neither, 0.0),
+	)
+	.await;
+	match r {
+		Ok(Ok(values)) => match values.first() {
+			Some(value) if value.status().is_bad() => value.status().bits() as i32,
+			Some(_) => 1,
+			None => StatusCode::BadUnexpectedError.bits() as i32,
+		},
+		Ok(Err(status)) => status.bits() as i32,
+		Err(_) => 0,
 	}
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_session_keepalive_ping(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	timeout_ms: u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, -ERR_INVALID_RUNTIME);
+		check_null!(session_in, -ERR_INVALID_CLIENT_REF);
 
-	unsafe {
-		let rt = &mut *rt_ptr;
-		rt.block_on(async {
-			session.delete_subscription(sub_id).await.unwrap();
-		});
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			rt.block_on(keepalive_ping(
+				session,
+				Duration::from_millis(timeout_ms as u64),
+			))
+		}
+	})
+}
+
+//==============================================================================
+// Ping the session every check_interval_ms, after max_failures failed pings
+// in a row the result of the last ping (I32, 0 - timeout) is posted to
+// user_event_ref. Posted again only after the session was alive in between.
+// Stop with lv_stop_session_watchdog()
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_start_session_watchdog(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	check_interval_ms: u32,
+	max_failures: u32,
+	user_event_ref: *mut c_void,
+) -> *mut JoinHandle<()> {
+	catch_panic!(std::ptr::null_mut(), {
+		if rt_ptr.is_null() || session_in.is_null() || user_event_ref.is_null() {
+			return std::ptr::null_mut();
+		}
+		if check_interval_ms == 0 || max_failures == 0 {
+			return std::ptr::null_mut();
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = (*session_in).clone();
+			let user_event_ref = user_event_ref as usize; // raw pointers are not Send
+			let interval = Duration::from_millis(check_interval_ms as u64);
+
+			let handle = rt.spawn(async move {
+				let mut ticker = tokio::time::interval(interval);
+				let mut failures = 0;
+				loop {
+					ticker.tick().await;
+					let mut result = keepalive_ping(&session, interval).await;
+					if result == 1 {
+						failures = 0;
+						continue;
+					}
+					failures += 1;
+					if failures == max_failures {
+						PostLVUserEvent(
+							user_event_ref as *mut c_void,
+							&mut result as *mut i32 as *mut c_void,
+						);
+					}
+				}
+			});
+			into_handle(handle, HandleKind::JoinHandle)
+		}
+	})
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_stop_session_watchdog(handle_in: *mut JoinHandle<()>) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_in, ERR_NULL_POINTER);
+
+		unsafe {
+			let handle = from_handle(handle_in);
+			handle.abort();
+		}
+		NO_ERR
+	})
+}
+
+// Post the time of the transition (DBL, Cocoa) to the user event, if any
+fn post_connection_event(user_event_ref: usize) {
+	if user_event_ref != 0 {
+		let mut now = crate::utils::get_current_cocoa_timestamp();
+		unsafe {
+			PostLVUserEvent(
+				user_event_ref as *mut c_void,
+				&mut now as *mut f64 as *mut c_void,
+			);
+		}
 	}
-	return 0;
+}
+
+//==============================================================================
+// Post to connected_event_ref when the session becomes connected (also right
+// away if it is connected now) and to disconnected_event_ref when it loses the
+// connection (disconnected or reconnecting). The events carry the time of the
+// transition (DBL, Cocoa), a null event ref isn't notified.
+// Stop with lv_cancel_connection_watch()
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_connection_event_callback(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	connected_event_ref: *mut c_void,
+	disconnected_event_ref: *mut c_void,
+	handle_out: *mut *mut JoinHandle<()>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_runtime!(rt_ptr);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(handle_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = (*session_in).clone();
+			// raw pointers are not Send
+			let connected_event_ref = connected_event_ref as usize;
+			let disconnected_event_ref = disconnected_event_ref as usize;
+
+			let handle = rt.spawn(async move {
+				loop {
+					if !session.wait_for_connection().await {
+						break;
+					}
+					post_connection_event(connected_event_ref);
+					if !session.wait_for_connection_lost().await {
+						break;
+					}
+					post_connection_event(disconnected_event_ref);
+				}
+			});
+			*handle_out = into_handle(handle, HandleKind::JoinHandle);
+		}
+		NO_ERR
+	})
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_cancel_connection_watch(handle_in: *mut JoinHandle<()>) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_in, ERR_NULL_POINTER);
+
+		unsafe {
+			let handle = from_handle(handle_in);
+			handle.abort();
+		}
+		NO_ERR
+	})
 }
@@ -0,0 +1,340 @@
+//==============================================================================
+//
+// Title:		Bulk read of attributes
+// Purpose:		Read a set of attributes of many nodes (e.g. for a tag
+//				import) in as few Read calls as the server allows
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+use crate::errors::*;
+use crate::labview::{
+	DSDisposeHandleLStr, LStrArrayHandle, LStrHandle, LvArrayHandle, lstr_array_to_strings,
+	lv_array_as_slice, string_to_new_lstr, write_lv_array,
+};
+use crate::subscription::{variant_to_f64, variant_to_string};
+use crate::utils::operation_limit;
+
+use opcua::{
+	client::Session,
+	types::{
+		AttributeId, DataValue, NodeId, ReadValueId, StatusCode, TimestampsToReturn, VariableId,
+	},
+};
+use std::{collections::VecDeque, ops::Range, str::FromStr, sync::Arc};
+use tokio::runtime::Runtime;
+
+// Used when the server doesn't report MaxNodesPerRead (0 means "no limit")
+const DEFAULT_READ_CHUNK_SIZE: usize = 1000;
+
+//==============================================================================
+// Result of one attribute of one node
+//
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+pub struct LvAttributeResult {
+	status: u32,
+	value: f64,       // NaN if not numeric
+	text: LStrHandle, // value as text
+}
+
+#[cfg(target_arch = "x86")]
+#[repr(C, packed(1))]
+pub struct LvAttributeResult {
+	status: u32,
+	value: f64,
+	text: LStrHandle,
+}
+
+// The request was too big for the server, worth retrying in smaller parts
+fn is_too_large(status: StatusCode) -> bool {
+	matches!(
+		status,
+		StatusCode::BadTooManyOperations
+			| StatusCode::BadEncodingLimitsExceeded
+			| StatusCode::BadResponseTooLarge
+			| StatusCode::BadRequestTooLarge
+	)
+}
+
+// No later chunk can succeed either
+fn is_connection_lost(status: StatusCode) -> bool {
+	matches!(
+		status,
+		StatusCode::BadNotConnected
+			| StatusCode::BadConnectionClosed
+			| StatusCode::BadSecureChannelClosed
+			| StatusCode::BadSessionClosed
+			| StatusCode::BadSessionIdInvalid
+	)
+}
+
+struct BulkRead {
+	values: Vec<DataValue>,
+	failed_chunks: u32,
+	connection_lost: Option<StatusCode>,
+}
+
+//==============================================================================
+// Read the items in chunks of at most chunk_size. A chunk rejected as too
+// large is split in halves and retried, a chunk failing otherwise gets its
+// status for all its items and the next chunk is read. After the connection
+// is lost the remaining items get that status without further requests
+//
+async fn read_chunked(session: &Session, items: &[ReadValueId], chunk_size: usize) -> BulkRead {
+	let mut read = BulkRead {
+		values: vec![DataValue::null(); items.len()],
+		failed_chunks: 0,
+		connection_lost: None,
+	};
+	let mut pending: VecDeque<Range<usize>> = (0..items.len())
+		.step_by(chunk_size)
+		.map(|start| start..(start + chunk_size).min(items.len()))
+		.collect();
+
+	while let Some(chunk) = pending.pop_front() {
+		if let Some(status) = read.connection_lost {
+			fail_chunk(&mut read.values[chunk], status);
+			continue;
+		}
+		let status = match session
+			.read(&items[chunk.clone()], TimestampsToReturn::Neither, 0.0)
+			.await
+		{
+			Ok(values) if values.len() == chunk.len() => {
+				read.values[chunk].clone_from_slice(&values);
+				continue;
+			}
+			Ok(_) => StatusCode::BadUnexpectedError, // response doesn't match the request
+			Err(status) => status,
+		};
+
+		if is_too_large(status) && chunk.len() > 1 {
+			let middle = chunk.start + chunk.len() / 2;
+			pending.push_front(middle..chunk.end);
+			pending.push_front(chunk.start..middle);
+			continue;
+		}
+		if is_connection_lost(status) {
+			read.connection_lost = Some(status);
+		}
+		read.failed_chunks += 1;
+		fail_chunk(&mut read.values[chunk], status);
+	}
+	read
+}
+
+fn fail_chunk(values: &mut [DataValue], status: StatusCode) {
+	for value in values {
+		value.status = Some(status);
+	}
+}
+
+//==============================================================================
+// Read every attribute of attribute_ids_in (attribute_count AttributeIds, e.g.
+// 13 - Value, 14 - DataType, 4 - BrowseName) of every node of node_ids_array
+// (full node ids as "ns=2;s=Tank1" or "i=2258", the namespace of each node
+// its own). results_hdl is resized to nodes x attributes, the result of node
+// n, attribute a is at n * attribute_count + a.
+// chunk_size = 0 means "use server's MaxNodesPerRead".
+// failed_chunks_out (may be null) receives the number of failed Read calls,
+// their items have the status of the call.
+// Returns the status if the connection was lost, NO_ERR otherwise.
+// ERR_INVALID_ARGUMENT if an element isn't a node id (the detail names it).
+// Release the strings with lv_free_attribute_results
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_read_attributes_bulk(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	node_ids_array: LStrArrayHandle,
+	attribute_ids_in: *const u32,
+	attribute_count: i32,
+	chunk_size: u32,
+	results_hdl: LvArrayHandle<LvAttributeResult>,
+	failed_chunks_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_ids_array, ERR_NULL_POINTER);
+		check_null!(attribute_ids_in, ERR_NULL_POINTER);
+		check_null!(results_hdl, ERR_NULL_POINTER);
+		if attribute_count <= 0 {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		let attribute_ids =
+			unsafe { std::slice::from_raw_parts(attribute_ids_in, attribute_count as usize) };
+		if let Some(id) = attribute_ids
+			.iter()
+			.find(|id| AttributeId::from_u32(**id).is_err())
+		{
+			set_last_error_detail(format!("Invalid AttributeId {id}"));
+			return ERR_INVALID_ARGUMENT;
+		}
+		let mut nodes = Vec::new();
+		for (i, text) in unsafe { lstr_array_to_strings(node_ids_array) }
+			.iter()
+			.enumerate()
+		{
+			match NodeId::from_str(text.trim()) {
+				Ok(node_id) => nodes.push(node_id),
+				Err(_) => {
+					set_last_error_detail(format!("Element {i} '{text}' is not a node id"));
+					return ERR_INVALID_ARGUMENT;
+				}
+			}
+		}
+		if nodes.is_empty() {
+			return ERR_INVALID_ARGUMENT;
+		}
+		let items: Vec<ReadValueId> = nodes
+			.iter()
+			.flat_map(|node_id| {
+				attribute_ids.iter().map(|attribute_id| ReadValueId {
+					node_id: node_id.clone(),
+					attribute_id: *attribute_id,
+					..Default::default()
+				})
+			})
+			.collect();
+
+		unsafe {
+			let rt = &*rt_ptr;
+			let session = &*session_in;
+			let read = rt.block_on(async {
+				let chunk_size =
+					match chunk_size {
+						0 => operation_limit(
+							session,
+							VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerRead,
+							DEFAULT_READ_CHUNK_SIZE,
+						)
+						.await,
+						n => n as usize,
+					};
+				read_chunked(session, &items, chunk_size).await
+			});
+
+			let n = read.values.len();
+			let results = read.values.iter().map(|dv| LvAttributeResult {
+				status: dv.status().bits(),
+				value: dv.value.as_ref().map(variant_to_f64).unwrap_or(f64::NAN),
+				text: string_to_new_lstr(
+					&dv.value.as_ref().map(variant_to_string).unwrap_or_default(),
+				),
+			});
+			let err = write_lv_array(results_hdl, results);
+			if err != 0 {
+				return err; // LabVIEW memory error
+			}
+
+			if !failed_chunks_out.is_null() {
+				*failed_chunks_out = read.failed_chunks;
+			}
+			match read.connection_lost {
+				Some(status) => {
+					set_last_error_detail(format!(
+						"Connection lost during bulk read: {status}, {} of {n} results are missing",
+						read.values
+							.iter()
+							.filter(|dv| dv.status() == status)
+							.count()
+					));
+					status.bits() as i32
+				}
+				None => NO_ERR,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Dispose the strings of the results from lv_read_attributes_bulk,
+// the array is left empty (the handle itself belongs to LabVIEW)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_free_attribute_results(results_hdl: LvArrayHandle<LvAttributeResult>) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(results_hdl, ERR_NULL_POINTER);
+
+		unsafe {
+			if (*results_hdl).is_null() {
+				return ERR_NULL_POINTER;
+			}
+			for result in lv_array_as_slice(results_hdl) {
+				let text = result.text;
+				if !text.is_null() {
+					DSDisposeHandleLStr(text);
+				}
+			}
+			(**results_hdl).dim_size = 0;
+		}
+		NO_ERR
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::labview::{
+		DSDisposeHandle, dispose_lstr_array, lstr_to_string, new_lv_array,
+		strings_to_new_lstr_array,
+	};
+	use crate::test_server::{TestClient, TestServer};
+
+	// Results of the nodes for Value and BrowseName, or the error
+	fn read_bulk(client: &TestClient, nodes: &[String]) -> Result<Vec<(u32, String)>, i32> {
+		unsafe {
+			let node_ids = strings_to_new_lstr_array(nodes);
+			let results = new_lv_array::<LvAttributeResult>(std::iter::empty());
+			let attribute_ids = [AttributeId::Value as u32, AttributeId::BrowseName as u32];
+			let err = lv_read_attributes_bulk(
+				client.rt_ptr,
+				client.session_ptr,
+				node_ids,
+				attribute_ids.as_ptr(),
+				attribute_ids.len() as i32,
+				0,
+				results,
+				std::ptr::null_mut(),
+			);
+			let read = lv_array_as_slice(results)
+				.iter()
+				.map(|r| (r.status, lstr_to_string(r.text)))
+				.collect();
+			assert_eq!(lv_free_attribute_results(results), NO_ERR);
+			DSDisposeHandle(results);
+			dispose_lstr_array(node_ids);
+			if err == NO_ERR { Ok(read) } else { Err(err) }
+		}
+	}
+
+	#[test]
+	fn bulk_read_of_node_ids_in_several_namespaces() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		assert_eq!(server.add_variable("Tank1", 11), NO_ERR);
+
+		let nodes = [
+			format!("ns={};s=Tank1", server.ns),
+			"i=2256".to_string(), // Server_ServerStatus
+			format!("ns={};s=Missing", server.ns),
+		];
+		let results = read_bulk(&client, &nodes).unwrap();
+		assert_eq!(results.len(), nodes.len() * 2);
+		// Value not written yet, BrowseName
+		assert_eq!(results[0].0, StatusCode::BadWaitingForInitialData.bits());
+		assert_eq!(results[1], (0, "Tank1".to_string()));
+		assert_eq!(results[2].0, 0);
+		assert_eq!(results[3], (0, "ServerStatus".to_string()));
+		assert_eq!(results[4].0, StatusCode::BadNodeIdUnknown.bits());
+		assert_eq!(results[5].0, StatusCode::BadNodeIdUnknown.bits());
+
+		let nodes = ["i=2256".to_string(), "Tank1;ns=2".to_string()];
+		assert_eq!(read_bulk(&client, &nodes), Err(ERR_INVALID_ARGUMENT));
+		assert_eq!(read_bulk(&client, &[]), Err(ERR_INVALID_ARGUMENT));
+	}
+}
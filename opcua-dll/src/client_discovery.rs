@@ -0,0 +1,529 @@
+//==============================================================================
+//
+// Title:		Discovery
+// Purpose:		Ask a Local Discovery Server (LDS-ME) for the OPC UA servers
+//				it found on the network, get the endpoints of a server and
+//				its certificate before connecting
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+use crate::errors::*;
+use crate::labview::{
+	DSDisposeHandleLStr, LStrHandle, LvArrayHandle, bytes_to_lstr, lstr_to_bytes,
+	lv_array_as_slice, string_to_lstr, string_to_new_lstr, write_lv_array,
+};
+
+use libc::c_char;
+use opcua::{
+	client::Client,
+	crypto::{AltName, CertificateStore, X509},
+	types::{EndpointDescription, StatusCode},
+};
+use tokio::runtime::Runtime;
+
+// Local Discovery Server on this machine, the standard LDS port
+const DEFAULT_DISCOVERY_URL: &str = "opc.tcp://localhost:4840";
+
+//==============================================================================
+// FindServersOnNetwork on the discovery server (null discovery_url_str -
+// opc.tcp://localhost:4840). results_lv_str gets one line per server,
+// the fields separated by tabs:
+//   <server name>\t<discovery URL>\t<capabilities, comma separated>
+// e.g. "MyServer\topc.tcp://host:4840\tDA,HD". Capabilities are the
+// identifiers of OPC 10000-12 Annex D (DA, HD, AC, LDS...), may be empty.
+// ERR_BROWSE_ERROR if the discovery server is unreachable or doesn't
+// support the service, the reason is in the last error detail
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_find_servers_on_network(
+	rt_ptr: *mut Runtime,
+	lv_client: *mut Client,
+	discovery_url_str: *const c_char,
+	results_lv_str: LStrHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(lv_client, ERR_INVALID_CLIENT_REF);
+		check_null!(results_lv_str, ERR_NULL_POINTER);
+
+		let discovery_url = if discovery_url_str.is_null() {
+			DEFAULT_DISCOVERY_URL.to_string()
+		} else {
+			cstr_to_string!(discovery_url_str)
+		};
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let client = &mut *lv_client;
+			let response = rt.block_on(async {
+				client
+					.find_servers_on_network(discovery_url.as_str(), 0, 0, None)
+					.await
+			});
+			let servers = match response {
+				Ok(response) => response.servers.unwrap_or_default(),
+				Err(status) => {
+					set_last_error_detail(format!(
+						"FindServersOnNetwork on {discovery_url} failed: {status}"
+					));
+					return ERR_BROWSE_ERROR;
+				}
+			};
+
+			let lines: Vec<String> = servers
+				.iter()
+				.map(|server| {
+					let capabilities: Vec<&str> = server
+						.server_capabilities
+						.iter()
+						.flatten()
+						.map(|c| c.as_ref())
+						.collect();
+					format!(
+						"{}\t{}\t{}",
+						server.server_name.as_ref(),
+						server.discovery_url.as_ref(),
+						capabilities.join(",")
+					)
+				})
+				.collect();
+			string_to_lstr(&lines.join("\n"), results_lv_str)
+		}
+	})
+}
+
+//==============================================================================
+// Endpoint of the server, array allocated by LabVIEW
+// security_mode: 1 - None, 2 - Sign, 3 - SignAndEncrypt
+// user_token_types: bit set of 1 - Anonymous, 2 - UserName, 4 - Certificate,
+// 8 - IssuedToken
+//
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+pub struct LvEndpointInfo {
+	security_policy: LStrHandle, // URI
+	security_mode: u32,
+	user_token_types: u32,
+	endpoint_url: LStrHandle,
+}
+
+#[cfg(target_arch = "x86")]
+#[repr(C, packed(1))]
+pub struct LvEndpointInfo {
+	security_policy: LStrHandle,
+	security_mode: u32,
+	user_token_types: u32,
+	endpoint_url: LStrHandle,
+}
+
+fn user_token_types(endpoint: &EndpointDescription) -> u32 {
+	endpoint
+		.user_identity_tokens
+		.iter()
+		.flatten()
+		.fold(0, |types, token| types | 1 << token.token_type as u32)
+}
+
+//==============================================================================
+// GetEndpoints of the server at url (no session needed), endpoint_array_hdl
+// is resized to the number of endpoints. Release the strings of the
+// endpoints with lv_free_endpoint_array. Returns the count of endpoints
+// or the status code of the failed request
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_endpoints_structured(
+	rt_ptr: *mut Runtime,
+	lv_client: *mut Client,
+	url: *const c_char,
+	endpoint_array_hdl: LvArrayHandle<LvEndpointInfo>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(lv_client, ERR_INVALID_CLIENT_REF);
+		check_null!(url, ERR_NULL_POINTER);
+		check_null!(endpoint_array_hdl, ERR_NULL_POINTER);
+
+		let url = cstr_to_string!(url);
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let client = &mut *lv_client;
+			let endpoints = match rt.block_on(client.get_server_endpoints_from_url(url.as_str())) {
+				Ok(endpoints) => endpoints,
+				Err(status) => {
+					set_last_error_detail(format!("GetEndpoints on {url} failed: {status}"));
+					return status.bits() as i32;
+				}
+			};
+
+			let n = endpoints.len();
+			let infos = endpoints.iter().map(|endpoint| LvEndpointInfo {
+				security_policy: string_to_new_lstr(endpoint.security_policy_uri.as_ref()),
+				security_mode: endpoint.security_mode as u32,
+				user_token_types: user_token_types(endpoint),
+				endpoint_url: string_to_new_lstr(endpoint.endpoint_url.as_ref()),
+			});
+			let err = write_lv_array(endpoint_array_hdl, infos);
+			if err != 0 {
+				return err; // LabVIEW memory error
+			}
+			n as i32
+		}
+	})
+}
+
+//==============================================================================
+// Dispose the strings of the endpoints from lv_get_endpoints_structured,
+// the array is left empty (the handle itself belongs to LabVIEW)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_free_endpoint_array(endpoint_array_hdl: LvArrayHandle<LvEndpointInfo>) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(endpoint_array_hdl, ERR_NULL_POINTER);
+
+		unsafe {
+			if (*endpoint_array_hdl).is_null() {
+				return ERR_NULL_POINTER;
+			}
+			for endpoint in lv_array_as_slice(endpoint_array_hdl) {
+				for s in [endpoint.security_policy, endpoint.endpoint_url] {
+					if !s.is_null() {
+						DSDisposeHandleLStr(s);
+					}
+				}
+			}
+			(**endpoint_array_hdl).dim_size = 0;
+		}
+		NO_ERR
+	})
+}
+
+// Unsecured GetEndpoints failed because the server wants a secure channel
+fn is_security_rejected(status: StatusCode) -> bool {
+	matches!(
+		status,
+		StatusCode::BadSecurityPolicyRejected
+			| StatusCode::BadSecurityModeRejected
+			| StatusCode::BadSecurityChecksFailed
+	)
+}
+
+fn alt_name_parts(name: AltName) -> (&'static str, String) {
+	match name {
+		AltName::Uri(uri) => ("URI", uri),
+		AltName::Dns(host) => ("DNS", host),
+		AltName::Ip(ip) => ("IP", ip.to_string()),
+		AltName::Email(email) => ("Email", email),
+		AltName::Directory(dn) => ("Directory", dn),
+		AltName::Other => ("Other", String::new()),
+	}
+}
+
+fn alt_name_json(name: AltName) -> serde_json::Value {
+	let (kind, value) = alt_name_parts(name);
+	serde_json::json!({ "type": kind, "value": value })
+}
+
+fn cert_summary_json(cert: &X509) -> serde_json::Value {
+	let alt_names: Vec<serde_json::Value> = cert
+		.alternate_names()
+		.map(|names| names.iter_typed().map(alt_name_json).collect())
+		.unwrap_or_default();
+	serde_json::json!({
+		"subject": cert.subject_name(),
+		"thumbprint": cert.thumbprint().as_hex_string(),
+		"applicationUri": cert.application_uri(),
+		"notBefore": cert.not_before().ok().map(|t| t.to_rfc3339()),
+		"notAfter": cert.not_after().ok().map(|t| t.to_rfc3339()),
+		"alternateNames": alt_names,
+	})
+}
+
+//==============================================================================
+// Certificate of the server at url without connecting: GetEndpoints over an
+// unsecured channel, the certificate is taken from the first endpoint which
+// has one. der_lv_str gets the DER (for lv_trust_server_certificate),
+// info_lv_str a JSON summary:
+//   {"subject", "thumbprint" (SHA-1, hex), "applicationUri", "notBefore",
+//    "notAfter" (RFC 3339), "alternateNames": [{"type", "value"}]}
+// ERR_CERT_NOT_FETCHED if the server accepts only secured discovery or
+// returns no valid certificate (e.g. only None endpoints), otherwise the status code
+// of the failed request
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_fetch_server_certificate(
+	rt_ptr: *mut Runtime,
+	lv_client: *mut Client,
+	url: *const c_char,
+	der_lv_str: LStrHandle,
+	info_lv_str: LStrHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(lv_client, ERR_INVALID_CLIENT_REF);
+		check_null!(url, ERR_NULL_POINTER);
+		check_null!(der_lv_str, ERR_NULL_POINTER);
+		check_null!(info_lv_str, ERR_NULL_POINTER);
+
+		let url = cstr_to_string!(url);
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let client = &mut *lv_client;
+			let endpoints = match rt.block_on(client.get_server_endpoints_from_url(url.as_str())) {
+				Ok(endpoints) => endpoints,
+				Err(status) if is_security_rejected(status) => {
+					set_last_error_detail(format!(
+						"{url} rejects unsecured GetEndpoints ({status}), the certificate can't be pre-fetched"
+					));
+					return ERR_CERT_NOT_FETCHED;
+				}
+				Err(status) => {
+					set_last_error_detail(format!("GetEndpoints on {url} failed: {status}"));
+					return status.bits() as i32;
+				}
+			};
+			let Some(der) = endpoints
+				.iter()
+				.map(|e| &e.server_certificate)
+				.find(|cert| !cert.is_null_or_empty())
+			else {
+				set_last_error_detail(format!(
+					"{url} returned no server certificate with its {} endpoint(s)",
+					endpoints.len()
+				));
+				return ERR_CERT_NOT_FETCHED;
+			};
+			let cert = match X509::from_byte_string(der) {
+				Ok(cert) => cert,
+				Err(e) => {
+					set_last_error_detail(format!("The certificate of {url} can't be parsed: {e}"));
+					return ERR_CERT_NOT_FETCHED;
+				}
+			};
+
+			let err = bytes_to_lstr(der.as_ref(), der_lv_str);
+			if err != NO_ERR {
+				return err;
+			}
+			string_to_lstr(&cert_summary_json(&cert).to_string(), info_lv_str)
+		}
+	})
+}
+
+//==============================================================================
+// Put the server certificate (DER from lv_fetch_server_certificate) into the
+// trusted folder of the client pki, e.g. after the operator accepted it.
+// ERR_CERT_INVALID if der_lv_str isn't a certificate, ERR_IO if it
+// can't be written
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_trust_server_certificate(
+	lv_client: *mut Client,
+	der_lv_str: LStrHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(lv_client, ERR_INVALID_CLIENT_REF);
+		check_null!(der_lv_str, ERR_NULL_POINTER);
+
+		let der = unsafe { lstr_to_bytes(der_lv_str) };
+		let cert = match X509::from_der(&der) {
+			Ok(cert) => cert,
+			Err(e) => {
+				set_last_error_detail(format!("Not a DER certificate: {e}"));
+				return ERR_CERT_INVALID;
+			}
+		};
+		let client = unsafe { &*lv_client };
+		let store = client.certificate_store().read();
+		if let Err(e) = store.ensure_pki_path() {
+			set_last_error_detail(e);
+			return ERR_IO;
+		}
+		let path = store
+			.trusted_certs_dir()
+			.join(CertificateStore::cert_file_name(&cert));
+		match std::fs::write(&path, &der) {
+			Ok(()) => NO_ERR,
+			Err(e) => {
+				set_last_error_detail(format!("{}: {e}", path.display()));
+				ERR_IO
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Check a server certificate (cert_len bytes of DER) before connecting:
+// validity period (check_time = 0 skips it, e.g. for expired test
+// certificates), host name and application URI against its alternate names.
+// result_code_out receives the status code of the first failed check, 0 if
+// all pass. ERR_CERT_INVALID if the bytes aren't a certificate
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_verify_certificate(
+	cert_der_ptr: *const u8,
+	cert_len: u32,
+	app_uri_str: *const c_char,
+	hostname_str: *const c_char,
+	check_time: u8,
+	result_code_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(cert_der_ptr, ERR_NULL_POINTER);
+		check_null!(app_uri_str, ERR_NULL_POINTER);
+		check_null!(hostname_str, ERR_NULL_POINTER);
+		check_null!(result_code_out, ERR_NULL_POINTER);
+
+		let cert = match cert_from_ptr(cert_der_ptr, cert_len) {
+			Ok(cert) => cert,
+			Err(err) => return err,
+		};
+		let app_uri = cstr_to_string!(app_uri_str);
+		let hostname = cstr_to_string!(hostname_str);
+
+		let result = if check_time != 0 {
+			cert.is_time_valid(&chrono::Utc::now())
+				.map_err(|status| (status, "The certificate is not valid now".to_string()))
+		} else {
+			Ok(())
+		}
+		.and_then(|_| {
+			cert.is_hostname_valid(&hostname).map_err(|status| {
+				(
+					status,
+					format!("The certificate is not for host {hostname}"),
+				)
+			})
+		})
+		.and_then(|_| {
+			cert.is_application_uri_valid(&app_uri).map_err(|status| {
+				(
+					status,
+					format!("The certificate is not for application {app_uri}"),
+				)
+			})
+		});
+
+		let status = match result {
+			Ok(()) => StatusCode::Good,
+			Err((status, detail)) => {
+				set_last_error_detail(detail);
+				status
+			}
+		};
+		unsafe { *result_code_out = status.bits() };
+		NO_ERR
+	})
+}
+
+// Certificate of cert_len bytes of DER at cert_der_ptr
+fn cert_from_ptr(cert_der_ptr: *const u8, cert_len: u32) -> Result<X509, i32> {
+	let der = unsafe { std::slice::from_raw_parts(cert_der_ptr, cert_len as usize) };
+	X509::from_der(der).map_err(|e| {
+		set_last_error_detail(format!("Not a DER certificate: {e}"));
+		ERR_CERT_INVALID
+	})
+}
+
+//==============================================================================
+// Common name (CN) of the certificate subject, empty if it has none.
+// ERR_CERT_INVALID if the bytes aren't a certificate
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_certificate_common_name(
+	cert_der_ptr: *const u8,
+	cert_len: u32,
+	cn_lv_str: LStrHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(cert_der_ptr, ERR_NULL_POINTER);
+		check_null!(cn_lv_str, ERR_NULL_POINTER);
+
+		let cert = match cert_from_ptr(cert_der_ptr, cert_len) {
+			Ok(cert) => cert,
+			Err(err) => return err,
+		};
+		let common_name = cert.common_name().unwrap_or_default();
+		unsafe { string_to_lstr(&common_name, cn_lv_str) }
+	})
+}
+
+//==============================================================================
+// Subject alternative names of the certificate, one per line with its type:
+//   URI:urn:host:MyServer
+//   DNS:host
+//   IP:192.168.1.10
+// (also Email:, Directory:, Other:), empty if the certificate has none.
+// ERR_CERT_INVALID if the bytes aren't a certificate
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_certificate_alt_names(
+	cert_der_ptr: *const u8,
+	cert_len: u32,
+	alt_names_lv_str: LStrHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(cert_der_ptr, ERR_NULL_POINTER);
+		check_null!(alt_names_lv_str, ERR_NULL_POINTER);
+
+		let cert = match cert_from_ptr(cert_der_ptr, cert_len) {
+			Ok(cert) => cert,
+			Err(err) => return err,
+		};
+		let lines: Vec<String> = cert
+			.alternate_names()
+			.map(|names| {
+				names
+					.iter_typed()
+					.map(|name| {
+						let (kind, value) = alt_name_parts(name);
+						format!("{kind}:{value}")
+					})
+					.collect()
+			})
+			.unwrap_or_default();
+		unsafe { string_to_lstr(&lines.join("\n"), alt_names_lv_str) }
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_server::TestServer;
+
+	fn verify(der: &[u8], hostname: &std::ffi::CStr) -> (i32, u32) {
+		let mut result_code = u32::MAX;
+		let err = lv_verify_certificate(
+			der.as_ptr(),
+			der.len() as u32,
+			c"urn:opcua-dll-test-server".as_ptr(),
+			hostname.as_ptr(),
+			1,
+			&mut result_code,
+		);
+		(err, result_code)
+	}
+
+	#[test]
+	fn verify_certificate() {
+		let server = TestServer::start();
+		let cert = server
+			.handle()
+			.info()
+			.server_certificate
+			.load_full()
+			.unwrap();
+		let der = cert.to_der().unwrap();
+		let (err, result_code) = verify(&der, c"other.invalid");
+		assert_eq!(err, NO_ERR, "{}", last_error_detail());
+		assert_eq!(
+			result_code,
+			StatusCode::BadCertificateHostNameInvalid.bits()
+		);
+
+		// Malformed DER, as the other certificate functions
+		assert_eq!(verify(&der[..der.len() / 2], c"").0, ERR_CERT_INVALID);
+		assert_eq!(verify(b"not a certificate", c"").0, ERR_CERT_INVALID);
+	}
+}
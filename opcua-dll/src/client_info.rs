@@ -0,0 +1,312 @@
+//==============================================================================
+//
+// Title:		OPC UA Server information for the client
+// Purpose:		BuildInfo, application description and certificate of the
+//				connected server
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+use crate::errors::*;
+use crate::labview::{LStrHandle, string_to_lstr};
+use crate::utils::date_time_to_cocoa;
+
+use libc::c_char;
+use opcua::{
+	client::Session,
+	crypto::X509Error,
+	types::{DateTime, NodeId, ReadValueId, TimestampsToReturn, VariableId, Variant},
+};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+// match_out of lv_compare_thumbprint()
+pub const THUMBPRINT_MISMATCH: i32 = 0;
+pub const THUMBPRINT_MATCH: i32 = 1;
+pub const THUMBPRINT_NO_CERTIFICATE: i32 = -1;
+
+// Certificate of the server, see lv_get_session_server_cert_info()
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+pub struct LvServerCertInfo {
+	exchanged: u32, // 0 - no certificate (unsecured connection), the other fields are empty
+	thumbprint: LStrHandle, // SHA-1, hex
+	subject: LStrHandle,
+	application_uri: LStrHandle,
+	not_before: f64, // Cocoa
+	not_after: f64,
+}
+
+#[cfg(target_arch = "x86")]
+#[repr(C, packed(1))]
+pub struct LvServerCertInfo {
+	exchanged: u32,
+	thumbprint: LStrHandle,
+	subject: LStrHandle,
+	application_uri: LStrHandle,
+	not_before: f64,
+	not_after: f64,
+}
+
+fn cert_date_to_cocoa(date: Result<chrono::DateTime<chrono::Utc>, X509Error>) -> f64 {
+	match date {
+		Ok(date) => date_time_to_cocoa(&DateTime::from(date)),
+		Err(_) => 0.0,
+	}
+}
+
+// Hex digits only, lowercase, e.g. "AB:CD ..." as shown by certificate viewers
+fn normalize_thumbprint(hex: &str) -> String {
+	hex.chars()
+		.filter(char::is_ascii_hexdigit)
+		.map(|c| c.to_ascii_lowercase())
+		.collect()
+}
+
+//==============================================================================
+// Product name, software version, build number and build date (Cocoa)
+// from Server_ServerStatus_BuildInfo. ERR_BROWSE_ERROR if any is unreadable
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_server_build_info(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	product_name_out: LStrHandle,
+	sw_version_out: LStrHandle,
+	build_number_out: LStrHandle,
+	build_date_out: *mut f64,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(product_name_out, ERR_NULL_POINTER);
+		check_null!(sw_version_out, ERR_NULL_POINTER);
+		check_null!(build_number_out, ERR_NULL_POINTER);
+		check_null!(build_date_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+
+			let nodes: Vec<ReadValueId> = [
+				VariableId::Server_ServerStatus_BuildInfo_ProductName,
+				VariableId::Server_ServerStatus_BuildInfo_SoftwareVersion,
+				VariableId::Server_ServerStatus_BuildInfo_BuildNumber,
+				VariableId::Server_ServerStatus_BuildInfo_BuildDate,
+			]
+			.into_iter()
+			.map(|id| NodeId::from(id).into())
+			.collect();
+
+			let r =
+				rt.block_on(async { session.read(&nodes, TimestampsToReturn::Neither, 0.0).await });
+			let values = match r {
+				Ok(values) if values.len() == nodes.len() => values,
+				Ok(_) => return ERR_BROWSE_ERROR,
+				Err(status) => return status.bits() as i32,
+			};
+
+			let (
+				Some(Variant::String(product_name)),
+				Some(Variant::String(sw_version)),
+				Some(Variant::String(build_number)),
+				Some(Variant::DateTime(build_date)),
+			) = (
+				&values[0].value,
+				&values[1].value,
+				&values[2].value,
+				&values[3].value,
+			)
+			else {
+				return ERR_BROWSE_ERROR;
+			};
+
+			for (s, lv_str) in [
+				(product_name, product_name_out),
+				(sw_version, sw_version_out),
+				(build_number, build_number_out),
+			] {
+				let err = string_to_lstr(s.as_ref(), lv_str);
+				if err != NO_ERR {
+					return err;
+				}
+			}
+			*build_date_out = date_time_to_cocoa(build_date);
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Application name, application URI, product URI and type of the server
+// from the endpoint description the session was created for.
+// app_type_out: 0 - Server, 1 - Client, 2 - ClientAndServer, 3 - DiscoveryServer
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_server_application_description(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	app_name_out: LStrHandle,
+	app_uri_out: LStrHandle,
+	product_uri_out: LStrHandle,
+	app_type_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(app_name_out, ERR_NULL_POINTER);
+		check_null!(app_uri_out, ERR_NULL_POINTER);
+		check_null!(product_uri_out, ERR_NULL_POINTER);
+		check_null!(app_type_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let session = &mut *session_in;
+			let server = &session.endpoint().server;
+
+			for (s, lv_str) in [
+				(server.application_name.text.as_ref(), app_name_out),
+				(server.application_uri.as_ref(), app_uri_out),
+				(server.product_uri.as_ref(), product_uri_out),
+			] {
+				let err = string_to_lstr(s, lv_str);
+				if err != NO_ERR {
+					return err;
+				}
+			}
+			*app_type_out = server.application_type as u32;
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Thumbprint (SHA-1, hex), subject, application URI and validity (Cocoa) of
+// the certificate the server presented for the session. For an unsecured
+// connection no certificate is exchanged: exchanged is 0, the strings are
+// empty and the dates 0
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_session_server_cert_info(
+	session_in: *mut Arc<Session>,
+	info_out: *mut LvServerCertInfo,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(info_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let session = &*session_in;
+			let info = &mut *info_out;
+			let cert = session.channel().remote_cert();
+			let (thumbprint, subject, application_uri) = match &cert {
+				Some(cert) => (
+					cert.thumbprint().as_hex_string(),
+					cert.subject_name(),
+					cert.application_uri().unwrap_or_default(),
+				),
+				None => Default::default(),
+			};
+			for (s, lv_str) in [
+				(thumbprint, info.thumbprint),
+				(subject, info.subject),
+				(application_uri, info.application_uri),
+			] {
+				let err = string_to_lstr(&s, lv_str);
+				if err != NO_ERR {
+					return err;
+				}
+			}
+			info.exchanged = cert.is_some() as u32;
+			(info.not_before, info.not_after) = match &cert {
+				Some(cert) => (
+					cert_date_to_cocoa(cert.not_before()),
+					cert_date_to_cocoa(cert.not_after()),
+				),
+				None => (0.0, 0.0),
+			};
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Compare the thumbprint of the server certificate of the session with
+// expected_hex_str (SHA-1, hex, case and separators like ':' or ' ' don't
+// matter). match_out: THUMBPRINT_MATCH, THUMBPRINT_MISMATCH or
+// THUMBPRINT_NO_CERTIFICATE for an unsecured connection
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_compare_thumbprint(
+	session_in: *mut Arc<Session>,
+	expected_hex_str: *const c_char,
+	match_out: *mut i32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(expected_hex_str, ERR_NULL_POINTER);
+		check_null!(match_out, ERR_NULL_POINTER);
+
+		let expected = normalize_thumbprint(&cstr_to_string!(expected_hex_str));
+		unsafe {
+			let session = &*session_in;
+			*match_out = match session.channel().remote_cert() {
+				Some(cert) if cert.thumbprint().as_hex_string() == expected => THUMBPRINT_MATCH,
+				Some(_) => THUMBPRINT_MISMATCH,
+				None => THUMBPRINT_NO_CERTIFICATE,
+			};
+		}
+		NO_ERR
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::labview::{DSDisposeHandleLStr, lstr_to_string, string_to_new_lstr};
+	use crate::test_server::{TestClient, TestServer};
+
+	// The BuildInfo of lvServerBuilder (ss in server.rs)
+	#[test]
+	fn build_info_of_the_server() {
+		let before = date_time_to_cocoa(&DateTime::now());
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		unsafe {
+			let strings = [(); 3].map(|_| string_to_new_lstr("previous"));
+			let mut build_date = 0.0;
+			let err = lv_get_server_build_info(
+				client.rt_ptr,
+				client.session_ptr,
+				strings[0],
+				strings[1],
+				strings[2],
+				&mut build_date,
+			);
+			assert_eq!(err, NO_ERR, "{}", last_error_detail());
+			assert_eq!(
+				strings.map(|s| lstr_to_string(s)),
+				["Rust OPC-UA sample server", "0.1.0", "1"]
+			);
+			// Set when the server was built
+			assert!(
+				build_date >= before.floor() && build_date <= date_time_to_cocoa(&DateTime::now())
+			);
+
+			assert_eq!(
+				lv_get_server_build_info(
+					client.rt_ptr,
+					client.session_ptr,
+					strings[0],
+					std::ptr::null_mut(),
+					strings[2],
+					&mut build_date,
+				),
+				ERR_NULL_POINTER
+			);
+			for s in strings {
+				DSDisposeHandleLStr(s);
+			}
+		}
+	}
+}
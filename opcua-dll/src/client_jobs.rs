@@ -0,0 +1,870 @@
+//==============================================================================
+//
+// Title:		Read/write of multiple values and method calls
+// Purpose:		Blocking with timeout, or as jobs: the request is submitted
+//				on the runtime and the call returns at once, the result is
+//				polled and fetched later (from any LabVIEW thread)
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+use crate::client_variables::parse_index_range;
+use crate::errors::*;
+use crate::labview::{LStrHandle, lstr_to_string, string_to_lstr};
+use crate::utils::{date_time_to_cocoa, with_timeout};
+
+use libc::c_char;
+use opcua::{
+	client::Session,
+	types::{
+		AttributeId, CallMethodRequest, DataValue, NodeId, ReadValueId, StatusCode,
+		TimestampsToReturn, Variant, VariantScalarTypeId, WriteValue,
+	},
+};
+use std::{
+	collections::HashMap,
+	sync::{
+		Arc, LazyLock, Mutex,
+		atomic::{AtomicU32, Ordering},
+	},
+	time::{Duration, Instant},
+};
+use tokio::{runtime::Runtime, task::JoinHandle};
+
+// lv_job_poll() result of a finished job (JOB_PENDING before), errors are
+// ERR_* codes or status codes
+pub const JOB_DONE: i32 = 1;
+
+// Finished jobs whose result is not fetched within this time are dropped
+const JOB_EXPIRY: Duration = Duration::from_secs(60);
+
+enum JobResult {
+	Read(Vec<DataValue>),
+	Write(Vec<StatusCode>),
+	Call(Vec<Variant>),
+}
+
+struct Job {
+	task: JoinHandle<()>,
+	session: Arc<Session>, // for the JSON encoding of call results
+	outcome: Option<(Instant, Result<JobResult, StatusCode>)>,
+}
+
+static NEXT_JOB_ID: AtomicU32 = AtomicU32::new(1);
+
+static JOBS: LazyLock<Mutex<HashMap<u32, Job>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Expiry sweep, done on every submit so abandoned results don't pile up
+fn sweep_expired_jobs(jobs: &mut HashMap<u32, Job>) {
+	jobs.retain(|_, job| match &job.outcome {
+		Some((done_at, _)) => done_at.elapsed() < JOB_EXPIRY,
+		None => true,
+	});
+}
+
+// Run the request on the runtime, the outcome is stored in the job
+fn submit_job<F>(rt: &Runtime, session: &Arc<Session>, request: F) -> u32
+where
+	F: Future<Output = Result<JobResult, StatusCode>> + Send + 'static,
+{
+	let job_id = NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed);
+	// Locked until the job is registered, the task can't finish before
+	let mut jobs = JOBS.lock().unwrap();
+	sweep_expired_jobs(&mut jobs);
+	let task = rt.spawn(async move {
+		let outcome = request.await;
+		if let Some(job) = JOBS.lock().unwrap().get_mut(&job_id) {
+			job.outcome = Some((Instant::now(), outcome));
+		}
+	});
+	jobs.insert(
+		job_id,
+		Job {
+			task,
+			session: session.clone(),
+			outcome: None,
+		},
+	);
+	job_id
+}
+
+// Remove the finished job and return its result, the job stays if it is
+// still pending or the result is of other kind (Err is the return code)
+fn take_result<T>(
+	job_id: u32,
+	select: impl FnOnce(JobResult) -> Result<T, JobResult>,
+) -> Result<(T, Arc<Session>), i32> {
+	let mut jobs = JOBS.lock().unwrap();
+	let Some(job) = jobs.get_mut(&job_id) else {
+		set_last_error_detail(format!("No job {job_id}, fetched or expired"));
+		return Err(ERR_INVALID_ARGUMENT);
+	};
+	let Some((done_at, outcome)) = job.outcome.take() else {
+		return Err(JOB_PENDING);
+	};
+	let result = match outcome {
+		Ok(result) => result,
+		Err(status) => {
+			jobs.remove(&job_id);
+			return Err(status.bits() as i32);
+		}
+	};
+	match select(result) {
+		Ok(value) => {
+			let job = jobs.remove(&job_id).unwrap();
+			Ok((value, job.session))
+		}
+		Err(result) => {
+			job.outcome = Some((done_at, Ok(result)));
+			set_last_error_detail(format!("Job {job_id} is of other kind"));
+			Err(ERR_INVALID_TYPE)
+		}
+	}
+}
+
+// Node ids of the namespace, one per line
+pub fn node_ids(ns: u16, node_ids_lv_str: LStrHandle) -> Vec<NodeId> {
+	unsafe { lstr_to_string(node_ids_lv_str) }
+		.lines()
+		.map(str::trim)
+		.filter(|l| !l.is_empty())
+		.map(|l| NodeId::new(ns, l.to_string()))
+		.collect()
+}
+
+// Values of the nodes
+async fn read_request(
+	session: Arc<Session>,
+	nodes: Vec<ReadValueId>,
+) -> Result<Vec<DataValue>, StatusCode> {
+	session.read(&nodes, TimestampsToReturn::Both, 0.0).await
+}
+
+// Write the values cast to the DataType of the nodes, read first.
+// Status code per value
+async fn write_request(
+	session: Arc<Session>,
+	nodes: Vec<NodeId>,
+	values: Vec<f64>,
+) -> Result<Vec<StatusCode>, StatusCode> {
+	let data_type_ids: Vec<ReadValueId> = nodes
+		.iter()
+		.map(|n| ReadValueId::new(n.clone(), AttributeId::DataType))
+		.collect();
+	let data_types = session
+		.read(&data_type_ids, TimestampsToReturn::Neither, 0.0)
+		.await?;
+
+	let mut results = vec![StatusCode::Good; nodes.len()];
+	let mut write_values = Vec::with_capacity(nodes.len());
+	let mut written = Vec::with_capacity(nodes.len());
+	for (i, (node_id, data_type)) in nodes.into_iter().zip(data_types).enumerate() {
+		let variant = match &data_type.value {
+			Some(Variant::NodeId(data_type)) => {
+				match VariantScalarTypeId::try_from(data_type.as_ref()) {
+					Ok(type_id) => Variant::Double(values[i]).cast(type_id),
+					Err(_) => Variant::Empty,
+				}
+			}
+			_ => {
+				results[i] = data_type.status();
+				continue;
+			}
+		};
+		if variant.is_empty() {
+			results[i] = StatusCode::BadTypeMismatch;
+			continue;
+		}
+		write_values.push(WriteValue {
+			node_id,
+			attribute_id: AttributeId::Value as u32,
+			index_range: Default::default(),
+			value: DataValue::value_only(variant),
+		});
+		written.push(i);
+	}
+	if !write_values.is_empty() {
+		let statuses = session.write(&write_values).await?;
+		for (i, status) in written.into_iter().zip(statuses) {
+			results[i] = status;
+		}
+	}
+	Ok(results)
+}
+
+// Output arguments of the method
+async fn call_request(
+	session: Arc<Session>,
+	request: CallMethodRequest,
+) -> Result<Vec<Variant>, StatusCode> {
+	let result = session.call_one(request).await?;
+	if result.status_code.is_bad() {
+		return Err(result.status_code);
+	}
+	Ok(result.output_arguments.unwrap_or_default())
+}
+
+// Input arguments of the call, OPC UA JSON one per line
+fn call_method_request(
+	session: &Session,
+	object_ns: u16,
+	object_str: *const c_char,
+	method_ns: u16,
+	method_str: *const c_char,
+	args_json_lv_str: LStrHandle,
+) -> Result<CallMethodRequest, i32> {
+	let mut args = Vec::new();
+	for json in unsafe { lstr_to_string(args_json_lv_str) }
+		.lines()
+		.map(str::trim)
+		.filter(|l| !l.is_empty())
+	{
+		match crate::client_json::json_to_variant(session, json) {
+			Ok(arg) => args.push(arg),
+			Err(e) => {
+				set_last_error_detail(format!("Argument {json}: {e}"));
+				return Err(e.status().bits() as i32);
+			}
+		}
+	}
+	Ok(CallMethodRequest {
+		object_id: NodeId::new(object_ns, cstr_to_string!(object_str)),
+		method_id: NodeId::new(method_ns, cstr_to_string!(method_str)),
+		input_arguments: Some(args),
+	})
+}
+
+// Output arguments as OPC UA JSON, one per line
+fn outputs_to_lstr(session: &Session, outputs: &[Variant], outputs_json_out: LStrHandle) -> i32 {
+	let mut lines = Vec::with_capacity(outputs.len());
+	for output in outputs {
+		match crate::client_json::variant_to_json(session, output) {
+			Ok(json) => lines.push(json),
+			Err(e) => return e.status().bits() as i32,
+		}
+	}
+	unsafe { string_to_lstr(&lines.join("\n"), outputs_json_out) }
+}
+
+//==============================================================================
+// Read result of one node, array allocated by LabVIEW
+//
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+pub struct LvReadResult {
+	value: f64, // NaN if not numeric
+	status: u32,
+	timestamp: f64, // Cocoa, source or server timestamp
+}
+
+#[cfg(target_arch = "x86")]
+#[repr(C, packed(1))]
+pub struct LvReadResult {
+	value: f64,
+	status: u32,
+	timestamp: f64,
+}
+
+unsafe fn fill_read_results(values: Vec<DataValue>, results_out: *mut LvReadResult, count: i32) {
+	let results = unsafe { std::slice::from_raw_parts_mut(results_out, count.max(0) as usize) };
+	for (result, dv) in results.iter_mut().zip(values) {
+		let value = match &dv.value {
+			// The element of an index range ("5")
+			Some(Variant::Array(array)) if array.values.len() == 1 => array.values.first(),
+			value => value.as_ref(),
+		};
+		*result = LvReadResult {
+			value: value
+				.map(crate::subscription::variant_to_f64)
+				.unwrap_or(f64::NAN),
+			status: dv.status().bits(),
+			timestamp: dv
+				.source_timestamp
+				.or(dv.server_timestamp)
+				.map(|t| date_time_to_cocoa(&t))
+				.unwrap_or(0.0),
+		};
+	}
+}
+
+unsafe fn fill_write_results(statuses: Vec<StatusCode>, results_out: *mut u32, count: i32) {
+	let results = unsafe { std::slice::from_raw_parts_mut(results_out, count.max(0) as usize) };
+	for (result, status) in results.iter_mut().zip(statuses) {
+		*result = status.bits();
+	}
+}
+
+//==============================================================================
+// Read the values of node_ids (one per line, namespace ns), results_out has
+// count elements. index_range_str reads one element of array values ("5",
+// null or empty - the whole values), a bad range is the status of the result
+// (BadIndexRangeNoData...). timeout_ms 0 - session default, ERR_TIMEOUT if it
+// passed, ERR_INVALID_ARGUMENT if index_range_str isn't a valid range
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_read_multiple(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	node_ids_lv_str: LStrHandle,
+	ns: u16,
+	index_range_str: *const c_char,
+	results_out: *mut LvReadResult,
+	count: i32,
+	timeout_ms: u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(results_out, ERR_NULL_POINTER);
+		let index_range = match parse_index_range(index_range_str) {
+			Ok(index_range) => index_range,
+			Err(err) => return err,
+		};
+
+		let nodes: Vec<ReadValueId> = node_ids(ns, node_ids_lv_str)
+			.into_iter()
+			.map(|node_id| ReadValueId {
+				index_range: index_range.clone(),
+				..ReadValueId::from(node_id)
+			})
+			.collect();
+		if nodes.is_empty() {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &*rt_ptr;
+			let session = &*session_in;
+			match rt.block_on(with_timeout(
+				timeout_ms,
+				read_request(session.clone(), nodes),
+			)) {
+				Ok(Ok(values)) => fill_read_results(values, results_out, count),
+				Ok(Err(status)) => return status.bits() as i32,
+				Err(err) => return err,
+			}
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Write values (count, one per line of node_ids) cast to the DataType of
+// the nodes, status code per value in results_out (count elements).
+// timeout_ms 0 - session default, ERR_TIMEOUT if it passed
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_multiple(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	node_ids_lv_str: LStrHandle,
+	ns: u16,
+	values_in: *const f64,
+	count: i32,
+	results_out: *mut u32,
+	timeout_ms: u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(values_in, ERR_NULL_POINTER);
+		check_null!(results_out, ERR_NULL_POINTER);
+
+		let nodes = node_ids(ns, node_ids_lv_str);
+		if nodes.is_empty() || nodes.len() != count as usize {
+			set_last_error_detail(format!("{} node ids for {} values", nodes.len(), count));
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &*rt_ptr;
+			let session = &*session_in;
+			let values = std::slice::from_raw_parts(values_in, nodes.len()).to_vec();
+			let request = write_request(session.clone(), nodes, values);
+			match rt.block_on(with_timeout(timeout_ms, request)) {
+				Ok(Ok(statuses)) => fill_write_results(statuses, results_out, count),
+				Ok(Err(status)) => return status.bits() as i32,
+				Err(err) => return err,
+			}
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Call the method, arguments and outputs_json_out are OPC UA JSON, one per
+// line. timeout_ms 0 - session default, ERR_TIMEOUT if it passed
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_call_method(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	object_ns: u16,
+	object_str: *const c_char,
+	method_ns: u16,
+	method_str: *const c_char,
+	args_json_lv_str: LStrHandle,
+	outputs_json_out: LStrHandle,
+	timeout_ms: u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(object_str, ERR_NULL_POINTER);
+		check_null!(method_str, ERR_NULL_POINTER);
+		check_null!(outputs_json_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &*rt_ptr;
+			let session = &*session_in;
+			let request = match call_method_request(
+				session,
+				object_ns,
+				object_str,
+				method_ns,
+				method_str,
+				args_json_lv_str,
+			) {
+				Ok(request) => request,
+				Err(err) => return err,
+			};
+			match rt.block_on(with_timeout(
+				timeout_ms,
+				call_request(session.clone(), request),
+			)) {
+				Ok(Ok(outputs)) => outputs_to_lstr(session, &outputs, outputs_json_out),
+				Ok(Err(status)) => status.bits() as i32,
+				Err(err) => err,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Submit the read of the values of node_ids (one per line, namespace ns),
+// job_id_out is used with lv_job_poll/lv_job_get_read_result/lv_job_cancel
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_read_async(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	node_ids_lv_str: LStrHandle,
+	ns: u16,
+	job_id_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(job_id_out, ERR_NULL_POINTER);
+
+		let nodes: Vec<ReadValueId> = node_ids(ns, node_ids_lv_str)
+			.into_iter()
+			.map(ReadValueId::from)
+			.collect();
+		if nodes.is_empty() {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &*rt_ptr;
+			let session = &*session_in;
+			let request = read_request(session.clone(), nodes);
+			*job_id_out = submit_job(rt, session, async { request.await.map(JobResult::Read) });
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Submit the write of values (count, one per line of node_ids), each value
+// is cast to the DataType of its node, read first in the same job
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_async(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	node_ids_lv_str: LStrHandle,
+	ns: u16,
+	values_in: *const f64,
+	count: i32,
+	job_id_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(values_in, ERR_NULL_POINTER);
+		check_null!(job_id_out, ERR_NULL_POINTER);
+
+		let nodes = node_ids(ns, node_ids_lv_str);
+		if nodes.is_empty() || nodes.len() != count as usize {
+			set_last_error_detail(format!("{} node ids for {} values", nodes.len(), count));
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &*rt_ptr;
+			let session = &*session_in;
+			let values = std::slice::from_raw_parts(values_in, nodes.len()).to_vec();
+			let request = write_request(session.clone(), nodes, values);
+			*job_id_out = submit_job(rt, session, async { request.await.map(JobResult::Write) });
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Submit the call of the method, input arguments as OPC UA JSON, one per
+// line, e.g. {"UaType":11,"Value":3.14} (see lv_read_variable_json)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_call_async(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	object_ns: u16,
+	object_str: *const c_char,
+	method_ns: u16,
+	method_str: *const c_char,
+	args_json_lv_str: LStrHandle,
+	job_id_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(object_str, ERR_NULL_POINTER);
+		check_null!(method_str, ERR_NULL_POINTER);
+		check_null!(job_id_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &*rt_ptr;
+			let session = &*session_in;
+			let request = match call_method_request(
+				session,
+				object_ns,
+				object_str,
+				method_ns,
+				method_str,
+				args_json_lv_str,
+			) {
+				Ok(request) => request,
+				Err(err) => return err,
+			};
+			let request = call_request(session.clone(), request);
+			*job_id_out = submit_job(rt, session, async { request.await.map(JobResult::Call) });
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// JOB_PENDING, JOB_DONE (fetch the result), or the error of the job
+// (ERR_INVALID_ARGUMENT if the job is unknown). The job stays until fetched
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_job_poll(job_id: u32) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		match JOBS.lock().unwrap().get(&job_id) {
+			None => ERR_INVALID_ARGUMENT,
+			Some(Job { outcome: None, .. }) => JOB_PENDING,
+			Some(Job {
+				outcome: Some((_, Ok(_))),
+				..
+			}) => JOB_DONE,
+			Some(Job {
+				outcome: Some((_, Err(status))),
+				..
+			}) => status.bits() as i32,
+		}
+	})
+}
+
+//==============================================================================
+// Values of the finished read, results_out has count elements (the number
+// of node ids). NO_ERR frees the job, JOB_PENDING if it is not finished
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_job_get_read_result(
+	job_id: u32,
+	results_out: *mut LvReadResult,
+	count: i32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(results_out, ERR_NULL_POINTER);
+
+		let values = match take_result(job_id, |result| match result {
+			JobResult::Read(values) => Ok(values),
+			other => Err(other),
+		}) {
+			Ok((values, _)) => values,
+			Err(err) => return err,
+		};
+		unsafe { fill_read_results(values, results_out, count) };
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Status code per value of the finished write, results_out has count
+// elements. NO_ERR frees the job, JOB_PENDING if it is not finished
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_job_get_write_result(job_id: u32, results_out: *mut u32, count: i32) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(results_out, ERR_NULL_POINTER);
+
+		let statuses = match take_result(job_id, |result| match result {
+			JobResult::Write(statuses) => Ok(statuses),
+			other => Err(other),
+		}) {
+			Ok((statuses, _)) => statuses,
+			Err(err) => return err,
+		};
+		unsafe { fill_write_results(statuses, results_out, count) };
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Output arguments of the finished call as OPC UA JSON, one per line.
+// NO_ERR frees the job, JOB_PENDING if it is not finished
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_job_get_call_result(job_id: u32, outputs_json_out: LStrHandle) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(outputs_json_out, ERR_NULL_POINTER);
+
+		let (outputs, session) = match take_result(job_id, |result| match result {
+			JobResult::Call(outputs) => Ok(outputs),
+			other => Err(other),
+		}) {
+			Ok(result) => result,
+			Err(err) => return err,
+		};
+		outputs_to_lstr(&session, &outputs, outputs_json_out)
+	})
+}
+
+//==============================================================================
+// Abort the job (the request may still reach the server) and free it
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_job_cancel(job_id: u32) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		match JOBS.lock().unwrap().remove(&job_id) {
+			Some(job) => {
+				job.task.abort();
+				NO_ERR
+			}
+			None => ERR_INVALID_ARGUMENT,
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::labview::{DSDisposeHandleLStr, string_to_new_lstr};
+	use crate::test_server::{TestClient, TestServer};
+	use std::ffi::CString;
+
+	// Poll until the job isn't pending, the last poll result
+	fn wait_for_job(job_id: u32) -> i32 {
+		let start = Instant::now();
+		loop {
+			let poll = lv_job_poll(job_id);
+			if poll != JOB_PENDING {
+				return poll;
+			}
+			assert!(
+				start.elapsed() < Duration::from_secs(5),
+				"job {job_id} pending"
+			);
+			std::thread::sleep(Duration::from_millis(10));
+		}
+	}
+
+	fn double_variables(server: &TestServer, values: &[f64]) -> String {
+		let mut lines = Vec::new();
+		for (i, value) in values.iter().enumerate() {
+			let node = format!("Job{i}");
+			assert_eq!(server.add_variable(&node, 11), NO_ERR);
+			let node_c = CString::new(node.as_str()).unwrap();
+			let err = crate::server_variables::lv_write_variableDouble(
+				node_c.as_ptr(),
+				server.ns,
+				*value,
+				server.manager_ptr,
+				server.handle_ptr,
+			);
+			assert_eq!(err, NO_ERR);
+			lines.push(node);
+		}
+		lines.join("\n")
+	}
+
+	fn read_async(client: &TestClient, ns: u16, node_ids: &str) -> u32 {
+		let mut job_id = 0;
+		unsafe {
+			let node_ids = string_to_new_lstr(node_ids);
+			let err = lv_read_async(client.rt_ptr, client.session_ptr, node_ids, ns, &mut job_id);
+			DSDisposeHandleLStr(node_ids);
+			assert_eq!(err, NO_ERR);
+		}
+		job_id
+	}
+
+	fn empty_result() -> LvReadResult {
+		LvReadResult {
+			value: -1.0,
+			status: 0,
+			timestamp: 0.0,
+		}
+	}
+
+	// A job that never finishes
+	fn pending_job(client: &TestClient) -> u32 {
+		submit_job(client.runtime(), client.session(), std::future::pending())
+	}
+
+	#[test]
+	fn pending_job_is_not_fetched() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		let job_id = pending_job(&client);
+
+		assert_eq!(lv_job_poll(job_id), JOB_PENDING);
+		let mut results = [empty_result()];
+		let err = lv_job_get_read_result(job_id, results.as_mut_ptr(), 1);
+		assert_eq!(err, JOB_PENDING);
+		assert_eq!({ results[0].value }, -1.0);
+		let mut statuses = [0u32];
+		let err = lv_job_get_write_result(job_id, statuses.as_mut_ptr(), 1);
+		assert_eq!(err, JOB_PENDING);
+		assert_eq!(lv_job_poll(job_id), JOB_PENDING);
+
+		assert_eq!(lv_job_cancel(job_id), NO_ERR);
+		assert_eq!(lv_job_poll(job_id), ERR_INVALID_ARGUMENT);
+		assert_eq!(lv_job_cancel(job_id), ERR_INVALID_ARGUMENT);
+	}
+
+	#[test]
+	fn read_job_is_fetched_once() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		let node_ids = double_variables(&server, &[1.5, 2.5]);
+		let job_id = read_async(&client, server.ns, &node_ids);
+
+		assert_eq!(wait_for_job(job_id), JOB_DONE);
+		// Stays until fetched, of the kind of its request only
+		assert_eq!(lv_job_poll(job_id), JOB_DONE);
+		let mut statuses = [0u32; 2];
+		let err = lv_job_get_write_result(job_id, statuses.as_mut_ptr(), 2);
+		assert_eq!(err, ERR_INVALID_TYPE);
+
+		let mut results = [empty_result(), empty_result()];
+		let err = lv_job_get_read_result(job_id, results.as_mut_ptr(), 2);
+		assert_eq!(err, NO_ERR);
+		let values: Vec<(f64, u32)> = results.iter().map(|r| (r.value, r.status)).collect();
+		assert_eq!(values, [(1.5, 0), (2.5, 0)]);
+		assert!(results.iter().all(|r| r.timestamp > 0.0));
+
+		assert_eq!(lv_job_poll(job_id), ERR_INVALID_ARGUMENT);
+		let err = lv_job_get_read_result(job_id, results.as_mut_ptr(), 2);
+		assert_eq!(err, ERR_INVALID_ARGUMENT);
+	}
+
+	#[test]
+	fn write_job_reports_the_status_per_value() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		let node_ids = double_variables(&server, &[0.0]) + "\nNoSuchNode";
+
+		let mut job_id = 0;
+		unsafe {
+			let node_ids = string_to_new_lstr(&node_ids);
+			let err = lv_write_async(
+				client.rt_ptr,
+				client.session_ptr,
+				node_ids,
+				server.ns,
+				[7.5, 1.0].as_ptr(),
+				2,
+				&mut job_id,
+			);
+			DSDisposeHandleLStr(node_ids);
+			assert_eq!(err, NO_ERR);
+		}
+		assert_eq!(wait_for_job(job_id), JOB_DONE);
+		let mut statuses = [u32::MAX; 2];
+		let err = lv_job_get_write_result(job_id, statuses.as_mut_ptr(), 2);
+		assert_eq!(err, NO_ERR);
+		assert_eq!(statuses, [0, StatusCode::BadNodeIdUnknown.bits()]);
+
+		let job_id = read_async(&client, server.ns, "Job0");
+		assert_eq!(wait_for_job(job_id), JOB_DONE);
+		let mut results = [empty_result()];
+		assert_eq!(
+			lv_job_get_read_result(job_id, results.as_mut_ptr(), 1),
+			NO_ERR
+		);
+		assert_eq!({ results[0].value }, 7.5);
+	}
+
+	// The status of a failed call is the poll result, fetching frees the job
+	#[test]
+	fn call_job_of_unknown_method_fails() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+
+		let mut job_id = 0;
+		unsafe {
+			let args = string_to_new_lstr("");
+			let err = lv_call_async(
+				client.rt_ptr,
+				client.session_ptr,
+				0,
+				c"i=85".as_ptr(),
+				server.ns,
+				c"NoSuchMethod".as_ptr(),
+				args,
+				&mut job_id,
+			);
+			DSDisposeHandleLStr(args);
+			assert_eq!(err, NO_ERR);
+		}
+		let poll = wait_for_job(job_id);
+		assert!(StatusCode::from(poll as u32).is_bad(), "{poll}");
+
+		let outputs = unsafe { string_to_new_lstr("") };
+		assert_eq!(lv_job_get_call_result(job_id, outputs), poll);
+		assert_eq!(lv_job_poll(job_id), ERR_INVALID_ARGUMENT);
+		unsafe { DSDisposeHandleLStr(outputs) };
+	}
+
+	// Results not fetched within JOB_EXPIRY are swept by the next submit,
+	// pending jobs stay
+	#[test]
+	fn abandoned_results_expire() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		let node_ids = double_variables(&server, &[1.0]);
+		let pending = pending_job(&client);
+		let abandoned = read_async(&client, server.ns, &node_ids);
+		assert_eq!(wait_for_job(abandoned), JOB_DONE);
+
+		let fresh = read_async(&client, server.ns, &node_ids);
+		assert_eq!(wait_for_job(fresh), JOB_DONE);
+		if let Some(job) = JOBS.lock().unwrap().get_mut(&abandoned) {
+			let (done_at, _) = job.outcome.as_mut().unwrap();
+			*done_at -= JOB_EXPIRY;
+		}
+		let next = read_async(&client, server.ns, &node_ids);
+
+		assert_eq!(lv_job_poll(abandoned), ERR_INVALID_ARGUMENT);
+		assert_eq!(lv_job_poll(fresh), JOB_DONE);
+		assert_eq!(lv_job_poll(pending), JOB_PENDING);
+		for job_id in [fresh, pending, next] {
+			assert_eq!(lv_job_cancel(job_id), NO_ERR);
+		}
+	}
+}
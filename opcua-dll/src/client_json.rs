@@ -0,0 +1,333 @@
+//==============================================================================
+//
+// Title:		OPC UA JSON encoding of variable values
+// Purpose:		Read/write any Variant, including structures (ExtensionObjects),
+//				as OPC UA JSON strings which can be parsed by LabVIEW JSON VIs
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+use crate::errors::*;
+use crate::labview::{LStrHandle, string_to_lstr};
+
+use libc::c_char;
+use opcua::{
+	client::{Session, custom_types::DataTypeTreeBuilder},
+	types::{
+		AttributeId, BinaryEncodable, ByteString, Context, DataValue, DynEncodable, EncodingResult,
+		Error, ExpandedMessageInfo, ExpandedNodeId, NodeId, StatusCode, TimestampsToReturn,
+		TypeLoader, TypeLoaderPriority, UaNullable, Variant, WriteValue,
+		custom::DynamicTypeLoader,
+		json::{
+			JsonDecodable, JsonEncodable, JsonReader, JsonStreamReader, JsonStreamWriter,
+			JsonWriter,
+		},
+		xml::{XmlEncodable, XmlStreamReader, XmlStreamWriter, XmlType},
+	},
+};
+use std::{
+	io::{Cursor, Read, Write},
+	sync::Arc,
+};
+use tokio::runtime::Runtime;
+
+//==============================================================================
+// Body of an ExtensionObject with an encoding unknown to the client.
+// Kept as raw binary, in JSON it is {"TypeId": <encoding id>, "Body": "<base64>"}
+//
+#[derive(Debug, Clone, PartialEq)]
+struct RawStructure {
+	type_id: NodeId,
+	body: ByteString,
+}
+
+impl UaNullable for RawStructure {}
+
+impl ExpandedMessageInfo for RawStructure {
+	fn full_type_id(&self) -> ExpandedNodeId {
+		self.type_id.clone().into()
+	}
+
+	fn full_json_type_id(&self) -> ExpandedNodeId {
+		self.type_id.clone().into()
+	}
+
+	fn full_xml_type_id(&self) -> ExpandedNodeId {
+		self.type_id.clone().into()
+	}
+
+	// The data type is unknown, only the encoding id is available
+	fn full_data_type_id(&self) -> ExpandedNodeId {
+		self.type_id.clone().into()
+	}
+}
+
+impl BinaryEncodable for RawStructure {
+	fn byte_len(&self, _ctx: &Context<'_>) -> usize {
+		self.body.value.as_ref().map_or(0, |b| b.len())
+	}
+
+	fn encode<S: Write + ?Sized>(&self, stream: &mut S, _ctx: &Context<'_>) -> EncodingResult<()> {
+		if let Some(bytes) = &self.body.value {
+			stream.write_all(bytes)?;
+		}
+		Ok(())
+	}
+}
+
+impl JsonEncodable for RawStructure {
+	fn encode(
+		&self,
+		stream: &mut JsonStreamWriter<&mut dyn Write>,
+		ctx: &Context<'_>,
+	) -> EncodingResult<()> {
+		stream.begin_object()?;
+		stream.name("TypeId")?;
+		JsonEncodable::encode(&self.type_id, stream, ctx)?;
+		stream.name("Body")?;
+		JsonEncodable::encode(&self.body, stream, ctx)?;
+		stream.end_object()?;
+		Ok(())
+	}
+}
+
+impl JsonDecodable for RawStructure {
+	fn decode(
+		stream: &mut JsonStreamReader<&mut dyn Read>,
+		ctx: &Context<'_>,
+	) -> EncodingResult<Self> {
+		let mut type_id = NodeId::null();
+		let mut body = ByteString::null();
+		stream.begin_object()?;
+		while stream.has_next()? {
+			match stream.next_name()? {
+				"TypeId" => type_id = JsonDecodable::decode(stream, ctx)?,
+				"Body" => body = JsonDecodable::decode(stream, ctx)?,
+				_ => stream.skip_value()?,
+			}
+		}
+		stream.end_object()?;
+		Ok(Self { type_id, body })
+	}
+}
+
+impl XmlType for RawStructure {
+	const TAG: &'static str = "ByteString";
+}
+
+impl XmlEncodable for RawStructure {
+	fn encode(
+		&self,
+		writer: &mut XmlStreamWriter<&mut dyn Write>,
+		ctx: &Context<'_>,
+	) -> EncodingResult<()> {
+		XmlEncodable::encode(&self.body, writer, ctx)
+	}
+}
+
+//==============================================================================
+// Type loader of the last resort, used when no other loader knows the encoding.
+// Keeps the body as is, so reading a node with unknown structure doesn't fail
+//
+struct RawStructureLoader;
+
+impl TypeLoader for RawStructureLoader {
+	fn load_from_xml(
+		&self,
+		_node_id: &NodeId,
+		_stream: &mut XmlStreamReader<&mut dyn Read>,
+		_ctx: &Context<'_>,
+	) -> Option<EncodingResult<Box<dyn DynEncodable>>> {
+		None
+	}
+
+	fn load_from_json(
+		&self,
+		node_id: &NodeId,
+		stream: &mut JsonStreamReader<&mut dyn Read>,
+		ctx: &Context<'_>,
+	) -> Option<EncodingResult<Box<dyn DynEncodable>>> {
+		Some(RawStructure::decode(stream, ctx).map(|mut raw| {
+			// UaTypeId of the ExtensionObject wins over the TypeId in the body
+			raw.type_id = node_id.clone();
+			Box::new(raw) as Box<dyn DynEncodable>
+		}))
+	}
+
+	fn load_from_binary(
+		&self,
+		node_id: &NodeId,
+		stream: &mut dyn Read,
+		_ctx: &Context<'_>,
+	) -> Option<EncodingResult<Box<dyn DynEncodable>>> {
+		let mut bytes = Vec::new();
+		if let Err(e) = stream.read_to_end(&mut bytes) {
+			return Some(Err(Error::decoding(e)));
+		}
+		Some(Ok(Box::new(RawStructure {
+			type_id: node_id.clone(),
+			body: ByteString::from(bytes),
+		})))
+	}
+
+	fn priority(&self) -> TypeLoaderPriority {
+		TypeLoaderPriority::Fallback
+	}
+}
+
+// Called once on connect, see client.rs
+pub fn add_raw_structure_loader(session: &Session) {
+	session.add_type_loader(Arc::new(RawStructureLoader));
+}
+
+pub fn variant_to_json(session: &Session, variant: &Variant) -> EncodingResult<String> {
+	let ctx_lock = session.context();
+	let ctx_owned = ctx_lock.read();
+	let ctx = ctx_owned.context();
+
+	let mut target = Vec::new();
+	let mut stream = Cursor::new(&mut target);
+	let mut writer = JsonStreamWriter::new(&mut stream as &mut dyn Write);
+	JsonEncodable::encode(variant, &mut writer, &ctx)?;
+	writer.finish_document()?;
+	Ok(String::from_utf8_lossy(&target).into_owned())
+}
+
+pub fn json_to_variant(session: &Session, json: &str) -> EncodingResult<Variant> {
+	let ctx_lock = session.context();
+	let ctx_owned = ctx_lock.read();
+	let ctx = ctx_owned.context();
+
+	let stream = &mut json.as_bytes() as &mut dyn Read;
+	let mut reader = JsonStreamReader::new(stream);
+	<Variant as JsonDecodable>::decode(&mut reader, &ctx)
+}
+
+//==============================================================================
+// Read the value of the node as OPC UA JSON, for example
+// {"UaType":11,"Value":3.14} or {"UaType":22,"Value":{"UaTypeId":...,"UaBody":{...}}}
+// lv_str_out is resized as needed.
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_read_variable_json(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	node_str: *const c_char,
+	ns: u16,
+	lv_str_out: LStrHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(lv_str_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+
+			let r = rt.block_on(async {
+				session
+					.read(&[node_id.into()], TimestampsToReturn::Neither, 0.0)
+					.await
+			});
+			let data_value = match r {
+				Ok(values) => match values.into_iter().next() {
+					Some(data_value) => data_value,
+					None => return StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => return status.bits() as i32,
+			};
+			if data_value.status().is_bad() {
+				return data_value.status().bits() as i32;
+			}
+
+			let variant = data_value.value.unwrap_or(Variant::Empty);
+			match variant_to_json(session, &variant) {
+				Ok(json) => string_to_lstr(&json, lv_str_out),
+				Err(e) => e.status().bits() as i32,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Write the value of the node from OPC UA JSON, same format as read returns
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_variable_json(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	node_str: *const c_char,
+	ns: u16,
+	json_str: *const c_char,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(json_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+
+			let variant = match json_to_variant(session, &cstr_to_string!(json_str)) {
+				Ok(variant) => variant,
+				Err(e) => return e.status().bits() as i32,
+			};
+			let write_value = WriteValue {
+				node_id: NodeId::new(ns, cstr_to_string!(node_str)),
+				attribute_id: AttributeId::Value as u32,
+				index_range: Default::default(),
+				value: DataValue::value_only(variant),
+			};
+
+			match rt.block_on(async { session.write(&[write_value]).await }) {
+				Ok(results) => match results.first() {
+					Some(status) if status.is_bad() => status.bits() as i32,
+					Some(_) => NO_ERR,
+					None => StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Browse the data types of the server and register them with the session,
+// so custom structures are decoded field by field instead of raw base64 body.
+// Call once after connect, takes some time on large servers.
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_load_custom_data_types(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+
+			rt.block_on(async {
+				if let Err(e) = session.read_namespace_array().await {
+					return e.status().bits() as i32;
+				}
+				match DataTypeTreeBuilder::new(|_| true).build(session).await {
+					Ok(type_tree) => {
+						session
+							.add_type_loader(Arc::new(DynamicTypeLoader::new(Arc::new(type_tree))));
+						NO_ERR
+					}
+					Err(e) => e.status().bits() as i32,
+				}
+			})
+		}
+	})
+}
@@ -0,0 +1,584 @@
+//==============================================================================
+//
+// Title:		Connection pool
+// Purpose:		Sessions to many servers kept under string aliases, read
+//				and write by alias instead of runtime/client/session pointers
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+//
+// The pool owns its runtime, a client per alias (with the retry settings of
+// the alias), the session and its event loop. The event loop reconnects a
+// lost session by itself, meanwhile lv_pool_read/lv_pool_write return
+// ERR_DISCONNECTED at once instead of waiting for the request timeout.
+// When it gives up (retry_limit), the alias stays down until the next
+// lv_pool_connect
+//
+use crate::errors::*;
+use crate::labview::{TVariant, lv_variant_to_variant, variant_to_lv_variant};
+
+use libc::c_char;
+use opcua::{
+	client::{Client, ClientBuilder, IdentityToken, Session},
+	crypto::SecurityPolicy,
+	types::{
+		AttributeId, DataValue, MessageSecurityMode, NodeId, NumericRange, StatusCode,
+		TimestampsToReturn, UserTokenPolicy, WriteValue,
+	},
+};
+use std::{
+	collections::HashMap,
+	str::FromStr,
+	sync::{Arc, LazyLock, Mutex},
+	time::Duration,
+};
+use tokio::{runtime::Runtime, task::JoinHandle};
+
+// Reconnect interval if retry_interval_ms is 0
+const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+// The arguments of lv_pool_connect the connection was made with
+#[derive(PartialEq)]
+struct PoolSettings {
+	url: String,
+	policy: SecurityPolicy,
+	mode: MessageSecurityMode,
+	user: Option<(String, String)>, // None - anonymous
+	retry_interval: Duration,
+	retry_limit: i32,
+}
+
+struct PooledConnection {
+	settings: PoolSettings,
+	_client: Client, // built with the retry settings of the alias
+	session: Arc<Session>,
+	event_loop: JoinHandle<StatusCode>,
+}
+
+static POOL_RUNTIME: LazyLock<Runtime> = LazyLock::new(|| Runtime::new().unwrap());
+
+static POOL: LazyLock<Mutex<HashMap<String, PooledConnection>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+async fn close_pooled(pooled: PooledConnection) {
+	crate::subscription::unregister_session(&pooled.session);
+	if pooled.session.is_connected() {
+		let _ = pooled.session.disconnect().await;
+	}
+	// The event loop of a reconnecting session would retry on
+	pooled.event_loop.abort();
+	let _ = pooled.event_loop.await;
+}
+
+// The session of the alias if it is connected, else ERR_INVALID_CLIENT_REF
+// (unknown alias) or ERR_DISCONNECTED
+fn connected_session(alias: &str) -> Result<Arc<Session>, i32> {
+	let pool = POOL.lock().unwrap();
+	let Some(pooled) = pool.get(alias) else {
+		set_last_error_detail(format!("No connection '{alias}' in the pool"));
+		return Err(ERR_INVALID_CLIENT_REF);
+	};
+	if pooled.event_loop.is_finished() {
+		set_last_error_detail(format!(
+			"'{alias}' ({}) is down, reconnecting gave up",
+			pooled.settings.url
+		));
+		return Err(ERR_DISCONNECTED);
+	}
+	if !pooled.session.is_connected() {
+		set_last_error_detail(format!(
+			"'{alias}' ({}) is reconnecting",
+			pooled.settings.url
+		));
+		return Err(ERR_DISCONNECTED);
+	}
+	Ok(pooled.session.clone())
+}
+
+//==============================================================================
+// Connect to url under alias_str with security_policy ("None",
+// "Basic256Sha256", ...) and security_mode ("None", "Sign",
+// "SignAndEncrypt"). Null username_str or password_str - anonymous.
+// A lost connection is reconnected every retry_interval_ms (0 - 1 s),
+// retry_limit times (-1 - forever).
+// The connection of the alias is reused if it has the same url and settings
+// and is up or reconnecting, one that gave up is replaced.
+// ERR_INVALID_ARGUMENT if the alias is connected to another url or with other
+// security, user or retry settings, disconnect it first
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_pool_connect(
+	url: *const c_char,
+	security_policy: *const c_char,
+	security_mode: *const c_char,
+	username_str: *const c_char,
+	password_str: *const c_char,
+	alias_str: *const c_char,
+	retry_interval_ms: u32,
+	retry_limit: i32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(url, ERR_NULL_POINTER);
+		check_null!(security_policy, ERR_NULL_POINTER);
+		check_null!(security_mode, ERR_NULL_POINTER);
+		check_null!(alias_str, ERR_NULL_POINTER);
+		if retry_limit < -1 {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		let url_str = cstr_to_string!(url);
+		let policy_str = cstr_to_string!(security_policy);
+		let mode_str = cstr_to_string!(security_mode);
+		let alias = cstr_to_string!(alias_str);
+
+		let policy = match SecurityPolicy::from_str(&policy_str) {
+			Ok(policy) if policy != SecurityPolicy::Unknown => policy,
+			_ => {
+				set_last_error_detail(format!("Unknown security policy '{policy_str}'"));
+				return ERR_INVALID_ARGUMENT;
+			}
+		};
+		let mode = MessageSecurityMode::from(mode_str.as_str());
+		if mode == MessageSecurityMode::Invalid {
+			set_last_error_detail(format!("Unknown security mode '{mode_str}'"));
+			return ERR_INVALID_ARGUMENT;
+		}
+		let user = if username_str.is_null() || password_str.is_null() {
+			None
+		} else {
+			Some((cstr_to_string!(username_str), cstr_to_string!(password_str)))
+		};
+		let identity = match &user {
+			Some((username, password)) => {
+				IdentityToken::UserName(username.clone(), password.clone())
+			}
+			None => IdentityToken::Anonymous,
+		};
+		let retry_interval = match retry_interval_ms {
+			0 => DEFAULT_RETRY_INTERVAL,
+			ms => Duration::from_millis(ms as u64),
+		};
+		let settings = PoolSettings {
+			url: url_str.clone(),
+			policy,
+			mode,
+			user,
+			retry_interval,
+			retry_limit,
+		};
+
+		let down = {
+			let mut pool = POOL.lock().unwrap();
+			match pool.get(&alias) {
+				Some(pooled) if pooled.settings.url != url_str => {
+					set_last_error_detail(format!(
+						"'{alias}' is connected to {}, disconnect it first",
+						pooled.settings.url
+					));
+					return ERR_INVALID_ARGUMENT;
+				}
+				Some(pooled) if pooled.settings != settings => {
+					set_last_error_detail(format!(
+						"'{alias}' is connected with other security, user or retry settings, \
+						 disconnect it first"
+					));
+					return ERR_INVALID_ARGUMENT;
+				}
+				Some(pooled) if !pooled.event_loop.is_finished() => return NO_ERR,
+				_ => pool.remove(&alias),
+			}
+		};
+
+		POOL_RUNTIME.block_on(async {
+			if let Some(down) = down {
+				close_pooled(down).await;
+			}
+
+			let mut client = match ClientBuilder::new()
+				.application_name("Simple Client")
+				.application_uri("urn:SimpleClient")
+				.product_uri("urn:SimpleClient")
+				.trust_server_certs(true)
+				.create_sample_keypair(true)
+				.session_retry_initial(retry_interval)
+				.session_retry_max(retry_interval)
+				.session_retry_limit(retry_limit)
+				.client()
+			{
+				Ok(client) => client,
+				Err(_) => return ERR_INVALID_ARGUMENT,
+			};
+			let (session, event_loop) = match client
+				.connect_to_matching_endpoint(
+					(
+						url_str.as_ref(),
+						policy.to_str(),
+						mode,
+						UserTokenPolicy::anonymous(),
+					),
+					identity,
+				)
+				.await
+			{
+				Ok(connection) => connection,
+				Err(e) => {
+					return crate::client::connect_error(&url_str, e, e.bits() as i32).await;
+				}
+			};
+			crate::client_json::add_raw_structure_loader(&session);
+			crate::certificate::register_session(&session);
+			let mut handle = event_loop.spawn();
+			tokio::select! {
+				_ = session.wait_for_connection() => {}
+				status = &mut handle => {
+					let status = status.unwrap_or(StatusCode::BadUnexpectedError);
+					return crate::client::connect_error(&url_str, status, status.bits() as i32)
+						.await;
+				}
+			}
+
+			let pooled = PooledConnection {
+				settings,
+				_client: client,
+				session,
+				event_loop: handle,
+			};
+			// A concurrent lv_pool_connect of the same alias may have won
+			let replaced = POOL.lock().unwrap().insert(alias, pooled);
+			if let Some(replaced) = replaced {
+				close_pooled(replaced).await;
+			}
+			NO_ERR
+		})
+	})
+}
+
+//==============================================================================
+// Read the value of the node into the LabVIEW Variant as
+// lv_read_variable_to_lv_variant, type_id_out receives its LVDataTypeId.
+// ERR_DISCONNECTED at once if the connection of the alias is down
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_pool_read(
+	alias_str: *const c_char,
+	ns: u16,
+	node_str: *const c_char,
+	lv_variant_hdl: TVariant,
+	type_id_out: *mut u16,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(alias_str, ERR_NULL_POINTER);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(lv_variant_hdl, ERR_NULL_POINTER);
+		check_null!(type_id_out, ERR_NULL_POINTER);
+
+		let session = match connected_session(&cstr_to_string!(alias_str)) {
+			Ok(session) => session,
+			Err(err) => return err,
+		};
+		let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+
+		let r = POOL_RUNTIME.block_on(async {
+			session
+				.read(&[node_id.into()], TimestampsToReturn::Neither, 0.0)
+				.await
+		});
+		let data_value = match r {
+			Ok(values) => match values.into_iter().next() {
+				Some(data_value) => data_value,
+				None => return StatusCode::BadUnexpectedError.bits() as i32,
+			},
+			Err(status) => return status.bits() as i32,
+		};
+		if data_value.status().is_bad() {
+			return data_value.status().bits() as i32;
+		}
+
+		unsafe {
+			match variant_to_lv_variant(&data_value.value.unwrap_or_default(), lv_variant_hdl) {
+				Ok(type_id) => {
+					*type_id_out = type_id as u16;
+					NO_ERR
+				}
+				Err(err) => err,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Write the value of the LabVIEW Variant to the node as
+// lv_write_lv_variant_to_variable, type_id is the LVDataTypeId of its data.
+// ERR_DISCONNECTED at once if the connection of the alias is down
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_pool_write(
+	alias_str: *const c_char,
+	ns: u16,
+	node_str: *const c_char,
+	lv_variant_hdl: TVariant,
+	type_id: u16,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(alias_str, ERR_NULL_POINTER);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(lv_variant_hdl, ERR_NULL_POINTER);
+
+		let session = match connected_session(&cstr_to_string!(alias_str)) {
+			Ok(session) => session,
+			Err(err) => return err,
+		};
+		let value = match unsafe { lv_variant_to_variant(lv_variant_hdl, type_id) } {
+			Ok(value) => value,
+			Err(err) => return err,
+		};
+		let write_value = WriteValue {
+			node_id: NodeId::new(ns, cstr_to_string!(node_str)),
+			attribute_id: AttributeId::Value as u32,
+			index_range: NumericRange::None,
+			value: DataValue::value_only(value),
+		};
+
+		match POOL_RUNTIME.block_on(async { session.write(&[write_value]).await }) {
+			Ok(results) => match results.first() {
+				Some(status) if status.is_bad() => status.bits() as i32,
+				Some(_) => NO_ERR,
+				None => StatusCode::BadUnexpectedError.bits() as i32,
+			},
+			Err(status) => status.bits() as i32,
+		}
+	})
+}
+
+//==============================================================================
+// Close the connection of alias_str (CloseSession, the reconnecting stops).
+// ERR_INVALID_CLIENT_REF if the alias isn't in the pool
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_pool_disconnect(alias_str: *const c_char) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(alias_str, ERR_NULL_POINTER);
+
+		let alias = cstr_to_string!(alias_str);
+		let Some(pooled) = POOL.lock().unwrap().remove(&alias) else {
+			set_last_error_detail(format!("No connection '{alias}' in the pool"));
+			return ERR_INVALID_CLIENT_REF;
+		};
+		POOL_RUNTIME.block_on(close_pooled(pooled));
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Close all connections of the pool, e.g. when the application stops
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_pool_disconnect_all() -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		let pooled: Vec<_> = POOL.lock().unwrap().drain().map(|(_, p)| p).collect();
+		POOL_RUNTIME.block_on(async {
+			for pooled in pooled {
+				close_pooled(pooled).await;
+			}
+		});
+		NO_ERR
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::labview::{
+		LVDataTypeId, flatten_lv_variant, unflatten_lv_variant, with_flat_lv_variants,
+	};
+	use crate::test_server::TestServer;
+	use opcua::types::Variant;
+	use std::{
+		ffi::CString,
+		time::{Duration, Instant},
+	};
+
+	struct Connect<'a> {
+		url: &'a str,
+		mode: &'a str,
+		user: Option<(&'a str, &'a str)>,
+		retry_interval_ms: u32,
+		retry_limit: i32,
+	}
+
+	impl Connect<'_> {
+		fn to(url: &str) -> Connect<'_> {
+			Connect {
+				url,
+				mode: "None",
+				user: None,
+				retry_interval_ms: 50,
+				retry_limit: -1,
+			}
+		}
+
+		fn alias(&self, alias: &str) -> i32 {
+			let c = |s: &str| CString::new(s).unwrap();
+			let (url, mode, alias) = (c(self.url), c(self.mode), c(alias));
+			let user = self
+				.user
+				.map(|(username, password)| (c(username), c(password)));
+			let (username, password) = match &user {
+				Some((username, password)) => (username.as_ptr(), password.as_ptr()),
+				None => (std::ptr::null(), std::ptr::null()),
+			};
+			lv_pool_connect(
+				url.as_ptr(),
+				c"None".as_ptr(),
+				mode.as_ptr(),
+				username,
+				password,
+				alias.as_ptr(),
+				self.retry_interval_ms,
+				self.retry_limit,
+			)
+		}
+	}
+
+	fn read(alias: &str, ns: u16, node: &str) -> Result<Variant, i32> {
+		let (alias, node) = (CString::new(alias).unwrap(), CString::new(node).unwrap());
+		let mut flat = Vec::<u8>::new();
+		let mut type_id = 0;
+		let err = with_flat_lv_variants(|| {
+			lv_pool_read(
+				alias.as_ptr(),
+				ns,
+				node.as_ptr(),
+				&mut flat as *mut Vec<u8> as TVariant,
+				&mut type_id,
+			)
+		});
+		if err != NO_ERR {
+			return Err(err);
+		}
+		Ok(unflatten_lv_variant(&flat, LVDataTypeId::from_u16(type_id).unwrap()).unwrap())
+	}
+
+	fn write(alias: &str, ns: u16, node: &str, value: Variant) -> i32 {
+		let (alias, node) = (CString::new(alias).unwrap(), CString::new(node).unwrap());
+		let (type_id, mut flat) = flatten_lv_variant(&value).unwrap();
+		with_flat_lv_variants(|| {
+			lv_pool_write(
+				alias.as_ptr(),
+				ns,
+				node.as_ptr(),
+				&mut flat as *mut Vec<u8> as TVariant,
+				type_id as u16,
+			)
+		})
+	}
+
+	fn disconnect(alias: &str) -> i32 {
+		lv_pool_disconnect(CString::new(alias).unwrap().as_ptr())
+	}
+
+	fn pooled_session(alias: &str) -> Arc<Session> {
+		POOL.lock().unwrap()[alias].session.clone()
+	}
+
+	#[test]
+	fn pooled_connection_reads_and_writes() {
+		let server = TestServer::start();
+		assert_eq!(server.add_variable("PoolVar", 11), NO_ERR);
+		let alias = "pool-read-write";
+		let connect = Connect::to(&server.url);
+		assert_eq!(connect.alias(alias), NO_ERR, "{}", last_error_detail());
+
+		assert_eq!(
+			write(alias, server.ns, "PoolVar", Variant::Double(2.5)),
+			NO_ERR
+		);
+		assert_eq!(read(alias, server.ns, "PoolVar"), Ok(Variant::Double(2.5)));
+		let unknown = StatusCode::BadNodeIdUnknown.bits() as i32;
+		assert_eq!(read(alias, server.ns, "NoSuchVar"), Err(unknown));
+		assert_eq!(
+			write(alias, server.ns, "NoSuchVar", Variant::Double(1.0)),
+			unknown
+		);
+
+		// Reused with the same settings only
+		let session = pooled_session(alias);
+		assert_eq!(connect.alias(alias), NO_ERR);
+		let other_url = server.url.replace("127.0.0.1", "localhost");
+		for other in [
+			Connect::to(&other_url),
+			Connect {
+				mode: "Sign",
+				..Connect::to(&server.url)
+			},
+			Connect {
+				user: Some(("user", "password")),
+				..Connect::to(&server.url)
+			},
+			Connect {
+				retry_interval_ms: 1000,
+				..Connect::to(&server.url)
+			},
+			Connect {
+				retry_limit: 3,
+				..Connect::to(&server.url)
+			},
+		] {
+			assert_eq!(other.alias(alias), ERR_INVALID_ARGUMENT);
+		}
+		assert!(Arc::ptr_eq(&session, &pooled_session(alias)));
+
+		assert_eq!(disconnect(alias), NO_ERR);
+		assert_eq!(
+			read(alias, server.ns, "PoolVar"),
+			Err(ERR_INVALID_CLIENT_REF)
+		);
+		let err = write(alias, server.ns, "PoolVar", Variant::Double(1.0));
+		assert_eq!(err, ERR_INVALID_CLIENT_REF);
+		assert_eq!(disconnect(alias), ERR_INVALID_CLIENT_REF);
+	}
+
+	// Reconnecting and given up connections fail at once, not after the
+	// request timeout
+	#[test]
+	fn down_connections_fail_fast() {
+		let server = TestServer::start();
+		assert_eq!(server.add_variable("PoolVar", 11), NO_ERR);
+		let (reconnecting, gave_up) = ("pool-reconnecting", "pool-gave-up");
+		assert_eq!(Connect::to(&server.url).alias(reconnecting), NO_ERR);
+		let connect = Connect {
+			retry_limit: 0,
+			..Connect::to(&server.url)
+		};
+		assert_eq!(connect.alias(gave_up), NO_ERR);
+		let ns = server.ns;
+		for alias in [reconnecting, gave_up] {
+			assert_eq!(write(alias, ns, "PoolVar", Variant::Double(1.0)), NO_ERR);
+		}
+		drop(server);
+
+		let start = Instant::now();
+		let event_loop_finished =
+			|alias: &str| POOL.lock().unwrap()[alias].event_loop.is_finished();
+		while read(reconnecting, ns, "PoolVar") != Err(ERR_DISCONNECTED)
+			|| !event_loop_finished(gave_up)
+		{
+			assert!(start.elapsed() < Duration::from_secs(10), "still connected");
+			std::thread::sleep(Duration::from_millis(20));
+		}
+		assert!(!event_loop_finished(reconnecting));
+
+		for alias in [reconnecting, gave_up] {
+			let start = Instant::now();
+			assert_eq!(read(alias, ns, "PoolVar"), Err(ERR_DISCONNECTED));
+			assert_eq!(
+				write(alias, ns, "PoolVar", Variant::Double(1.0)),
+				ERR_DISCONNECTED
+			);
+			assert!(start.elapsed() < Duration::from_millis(100));
+			assert_eq!(disconnect(alias), NO_ERR);
+		}
+	}
+}
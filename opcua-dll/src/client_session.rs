@@ -0,0 +1,155 @@
+//==============================================================================
+//
+// Title:		Session registry
+// Purpose:		Park a connected session between VI runs and take it back
+//				on the next run instead of connecting again
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+use crate::errors::*;
+use crate::handles::{HandleKind, from_handle, into_handle};
+
+use libc::c_char;
+use opcua::{client::Session, types::StatusCode};
+use std::{
+	collections::HashMap,
+	sync::{
+		Arc, LazyLock, Mutex,
+		atomic::{AtomicU64, Ordering},
+	},
+	time::Duration,
+};
+use tokio::{runtime::Runtime, task::JoinHandle};
+
+// Parked sessions are closed after this time if idle_timeout_ms is 0
+const DEFAULT_PARK_TIMEOUT: Duration = Duration::from_secs(300);
+
+// Budget of the Server_ServerStatus_State read on attach
+const ATTACH_PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct ParkedSession {
+	id: u64, // tells the idle timer if the session was attached and parked again
+	session: Arc<Session>,
+	event_loop: JoinHandle<StatusCode>,
+}
+
+static NEXT_PARKED_ID: AtomicU64 = AtomicU64::new(1);
+
+static PARKED_SESSIONS: LazyLock<Mutex<HashMap<String, ParkedSession>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+async fn close_parked(parked: ParkedSession) {
+	crate::subscription::unregister_session(&parked.session);
+	let _ = parked.session.disconnect().await;
+	let _ = parked.event_loop.await;
+}
+
+//==============================================================================
+// Park the session (from lv_connect_simple) with its event loop under
+// name_str, session_in and handle_in must not be used after this call.
+// The session is closed if it isn't attached within idle_timeout_ms
+// (0 - 5 minutes), a session parked under the same name before is closed.
+// The runtime must stay alive until the session is attached or closed
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_session_detach(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	handle_in: *mut JoinHandle<StatusCode>,
+	name_str: *const c_char,
+	idle_timeout_ms: u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(handle_in, ERR_NULL_POINTER);
+		check_null!(name_str, ERR_NULL_POINTER);
+
+		let name = cstr_to_string!(name_str);
+		let idle_timeout = match idle_timeout_ms {
+			0 => DEFAULT_PARK_TIMEOUT,
+			ms => Duration::from_millis(ms as u64),
+		};
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let id = NEXT_PARKED_ID.fetch_add(1, Ordering::Relaxed);
+			let parked = ParkedSession {
+				id,
+				session: *from_handle(session_in),
+				event_loop: *from_handle(handle_in),
+			};
+			let replaced = PARKED_SESSIONS.lock().unwrap().insert(name.clone(), parked);
+			if let Some(replaced) = replaced {
+				rt.spawn(close_parked(replaced));
+			}
+
+			rt.spawn(async move {
+				tokio::time::sleep(idle_timeout).await;
+				let expired = {
+					let mut parked_sessions = PARKED_SESSIONS.lock().unwrap();
+					match parked_sessions.get(&name) {
+						Some(parked) if parked.id == id => parked_sessions.remove(&name),
+						_ => None,
+					}
+				};
+				if let Some(expired) = expired {
+					close_parked(expired).await;
+				}
+			});
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Take the session parked under name_str, it is checked with a read of
+// Server_ServerStatus_State. ERR_INVALID_CLIENT_REF if nothing is parked
+// under the name, the status of the failed read if the session is dead
+// (it is closed then and the caller must connect again)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_session_attach(
+	rt_ptr: *mut Runtime,
+	name_str: *const c_char,
+	session_out: *mut *mut Arc<Session>,
+	handle_out: *mut *mut JoinHandle<StatusCode>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(name_str, ERR_NULL_POINTER);
+		check_null!(session_out, ERR_NULL_POINTER);
+		check_null!(handle_out, ERR_NULL_POINTER);
+
+		let name = cstr_to_string!(name_str);
+		let Some(parked) = PARKED_SESSIONS.lock().unwrap().remove(&name) else {
+			set_last_error_detail(format!("No session parked as '{name}'"));
+			return ERR_INVALID_CLIENT_REF;
+		};
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			rt.block_on(async {
+				let alive = if parked.event_loop.is_finished() {
+					StatusCode::BadConnectionClosed.bits() as i32
+				} else {
+					crate::client::keepalive_ping(&parked.session, ATTACH_PING_TIMEOUT).await
+				};
+				if alive != 1 {
+					set_last_error_detail(format!("Session parked as '{name}' is not alive"));
+					close_parked(parked).await;
+					// 0 is a timeout of the read
+					return match alive {
+						0 => StatusCode::BadTimeout.bits() as i32,
+						err => err,
+					};
+				}
+				*session_out = into_handle(parked.session, HandleKind::Session);
+				*handle_out = into_handle(parked.event_loop, HandleKind::JoinHandle);
+				NO_ERR
+			})
+		}
+	})
+}
@@ -0,0 +1,153 @@
+//==============================================================================
+//
+// Title:		Endpoint URL diagnostics
+// Purpose:		Find out why the connection to the server fails:
+//				bad URL, DNS, TCP or OPC UA Hello
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+use crate::errors::*;
+use crate::labview::{LStrHandle, string_to_lstr};
+
+use libc::c_char;
+use opcua::{
+	core::{
+		comms::{
+			tcp_types::{ErrorMessage, HelloMessage, MESSAGE_HEADER_LEN},
+			url::hostname_port_from_url,
+		},
+		constants::DEFAULT_OPC_UA_SERVER_PORT,
+	},
+	types::{DecodingOptions, SimpleBinaryDecodable, SimpleBinaryEncodable},
+};
+use std::{io::ErrorKind, net::SocketAddr, time::Duration};
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::{TcpStream, lookup_host},
+	time::timeout,
+};
+
+// Budget of each step (DNS, TCP connect, Hello)
+const DIAGNOSE_STEP_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Buffer sizes announced in the Hello, only to get Acknowledge/Error back
+const HELLO_BUFFER_SIZE: usize = 65535;
+
+//==============================================================================
+// Check the endpoint URL step by step, the first failing step gives the
+// error code (ERR_BAD_URL, ERR_DNS_FAILED, ERR_TCP_REFUSED, ERR_TCP_TIMEOUT,
+// ERR_HELLO_REJECTED) and the text for the last error detail
+//
+pub async fn diagnose_endpoint_url(url: &str) -> Result<(), (i32, String)> {
+	let (host, port) = hostname_port_from_url(url, DEFAULT_OPC_UA_SERVER_PORT).map_err(|_| {
+		(
+			ERR_BAD_URL,
+			format!("'{url}' is not a valid endpoint URL, expected opc.tcp://host:port"),
+		)
+	})?;
+
+	let addrs: Vec<SocketAddr> =
+		match timeout(DIAGNOSE_STEP_TIMEOUT, lookup_host((host.as_str(), port))).await {
+			Ok(Ok(addrs)) => addrs.collect(),
+			Ok(Err(e)) => return Err((ERR_DNS_FAILED, format!("Can't resolve '{host}': {e}"))),
+			Err(_) => return Err((ERR_DNS_FAILED, format!("Timeout resolving '{host}'"))),
+		};
+	if addrs.is_empty() {
+		return Err((ERR_DNS_FAILED, format!("'{host}' has no addresses")));
+	}
+
+	// Try all the addresses, report the last failure
+	let mut failure = (ERR_TCP_REFUSED, String::new());
+	for addr in addrs {
+		let mut stream = match timeout(DIAGNOSE_STEP_TIMEOUT, TcpStream::connect(addr)).await {
+			Ok(Ok(stream)) => stream,
+			Ok(Err(e)) if e.kind() == ErrorKind::TimedOut => {
+				failure = (ERR_TCP_TIMEOUT, format!("Timeout connecting to {addr}"));
+				continue;
+			}
+			Ok(Err(e)) => {
+				failure = (ERR_TCP_REFUSED, format!("Connection to {addr} failed: {e}"));
+				continue;
+			}
+			Err(_) => {
+				failure = (ERR_TCP_TIMEOUT, format!("Timeout connecting to {addr}"));
+				continue;
+			}
+		};
+		return match timeout(DIAGNOSE_STEP_TIMEOUT, hello(&mut stream, url)).await {
+			Ok(Ok(())) => Ok(()),
+			Ok(Err(detail)) => Err((ERR_HELLO_REJECTED, format!("{addr}: {detail}"))),
+			Err(_) => Err((
+				ERR_HELLO_REJECTED,
+				format!("{addr}: no answer to Hello, not an OPC UA server?"),
+			)),
+		};
+	}
+	Err(failure)
+}
+
+// Send Hello, Ok if the server answers with Acknowledge
+async fn hello(stream: &mut TcpStream, url: &str) -> Result<(), String> {
+	let hello = HelloMessage::new(url, HELLO_BUFFER_SIZE, HELLO_BUFFER_SIZE, 0, 0);
+	let mut request = Vec::with_capacity(hello.byte_len());
+	hello.encode(&mut request).map_err(|e| e.to_string())?;
+	stream
+		.write_all(&request)
+		.await
+		.map_err(|e| e.to_string())?;
+
+	let mut header = [0u8; MESSAGE_HEADER_LEN];
+	stream
+		.read_exact(&mut header)
+		.await
+		.map_err(|e| e.to_string())?;
+	match &header[0..3] {
+		b"ACK" => Ok(()),
+		b"ERR" => {
+			let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+			let mut message = header.to_vec();
+			message.resize(size.clamp(MESSAGE_HEADER_LEN, HELLO_BUFFER_SIZE), 0);
+			stream
+				.read_exact(&mut message[MESSAGE_HEADER_LEN..])
+				.await
+				.map_err(|e| e.to_string())?;
+			match ErrorMessage::decode(&mut message.as_slice(), &DecodingOptions::default()) {
+				Ok(error) => Err(format!("Hello rejected: {} {}", error.error, error.reason)),
+				Err(_) => Err("Hello rejected".to_string()),
+			}
+		}
+		_ => Err("unexpected answer to Hello, not an OPC UA server?".to_string()),
+	}
+}
+
+//==============================================================================
+// Check the endpoint URL before connecting (or after the connect failed).
+// Returns NO_ERR if the server answers the Hello, otherwise ERR_BAD_URL,
+// ERR_DNS_FAILED, ERR_TCP_REFUSED, ERR_TCP_TIMEOUT or ERR_HELLO_REJECTED.
+// detail_out receives the text of the diagnosis, takes up to ~10 seconds
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_validate_endpoint_url(url: *const c_char, detail_out: LStrHandle) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(url, ERR_NULL_POINTER);
+		check_null!(detail_out, ERR_NULL_POINTER);
+
+		let url = cstr_to_string!(url);
+		let rt = match tokio::runtime::Builder::new_current_thread()
+			.enable_all()
+			.build()
+		{
+			Ok(rt) => rt,
+			Err(_) => return ERR_INVALID_RUNTIME,
+		};
+		let (err, detail) = match rt.block_on(diagnose_endpoint_url(&url)) {
+			Ok(()) => (NO_ERR, format!("{url} is reachable")),
+			Err((err, detail)) => (err, detail),
+		};
+		set_last_error_detail(detail.clone());
+		unsafe { string_to_lstr(&detail, detail_out) };
+		err
+	})
+}
@@ -9,12 +9,23 @@
 // 21-MAR-2025 - ns added
 //==============================================================================
 
+use crate::client::read_value_ids;
+use crate::errors::*;
+use crate::labview::{
+	LStrArrayHandle, LStrHandle, LV_ARRAY_TYPE_FLAG, LVDataTypeId, TVariant, lstr_array_to_strings,
+	lv_variant_to_variant, string_to_lstr, strings_to_lstr_array, variant_to_lv_variant,
+};
+use crate::server_variables::lv_parse_value;
+use crate::utils::date_time_to_cocoa;
 use opcua::{
 	client::Session,
 	//crypto::SecurityPolicy, //later
-	types::{NodeId, TimestampsToReturn, Variant},
+	types::{
+		AttributeId, ByteString, Context, DataValue, NodeId, NumericRange, ReadValueId, StatusCode,
+		TimestampsToReturn, Variant, VariantScalarTypeId, WriteValue,
+	},
 };
-use std::{os::raw::*, sync::Arc};
+use std::{ffi::CStr, os::raw::*, str::FromStr, sync::Arc};
 use tokio::runtime::Runtime;
 
 macro_rules! create_lv_read_variable {
@@ -28,54 +39,56 @@ macro_rules! create_lv_read_variable {
 			ns: u16,
 			output: *mut $c_type,
 		) -> i32 {
-			if lv_session.is_null() {
-				return -1;
-			}
-			if rt_ptr.is_null() {
-				return -2;
-			}
+			catch_panic!(ERR_INTERNAL_PANIC, {
+				if lv_session.is_null() {
+					return -1;
+				}
+				if rt_ptr.is_null() {
+					return -2;
+				}
 
-			let session = unsafe { &mut *lv_session };
+				let session = unsafe { &mut *lv_session };
 
-			let vurl_str = unsafe {
-				match std::ffi::CStr::from_ptr(vurl).to_str() {
-					Ok(s) => s.to_string(),
-					Err(_) => return -3,
-				}
-			};
-			unsafe {
-				let rt = &mut *rt_ptr;
-				let var = rt.block_on(async {
-					session
-						.read(
-							&[NodeId::new(ns, vurl_str).into()],
-							TimestampsToReturn::Both,
-							0.0,
-						)
-						.await
-				});
-
-				match var {
-					Ok(read_values) => {
-						if let Some(data_value) = read_values.first() {
-							if let Some(variant) = &data_value.value {
-								if let Variant::$variant(value) = variant {
-									*output = *value as $c_type;
-
-									return 0;
+				let vurl_str = unsafe {
+					match std::ffi::CStr::from_ptr(vurl).to_str() {
+						Ok(s) => s.to_string(),
+						Err(_) => return -3,
+					}
+				};
+				unsafe {
+					let rt = &mut *rt_ptr;
+					let var = rt.block_on(async {
+						session
+							.read(
+								&[NodeId::new(ns, vurl_str).into()],
+								TimestampsToReturn::Both,
+								0.0,
+							)
+							.await
+					});
+
+					match var {
+						Ok(read_values) => {
+							if let Some(data_value) = read_values.first() {
+								if let Some(variant) = &data_value.value {
+									if let Variant::$variant(value) = variant {
+										*output = *value as $c_type;
+
+										return 0;
+									} else {
+										-4 //Type mismatch
+									}
 								} else {
-									-4 //Type mismatch
+									-5 //No value
 								}
 							} else {
-								-5 //No value
+								-6
 							}
-						} else {
-							-6
 						}
+						Err(_) => -7, //Bad quality
 					}
-					Err(_) => -7, //Bad quality
 				}
-			}
+			})
 		}
 	};
 }
@@ -91,3 +104,1040 @@ create_lv_read_variable!(lv_read_variableInt64, i64, c_longlong, Int64);
 create_lv_read_variable!(lv_read_variableUInt64, u64, c_ulonglong, UInt64);
 create_lv_read_variable!(lv_read_variableFloat, f32, c_float, Float);
 create_lv_read_variable!(lv_read_variableDouble, f64, c_double, Double); // 11
+
+//==============================================================================
+// Read the DateTime variable as LabVIEW timestamp (Cocoa).
+// ERR_INVALID_TYPE if the value isn't a DateTime
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_read_variableDateTime(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	cocoa_timestamp_out: *mut f64,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(cocoa_timestamp_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+
+			let r = rt.block_on(async {
+				session
+					.read(&[node_id.into()], TimestampsToReturn::Neither, 0.0)
+					.await
+			});
+			let data_value = match r {
+				Ok(values) => match values.into_iter().next() {
+					Some(data_value) => data_value,
+					None => return StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => return status.bits() as i32,
+			};
+			if data_value.status().is_bad() {
+				return data_value.status().bits() as i32;
+			}
+			let Some(Variant::DateTime(value)) = data_value.value else {
+				set_last_error_detail("The value isn't a DateTime");
+				return ERR_INVALID_TYPE;
+			};
+			*cocoa_timestamp_out = date_time_to_cocoa(&value);
+			NO_ERR
+		}
+	})
+}
+
+//==============================================================================
+// Read the Enumeration variable (the index of its label in EnumStrings).
+// ERR_INVALID_TYPE if the value isn't an Int32
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_read_variableEnum(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	value_out: *mut i32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(value_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+
+			let r = rt.block_on(async {
+				session
+					.read(&[node_id.into()], TimestampsToReturn::Neither, 0.0)
+					.await
+			});
+			let data_value = match r {
+				Ok(values) => match values.into_iter().next() {
+					Some(data_value) => data_value,
+					None => return StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => return status.bits() as i32,
+			};
+			if data_value.status().is_bad() {
+				return data_value.status().bits() as i32;
+			}
+			let Some(Variant::Int32(value)) = data_value.value else {
+				set_last_error_detail("The value isn't an enumeration (Int32)");
+				return ERR_INVALID_TYPE;
+			};
+			*value_out = value;
+			NO_ERR
+		}
+	})
+}
+
+//==============================================================================
+// Read the Guid variable into guid_str_out as
+// {xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx}, the format of lv_write_variableGuid.
+// ERR_INVALID_TYPE if the value isn't a Guid
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_read_variableGuid(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	guid_str_out: LStrHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(guid_str_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+
+			let r = rt.block_on(async {
+				session
+					.read(&[node_id.into()], TimestampsToReturn::Neither, 0.0)
+					.await
+			});
+			let data_value = match r {
+				Ok(values) => match values.into_iter().next() {
+					Some(data_value) => data_value,
+					None => return StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => return status.bits() as i32,
+			};
+			if data_value.status().is_bad() {
+				return data_value.status().bits() as i32;
+			}
+			let Some(Variant::Guid(value)) = data_value.value else {
+				set_last_error_detail("The value isn't a Guid");
+				return ERR_INVALID_TYPE;
+			};
+			string_to_lstr(&format!("{{{value}}}"), guid_str_out)
+		}
+	})
+}
+
+//==============================================================================
+// Read the ByteString variable into output_ptr, which has room for max_len
+// bytes. actual_len_out receives the length of the value (0 for a null
+// ByteString), ERR_BUFFER_TOO_SMALL and nothing copied if it is longer than
+// max_len, call again with a buffer of actual_len_out bytes.
+// ERR_INVALID_TYPE if the value isn't a ByteString
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_read_variableByteString(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	output_ptr: *mut u8,
+	max_len: u32,
+	actual_len_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(actual_len_out, ERR_NULL_POINTER);
+		if max_len > 0 && output_ptr.is_null() {
+			return ERR_NULL_POINTER;
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+
+			let r = rt.block_on(async {
+				session
+					.read(&[node_id.into()], TimestampsToReturn::Neither, 0.0)
+					.await
+			});
+			let data_value = match r {
+				Ok(values) => match values.into_iter().next() {
+					Some(data_value) => data_value,
+					None => return StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => return status.bits() as i32,
+			};
+			if data_value.status().is_bad() {
+				return data_value.status().bits() as i32;
+			}
+			let Some(Variant::ByteString(value)) = data_value.value else {
+				set_last_error_detail("The value isn't a ByteString");
+				return ERR_INVALID_TYPE;
+			};
+			let bytes = value.as_ref();
+			*actual_len_out = bytes.len() as u32;
+			if bytes.len() > max_len as usize {
+				set_last_error_detail(format!(
+					"The ByteString has {} bytes, the buffer {max_len}",
+					bytes.len()
+				));
+				return ERR_BUFFER_TOO_SMALL;
+			}
+			if !bytes.is_empty() {
+				std::ptr::copy_nonoverlapping(bytes.as_ptr(), output_ptr, bytes.len());
+			}
+			NO_ERR
+		}
+	})
+}
+
+//==============================================================================
+// Read the array variable (e.g. of lv_add_variable_array*) into output_ptr,
+// which has room for capacity values. count_out receives the length of the
+// array, only the first capacity elements are copied if it is longer.
+// index_range_str reads a slice only ("100:199", "5", "0:9,0:1" for 2D, row
+// by row), null or empty - the whole array.
+// ERR_INVALID_TYPE if the value isn't an array of the type, ERR_INVALID_ARGUMENT
+// if index_range_str isn't a valid range, BadIndexRangeNoData if the array
+// ends before it
+//
+macro_rules! create_lv_read_array_variable {
+	($fn_name:ident, $value_type:ty, $variant:ident) => {
+		#[allow(non_snake_case)]
+		#[unsafe(no_mangle)]
+		pub extern "C" fn $fn_name(
+			rt_ptr: *mut Runtime,
+			session_in: *mut Arc<Session>,
+			ns: u16,
+			node_str: *const c_char,
+			index_range_str: *const c_char,
+			output_ptr: *mut $value_type,
+			capacity: i32,
+			count_out: *mut i32,
+		) -> i32 {
+			catch_panic!(ERR_INTERNAL_PANIC, {
+				check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+				check_null!(session_in, ERR_INVALID_CLIENT_REF);
+				check_null!(node_str, ERR_NULL_POINTER);
+				check_null!(count_out, ERR_NULL_POINTER);
+				if capacity < 0 || (capacity > 0 && output_ptr.is_null()) {
+					return ERR_INVALID_ARGUMENT;
+				}
+				let index_range = match parse_index_range(index_range_str) {
+					Ok(index_range) => index_range,
+					Err(err) => return err,
+				};
+
+				unsafe {
+					let rt = &mut *rt_ptr;
+					let session = &mut *session_in;
+					let read_value_id = ReadValueId {
+						node_id: NodeId::new(ns, cstr_to_string!(node_str)),
+						attribute_id: AttributeId::Value as u32,
+						index_range,
+						..Default::default()
+					};
+
+					let r = rt.block_on(async {
+						session
+							.read(&[read_value_id], TimestampsToReturn::Neither, 0.0)
+							.await
+					});
+					let data_value = match r {
+						Ok(values) => match values.into_iter().next() {
+							Some(data_value) => data_value,
+							None => return StatusCode::BadUnexpectedError.bits() as i32,
+						},
+						Err(status) => return status.bits() as i32,
+					};
+					if data_value.status().is_bad() {
+						return data_value.status().bits() as i32;
+					}
+
+					let Some(Variant::Array(array)) = data_value.value else {
+						set_last_error_detail("The value isn't an array");
+						return ERR_INVALID_TYPE;
+					};
+					for (i, value) in array.values.iter().enumerate() {
+						let Variant::$variant(value) = value else {
+							set_last_error_detail(format!(
+								"The array isn't {} but {}",
+								stringify!($variant),
+								array.value_type
+							));
+							return ERR_INVALID_TYPE;
+						};
+						if i < capacity as usize {
+							*output_ptr.add(i) = *value;
+						}
+					}
+					*count_out = array.values.len() as i32;
+					NO_ERR
+				}
+			})
+		}
+	};
+}
+
+create_lv_read_array_variable!(lv_read_variable_array_Boolean, bool, Boolean); // 1
+create_lv_read_array_variable!(lv_read_variable_array_SByte, i8, SByte); // 2
+create_lv_read_array_variable!(lv_read_variable_array_Byte, u8, Byte); // 3
+create_lv_read_array_variable!(lv_read_variable_array_Int16, i16, Int16); //...
+create_lv_read_array_variable!(lv_read_variable_array_UInt16, u16, UInt16);
+create_lv_read_array_variable!(lv_read_variable_array_Int32, i32, Int32);
+create_lv_read_array_variable!(lv_read_variable_array_UInt32, u32, UInt32);
+create_lv_read_array_variable!(lv_read_variable_array_Int64, i64, Int64);
+create_lv_read_array_variable!(lv_read_variable_array_UInt64, u64, UInt64);
+create_lv_read_array_variable!(lv_read_variable_array_Float, f32, Float);
+create_lv_read_array_variable!(lv_read_variable_array_Double, f64, Double); // 11
+
+//==============================================================================
+// Read the String array variable (e.g. of lv_add_string_array_variable) into
+// the LabVIEW 1D string array, which is resized as needed. count_out
+// receives the number of strings, null strings of the server are empty.
+// index_range_str reads a slice only as with lv_read_variable_array*.
+// ERR_INVALID_TYPE if the value isn't a String array
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_read_variable_array_String(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	index_range_str: *const c_char,
+	lstr_array_out: LStrArrayHandle,
+	count_out: *mut i32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(lstr_array_out, ERR_NULL_POINTER);
+		check_null!(count_out, ERR_NULL_POINTER);
+		let index_range = match parse_index_range(index_range_str) {
+			Ok(index_range) => index_range,
+			Err(err) => return err,
+		};
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let read_value_id = ReadValueId {
+				node_id: NodeId::new(ns, cstr_to_string!(node_str)),
+				attribute_id: AttributeId::Value as u32,
+				index_range,
+				..Default::default()
+			};
+
+			let r = rt.block_on(async {
+				session
+					.read(&[read_value_id], TimestampsToReturn::Neither, 0.0)
+					.await
+			});
+			let data_value = match r {
+				Ok(values) => match values.into_iter().next() {
+					Some(data_value) => data_value,
+					None => return StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => return status.bits() as i32,
+			};
+			if data_value.status().is_bad() {
+				return data_value.status().bits() as i32;
+			}
+
+			let Some(Variant::Array(array)) = data_value.value else {
+				set_last_error_detail("The value isn't an array");
+				return ERR_INVALID_TYPE;
+			};
+			let mut strings = Vec::with_capacity(array.values.len());
+			for value in &array.values {
+				let Variant::String(s) = value else {
+					set_last_error_detail(format!(
+						"The array isn't String but {}",
+						array.value_type
+					));
+					return ERR_INVALID_TYPE;
+				};
+				strings.push(s.as_ref().to_string());
+			}
+			let err = strings_to_lstr_array(&strings, lstr_array_out);
+			if err != 0 {
+				return err; // LabVIEW error
+			}
+			*count_out = strings.len() as i32;
+			NO_ERR
+		}
+	})
+}
+
+// Elements start_idx..=end_idx of an array, a single element is an index
+// ("5:5" isn't a valid range)
+fn index_range(start_idx: u32, end_idx: u32) -> NumericRange {
+	if start_idx == end_idx {
+		NumericRange::Index(start_idx)
+	} else {
+		NumericRange::Range(start_idx, end_idx)
+	}
+}
+
+// Index range of the text ("5", "100:199", "0:9,0:1" for 2D), null or empty
+// is the whole value. ERR_INVALID_ARGUMENT if it isn't NumericRange syntax,
+// checked here instead of the server's BadIndexRangeInvalid
+pub fn parse_index_range(index_range_str: *const c_char) -> Result<NumericRange, i32> {
+	if index_range_str.is_null() {
+		return Ok(NumericRange::None);
+	}
+	let text = unsafe { CStr::from_ptr(index_range_str) }.to_string_lossy();
+	match NumericRange::from_str(text.trim()) {
+		Ok(range) => Ok(range),
+		Err(_) => {
+			set_last_error_detail(format!("Invalid index range '{text}'"));
+			Err(ERR_INVALID_ARGUMENT)
+		}
+	}
+}
+
+//==============================================================================
+// Read the elements start_idx..=end_idx of the Int32 array variable (e.g. a
+// part of a large waveform) without reading all of it. output_ptr shall have
+// room for end_idx - start_idx + 1 values, count_out receives the number read,
+// fewer if the array ends before end_idx.
+// ERR_INVALID_ARGUMENT if start_idx > end_idx, ERR_INVALID_TYPE if the value
+// isn't an Int32 array, BadIndexRangeNoData if the array ends before start_idx
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_read_variable_index_range_Int32(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	start_idx: u32,
+	end_idx: u32,
+	output_ptr: *mut i32,
+	count_out: *mut i32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(output_ptr, ERR_NULL_POINTER);
+		check_null!(count_out, ERR_NULL_POINTER);
+		if start_idx > end_idx {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let read_value_id = ReadValueId {
+				node_id: NodeId::new(ns, cstr_to_string!(node_str)),
+				attribute_id: AttributeId::Value as u32,
+				index_range: index_range(start_idx, end_idx),
+				..Default::default()
+			};
+
+			let r = rt.block_on(async {
+				session
+					.read(&[read_value_id], TimestampsToReturn::Neither, 0.0)
+					.await
+			});
+			let data_value = match r {
+				Ok(values) => match values.into_iter().next() {
+					Some(data_value) => data_value,
+					None => return StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => return status.bits() as i32,
+			};
+			if data_value.status().is_bad() {
+				return data_value.status().bits() as i32;
+			}
+
+			let Some(Variant::Array(array)) = data_value.value else {
+				set_last_error_detail("The value isn't an array");
+				return ERR_INVALID_TYPE;
+			};
+			let capacity = (end_idx - start_idx) as usize + 1;
+			let mut count = 0;
+			for value in array.values.iter().take(capacity) {
+				let Variant::Int32(value) = value else {
+					set_last_error_detail(format!(
+						"The array isn't Int32 but {}",
+						array.value_type
+					));
+					return ERR_INVALID_TYPE;
+				};
+				*output_ptr.add(count) = *value;
+				count += 1;
+			}
+			*count_out = count as i32;
+			NO_ERR
+		}
+	})
+}
+
+//==============================================================================
+// Write count values from input_ptr to the elements start_idx..=end_idx of the
+// Int32 array variable, the other elements are unchanged.
+// count shall be end_idx - start_idx + 1, else ERR_INVALID_ARGUMENT.
+// Servers which don't write parts of arrays return BadWriteNotSupported
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_variable_index_range_Int32(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	start_idx: u32,
+	end_idx: u32,
+	input_ptr: *const i32,
+	count: i32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(input_ptr, ERR_NULL_POINTER);
+		if start_idx > end_idx || count as i64 != (end_idx - start_idx) as i64 + 1 {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let values = std::slice::from_raw_parts(input_ptr, count as usize).to_vec();
+			let write_value = WriteValue {
+				node_id: NodeId::new(ns, cstr_to_string!(node_str)),
+				attribute_id: AttributeId::Value as u32,
+				index_range: index_range(start_idx, end_idx),
+				value: DataValue::value_only(Variant::from(values)),
+			};
+
+			match rt.block_on(async { session.write(&[write_value]).await }) {
+				Ok(results) => match results.first() {
+					Some(status) if status.is_bad() => status.bits() as i32,
+					Some(_) => NO_ERR,
+					None => StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+// Write the array value with the index range, the server reports a bad range
+async fn write_slice(
+	session: &Session,
+	node_id: NodeId,
+	index_range: NumericRange,
+	value: Variant,
+) -> i32 {
+	let write_value = WriteValue {
+		node_id,
+		attribute_id: AttributeId::Value as u32,
+		index_range,
+		value: DataValue::value_only(value),
+	};
+	match session.write(&[write_value]).await {
+		Ok(results) => match results.first() {
+			Some(status) if status.is_bad() => status.bits() as i32,
+			Some(_) => NO_ERR,
+			None => StatusCode::BadUnexpectedError.bits() as i32,
+		},
+		Err(status) => status.bits() as i32,
+	}
+}
+
+//==============================================================================
+// Write count values from values_in to the slice index_range_str ("100:199",
+// "5", "0:9,0:1" for 2D, row by row) of the array variable, the other elements
+// are unchanged. Null or empty index_range_str writes the whole array.
+// ERR_INVALID_ARGUMENT if index_range_str isn't a valid range,
+// BadIndexRangeNoData if the array ends before it, BadIndexRangeInvalid if
+// count doesn't fit it, BadWriteNotSupported if the server doesn't write
+// parts of arrays
+//
+macro_rules! create_lv_write_array_slice {
+	($fn_name:ident, $value_type:ty) => {
+		#[allow(non_snake_case)]
+		#[unsafe(no_mangle)]
+		pub extern "C" fn $fn_name(
+			rt_ptr: *mut Runtime,
+			session_in: *mut Arc<Session>,
+			ns: u16,
+			node_str: *const c_char,
+			index_range_str: *const c_char,
+			values_in: *const $value_type,
+			count: i32,
+		) -> i32 {
+			catch_panic!(ERR_INTERNAL_PANIC, {
+				check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+				check_null!(session_in, ERR_INVALID_CLIENT_REF);
+				check_null!(node_str, ERR_NULL_POINTER);
+				check_null!(values_in, ERR_NULL_POINTER);
+				if count <= 0 {
+					return ERR_INVALID_ARGUMENT;
+				}
+				let index_range = match parse_index_range(index_range_str) {
+					Ok(index_range) => index_range,
+					Err(err) => return err,
+				};
+
+				unsafe {
+					let rt = &mut *rt_ptr;
+					let session = &mut *session_in;
+					let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+					let values = std::slice::from_raw_parts(values_in, count as usize).to_vec();
+					rt.block_on(write_slice(
+						session,
+						node_id,
+						index_range,
+						Variant::from(values),
+					))
+				}
+			})
+		}
+	};
+}
+
+create_lv_write_array_slice!(lv_write_variable_slice_Boolean, bool); // 1
+create_lv_write_array_slice!(lv_write_variable_slice_SByte, i8); // 2
+create_lv_write_array_slice!(lv_write_variable_slice_Byte, u8); // 3
+create_lv_write_array_slice!(lv_write_variable_slice_Int16, i16); //...
+create_lv_write_array_slice!(lv_write_variable_slice_UInt16, u16);
+create_lv_write_array_slice!(lv_write_variable_slice_Int32, i32);
+create_lv_write_array_slice!(lv_write_variable_slice_UInt32, u32);
+create_lv_write_array_slice!(lv_write_variable_slice_Int64, i64);
+create_lv_write_array_slice!(lv_write_variable_slice_UInt64, u64);
+create_lv_write_array_slice!(lv_write_variable_slice_Float, f32);
+create_lv_write_array_slice!(lv_write_variable_slice_Double, f64); // 11
+
+//==============================================================================
+// Write the strings of the LabVIEW 1D string array to the slice
+// index_range_str of the String array variable as lv_write_variable_slice*
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_variable_slice_String(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	index_range_str: *const c_char,
+	lstr_array_in: LStrArrayHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(lstr_array_in, ERR_NULL_POINTER);
+		let index_range = match parse_index_range(index_range_str) {
+			Ok(index_range) => index_range,
+			Err(err) => return err,
+		};
+
+		unsafe {
+			let strings = lstr_array_to_strings(lstr_array_in);
+			if strings.is_empty() {
+				return ERR_INVALID_ARGUMENT;
+			}
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+			rt.block_on(write_slice(
+				session,
+				node_id,
+				index_range,
+				Variant::from(strings),
+			))
+		}
+	})
+}
+
+//==============================================================================
+// Read the value of the node into the LabVIEW Variant, whatever its type.
+// type_id_out receives the LVDataTypeId of the value (1 Boolean .. 12 String)
+// for Variant To Data, so servers may change the type at runtime.
+// ERR_INVALID_TYPE for other types (arrays, structures...)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_read_variable_to_lv_variant(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	lv_variant_hdl: TVariant,
+	type_id_out: *mut u16,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(lv_variant_hdl, ERR_NULL_POINTER);
+		check_null!(type_id_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+
+			let r = rt.block_on(async {
+				session
+					.read(&[node_id.into()], TimestampsToReturn::Neither, 0.0)
+					.await
+			});
+			let data_value = match r {
+				Ok(values) => match values.into_iter().next() {
+					Some(data_value) => data_value,
+					None => return StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => return status.bits() as i32,
+			};
+			if data_value.status().is_bad() {
+				return data_value.status().bits() as i32;
+			}
+
+			match variant_to_lv_variant(&data_value.value.unwrap_or_default(), lv_variant_hdl) {
+				Ok(type_id) => {
+					*type_id_out = type_id as u16;
+					NO_ERR
+				}
+				Err(err) => err,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Write the value of the LabVIEW Variant to the node, type_id is the
+// LVDataTypeId of the data in the Variant (1 Boolean .. 12 String) and the
+// type written. ERR_INVALID_TYPE if the Variant holds another type
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_lv_variant_to_variable(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	lv_variant_hdl: TVariant,
+	type_id: u16,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(lv_variant_hdl, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let value = match lv_variant_to_variant(lv_variant_hdl, type_id) {
+				Ok(value) => value,
+				Err(err) => return err,
+			};
+			let write_value = WriteValue {
+				node_id: NodeId::new(ns, cstr_to_string!(node_str)),
+				attribute_id: AttributeId::Value as u32,
+				index_range: NumericRange::None,
+				value: DataValue::value_only(value),
+			};
+
+			match rt.block_on(async { session.write(&[write_value]).await }) {
+				Ok(results) => match results.first() {
+					Some(status) if status.is_bad() => status.bits() as i32,
+					Some(_) => NO_ERR,
+					None => StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+// Element of a value as JSON for the arrays of lv_read_value_as_string:
+// Boolean and numbers as JSON values, the other types as their text
+fn value_json(ctx: &Context<'_>, v: &Variant) -> serde_json::Value {
+	match v {
+		Variant::Boolean(v) => (*v).into(),
+		Variant::SByte(v) => (*v).into(),
+		Variant::Byte(v) => (*v).into(),
+		Variant::Int16(v) => (*v).into(),
+		Variant::UInt16(v) => (*v).into(),
+		Variant::Int32(v) => (*v).into(),
+		Variant::UInt32(v) => (*v).into(),
+		Variant::Int64(v) => (*v).into(),
+		Variant::UInt64(v) => (*v).into(),
+		// NaN and Inf are not JSON numbers, null then
+		Variant::Float(v) => (*v).into(),
+		Variant::Double(v) => (*v).into(),
+		v => value_text(ctx, v).into(),
+	}
+}
+
+// Canonical text of the value: DateTime ISO 8601 (UTC), ByteString base64,
+// QualifiedName "ns:name", LocalizedText its text, ExtensionObject
+// {"TypeId": <binary encoding id>, "Body": "<base64 of the binary body>"},
+// arrays as JSON arrays (all dimensions flattened)
+fn value_text(ctx: &Context<'_>, v: &Variant) -> String {
+	match v {
+		Variant::Empty => String::new(),
+		Variant::String(s) => s.as_ref().to_string(),
+		Variant::DateTime(t) => t.to_rfc3339(),
+		Variant::ByteString(b) => b.as_base64(),
+		Variant::QualifiedName(q) => format!("{}:{}", q.namespace_index, q.name),
+		Variant::LocalizedText(t) => t.text.to_string(),
+		Variant::ExtensionObject(eo) => match &eo.body {
+			None => String::new(),
+			Some(body) => {
+				let mut bytes = Vec::new();
+				let _ = body.encode_binary(&mut bytes, ctx);
+				serde_json::json!({
+					"TypeId": body.binary_type_id().to_string(),
+					"Body": ByteString::from(bytes).as_base64(),
+				})
+				.to_string()
+			}
+		},
+		Variant::Array(array) => {
+			let values: Vec<_> = array.values.iter().map(|v| value_json(ctx, v)).collect();
+			serde_json::Value::from(values).to_string()
+		}
+		v => v.to_string(),
+	}
+}
+
+// LVDataTypeId of the value, with LV_ARRAY_TYPE_FLAG for arrays,
+// 0 for empty values and types without one (DataValue, Variant...)
+fn value_type_tag(v: &Variant) -> u16 {
+	let tag = v
+		.scalar_type_id()
+		.and_then(|t| LVDataTypeId::from_u16(t as u16))
+		.map_or(0, |t| t as u16);
+	match v {
+		Variant::Array(_) if tag != 0 => tag | LV_ARRAY_TYPE_FLAG,
+		_ => tag,
+	}
+}
+
+//==============================================================================
+// Read the value of the node as text, whatever its type, e.g. for generic HMI
+// displays. type_tag_out receives the LVDataTypeId (1 Boolean .. 12 String,
+// 13 DateTime .. 22 ExtensionObject as the OPC UA type ids) plus 0x100 for
+// arrays, to read it again with the typed function. 0 for empty values and
+// other types. status_out receives the status of the value, a bad value is
+// an empty string and NO_ERR. value_str_out is resized as needed
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_read_value_as_string(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	node_str: *const c_char,
+	ns: u16,
+	value_str_out: LStrHandle,
+	type_tag_out: *mut u16,
+	status_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(value_str_out, ERR_NULL_POINTER);
+		check_null!(type_tag_out, ERR_NULL_POINTER);
+		check_null!(status_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+
+			let r = rt.block_on(async {
+				session
+					.read(&[node_id.into()], TimestampsToReturn::Neither, 0.0)
+					.await
+			});
+			let data_value = match r {
+				Ok(values) => match values.into_iter().next() {
+					Some(data_value) => data_value,
+					None => return StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => return status.bits() as i32,
+			};
+
+			let value = data_value.value.unwrap_or_default();
+			let text = {
+				let ctx_lock = session.context();
+				let ctx_owned = ctx_lock.read();
+				value_text(&ctx_owned.context(), &value)
+			};
+			let err = string_to_lstr(&text, value_str_out);
+			if err != 0 {
+				return err; // LabVIEW error
+			}
+			*type_tag_out = value_type_tag(&value);
+			*status_out = data_value.status.unwrap_or(StatusCode::Good).bits();
+			NO_ERR
+		}
+	})
+}
+
+// Scalar of the type from the text, Err is the name of the type.
+// 1 Boolean .. 14 Guid as lv_add_variable_with_initial_value
+fn parse_typed_value(scalar_type: VariantScalarTypeId, text: &str) -> Result<Variant, String> {
+	lv_parse_value(scalar_type as u16, text).ok_or_else(|| scalar_type.to_string())
+}
+
+// Elements of an array as text: a JSON array as from lv_read_value_as_string
+// (["a","b"] or [1,2]) or comma-separated values. None if the JSON doesn't parse
+fn array_elements(text: &str) -> Option<Vec<String>> {
+	let t = text.trim();
+	if t.is_empty() {
+		return Some(Vec::new());
+	}
+	if !t.starts_with('[') {
+		return Some(t.split(',').map(|e| e.to_string()).collect());
+	}
+	let values: Vec<serde_json::Value> = serde_json::from_str(t).ok()?;
+	Some(
+		values
+			.into_iter()
+			.map(|v| match v {
+				serde_json::Value::String(s) => s,
+				v => v.to_string(),
+			})
+			.collect(),
+	)
+}
+
+//==============================================================================
+// Write the value given as text to the node, whatever its type. The DataType
+// and ValueRank of the node are read first, the text is parsed as: Boolean
+// true/false or 1/0, integers, floats, String as is, DateTime ISO 8601
+// (e.g. 2026-10-16T12:00:00Z), Guid. Arrays (ValueRank >= 0, or -2/-3 with a
+// leading '[') as comma-separated values or a JSON array.
+// ERR_INVALID_ARGUMENT if the text doesn't parse (the detail names the
+// expected type), ERR_INVALID_TYPE for other data types (structures,
+// enumerations, abstract types)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_value_from_string(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	node_str: *const c_char,
+	ns: u16,
+	value_str: *const c_char,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(value_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+			let text = cstr_to_string!(value_str);
+
+			let read_ids =
+				read_value_ids(&[AttributeId::DataType, AttributeId::ValueRank], &node_id);
+			let r = rt.block_on(async {
+				session
+					.read(&read_ids, TimestampsToReturn::Neither, 0.0)
+					.await
+			});
+			let attributes = match r {
+				Ok(values) => values,
+				Err(status) => return status.bits() as i32,
+			};
+			if let Some(bad) = attributes.iter().find(|dv| dv.status().is_bad()) {
+				return bad.status().bits() as i32;
+			}
+			let (Some(Variant::NodeId(data_type)), Some(Variant::Int32(value_rank))) = (
+				attributes.first().and_then(|dv| dv.value.clone()),
+				attributes.get(1).and_then(|dv| dv.value.clone()),
+			) else {
+				set_last_error_detail("The node has no DataType or ValueRank");
+				return ERR_INVALID_TYPE;
+			};
+			// Boolean .. String, DateTime, Guid
+			let scalar_type = match VariantScalarTypeId::try_from(&*data_type) {
+				Ok(t) if t as u32 <= VariantScalarTypeId::Guid as u32 => t,
+				_ => {
+					set_last_error_detail(format!("Data type {data_type} isn't supported"));
+					return ERR_INVALID_TYPE;
+				}
+			};
+
+			let is_array = match value_rank {
+				-1 => false,
+				-2 | -3 => text.trim_start().starts_with('['),
+				_ => true,
+			};
+			let parsed = if is_array {
+				let elements = array_elements(&text).ok_or_else(|| format!("{scalar_type} array"));
+				elements.and_then(|elements| {
+					elements
+						.iter()
+						.map(|e| parse_typed_value(scalar_type, e))
+						.collect::<Result<Vec<_>, _>>()
+						.map(|values| Variant::from((scalar_type, values)))
+				})
+			} else {
+				parse_typed_value(scalar_type, &text)
+			};
+			let value = match parsed {
+				Ok(value) => value,
+				Err(expected) => {
+					set_last_error_detail(format!("'{text}' isn't a valid {expected}"));
+					return ERR_INVALID_ARGUMENT;
+				}
+			};
+
+			let write_value = WriteValue {
+				node_id,
+				attribute_id: AttributeId::Value as u32,
+				index_range: NumericRange::None,
+				value: DataValue::value_only(value),
+			};
+			match rt.block_on(async { session.write(&[write_value]).await }) {
+				Ok(results) => match results.first() {
+					Some(status) if status.is_bad() => status.bits() as i32,
+					Some(_) => NO_ERR,
+					None => StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
@@ -0,0 +1,264 @@
+//==============================================================================
+//
+// Title:		String encoding
+// Purpose:		Convert the strings of LabVIEW (system codepage, e.g.
+//				Windows-1252) from and to the UTF-8 strings of OPC UA
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+//
+// All strings from LabVIEW (cstr_to_string!, lstr_to_string) and to LabVIEW
+// (string_to_lstr, string_to_new_lstr...) pass through here. The default
+// UTF-8 is a passthrough, with lv_set_string_encoding(1252) umlauts of
+// a German LabVIEW arrive in the browse and display names unchanged.
+// Windows-1252 and ISO 8859-1 are converted here, other codepages (e.g. 932
+// Japanese, 936 Chinese) by Windows. Characters the codepage doesn't have
+// are sent to LabVIEW as '?'
+//
+use crate::errors::*;
+
+use std::{
+	borrow::Cow,
+	sync::atomic::{AtomicU32, Ordering},
+};
+
+pub const CP_UTF8: u32 = 65001;
+pub const CP_WINDOWS_1252: u32 = 1252;
+pub const CP_ISO_8859_1: u32 = 28591;
+
+static STRING_CODEPAGE: AtomicU32 = AtomicU32::new(CP_UTF8);
+
+// Windows-1252 0x80..=0x9F, the undefined bytes map to the C1 controls as
+// with Windows
+const WINDOWS_1252_80_9F: [char; 32] = [
+	'\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+	'\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+	'\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+	'\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+fn windows_1252_to_char(b: u8) -> char {
+	match b {
+		0x80..=0x9F => WINDOWS_1252_80_9F[(b - 0x80) as usize],
+		_ => b as char,
+	}
+}
+
+fn char_to_windows_1252(c: char) -> u8 {
+	match c as u32 {
+		0x00..=0x7F | 0xA0..=0xFF => c as u8,
+		_ => WINDOWS_1252_80_9F
+			.iter()
+			.position(|&w| w == c)
+			.map_or(b'?', |i| 0x80 + i as u8),
+	}
+}
+
+#[cfg(windows)]
+mod windows {
+	use std::ptr::null_mut;
+
+	#[link(name = "kernel32")]
+	unsafe extern "system" {
+		fn IsValidCodePage(code_page: u32) -> i32;
+		fn MultiByteToWideChar(
+			code_page: u32,
+			flags: u32,
+			multi_byte: *const u8,
+			multi_byte_len: i32,
+			wide_char: *mut u16,
+			wide_char_len: i32,
+		) -> i32;
+		fn WideCharToMultiByte(
+			code_page: u32,
+			flags: u32,
+			wide_char: *const u16,
+			wide_char_len: i32,
+			multi_byte: *mut u8,
+			multi_byte_len: i32,
+			default_char: *const u8,
+			used_default_char: *mut i32,
+		) -> i32;
+	}
+
+	pub fn is_valid_codepage(codepage: u32) -> bool {
+		unsafe { IsValidCodePage(codepage) != 0 }
+	}
+
+	pub fn decode(codepage: u32, bytes: &[u8]) -> String {
+		unsafe {
+			let len = bytes.len() as i32;
+			let n = MultiByteToWideChar(codepage, 0, bytes.as_ptr(), len, null_mut(), 0);
+			let mut wide = vec![0u16; n.max(0) as usize];
+			let n = MultiByteToWideChar(codepage, 0, bytes.as_ptr(), len, wide.as_mut_ptr(), n);
+			String::from_utf16_lossy(&wide[..n.max(0) as usize])
+		}
+	}
+
+	pub fn encode(codepage: u32, s: &str) -> Vec<u8> {
+		let wide: Vec<u16> = s.encode_utf16().collect();
+		unsafe {
+			let len = wide.len() as i32;
+			let default_char = b"?\0".as_ptr();
+			let n = WideCharToMultiByte(
+				codepage,
+				0,
+				wide.as_ptr(),
+				len,
+				null_mut(),
+				0,
+				default_char,
+				null_mut(),
+			);
+			let mut bytes = vec![0u8; n.max(0) as usize];
+			let n = WideCharToMultiByte(
+				codepage,
+				0,
+				wide.as_ptr(),
+				len,
+				bytes.as_mut_ptr(),
+				n,
+				default_char,
+				null_mut(),
+			);
+			bytes.truncate(n.max(0) as usize);
+			bytes
+		}
+	}
+}
+
+fn is_supported(codepage: u32) -> bool {
+	match codepage {
+		CP_UTF8 | CP_WINDOWS_1252 | CP_ISO_8859_1 => true,
+		#[cfg(windows)]
+		_ => windows::is_valid_codepage(codepage),
+		#[cfg(not(windows))]
+		_ => false,
+	}
+}
+
+fn decode(codepage: u32, bytes: &[u8]) -> String {
+	match codepage {
+		CP_UTF8 => String::from_utf8_lossy(bytes).into_owned(),
+		CP_WINDOWS_1252 => bytes.iter().map(|&b| windows_1252_to_char(b)).collect(),
+		CP_ISO_8859_1 => bytes.iter().map(|&b| b as char).collect(),
+		#[cfg(windows)]
+		codepage => windows::decode(codepage, bytes),
+		#[cfg(not(windows))]
+		_ => String::from_utf8_lossy(bytes).into_owned(),
+	}
+}
+
+fn encode(codepage: u32, s: &str) -> Cow<'_, [u8]> {
+	match codepage {
+		CP_UTF8 => Cow::Borrowed(s.as_bytes()),
+		_ if s.is_ascii() => Cow::Borrowed(s.as_bytes()),
+		CP_WINDOWS_1252 => Cow::Owned(s.chars().map(char_to_windows_1252).collect()),
+		CP_ISO_8859_1 => Cow::Owned(s.chars().map(|c| u8::try_from(c).unwrap_or(b'?')).collect()),
+		#[cfg(windows)]
+		codepage => Cow::Owned(windows::encode(codepage, s)),
+		#[cfg(not(windows))]
+		_ => Cow::Borrowed(s.as_bytes()),
+	}
+}
+
+//==============================================================================
+// String from LabVIEW bytes in the current codepage
+//
+pub fn from_lv_bytes(bytes: &[u8]) -> String {
+	decode(STRING_CODEPAGE.load(Ordering::Relaxed), bytes)
+}
+
+//==============================================================================
+// LabVIEW bytes of the string in the current codepage
+//
+pub fn to_lv_bytes(s: &str) -> Cow<'_, [u8]> {
+	encode(STRING_CODEPAGE.load(Ordering::Relaxed), s)
+}
+
+//==============================================================================
+// Codepage of the LabVIEW strings: 65001 UTF-8 (default, no conversion),
+// 1252 Windows Western European, 28591 ISO 8859-1, on Windows also any other
+// installed codepage, e.g. 1250, 1251, 932, 936 (see "chcp").
+// ERR_NOT_SUPPORTED for an unknown codepage, the encoding is unchanged then.
+// Applies to all strings of all clients and servers of the DLL
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_string_encoding(codepage: u32) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		if !is_supported(codepage) {
+			set_last_error_detail(format!("Codepage {codepage} is not supported"));
+			return ERR_NOT_SUPPORTED;
+		}
+		STRING_CODEPAGE.store(codepage, Ordering::Relaxed);
+		NO_ERR
+	})
+}
+
+// The tests convert with the codepage given, lv_set_string_encoding would
+// change the strings of the tests running in parallel
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const UMLAUTS: &str = "Füllstand Behälter Größe ÄÖÜ";
+	const CJK: &str = "温度センサー 压力";
+
+	#[test]
+	fn umlauts_round_trip() {
+		for codepage in [CP_UTF8, CP_WINDOWS_1252, CP_ISO_8859_1] {
+			let bytes = encode(codepage, UMLAUTS);
+			assert_eq!(decode(codepage, &bytes), UMLAUTS, "codepage {codepage}");
+		}
+		// One byte each in the single-byte codepages
+		assert_eq!(&encode(CP_WINDOWS_1252, "äöüß")[..], b"\xE4\xF6\xFC\xDF");
+		assert_eq!(&encode(CP_ISO_8859_1, "äöüß")[..], b"\xE4\xF6\xFC\xDF");
+		assert_eq!(decode(CP_WINDOWS_1252, b"Gr\xF6\xDFe"), "Größe");
+	}
+
+	#[test]
+	fn windows_1252_80_9f() {
+		assert_eq!(
+			&encode(CP_WINDOWS_1252, "€ „Zitat“ – ™")[..],
+			b"\x80 \x84Zitat\x93 \x96 \x99"
+		);
+		assert_eq!(
+			decode(CP_WINDOWS_1252, b"\x80 \x84Zitat\x93 \x96 \x99"),
+			"€ „Zitat“ – ™"
+		);
+		// ISO 8859-1 has the C1 controls there
+		assert_eq!(decode(CP_ISO_8859_1, b"\x80"), "\u{80}");
+		assert_eq!(&encode(CP_ISO_8859_1, "€")[..], b"?");
+	}
+
+	#[test]
+	fn cjk_round_trip() {
+		let bytes = encode(CP_UTF8, CJK);
+		assert_eq!(&bytes[..], CJK.as_bytes());
+		assert_eq!(decode(CP_UTF8, &bytes), CJK);
+
+		// Not in the Western codepages, '?' for each character
+		for codepage in [CP_WINDOWS_1252, CP_ISO_8859_1] {
+			assert_eq!(&encode(codepage, "温度 x")[..], b"?? x");
+		}
+	}
+
+	// Japanese and Chinese codepages of Windows
+	#[cfg(windows)]
+	#[test]
+	fn cjk_round_trip_windows_codepages() {
+		for (codepage, text) in [(932, "温度センサー"), (936, "温度 压力")] {
+			let bytes = encode(codepage, text);
+			assert!(bytes.len() < text.len(), "codepage {codepage}");
+			assert_eq!(decode(codepage, &bytes), text, "codepage {codepage}");
+		}
+	}
+
+	#[test]
+	fn unknown_codepage_is_rejected() {
+		assert_eq!(lv_set_string_encoding(12345), ERR_NOT_SUPPORTED);
+		assert_eq!(STRING_CODEPAGE.load(Ordering::Relaxed), CP_UTF8);
+	}
+}
@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 pub const NO_ERR: i32 = 0;
+pub const MORE_HISTORY: i32 = 1; // not an error, continuation point present
 pub const ERR_INVALID_RUNTIME: i32 = 5001;
 pub const ERR_INVALID_CLIENT_REF: i32 = 5002;
 pub const ERR_INVALID_SERVER_REF: i32 = 5003;
@@ -8,3 +9,342 @@ pub const ERR_NULL_POINTER: i32 = 5005;
 pub const ERR_INVALID_ARGUMENT: i32 = 5006;
 pub const ERR_INVALID_SERVER_CONFIG: i32 = 5007;
 pub const ERR_BROWSE_ERROR: i32 = 5008;
+pub const ERR_NODE_EXISTS: i32 = 5009;
+pub const ERR_BAD_URL: i32 = 5010;
+pub const ERR_DNS_FAILED: i32 = 5011;
+pub const ERR_NAMESPACE_NOT_FOUND: i32 = 5012;
+pub const ERR_IO: i32 = 5013;
+pub const ERR_NO_SUITABLE_ENDPOINT: i32 = 5014;
+pub const ERR_TIMEOUT: i32 = 5015; // timeout_ms of the call passed
+pub const ERR_NOT_SUPPORTED: i32 = 5016; // not available in this build of the DLL
+pub const ERR_INTERNAL_PANIC: i32 = 5017; // see lv_get_last_panic_message
+pub const ERR_BUFFER_TOO_SMALL: i32 = 5018; // output buffer shorter than the value
+pub const ERR_TCP_REFUSED: i32 = 5019;
+pub const ERR_TCP_TIMEOUT: i32 = 5020;
+pub const ERR_HELLO_REJECTED: i32 = 5021;
+pub const ERR_CERT_IN_USE: i32 = 5022; // own certificate used by a connected session
+pub const ERR_CERT_NOT_FETCHED: i32 = 5023; // no server certificate from unsecured GetEndpoints
+pub const ERR_INVALID_CLIENT_CONFIG: i32 = 5024; // config file doesn't parse or validate
+pub const ERR_CERT_INVALID: i32 = 5025; // bytes are not a DER certificate
+pub const ERR_DISCONNECTED: i32 = 5026; // pooled connection is down (reconnecting)
+pub const JOB_PENDING: i32 = 5027; // not an error, the job isn't finished yet
+
+//==============================================================================
+// Detail text of the last error, for the codes where the number alone
+// isn't enough (e.g. which node already exists)
+//
+use crate::labview::{LStrHandle, string_to_lstr};
+use opcua::{core::config::ConfigError, types::StatusCode};
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::sync::{Mutex, Once};
+
+static LAST_ERROR_DETAIL: Mutex<String> = Mutex::new(String::new());
+
+pub fn set_last_error_detail(detail: impl Into<String>) {
+	*LAST_ERROR_DETAIL.lock().unwrap() = detail.into();
+}
+
+pub fn last_error_detail() -> String {
+	LAST_ERROR_DETAIL.lock().unwrap().clone()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_last_error_detail(detail_out: LStrHandle) -> i32 {
+	crate::catch_panic!(ERR_INTERNAL_PANIC, {
+		if detail_out.is_null() {
+			return ERR_NULL_POINTER;
+		}
+
+		unsafe { string_to_lstr(&last_error_detail(), detail_out) }
+	})
+}
+
+//==============================================================================
+// Error code of a client or server config file that can't be loaded, saved
+// or validated (invalid_config is ERR_INVALID_CLIENT_CONFIG or
+// ERR_INVALID_SERVER_CONFIG), what is wrong is left in the last error detail
+//
+pub fn config_error(path: &str, err: ConfigError, invalid_config: i32) -> i32 {
+	match err {
+		ConfigError::IO(err) => {
+			set_last_error_detail(format!("Cannot access {path}: {err}"));
+			ERR_IO
+		}
+		ConfigError::Yaml(err) => {
+			set_last_error_detail(format!("{path} is not a config file: {err}"));
+			invalid_config
+		}
+		ConfigError::ConfigInvalid(errors) => {
+			set_last_error_detail(errors.join("\n"));
+			invalid_config
+		}
+	}
+}
+
+//==============================================================================
+// Panics at the FFI boundary: a panic unwinding out of an extern "C"
+// function aborts LabVIEW, so the exported functions run their body in
+// catch_panic!() and return ERR_INTERNAL_PANIC instead.
+// The panic hook keeps the message, location and backtrace of the panic
+// in a thread-local buffer (a panic of a task on another tokio worker
+// doesn't overwrite it), catch_panic!() moves it to the last panic message
+//
+static LAST_PANIC_MESSAGE: Mutex<String> = Mutex::new(String::new());
+static PANIC_HOOK: Once = Once::new();
+
+thread_local! {
+	static THREAD_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+pub fn install_panic_hook() {
+	PANIC_HOOK.call_once(|| {
+		let default_hook = std::panic::take_hook();
+		std::panic::set_hook(Box::new(move |info| {
+			let message = format!("{info}\n{}", Backtrace::force_capture());
+			THREAD_PANIC.with(|p| *p.borrow_mut() = Some(message));
+			default_hook(info);
+		}));
+	});
+}
+
+// Called by catch_panic!() with the payload of the caught panic
+pub fn set_panic_message(payload: Box<dyn std::any::Any + Send>) {
+	let message = THREAD_PANIC
+		.with(|p| p.borrow_mut().take())
+		.unwrap_or_else(|| {
+			payload
+				.downcast_ref::<&str>()
+				.map(|s| s.to_string())
+				.or_else(|| payload.downcast_ref::<String>().cloned())
+				.unwrap_or_else(|| "panic with unknown payload".to_string())
+		});
+	set_last_error_detail(format!(
+		"Internal panic: {}",
+		message.lines().next().unwrap_or_default()
+	));
+	*LAST_PANIC_MESSAGE.lock().unwrap() = message;
+}
+
+#[macro_export]
+macro_rules! catch_panic {
+	($on_panic:expr, $body:block) => {{
+		$crate::errors::install_panic_hook();
+		match ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| $body)) {
+			Ok(result) => result,
+			Err(payload) => {
+				$crate::errors::set_panic_message(payload);
+				$on_panic
+			}
+		}
+	}};
+}
+
+//==============================================================================
+// Message, location and backtrace of the last panic caught at the FFI
+// boundary (ERR_INTERNAL_PANIC), empty if there was none
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_last_panic_message(lv_str: LStrHandle) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		if lv_str.is_null() {
+			return ERR_NULL_POINTER;
+		}
+
+		let message = LAST_PANIC_MESSAGE.lock().unwrap().clone();
+		unsafe { string_to_lstr(&message, lv_str) }
+	})
+}
+
+//==============================================================================
+// LabVIEW error cluster {code, source, description} of an error returned by
+// any function of the DLL: the source "opcua-labview::<module>" identifies
+// the part of the DLL (or OPC UA service set) the code belongs to, the
+// description is the text of the ERR_* code or of the OPC UA status code.
+// NO_ERR gives empty strings
+//
+fn error_source_and_description(error_code: i32) -> (&'static str, String) {
+	let (source, description) = match error_code {
+		NO_ERR => return ("", String::new()),
+		MORE_HISTORY => (
+			"opcua-labview::history",
+			"More history data available (continuation point)",
+		),
+		ERR_INVALID_RUNTIME => ("opcua-labview::runtime", "Invalid runtime pointer"),
+		ERR_INVALID_CLIENT_REF => ("opcua-labview::client", "Invalid client or session pointer"),
+		ERR_INVALID_SERVER_REF => ("opcua-labview::server", "Invalid server pointer"),
+		ERR_INVALID_TYPE => (
+			"opcua-labview::variables",
+			"Invalid or unsupported data type",
+		),
+		ERR_NULL_POINTER => ("opcua-labview::dll", "Null pointer argument"),
+		ERR_INVALID_ARGUMENT => ("opcua-labview::dll", "Invalid argument"),
+		ERR_INVALID_SERVER_CONFIG => ("opcua-labview::server", "Invalid server configuration"),
+		ERR_BROWSE_ERROR => ("opcua-labview::browser", "Browse failed"),
+		ERR_NODE_EXISTS => ("opcua-labview::server", "Node already exists"),
+		ERR_BAD_URL => ("opcua-labview::client", "Invalid endpoint URL"),
+		ERR_DNS_FAILED => (
+			"opcua-labview::client",
+			"Host name of the URL could not be resolved",
+		),
+		ERR_NAMESPACE_NOT_FOUND => ("opcua-labview::server", "Namespace not found"),
+		ERR_IO => ("opcua-labview::dll", "File or I/O error"),
+		ERR_NO_SUITABLE_ENDPOINT => (
+			"opcua-labview::client",
+			"No endpoint matches the security settings",
+		),
+		ERR_TIMEOUT => ("opcua-labview::client", "No response within the timeout"),
+		ERR_NOT_SUPPORTED => (
+			"opcua-labview::dll",
+			"Not supported by this build of the DLL",
+		),
+		ERR_INTERNAL_PANIC => (
+			"opcua-labview::dll",
+			"Internal error (panic), see the last panic message",
+		),
+		ERR_BUFFER_TOO_SMALL => (
+			"opcua-labview::variables",
+			"Output buffer is shorter than the value",
+		),
+		ERR_TCP_REFUSED => ("opcua-labview::client", "TCP connection refused"),
+		ERR_TCP_TIMEOUT => ("opcua-labview::client", "TCP connection timed out"),
+		ERR_HELLO_REJECTED => (
+			"opcua-labview::client",
+			"OPC UA Hello rejected by the server",
+		),
+		ERR_INVALID_CLIENT_CONFIG => ("opcua-labview::client", "Invalid client configuration file"),
+		ERR_CERT_IN_USE => (
+			"opcua-labview::certificate",
+			"Certificate is used by a connected session",
+		),
+		ERR_CERT_NOT_FETCHED => (
+			"opcua-labview::certificate",
+			"Server certificate could not be fetched",
+		),
+		ERR_CERT_INVALID => ("opcua-labview::certificate", "Not a DER certificate"),
+		ERR_DISCONNECTED => ("opcua-labview::client", "Connection is down"),
+		JOB_PENDING => ("opcua-labview::client", "Job not finished yet"),
+		5000..=5999 => ("opcua-labview::dll", "Unknown error code of the DLL"),
+		// Codes of the first functions of the DLL (lv_connect_loop, lv_read_*),
+		// Bad status codes as i32 are negative too but far below these
+		-1 => ("opcua-labview::dll", "Null pointer argument"),
+		-2 => ("opcua-labview::runtime", "Invalid runtime pointer"),
+		-3 => ("opcua-labview::dll", "String argument is not valid UTF-8"),
+		-4 => (
+			"opcua-labview::client",
+			"Connect failed or value of another type",
+		),
+		-5 => ("opcua-labview::client", "No value in the read result"),
+		-6 => ("opcua-labview::client", "No values returned by the read"),
+		-7 => ("opcua-labview::client", "Read failed"),
+		-8 => ("opcua-labview::client", "Connect failed"),
+		// Memory manager errors of LabVIEW (mgErr) from resizing handles
+		2..=4999 => {
+			return (
+				"opcua-labview::labview",
+				format!("LabVIEW memory manager error {error_code}"),
+			);
+		}
+		_ => {
+			let status = StatusCode::from(error_code as u32);
+			let description = format!(
+				"{} (0x{:08X}): {}",
+				status.sub_code().name(),
+				status.bits(),
+				status.sub_code().description()
+			);
+			return (status_code_source(status), description);
+		}
+	};
+	(source, description.to_string())
+}
+
+// Module of an OPC UA status code, by the service set of its name
+fn status_code_source(status: StatusCode) -> &'static str {
+	let name = status.sub_code().name();
+	let starts_with_any = |prefixes: &[&str]| prefixes.iter().any(|p| name.starts_with(p));
+	if starts_with_any(&[
+		"BadTcp",
+		"BadSecureChannel",
+		"BadConnection",
+		"BadCommunication",
+		"BadServerNotConnected",
+		"BadNotConnected",
+		"BadSecurity",
+		"BadCertificate",
+		"BadNonce",
+		"BadTimeout",
+		"BadRequestTimeout",
+		"BadServerHalted",
+		"BadShutdown",
+	]) {
+		"opcua-labview::client"
+	} else if starts_with_any(&["BadSession", "BadIdentity", "BadUser", "BadTooManySessions"]) {
+		"opcua-labview::session"
+	} else if starts_with_any(&[
+		"BadNode",
+		"BadBrowse",
+		"BadReference",
+		"BadView",
+		"BadContinuationPoint",
+		"BadNoContinuationPoints",
+		"BadSourceNode",
+		"BadTargetNode",
+	]) {
+		"opcua-labview::browser"
+	} else if starts_with_any(&[
+		"BadSubscription",
+		"BadNoSubscription",
+		"BadMonitoredItem",
+		"BadMonitoring",
+		"BadFilter",
+		"BadDeadband",
+		"BadSequenceNumber",
+		"BadMessageNotAvailable",
+		"BadTooManyPublishRequests",
+		"BadTooManySubscriptions",
+		"BadTooManyMonitoredItems",
+	]) {
+		"opcua-labview::subscription"
+	} else if starts_with_any(&["BadHistory", "BadNoData", "BadDataLost", "BadBoundNotFound"]) {
+		"opcua-labview::history"
+	} else if starts_with_any(&["BadMethod", "BadArgumentsMissing", "BadTooManyArguments"]) {
+		"opcua-labview::method"
+	} else if starts_with_any(&[
+		"BadAttribute",
+		"BadType",
+		"BadWrite",
+		"BadNotWritable",
+		"BadNotReadable",
+		"BadOutOfRange",
+		"BadIndexRange",
+		"BadDataEncoding",
+		"BadDataType",
+	]) {
+		"opcua-labview::variables"
+	} else {
+		"opcua-labview::opcua"
+	}
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_last_error_to_lv_cluster(
+	error_code: i32,
+	source_lv_str: LStrHandle,
+	description_lv_str: LStrHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		if source_lv_str.is_null() || description_lv_str.is_null() {
+			return ERR_NULL_POINTER;
+		}
+
+		let (source, description) = error_source_and_description(error_code);
+		unsafe {
+			let err = string_to_lstr(source, source_lv_str);
+			if err != NO_ERR {
+				return err;
+			}
+			string_to_lstr(&description, description_lv_str)
+		}
+	})
+}
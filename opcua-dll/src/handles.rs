@@ -0,0 +1,130 @@
+//==============================================================================
+//
+// Title:		Handle validation
+// Purpose:		Check if an opaque pointer passed from LabVIEW was allocated
+//				by this DLL (and is still alive) before it is dereferenced
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+//
+// The handles aren't read for the check: every pointer handed out to LabVIEW
+// is recorded with its kind and removed when it is freed, so a stale or
+// wrong-type pointer is detected without touching the memory behind it
+// (reading a sentinel through a freed pointer would already be the crash)
+//
+use crate::errors::*;
+use opcua::client::{Client, Session, SessionEventLoop};
+use opcua::server::ServerHandle;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::runtime::Runtime;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HandleKind {
+	Runtime,
+	Client,
+	Session,
+	EventLoop,
+	JoinHandle,
+	ServerHandle,
+}
+
+// Address of the allocation -> kind
+static HANDLES: LazyLock<Mutex<HashMap<usize, HandleKind>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+//==============================================================================
+// Box the value for LabVIEW and record the pointer
+//
+pub fn into_handle<T>(value: T, kind: HandleKind) -> *mut T {
+	let ptr = Box::into_raw(Box::new(value));
+	HANDLES.lock().unwrap().insert(ptr as usize, kind);
+	ptr
+}
+
+//==============================================================================
+// Take the value of a handle back (the pointer isn't valid afterwards)
+//
+// # Safety
+// ptr must come from into_handle() and not be freed yet
+//
+pub unsafe fn from_handle<T>(ptr: *mut T) -> Box<T> {
+	HANDLES.lock().unwrap().remove(&(ptr as usize));
+	unsafe { Box::from_raw(ptr) }
+}
+
+pub fn is_handle<T>(ptr: *const T, kind: HandleKind) -> bool {
+	!ptr.is_null() && HANDLES.lock().unwrap().get(&(ptr as usize)) == Some(&kind)
+}
+
+//==============================================================================
+// 1 if the pointer is a live handle of this kind from this DLL, 0 otherwise
+// (also for null)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_is_valid_runtime(rt_ptr: *const Runtime) -> u8 {
+	catch_panic!(0, { is_handle(rt_ptr, HandleKind::Runtime) as u8 })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_is_valid_client(client_ptr: *const Client) -> u8 {
+	catch_panic!(0, { is_handle(client_ptr, HandleKind::Client) as u8 })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_is_valid_session(session_ptr: *const Arc<Session>) -> u8 {
+	catch_panic!(0, { is_handle(session_ptr, HandleKind::Session) as u8 })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_is_valid_event_loop(event_loop_ptr: *const Arc<SessionEventLoop>) -> u8 {
+	catch_panic!(0, {
+		is_handle(event_loop_ptr, HandleKind::EventLoop) as u8
+	})
+}
+
+// Any join handle: the event loop handle of a session, watchdogs, watches
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_is_valid_join_handle(handle_ptr: *const c_void) -> u8 {
+	catch_panic!(0, { is_handle(handle_ptr, HandleKind::JoinHandle) as u8 })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_is_valid_server_handle(handle_ptr: *const ServerHandle) -> u8 {
+	catch_panic!(0, { is_handle(handle_ptr, HandleKind::ServerHandle) as u8 })
+}
+
+//==============================================================================
+// Check the handles of a client connection at once, results_out (5 elements)
+// takes 1/0 for rt, client, session, event_loop and join_handle in this order.
+// Connects that spawn the event loop themselves leave event_loop null (0),
+// then join_handle is the one to check
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_validate_all_handles(
+	rt_ptr: *const Runtime,
+	client_ptr: *const Client,
+	session_ptr: *const Arc<Session>,
+	event_loop_ptr: *const Arc<SessionEventLoop>,
+	join_handle_ptr: *const c_void,
+	results_out: *mut u8,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(results_out, ERR_NULL_POINTER);
+
+		let results = [
+			lv_is_valid_runtime(rt_ptr),
+			lv_is_valid_client(client_ptr),
+			lv_is_valid_session(session_ptr),
+			lv_is_valid_event_loop(event_loop_ptr),
+			lv_is_valid_join_handle(join_handle_ptr),
+		];
+		unsafe {
+			std::ptr::copy_nonoverlapping(results.as_ptr(), results_out, results.len());
+		}
+		NO_ERR
+	})
+}
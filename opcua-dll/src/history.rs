@@ -0,0 +1,268 @@
+//==============================================================================
+//
+// Title:		OPC UA Historical Access functions wrapper
+// Purpose:		Read historian data, push LabVIEW-logged data into the historian
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+use crate::errors::*;
+use crate::utils::{cocoa_to_date_time, date_time_to_cocoa, operation_limit, with_timeout};
+
+use libc::c_char;
+use opcua::{
+	client::{HistoryReadAction, HistoryUpdateAction, Session},
+	types::{
+		DataValue, HistoryData, HistoryReadValueId, NodeId, PerformUpdateType,
+		ReadRawModifiedDetails, StatusCode, TimestampsToReturn, UpdateDataDetails, VariableId,
+		Variant,
+	},
+};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+// Used when the server doesn't report MaxNodesPerHistoryUpdateData (0 means "no limit")
+const DEFAULT_HISTORY_CHUNK_SIZE: usize = 1000;
+
+//==============================================================================
+// Insert the values with their Cocoa (LabVIEW) timestamps into the history of
+// the node. timestamps_in, values_in and results_out are arrays of count elements,
+// allocated by LabVIEW. results_out receives the status code of each value.
+// chunk_size = 0 means "use server's MaxNodesPerHistoryUpdateData".
+// A failed chunk reports the service StatusCode for all its values, values
+// without a result of the server get a Bad status too.
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_history_update_insert(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	node_str: *const c_char,
+	ns: u16,
+	timestamps_in: *const f64,
+	values_in: *const f64,
+	count: i32,
+	chunk_size: u32,
+	results_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(timestamps_in, ERR_NULL_POINTER);
+		check_null!(values_in, ERR_NULL_POINTER);
+		check_null!(results_out, ERR_NULL_POINTER);
+		if count <= 0 {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+			let timestamps = std::slice::from_raw_parts(timestamps_in, count as usize);
+			let values = std::slice::from_raw_parts(values_in, count as usize);
+			let results = std::slice::from_raw_parts_mut(results_out, count as usize);
+
+			rt.block_on(async {
+				let chunk_size = match chunk_size {
+					0 => {
+						operation_limit(
+							session,
+							VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerHistoryUpdateData,
+							DEFAULT_HISTORY_CHUNK_SIZE,
+						)
+						.await
+					}
+					n => n as usize,
+				};
+
+				let mut offset = 0;
+				for chunk in timestamps.chunks(chunk_size) {
+					let update_values: Vec<DataValue> = chunk
+						.iter()
+						.zip(&values[offset..])
+						.map(|(t, v)| DataValue::new_at(*v, cocoa_to_date_time(*t)))
+						.collect();
+					let details = UpdateDataDetails {
+						node_id: node_id.clone(),
+						perform_insert_replace: PerformUpdateType::Insert,
+						update_values: Some(update_values),
+					};
+
+					let status = match session
+						.history_update(&[HistoryUpdateAction::from(details)])
+						.await
+					{
+						Ok(r) => match r.into_iter().next() {
+							Some(result) => {
+								if let Some(op_results) = result.operation_results {
+									// Values the server left without a result failed as far as we know
+									let missing = match result.status_code {
+										s if s.is_bad() => s,
+										_ => StatusCode::BadUnexpectedError,
+									};
+									let chunk_results = &mut results[offset..offset + chunk.len()];
+									for (i, r) in chunk_results.iter_mut().enumerate() {
+										*r = op_results.get(i).unwrap_or(&missing).bits();
+									}
+									offset += chunk.len();
+									continue;
+								}
+								result.status_code
+							}
+							None => StatusCode::BadUnexpectedError,
+						},
+						Err(status) => status,
+					};
+					// No per-value results, report the service status for the whole chunk
+					for r in &mut results[offset..offset + chunk.len()] {
+						*r = status.bits();
+					}
+					offset += chunk.len();
+				}
+			});
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Read raw historical Double values of the node between two Cocoa timestamps.
+// values_out, timestamps_out and statuses_out are allocated by LabVIEW
+// with at least max_values elements.
+// Returns MORE_HISTORY if the server has more values (continuation point)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_historical_read(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	start_time_cocoa: f64,
+	end_time_cocoa: f64,
+	max_values: u32,
+	values_out: *mut f64,
+	timestamps_out: *mut f64,
+	statuses_out: *mut u32,
+	count_out: *mut i32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		lv_historical_read_ex(
+			rt_ptr,
+			session_in,
+			ns,
+			node_str,
+			start_time_cocoa,
+			end_time_cocoa,
+			max_values,
+			0,
+			values_out,
+			timestamps_out,
+			statuses_out,
+			count_out,
+		)
+	})
+}
+
+//==============================================================================
+// Same as lv_historical_read, but gives up after timeout_ms (0 - session
+// default) with ERR_TIMEOUT
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_historical_read_ex(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	start_time_cocoa: f64,
+	end_time_cocoa: f64,
+	max_values: u32,
+	timeout_ms: u32,
+	values_out: *mut f64,
+	timestamps_out: *mut f64,
+	statuses_out: *mut u32,
+	count_out: *mut i32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(values_out, ERR_NULL_POINTER);
+		check_null!(timestamps_out, ERR_NULL_POINTER);
+		check_null!(statuses_out, ERR_NULL_POINTER);
+		check_null!(count_out, ERR_NULL_POINTER);
+		if max_values == 0 {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			*count_out = 0;
+
+			let action = HistoryReadAction::ReadRawModifiedDetails(ReadRawModifiedDetails {
+				is_read_modified: false,
+				start_time: cocoa_to_date_time(start_time_cocoa),
+				end_time: cocoa_to_date_time(end_time_cocoa),
+				num_values_per_node: max_values,
+				return_bounds: false,
+			});
+			let node = HistoryReadValueId {
+				node_id: NodeId::new(ns, cstr_to_string!(node_str)),
+				index_range: Default::default(),
+				data_encoding: Default::default(),
+				continuation_point: Default::default(),
+			};
+
+			let r = rt.block_on(with_timeout(
+				timeout_ms,
+				session.history_read(action, TimestampsToReturn::Both, false, &[node]),
+			));
+
+			let result = match r {
+				Ok(Ok(results)) => match results.into_iter().next() {
+					Some(result) => result,
+					None => return StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Ok(Err(status)) => return status.bits() as i32,
+				Err(err) => return err,
+			};
+			if result.status_code.is_bad() {
+				return result.status_code.bits() as i32;
+			}
+
+			let data_values = result
+				.history_data
+				.inner_as::<HistoryData>()
+				.and_then(|d| d.data_values.clone())
+				.unwrap_or_default();
+
+			let values = std::slice::from_raw_parts_mut(values_out, max_values as usize);
+			let timestamps = std::slice::from_raw_parts_mut(timestamps_out, max_values as usize);
+			let statuses = std::slice::from_raw_parts_mut(statuses_out, max_values as usize);
+
+			let mut n = 0;
+			for dv in data_values.iter().take(max_values as usize) {
+				//#ToDo: other types than Double
+				values[n] = match &dv.value {
+					Some(Variant::Double(v)) => *v,
+					_ => f64::NAN,
+				};
+				timestamps[n] = match dv.source_timestamp.or(dv.server_timestamp) {
+					Some(t) => date_time_to_cocoa(&t),
+					None => 0.0,
+				};
+				statuses[n] = dv.status().bits();
+				n += 1;
+			}
+			*count_out = n as i32;
+
+			if result.continuation_point.is_null() {
+				NO_ERR
+			} else {
+				MORE_HISTORY
+			}
+		}
+	})
+}
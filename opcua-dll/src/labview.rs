@@ -7,7 +7,13 @@
 // License: MPL-2.0
 //
 //==============================================================================
-use std::ffi::c_void;
+use crate::encoding::{from_lv_bytes, to_lv_bytes};
+use crate::errors::{ERR_INVALID_TYPE, set_last_error_detail};
+use opcua::types::Variant;
+use std::{
+	ffi::{CStr, c_int, c_void},
+	sync::LazyLock,
+};
 
 //Pay attention to alignment in 32-bit environment
 #[cfg(target_arch = "x86")]
@@ -23,25 +29,61 @@ pub struct TD1Variant {
 	data_type: u16,
 	data_value: TVariant,
 }
-/*
+
+#[cfg(target_arch = "x86_64")]
 #[repr(C)]
 pub struct LStr {
-	cnt: i32,
-	str: [u8; 0],
+	pub cnt: i32,
+	pub str: [u8; 0],
+}
+
+#[cfg(target_arch = "x86")]
+#[repr(C, packed(1))]
+pub struct LStr {
+	pub cnt: i32,
+	pub str: [u8; 0],
+}
+
+pub type LStrHandle = *mut *mut LStr;
+
+// 1D array of strings
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+pub struct LStrArray {
+	pub dim_size: i32,
+	pub elt: [LStrHandle; 0],
+}
+
+#[cfg(target_arch = "x86")]
+#[repr(C, packed(1))]
+pub struct LStrArray {
+	pub dim_size: i32,
+	pub elt: [LStrHandle; 0],
 }
 
+pub type LStrArrayHandle = *mut *mut LStrArray;
+
+// 1D array of clusters, the cluster shall follow the same alignment rules
+#[cfg(target_arch = "x86_64")]
 #[repr(C)]
-pub struct LStr1Darray {
-	dim_size: i32,
-	node_ru: [*mut *mut LStr; 9999],
+pub struct LvArray<T> {
+	pub dim_size: i32,
+	pub elt: [T; 0],
+}
+
+#[cfg(target_arch = "x86")]
+#[repr(C, packed(1))]
+pub struct LvArray<T> {
+	pub dim_size: i32,
+	pub elt: [T; 0],
 }
 
-type LStr1DarrayHdl = *mut LStr1Darray;
-*/
+pub type LvArrayHandle<T> = *mut *mut LvArray<T>;
 
-type TVariant = *mut *mut c_void;
+pub type TVariant = *mut *mut c_void;
 pub type MgErr = i32;
 
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum LVDataTypeId {
 	LvBoolean = 1,
 	LvSByte = 2,
@@ -54,28 +96,597 @@ pub enum LVDataTypeId {
 	LvUInt64 = 9,
 	LvFloat = 10,
 	LvDouble = 11,
+	LvString = 12,
+	// Further OPC UA built-in types, with their OPC UA type ids. No LabVIEW
+	// scalars, lv_read_value_as_string gives them as text
+	LvDateTime = 13,
+	LvGuid = 14,
+	LvByteString = 15,
+	LvXmlElement = 16,
+	LvNodeId = 17,
+	LvExpandedNodeId = 18,
+	LvStatusCode = 19,
+	LvQualifiedName = 20,
+	LvLocalizedText = 21,
+	LvExtensionObject = 22,
 } //currently only support these types
 
-unsafe extern "C" {
-	//exported from LabVIEW.exe
-	pub fn PostLVUserEvent(user_event_ref: *mut c_void, data: *mut c_void) -> MgErr;
-	pub fn LvVariantUnFlattenExp(
+// Added to the type id of lv_read_value_as_string for arrays of the type
+pub const LV_ARRAY_TYPE_FLAG: u16 = 0x100;
+
+impl LVDataTypeId {
+	pub fn from_u16(type_id: u16) -> Option<Self> {
+		Some(match type_id {
+			1 => Self::LvBoolean,
+			2 => Self::LvSByte,
+			3 => Self::LvByte,
+			4 => Self::LvInt16,
+			5 => Self::LvUInt16,
+			6 => Self::LvInt32,
+			7 => Self::LvUInt32,
+			8 => Self::LvInt64,
+			9 => Self::LvUInt64,
+			10 => Self::LvFloat,
+			11 => Self::LvDouble,
+			12 => Self::LvString,
+			13 => Self::LvDateTime,
+			14 => Self::LvGuid,
+			15 => Self::LvByteString,
+			16 => Self::LvXmlElement,
+			17 => Self::LvNodeId,
+			18 => Self::LvExpandedNodeId,
+			19 => Self::LvStatusCode,
+			20 => Self::LvQualifiedName,
+			21 => Self::LvLocalizedText,
+			22 => Self::LvExtensionObject,
+			_ => return None,
+		})
+	}
+
+	// Type code of the LabVIEW type descriptor, None if it isn't a LabVIEW scalar
+	fn type_code(self) -> Option<u16> {
+		Some(match self {
+			Self::LvBoolean => 0x21,
+			Self::LvSByte => 0x01,
+			Self::LvByte => 0x05,
+			Self::LvInt16 => 0x02,
+			Self::LvUInt16 => 0x06,
+			Self::LvInt32 => 0x03,
+			Self::LvUInt32 => 0x07,
+			Self::LvInt64 => 0x04,
+			Self::LvUInt64 => 0x08,
+			Self::LvFloat => 0x09,
+			Self::LvDouble => 0x0A,
+			Self::LvString => 0x30,
+			_ => return None,
+		})
+	}
+}
+
+//==============================================================================
+// LabVIEW manager functions, resolved at runtime in the hosting process:
+// LabVIEW.exe (development system) or lvrt.dll (run-time engine of built
+// applications). The DLL doesn't link labview.lib of cintools, so it loads
+// outside LabVIEW too (e.g. tests), the memory manager is emulated over malloc
+// then and the user events go nowhere
+//
+type UHandle = *mut *mut c_void;
+
+type PostLVUserEventFn = unsafe extern "C" fn(*mut c_void, *mut c_void) -> MgErr;
+type DSNewHandleFn = unsafe extern "C" fn(usize) -> UHandle;
+type DSDisposeHandleFn = unsafe extern "C" fn(UHandle) -> MgErr;
+type DSSetHandleSizeFn = unsafe extern "C" fn(UHandle, usize) -> MgErr;
+type MoveBlockFn = unsafe extern "C" fn(*const c_void, *mut c_void, usize);
+type NumericArrayResizeFn = unsafe extern "C" fn(u32, i32, *mut UHandle, usize) -> MgErr;
+type LvVariantUnFlattenExpFn = unsafe extern "C" fn(TVariant, *const u8, i32, i32, i32) -> MgErr;
+type LvVariantFlattenExpFn = unsafe extern "C" fn(TVariant, LStrHandle, i32) -> MgErr;
+
+// LabVIEW error codes of the manager functions
+const MG_ARG_ERR: MgErr = 1;
+pub const M_FULL_ERR: MgErr = 2;
+const MG_NOT_SUPPORTED: MgErr = 53;
+
+struct LvFunctions {
+	post_lv_user_event: PostLVUserEventFn,
+	ds_new_handle: DSNewHandleFn,
+	ds_dispose_handle: DSDisposeHandleFn,
+	ds_set_handle_size: DSSetHandleSizeFn,
+	move_block: MoveBlockFn,
+	numeric_array_resize: NumericArrayResizeFn,
+	lv_variant_unflatten_exp: Option<LvVariantUnFlattenExpFn>,
+	lv_variant_flatten_exp: Option<LvVariantFlattenExpFn>,
+}
+
+impl LvFunctions {
+	// All memory manager functions from the same module or none, handles of
+	// the emulation must never reach LabVIEW
+	unsafe fn from_symbols(symbol: impl Fn(&CStr) -> *mut c_void) -> Option<Self> {
+		macro_rules! resolve {
+			($name:literal) => {{
+				let f = symbol($name);
+				if f.is_null() {
+					return None;
+				}
+				unsafe { std::mem::transmute::<*mut c_void, _>(f) }
+			}};
+		}
+		let variant_unflatten = symbol(c"LvVariantUnFlattenExp");
+		let variant_flatten = symbol(c"LvVariantFlattenExp");
+		Some(Self {
+			post_lv_user_event: resolve!(c"PostLVUserEvent"),
+			ds_new_handle: resolve!(c"DSNewHandle"),
+			ds_dispose_handle: resolve!(c"DSDisposeHandle"),
+			ds_set_handle_size: resolve!(c"DSSetHandleSize"),
+			move_block: resolve!(c"MoveBlock"),
+			numeric_array_resize: resolve!(c"NumericArrayResize"),
+			lv_variant_unflatten_exp: (!variant_unflatten.is_null()).then(|| unsafe {
+				std::mem::transmute::<*mut c_void, LvVariantUnFlattenExpFn>(variant_unflatten)
+			}),
+			lv_variant_flatten_exp: (!variant_flatten.is_null()).then(|| unsafe {
+				std::mem::transmute::<*mut c_void, LvVariantFlattenExpFn>(variant_flatten)
+			}),
+		})
+	}
+
+	const EMULATED: Self = Self {
+		post_lv_user_event: emulated::post_lv_user_event,
+		ds_new_handle: emulated::ds_new_handle,
+		ds_dispose_handle: emulated::ds_dispose_handle,
+		ds_set_handle_size: emulated::ds_set_handle_size,
+		move_block: emulated::move_block,
+		numeric_array_resize: emulated::numeric_array_resize,
+		lv_variant_unflatten_exp: None,
+		lv_variant_flatten_exp: None,
+	};
+}
+
+#[cfg(windows)]
+fn resolve_host() -> Option<LvFunctions> {
+	#[link(name = "kernel32")]
+	unsafe extern "system" {
+		fn GetModuleHandleA(module_name: *const std::ffi::c_char) -> *mut c_void;
+		fn GetProcAddress(module: *mut c_void, proc_name: *const std::ffi::c_char) -> *mut c_void;
+	}
+
+	// The executable is LabVIEW.exe in the development system only
+	[std::ptr::null(), c"lvrt.dll".as_ptr()]
+		.into_iter()
+		.find_map(|module_name| unsafe {
+			let module = GetModuleHandleA(module_name);
+			if module.is_null() {
+				return None;
+			}
+			LvFunctions::from_symbols(|name| GetProcAddress(module, name.as_ptr()))
+		})
+}
+
+#[cfg(not(windows))]
+fn resolve_host() -> Option<LvFunctions> {
+	unsafe { LvFunctions::from_symbols(|name| libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr())) }
+}
+
+static LV_FUNCTIONS: LazyLock<LvFunctions> =
+	LazyLock::new(|| resolve_host().unwrap_or(LvFunctions::EMULATED));
+
+// Functions the unit tests inject on their thread (with_lv_functions)
+#[cfg(test)]
+thread_local! {
+	static INJECTED_FUNCTIONS: std::cell::Cell<Option<&'static LvFunctions>> =
+		const { std::cell::Cell::new(None) };
+}
+
+fn lv_functions() -> &'static LvFunctions {
+	#[cfg(test)]
+	if let Some(functions) = INJECTED_FUNCTIONS.get() {
+		return functions;
+	}
+	&LV_FUNCTIONS
+}
+
+// Run f with the manager functions of the test on this thread
+#[cfg(test)]
+fn with_lv_functions<R>(functions: &'static LvFunctions, f: impl FnOnce() -> R) -> R {
+	struct Restore(Option<&'static LvFunctions>);
+	impl Drop for Restore {
+		fn drop(&mut self) {
+			INJECTED_FUNCTIONS.set(self.0);
+		}
+	}
+	let _restore = Restore(INJECTED_FUNCTIONS.replace(Some(functions)));
+	f()
+}
+
+// Int32 events posted on this thread while f runs: (user event, value). The
+// notifications of subscriptions are posted on threads of the runtime and
+// aren't recorded
+#[cfg(test)]
+pub(crate) fn record_i32_events<R>(f: impl FnOnce() -> R) -> (R, Vec<(usize, i32)>) {
+	thread_local! {
+		static POSTED: std::cell::RefCell<Vec<(usize, i32)>> =
+			const { std::cell::RefCell::new(Vec::new()) };
+	}
+	unsafe extern "C" fn record(user_event_ref: *mut c_void, data: *mut c_void) -> MgErr {
+		let value = unsafe { *(data as *const i32) };
+		POSTED.with_borrow_mut(|posted| posted.push((user_event_ref as usize, value)));
+		0
+	}
+	static RECORDING_FUNCTIONS: LvFunctions = LvFunctions {
+		post_lv_user_event: record,
+		..LvFunctions::EMULATED
+	};
+
+	POSTED.take();
+	let r = with_lv_functions(&RECORDING_FUNCTIONS, f);
+	(r, POSTED.take())
+}
+
+// Run f with LabVIEW Variants on this thread: the TVariant points to the
+// flattened data (Vec<u8>), which LvVariantFlattenExp and
+// LvVariantUnFlattenExp copy
+#[cfg(test)]
+pub(crate) fn with_flat_lv_variants<R>(f: impl FnOnce() -> R) -> R {
+	unsafe extern "C" fn unflatten(
 		variant: TVariant,
-		str: *const u8,
+		flat: *const u8,
 		size: i32,
-		version: i32,
-		context: i32,
-	) -> MgErr;
+		_: i32,
+		_: i32,
+	) -> MgErr {
+		unsafe {
+			let flat = std::slice::from_raw_parts(flat, size as usize);
+			*(variant as *mut Vec<u8>) = flat.to_vec();
+		}
+		0
+	}
+	unsafe extern "C" fn flatten(variant: TVariant, flat: LStrHandle, _: i32) -> MgErr {
+		unsafe { write_lv_string(flat, &*(variant as *const Vec<u8>)) }
+	}
+	static VARIANT_FUNCTIONS: LvFunctions = LvFunctions {
+		lv_variant_unflatten_exp: Some(unflatten),
+		lv_variant_flatten_exp: Some(flatten),
+		..LvFunctions::EMULATED
+	};
+
+	with_lv_functions(&VARIANT_FUNCTIONS, f)
+}
+
+// Memory manager over malloc, handles as LabVIEW: pointer to the master
+// pointer of the block
+mod emulated {
+	use super::{M_FULL_ERR, MG_ARG_ERR, MgErr, UHandle};
+	use std::ffi::c_void;
+
+	// No LabVIEW, no user events
+	pub unsafe extern "C" fn post_lv_user_event(_: *mut c_void, _: *mut c_void) -> MgErr {
+		0
+	}
+
+	pub unsafe extern "C" fn ds_new_handle(size: usize) -> UHandle {
+		unsafe {
+			let handle = libc::malloc(std::mem::size_of::<*mut c_void>()) as UHandle;
+			if handle.is_null() {
+				return handle;
+			}
+			let block = libc::calloc(size.max(1), 1);
+			if block.is_null() {
+				libc::free(handle as *mut c_void);
+				return std::ptr::null_mut();
+			}
+			*handle = block;
+			handle
+		}
+	}
+
+	pub unsafe extern "C" fn ds_dispose_handle(handle: UHandle) -> MgErr {
+		unsafe {
+			if handle.is_null() {
+				return MG_ARG_ERR;
+			}
+			libc::free(*handle);
+			libc::free(handle as *mut c_void);
+			0
+		}
+	}
+
+	pub unsafe extern "C" fn ds_set_handle_size(handle: UHandle, size: usize) -> MgErr {
+		unsafe {
+			if handle.is_null() {
+				return MG_ARG_ERR;
+			}
+			let block = libc::realloc(*handle, size.max(1));
+			if block.is_null() {
+				return M_FULL_ERR;
+			}
+			*handle = block;
+			0
+		}
+	}
+
+	// MoveBlock allows overlapping blocks
+	pub unsafe extern "C" fn move_block(src: *const c_void, dest: *mut c_void, size: usize) {
+		unsafe { std::ptr::copy(src as *const u8, dest as *mut u8, size) }
+	}
+
+	// Type codes iB..fD, the header of the dimension sizes is padded to the
+	// element on 64-bit as LabVIEW does
+	pub unsafe extern "C" fn numeric_array_resize(
+		type_code: u32,
+		num_dims: i32,
+		handle: *mut UHandle,
+		new_size: usize,
+	) -> MgErr {
+		unsafe {
+			let elt_size = match type_code {
+				1 | 5 => 1,
+				2 | 6 => 2,
+				3 | 7 | 9 => 4,
+				4 | 8 | 10 => 8,
+				_ => return MG_ARG_ERR,
+			};
+			if handle.is_null() || num_dims < 1 {
+				return MG_ARG_ERR;
+			}
+			let header = num_dims as usize * std::mem::size_of::<i32>();
+			#[cfg(target_arch = "x86_64")]
+			let header = header.next_multiple_of(elt_size);
+			let size = header + new_size * elt_size;
+			if (*handle).is_null() {
+				*handle = ds_new_handle(size);
+				if (*handle).is_null() {
+					return M_FULL_ERR;
+				}
+				return 0;
+			}
+			ds_set_handle_size(*handle, size)
+		}
+	}
+}
+
+//==============================================================================
+// The manager functions as declared before with labview.lib
+//
+#[allow(non_snake_case)]
+pub unsafe fn PostLVUserEvent(user_event_ref: *mut c_void, data: *mut c_void) -> MgErr {
+	unsafe { (lv_functions().post_lv_user_event)(user_event_ref, data) }
+}
+
+#[allow(non_snake_case)]
+unsafe fn DSNewHandleLStr(size: usize) -> LStrHandle {
+	unsafe { (lv_functions().ds_new_handle)(size) as LStrHandle }
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn DSDisposeHandleLStr(handle: LStrHandle) -> MgErr {
+	unsafe { DSDisposeHandle(handle) }
+}
+
+// Handle of any type, e.g. a new array of clusters
+#[allow(non_snake_case)]
+pub unsafe fn DSDisposeHandle<T>(handle: *mut *mut T) -> MgErr {
+	unsafe { (lv_functions().ds_dispose_handle)(handle as UHandle) }
+}
+
+// Resize of the handle of an array of clusters, allocated by LabVIEW
+#[allow(non_snake_case)]
+pub unsafe fn DSSetHandleSize(handle: *mut c_void, size: usize) -> MgErr {
+	unsafe { (lv_functions().ds_set_handle_size)(handle as UHandle, size) }
+}
+
+#[allow(non_snake_case)]
+unsafe fn MoveBlockChar(src: *const i8, destination: *mut u8, size: usize) {
+	unsafe { (lv_functions().move_block)(src as *const c_void, destination as *mut c_void, size) }
+}
+
+unsafe fn string_resize(
+	numeric_type: u32,
+	num_dimensions: i32,
+	data_handle: *mut LStrHandle,
+	new_size: usize,
+) -> c_int {
+	unsafe {
+		(lv_functions().numeric_array_resize)(
+			numeric_type,
+			num_dimensions,
+			data_handle as *mut UHandle,
+			new_size,
+		)
+	}
+}
+
+// MG_NOT_SUPPORTED outside LabVIEW
+#[allow(non_snake_case)]
+pub unsafe fn LvVariantUnFlattenExp(
+	variant: TVariant,
+	str: *const u8,
+	size: i32,
+	version: i32,
+	context: i32,
+) -> MgErr {
+	match lv_functions().lv_variant_unflatten_exp {
+		Some(f) => unsafe { f(variant, str, size, version, context) },
+		None => MG_NOT_SUPPORTED,
+	}
+}
+
+// Flattened Variant into str (resized by LabVIEW), MG_NOT_SUPPORTED outside
+// LabVIEW
+#[allow(non_snake_case)]
+pub unsafe fn LvVariantFlattenExp(variant: TVariant, str: LStrHandle, version: i32) -> MgErr {
+	match lv_functions().lv_variant_flatten_exp {
+		Some(f) => unsafe { f(variant, str, version) },
+		None => MG_NOT_SUPPORTED,
+	}
+}
+
+//==============================================================================
+// OPC UA scalar as flattened LabVIEW Variant for LvVariantUnFlattenExp:
+// version, count of type descriptors (1), the type descriptor, count and
+// index of the type of the data (1, 0), the data (big endian as with Flatten
+// To String, strings with I32 length) and count of attributes (0).
+// None if the type isn't one of LVDataTypeId
+//
+pub const FLATTEN_VERSION: u32 = 0x0800_8000; // LabVIEW 8.0, unflattened by all later versions
+
+pub fn flatten_lv_variant(v: &Variant) -> Option<(LVDataTypeId, Vec<u8>)> {
+	let (type_id, data) = match v {
+		Variant::Boolean(v) => (LVDataTypeId::LvBoolean, vec![*v as u8]),
+		Variant::SByte(v) => (LVDataTypeId::LvSByte, v.to_be_bytes().to_vec()),
+		Variant::Byte(v) => (LVDataTypeId::LvByte, v.to_be_bytes().to_vec()),
+		Variant::Int16(v) => (LVDataTypeId::LvInt16, v.to_be_bytes().to_vec()),
+		Variant::UInt16(v) => (LVDataTypeId::LvUInt16, v.to_be_bytes().to_vec()),
+		Variant::Int32(v) => (LVDataTypeId::LvInt32, v.to_be_bytes().to_vec()),
+		Variant::UInt32(v) => (LVDataTypeId::LvUInt32, v.to_be_bytes().to_vec()),
+		Variant::Int64(v) => (LVDataTypeId::LvInt64, v.to_be_bytes().to_vec()),
+		Variant::UInt64(v) => (LVDataTypeId::LvUInt64, v.to_be_bytes().to_vec()),
+		Variant::Float(v) => (LVDataTypeId::LvFloat, v.to_be_bytes().to_vec()),
+		Variant::Double(v) => (LVDataTypeId::LvDouble, v.to_be_bytes().to_vec()),
+		Variant::String(s) => {
+			let bytes = to_lv_bytes(s.as_ref());
+			let mut data = (bytes.len() as i32).to_be_bytes().to_vec();
+			data.extend_from_slice(&bytes);
+			(LVDataTypeId::LvString, data)
+		}
+		_ => return None,
+	};
+	let type_code = type_id.type_code()?;
+
+	let mut flat = Vec::with_capacity(data.len() + 24);
+	flat.extend_from_slice(&FLATTEN_VERSION.to_be_bytes());
+	flat.extend_from_slice(&1u32.to_be_bytes());
+	if let LVDataTypeId::LvString = type_id {
+		// Size, type code and dimension size -1 (variable)
+		flat.extend_from_slice(&8u16.to_be_bytes());
+		flat.extend_from_slice(&type_code.to_be_bytes());
+		flat.extend_from_slice(&u32::MAX.to_be_bytes());
+	} else {
+		flat.extend_from_slice(&4u16.to_be_bytes());
+		flat.extend_from_slice(&type_code.to_be_bytes());
+	}
+	flat.extend_from_slice(&1u16.to_be_bytes());
+	flat.extend_from_slice(&0u16.to_be_bytes());
+	flat.extend_from_slice(&data);
+	flat.extend_from_slice(&0u32.to_be_bytes());
+	Some((type_id, flat))
+}
+
+//==============================================================================
+// OPC UA scalar of the flattened LabVIEW Variant of LvVariantFlattenExp, the
+// layout of flatten_lv_variant. None if it doesn't parse or the type of the
+// data isn't type_id
+//
+pub fn unflatten_lv_variant(flat: &[u8], type_id: LVDataTypeId) -> Option<Variant> {
+	fn take<'a>(flat: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+		if flat.len() < n {
+			return None;
+		}
+		let (head, tail) = flat.split_at(n);
+		*flat = tail;
+		Some(head)
+	}
+	fn take_array<const N: usize>(flat: &mut &[u8]) -> Option<[u8; N]> {
+		take(flat, N)?.try_into().ok()
+	}
+
+	let mut flat = flat;
+	let _version = take_array::<4>(&mut flat)?;
+	let td_count = u32::from_be_bytes(take_array(&mut flat)?);
+	let mut type_codes = Vec::new();
+	for _ in 0..td_count {
+		let td = flat;
+		let size = u16::from_be_bytes(take_array(&mut flat)?) as usize;
+		if size < 4 || td.len() < size {
+			return None;
+		}
+		// High byte of the code has the flags of the type descriptor
+		type_codes.push(u16::from_be_bytes([td[2], td[3]]) & 0xFF);
+		flat = &td[size..];
+	}
+	let _count = u16::from_be_bytes(take_array(&mut flat)?);
+	let index = u16::from_be_bytes(take_array(&mut flat)?) as usize;
+	if *type_codes.get(index)? != type_id.type_code()? {
+		return None;
+	}
+
+	Some(match type_id {
+		LVDataTypeId::LvBoolean => Variant::Boolean(take_array::<1>(&mut flat)?[0] != 0),
+		LVDataTypeId::LvSByte => Variant::SByte(i8::from_be_bytes(take_array(&mut flat)?)),
+		LVDataTypeId::LvByte => Variant::Byte(u8::from_be_bytes(take_array(&mut flat)?)),
+		LVDataTypeId::LvInt16 => Variant::Int16(i16::from_be_bytes(take_array(&mut flat)?)),
+		LVDataTypeId::LvUInt16 => Variant::UInt16(u16::from_be_bytes(take_array(&mut flat)?)),
+		LVDataTypeId::LvInt32 => Variant::Int32(i32::from_be_bytes(take_array(&mut flat)?)),
+		LVDataTypeId::LvUInt32 => Variant::UInt32(u32::from_be_bytes(take_array(&mut flat)?)),
+		LVDataTypeId::LvInt64 => Variant::Int64(i64::from_be_bytes(take_array(&mut flat)?)),
+		LVDataTypeId::LvUInt64 => Variant::UInt64(u64::from_be_bytes(take_array(&mut flat)?)),
+		LVDataTypeId::LvFloat => Variant::Float(f32::from_be_bytes(take_array(&mut flat)?)),
+		LVDataTypeId::LvDouble => Variant::Double(f64::from_be_bytes(take_array(&mut flat)?)),
+		LVDataTypeId::LvString => {
+			let len = i32::from_be_bytes(take_array(&mut flat)?);
+			let bytes = take(&mut flat, usize::try_from(len).ok()?)?;
+			Variant::from(from_lv_bytes(bytes))
+		}
+		_ => return None,
+	})
+}
+
+// Put the OPC UA scalar into the LabVIEW Variant handle, Ok is the type of
+// the data. ERR_INVALID_TYPE for values that aren't LabVIEW scalars
+// (arrays, structures...), the LabVIEW error if it doesn't unflatten
+pub unsafe fn variant_to_lv_variant(
+	value: &Variant,
+	variant: TVariant,
+) -> Result<LVDataTypeId, i32> {
+	let Some((type_id, flat)) = flatten_lv_variant(value) else {
+		set_last_error_detail(format!("{:?} isn't a LabVIEW scalar type", value.type_id()));
+		return Err(ERR_INVALID_TYPE);
+	};
+	let err = unsafe {
+		LvVariantUnFlattenExp(
+			variant,
+			flat.as_ptr(),
+			flat.len() as i32,
+			FLATTEN_VERSION as i32,
+			0,
+		)
+	};
+	if err != 0 {
+		return Err(err); // LabVIEW error
+	}
+	Ok(type_id)
+}
+
+// OPC UA scalar of the LabVIEW Variant handle, which shall hold type_id.
+// ERR_INVALID_TYPE for an unknown type_id or other data in the Variant,
+// the LabVIEW error if it doesn't flatten
+pub unsafe fn lv_variant_to_variant(variant: TVariant, type_id: u16) -> Result<Variant, i32> {
+	let Some(lv_type) = LVDataTypeId::from_u16(type_id).filter(|t| t.type_code().is_some()) else {
+		set_last_error_detail(format!(
+			"{type_id} isn't a LVDataTypeId of a LabVIEW scalar"
+		));
+		return Err(ERR_INVALID_TYPE);
+	};
+	let flat = unsafe {
+		let handle = bytes_to_new_lstr(&[]);
+		if handle.is_null() {
+			return Err(M_FULL_ERR);
+		}
+		let err = LvVariantFlattenExp(variant, handle, FLATTEN_VERSION as i32);
+		let flat = lstr_to_bytes(handle);
+		DSDisposeHandleLStr(handle);
+		if err != 0 {
+			return Err(err); // LabVIEW error
+		}
+		flat
+	};
+	unflatten_lv_variant(&flat, lv_type).ok_or_else(|| {
+		set_last_error_detail(format!("The LabVIEW Variant doesn't hold a {lv_type:?}"));
+		ERR_INVALID_TYPE
+	})
 }
 
 #[macro_export]
 macro_rules! cstr_to_string {
 	($ptr:expr) => {
-		unsafe {
-			::std::ffi::CStr::from_ptr($ptr)
-				.to_string_lossy()
-				.into_owned()
-		}
+		unsafe { $crate::encoding::from_lv_bytes(::std::ffi::CStr::from_ptr($ptr).to_bytes()) }
 	};
 }
 
@@ -86,3 +697,469 @@ macro_rules! check_null {
 		}
 	};
 }
+
+//==============================================================================
+// LabVIEW string helpers
+//
+
+// Bytes of the LabVIEW string, empty if handle is null
+pub unsafe fn read_lv_string(handle: LStrHandle) -> Vec<u8> {
+	unsafe {
+		if handle.is_null() || (*handle).is_null() {
+			return Vec::new();
+		}
+		let cnt = (**handle).cnt;
+		if cnt <= 0 {
+			return Vec::new();
+		}
+		std::slice::from_raw_parts((**handle).str.as_ptr(), cnt as usize).to_vec()
+	}
+}
+
+// Copy the bytes into the existing LabVIEW string handle (resized as needed)
+pub unsafe fn write_lv_string(mut handle: LStrHandle, s: &[u8]) -> MgErr {
+	unsafe {
+		if handle.is_null() {
+			return MG_ARG_ERR;
+		}
+		let err = string_resize(1, 1, &mut handle as *mut LStrHandle, s.len());
+		if err != 0 {
+			return err;
+		}
+		copy_to_lstr(s, handle);
+		0
+	}
+}
+
+// New empty LabVIEW string handle with room for len bytes, null on a memory
+// error. Shall be disposed by the caller
+pub unsafe fn alloc_lv_string(len: usize) -> LStrHandle {
+	unsafe {
+		let handle = DSNewHandleLStr(len + std::mem::size_of::<i32>());
+		if !handle.is_null() {
+			(**handle).cnt = 0;
+		}
+		handle
+	}
+}
+
+// Content of the LabVIEW string (see lv_set_string_encoding), empty if handle is null
+pub unsafe fn lstr_to_string(handle: LStrHandle) -> String {
+	unsafe { from_lv_bytes(&read_lv_string(handle)) }
+}
+
+// Raw bytes of the LabVIEW string (e.g. a DER certificate), empty if handle is null
+pub unsafe fn lstr_to_bytes(handle: LStrHandle) -> Vec<u8> {
+	unsafe { read_lv_string(handle) }
+}
+
+// Copy the bytes into the handle, which has room for them. LStr is length
+// prefixed, so NULs are copied as any other byte. MoveBlock isn't called for
+// the empty string
+unsafe fn copy_to_lstr(bytes: &[u8], handle: LStrHandle) {
+	unsafe {
+		if !bytes.is_empty() {
+			MoveBlockChar(
+				bytes.as_ptr() as *const i8,
+				(**handle).str.as_mut_ptr(),
+				bytes.len(),
+			);
+		}
+		(**handle).cnt = bytes.len() as i32;
+	}
+}
+
+// New LabVIEW string handle, shall be disposed by the caller
+// (PostLVUserEvent copies the data, so dispose right after posting)
+pub unsafe fn string_to_new_lstr(s: &str) -> LStrHandle {
+	unsafe { bytes_to_new_lstr(&to_lv_bytes(s)) }
+}
+
+// New LabVIEW string handle of raw bytes, shall be disposed by the caller
+pub unsafe fn bytes_to_new_lstr(bytes: &[u8]) -> LStrHandle {
+	unsafe {
+		let handle = alloc_lv_string(bytes.len());
+		if !handle.is_null() {
+			copy_to_lstr(bytes, handle);
+		}
+		handle
+	}
+}
+
+// Copy the string into the existing LabVIEW string handle (resized as needed)
+pub unsafe fn string_to_lstr(s: &str, handle: LStrHandle) -> MgErr {
+	unsafe { bytes_to_lstr(&to_lv_bytes(s), handle) }
+}
+
+// Copy raw bytes into the existing LabVIEW string handle (resized as needed)
+pub unsafe fn bytes_to_lstr(bytes: &[u8], handle: LStrHandle) -> MgErr {
+	unsafe { write_lv_string(handle, bytes) }
+}
+
+// New LabVIEW 1D string array handle, dispose with dispose_lstr_array()
+pub unsafe fn strings_to_new_lstr_array(strings: &[String]) -> LStrArrayHandle {
+	unsafe {
+		let size = std::mem::offset_of!(LStrArray, elt)
+			+ strings.len() * std::mem::size_of::<LStrHandle>();
+		// DSNewHandle returns untyped handle, declared as LStrHandle here
+		let handle = DSNewHandleLStr(size) as LStrArrayHandle;
+		if !handle.is_null() {
+			let elt = std::ptr::addr_of_mut!((**handle).elt) as *mut LStrHandle;
+			for (i, s) in strings.iter().enumerate() {
+				elt.add(i).write_unaligned(string_to_new_lstr(s));
+			}
+			(**handle).dim_size = strings.len() as i32;
+		}
+		handle
+	}
+}
+
+// Copy the strings into the existing LabVIEW 1D string array handle: the
+// element handles are reused, extra ones disposed and missing ones created
+pub unsafe fn strings_to_lstr_array(strings: &[String], handle: LStrArrayHandle) -> MgErr {
+	unsafe {
+		let old_len = if (*handle).is_null() {
+			0
+		} else {
+			(**handle).dim_size.max(0) as usize
+		};
+		let elt =
+			|handle: LStrArrayHandle| std::ptr::addr_of_mut!((**handle).elt) as *mut LStrHandle;
+		for i in strings.len()..old_len {
+			let s = elt(handle).add(i).read_unaligned();
+			if !s.is_null() {
+				DSDisposeHandleLStr(s);
+			}
+		}
+		if old_len > strings.len() {
+			(**handle).dim_size = strings.len() as i32;
+		}
+
+		let size = std::mem::offset_of!(LStrArray, elt)
+			+ strings.len() * std::mem::size_of::<LStrHandle>();
+		let err = DSSetHandleSize(handle as *mut c_void, size);
+		if err != 0 {
+			return err;
+		}
+		for (i, s) in strings.iter().enumerate() {
+			let old = if i < old_len {
+				elt(handle).add(i).read_unaligned()
+			} else {
+				std::ptr::null_mut()
+			};
+			let err = if old.is_null() {
+				let new = string_to_new_lstr(s);
+				elt(handle).add(i).write_unaligned(new);
+				if new.is_null() { M_FULL_ERR } else { 0 }
+			} else {
+				string_to_lstr(s, old)
+			};
+			if err != 0 {
+				// The elements before the failed one and the reused ones are valid
+				(**handle).dim_size = i.max(old_len.min(strings.len())) as i32;
+				return err;
+			}
+		}
+		(**handle).dim_size = strings.len() as i32;
+		0
+	}
+}
+
+// Strings of the LabVIEW 1D string array, empty if handle is null
+pub unsafe fn lstr_array_to_strings(handle: LStrArrayHandle) -> Vec<String> {
+	unsafe {
+		if handle.is_null() || (*handle).is_null() || (**handle).dim_size <= 0 {
+			return Vec::new();
+		}
+		let elt = std::ptr::addr_of!((**handle).elt) as *const LStrHandle;
+		(0..(**handle).dim_size as usize)
+			.map(|i| lstr_to_string(elt.add(i).read_unaligned()))
+			.collect()
+	}
+}
+
+// Elements of the LabVIEW array, empty if handle is null
+pub unsafe fn lv_array_as_slice<'a, T>(handle: LvArrayHandle<T>) -> &'a [T] {
+	unsafe {
+		if handle.is_null() || (*handle).is_null() || (**handle).dim_size <= 0 {
+			return &[];
+		}
+		let elt = std::ptr::addr_of!((**handle).elt) as *const T;
+		std::slice::from_raw_parts(elt, (**handle).dim_size as usize)
+	}
+}
+
+// Resize the array of clusters (the handle belongs to LabVIEW) and write the
+// items. dim_size is padded on 64-bit only as far as the cluster needs it,
+// so the size comes from the layout of LvArray<T> and not from dim_size.
+// The items are taken only after the resize succeeded, strings they allocate
+// don't leak on a memory error
+pub unsafe fn write_lv_array<T>(
+	handle: LvArrayHandle<T>,
+	items: impl ExactSizeIterator<Item = T>,
+) -> MgErr {
+	unsafe {
+		let n = items.len();
+		let size = std::mem::offset_of!(LvArray<T>, elt) + n * std::mem::size_of::<T>();
+		let err = DSSetHandleSize(handle as *mut c_void, size);
+		if err != 0 {
+			return err;
+		}
+		let elt = std::ptr::addr_of_mut!((**handle).elt) as *mut T;
+		for (i, item) in items.enumerate() {
+			elt.add(i).write_unaligned(item);
+		}
+		(**handle).dim_size = n as i32;
+		0
+	}
+}
+
+// New LabVIEW array of clusters of the items, null on a memory error.
+// Shall be disposed by the caller, as the strings of the items
+pub unsafe fn new_lv_array<T>(items: impl ExactSizeIterator<Item = T>) -> LvArrayHandle<T> {
+	unsafe {
+		let handle = DSNewHandleLStr(std::mem::offset_of!(LvArray<T>, elt)) as LvArrayHandle<T>;
+		if !handle.is_null() && write_lv_array(handle, items) != 0 {
+			DSDisposeHandle(handle);
+			return std::ptr::null_mut();
+		}
+		handle
+	}
+}
+
+pub unsafe fn dispose_lstr_array(handle: LStrArrayHandle) {
+	unsafe {
+		if handle.is_null() {
+			return;
+		}
+		let elt = std::ptr::addr_of_mut!((**handle).elt) as *mut LStrHandle;
+		for i in 0..(**handle).dim_size as usize {
+			let s = elt.add(i).read_unaligned();
+			if !s.is_null() {
+				DSDisposeHandleLStr(s);
+			}
+		}
+		DSDisposeHandle(handle);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::cell::Cell;
+
+	// Memory manager of emulated, counting the MoveBlock calls and failing
+	// the allocations while FAIL_ALLOC is set
+	thread_local! {
+		static MOVE_BLOCK_CALLS: Cell<usize> = const { Cell::new(0) };
+		static FAIL_ALLOC: Cell<bool> = const { Cell::new(false) };
+	}
+
+	unsafe extern "C" fn counting_move_block(src: *const c_void, dest: *mut c_void, size: usize) {
+		MOVE_BLOCK_CALLS.set(MOVE_BLOCK_CALLS.get() + 1);
+		unsafe { emulated::move_block(src, dest, size) }
+	}
+
+	unsafe extern "C" fn failing_new_handle(size: usize) -> UHandle {
+		match FAIL_ALLOC.get() {
+			true => std::ptr::null_mut(),
+			false => unsafe { emulated::ds_new_handle(size) },
+		}
+	}
+
+	unsafe extern "C" fn failing_array_resize(
+		type_code: u32,
+		num_dims: i32,
+		handle: *mut UHandle,
+		new_size: usize,
+	) -> MgErr {
+		match FAIL_ALLOC.get() {
+			true => M_FULL_ERR,
+			false => unsafe {
+				emulated::numeric_array_resize(type_code, num_dims, handle, new_size)
+			},
+		}
+	}
+
+	static FAKE_FUNCTIONS: LvFunctions = LvFunctions {
+		ds_new_handle: failing_new_handle,
+		move_block: counting_move_block,
+		numeric_array_resize: failing_array_resize,
+		..LvFunctions::EMULATED
+	};
+
+	#[test]
+	fn string_round_trip() {
+		unsafe {
+			let handle = alloc_lv_string(16);
+			assert!(!handle.is_null());
+			assert!(read_lv_string(handle).is_empty());
+
+			for s in [&b"some longer string than 16 bytes"[..], b"short", b""] {
+				assert_eq!(write_lv_string(handle, s), 0);
+				assert_eq!(read_lv_string(handle), s);
+			}
+			DSDisposeHandleLStr(handle);
+		}
+	}
+
+	#[test]
+	fn helpers_use_the_injected_functions() {
+		with_lv_functions(&FAKE_FUNCTIONS, || unsafe {
+			let handle = alloc_lv_string(0);
+			assert_eq!(write_lv_string(handle, b"abc"), 0);
+			assert_eq!(MOVE_BLOCK_CALLS.get(), 1);
+			assert_eq!(write_lv_string(handle, b""), 0);
+			assert_eq!(MOVE_BLOCK_CALLS.get(), 1);
+			DSDisposeHandleLStr(handle);
+		});
+
+		// Restored after the closure
+		MOVE_BLOCK_CALLS.set(0);
+		unsafe {
+			let handle = bytes_to_new_lstr(b"abc");
+			assert_eq!(MOVE_BLOCK_CALLS.get(), 0);
+			DSDisposeHandleLStr(handle);
+		}
+	}
+
+	#[test]
+	fn memory_errors_leave_the_string() {
+		with_lv_functions(&FAKE_FUNCTIONS, || unsafe {
+			let handle = bytes_to_new_lstr(b"old");
+			FAIL_ALLOC.set(true);
+			assert!(alloc_lv_string(8).is_null());
+			assert!(string_to_new_lstr("new").is_null());
+			assert_eq!(write_lv_string(handle, b"new value"), M_FULL_ERR);
+			assert_eq!(read_lv_string(handle), b"old");
+			FAIL_ALLOC.set(false);
+			DSDisposeHandleLStr(handle);
+		});
+	}
+
+	// LStr is length prefixed, the NULs are copied, the empty string sets cnt
+	// only
+	#[test]
+	fn strings_with_nuls_empty_and_megabytes() {
+		let padded = b"PLC\0\0\0\0\0".to_vec();
+		let megabytes: Vec<u8> = (0..8 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+		with_lv_functions(&FAKE_FUNCTIONS, || unsafe {
+			let handle = alloc_lv_string(0);
+			for (bytes, move_blocks) in [(&padded, 1), (&Vec::new(), 0), (&megabytes, 1)] {
+				MOVE_BLOCK_CALLS.set(0);
+				assert_eq!(bytes_to_lstr(bytes, handle), 0);
+				assert_eq!(MOVE_BLOCK_CALLS.get(), move_blocks);
+				assert_eq!((**handle).cnt as usize, bytes.len());
+				assert_eq!(&read_lv_string(handle), bytes);
+
+				MOVE_BLOCK_CALLS.set(0);
+				let new = bytes_to_new_lstr(bytes);
+				assert_eq!(MOVE_BLOCK_CALLS.get(), move_blocks);
+				assert_eq!(&read_lv_string(new), bytes);
+				DSDisposeHandleLStr(new);
+			}
+			DSDisposeHandleLStr(handle);
+
+			let new = string_to_new_lstr("a\0b");
+			assert_eq!(read_lv_string(new), b"a\0b");
+			DSDisposeHandleLStr(new);
+		});
+	}
+
+	// Outside LabVIEW there are no manager functions to resolve
+	#[test]
+	fn tests_run_on_the_emulation() {
+		assert!(resolve_host().is_none());
+		unsafe {
+			assert_eq!(
+				PostLVUserEvent(std::ptr::null_mut(), std::ptr::null_mut()),
+				0
+			);
+		}
+	}
+
+	#[test]
+	fn emulated_handles() {
+		unsafe {
+			let handle = emulated::ds_new_handle(4) as *mut *mut [u8; 4];
+			assert!(!handle.is_null());
+			assert_eq!(**handle, [0; 4]); // calloc
+			**handle = *b"abcd";
+			assert_eq!(emulated::ds_set_handle_size(handle as UHandle, 4096), 0);
+			assert_eq!(**handle, *b"abcd");
+			assert_eq!(emulated::ds_dispose_handle(handle as UHandle), 0);
+			assert_eq!(
+				emulated::ds_dispose_handle(std::ptr::null_mut()),
+				MG_ARG_ERR
+			);
+			assert_eq!(
+				emulated::ds_set_handle_size(std::ptr::null_mut(), 1),
+				MG_ARG_ERR
+			);
+		}
+	}
+
+	#[test]
+	fn emulated_move_block_of_overlapping_blocks() {
+		let mut block = *b"abcdef";
+		unsafe {
+			let p = block.as_mut_ptr() as *mut c_void;
+			emulated::move_block(p, p.add(2), 4);
+		}
+		assert_eq!(&block, b"ababcd");
+	}
+
+	// The elements are where LvArray<T> has them, also for 8-byte elements
+	// after the padded dimension size
+	#[test]
+	fn emulated_numeric_array_resize() {
+		unsafe {
+			let mut handle: LvArrayHandle<f64> = std::ptr::null_mut();
+			let resize = |handle: &mut LvArrayHandle<f64>, n| {
+				emulated::numeric_array_resize(10, 1, handle as *mut _ as *mut UHandle, n)
+			};
+			assert_eq!(resize(&mut handle, 2), 0);
+			assert!(!handle.is_null());
+			let elt =
+				|handle: LvArrayHandle<f64>| std::ptr::addr_of_mut!((**handle).elt) as *mut f64;
+			elt(handle).write_unaligned(1.5);
+			elt(handle).add(1).write_unaligned(2.5);
+			(**handle).dim_size = 2;
+
+			assert_eq!(resize(&mut handle, 100_000), 0);
+			elt(handle).add(99_999).write_unaligned(3.5);
+			assert_eq!(elt(handle).read_unaligned(), 1.5);
+			assert_eq!(elt(handle).add(1).read_unaligned(), 2.5);
+
+			let mut bytes: LStrHandle = std::ptr::null_mut();
+			assert_eq!(
+				emulated::numeric_array_resize(1, 1, &mut bytes as *mut _ as *mut UHandle, 3),
+				0
+			);
+			copy_to_lstr(b"xyz", bytes);
+			assert_eq!(read_lv_string(bytes), b"xyz");
+
+			assert_eq!(resize(&mut handle, 1), 0); // shrinks too
+
+			// Unknown type code, no dimensions
+			assert_eq!(
+				emulated::numeric_array_resize(0x30, 1, &mut handle as *mut _ as *mut UHandle, 1),
+				MG_ARG_ERR
+			);
+			assert_eq!(
+				emulated::numeric_array_resize(10, 0, &mut handle as *mut _ as *mut UHandle, 1),
+				MG_ARG_ERR
+			);
+			DSDisposeHandle(handle);
+			DSDisposeHandleLStr(bytes);
+		}
+	}
+
+	#[test]
+	fn null_handles() {
+		unsafe {
+			assert_eq!(write_lv_string(std::ptr::null_mut(), b"abc"), MG_ARG_ERR);
+			assert!(read_lv_string(std::ptr::null_mut()).is_empty());
+		}
+	}
+}
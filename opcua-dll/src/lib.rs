@@ -8,12 +8,33 @@
 // License:     MPL-2.0
 // (based on https://github.com/FreeOpcUa/async-opcua)
 //==============================================================================
+#[macro_use]
 pub mod errors;
 #[macro_use]
 pub mod labview; // common functions and structures
 pub mod browser;
+pub mod certificate;
 pub mod client;
+pub mod client_attributes;
+pub mod client_discovery;
+pub mod client_info;
+pub mod client_jobs;
+pub mod client_json;
+pub mod client_pool;
+pub mod client_session;
+pub mod client_url;
 pub mod client_variables;
+pub mod encoding;
+pub mod handles;
+pub mod history;
+pub mod reference_types;
 pub mod runtime;
 pub mod server; //tokio helper
+pub mod server_audit;
+pub mod server_simulation;
+pub mod server_users;
 pub mod server_variables;
+pub mod subscription;
+#[cfg(test)]
+mod test_server;
+pub mod utils;
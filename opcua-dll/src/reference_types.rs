@@ -0,0 +1,36 @@
+//==============================================================================
+//
+// Title:		Reference types
+// Purpose:		Numeric ids (namespace 0) of the common OPC UA reference
+//				types, for ref_type_id of lv_add_reference, lv_delete_reference
+//				and lvBrowserFiltered
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+use opcua::types::ReferenceTypeId;
+
+// Abstract types, for browsing with subtypes only
+pub const REF_REFERENCES: u32 = ReferenceTypeId::References as u32; // 31
+pub const REF_NON_HIERARCHICAL: u32 = ReferenceTypeId::NonHierarchicalReferences as u32; // 32
+pub const REF_HIERARCHICAL: u32 = ReferenceTypeId::HierarchicalReferences as u32; // 33
+pub const REF_HAS_CHILD: u32 = ReferenceTypeId::HasChild as u32; // 34
+pub const REF_AGGREGATES: u32 = ReferenceTypeId::Aggregates as u32; // 44
+
+// Hierarchical
+pub const REF_ORGANIZES: u32 = ReferenceTypeId::Organizes as u32; // 35
+pub const REF_HAS_EVENT_SOURCE: u32 = ReferenceTypeId::HasEventSource as u32; // 36
+pub const REF_HAS_SUBTYPE: u32 = ReferenceTypeId::HasSubtype as u32; // 45
+pub const REF_HAS_PROPERTY: u32 = ReferenceTypeId::HasProperty as u32; // 46
+pub const REF_HAS_COMPONENT: u32 = ReferenceTypeId::HasComponent as u32; // 47
+pub const REF_HAS_NOTIFIER: u32 = ReferenceTypeId::HasNotifier as u32; // 48
+pub const REF_HAS_ORDERED_COMPONENT: u32 = ReferenceTypeId::HasOrderedComponent as u32; // 49
+
+// Non-hierarchical
+pub const REF_HAS_MODELLING_RULE: u32 = ReferenceTypeId::HasModellingRule as u32; // 37
+pub const REF_HAS_ENCODING: u32 = ReferenceTypeId::HasEncoding as u32; // 38
+pub const REF_HAS_TYPE_DEFINITION: u32 = ReferenceTypeId::HasTypeDefinition as u32; // 40
+pub const REF_GENERATES_EVENT: u32 = ReferenceTypeId::GeneratesEvent as u32; // 41
+pub const REF_HAS_INTERFACE: u32 = ReferenceTypeId::HasInterface as u32; // 17603
+pub const REF_HAS_ADD_IN: u32 = ReferenceTypeId::HasAddIn as u32; // 17604
@@ -7,6 +7,9 @@
 // License: MPL-2.0
 //
 //==============================================================================
+use crate::errors::*;
+use crate::handles::{HandleKind, from_handle, into_handle};
+
 use tokio::runtime::Runtime;
 use tokio::sync::oneshot;
 use tokio::time::Duration;
@@ -37,43 +40,88 @@ pub mod runtime {
 
 #[unsafe(no_mangle)]
 pub extern "C" fn lv_new_runtime() -> *mut Runtime {
-	Box::into_raw(Box::new(Runtime::new().unwrap()))
+	catch_panic!(std::ptr::null_mut(), {
+		into_handle(Runtime::new().unwrap(), HandleKind::Runtime)
 
-	/*
-		let rt = {
-			runtime::Builder::new_multi_thread()
-				.enable_io()
-				.build()
-				.unwrap()
-		};
+		/*
+			let rt = {
+				runtime::Builder::new_multi_thread()
+					.enable_io()
+					.build()
+					.unwrap()
+			};
 
-		Box::into_raw(Box::new(rt))
-	*/
+			Box::into_raw(Box::new(rt))
+		*/
+	})
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn lv_shutdown_runtime(rt_ptr: *mut Runtime) -> i32 {
-	if rt_ptr.is_null() {
-		return -1;
-	}
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		if rt_ptr.is_null() {
+			return -1;
+		}
 
-	unsafe {
-		let rt = Box::from_raw(rt_ptr);
-		let handle = rt.handle().clone();
-		let (s, r) = oneshot::channel();
+		unsafe {
+			let rt = from_handle(rt_ptr);
+			let handle = rt.handle().clone();
+			let (s, r) = oneshot::channel();
 
-		rt.spawn(async move {
-			sleep(Duration::from_secs(1)).await;
-			let _ = s.send(0);
-		});
+			rt.spawn(async move {
+				sleep(Duration::from_secs(1)).await;
+				let _ = s.send(0);
+			});
 
-		handle.block_on(async move {
-			let _ = r.await;
-			rt.shutdown_background();
-		});
+			handle.block_on(async move {
+				let _ = r.await;
+				rt.shutdown_background();
+			});
 
-		// Return the pointer to the caller to handle deallocation
-		//std::mem::forget(rt); // Dangerous! Make sure the caller knows to call Box::into_raw
-		return 0;
-	}
+			// Return the pointer to the caller to handle deallocation
+			//std::mem::forget(rt); // Dangerous! Make sure the caller knows to call Box::into_raw
+			return 0;
+		}
+	})
+}
+
+//==============================================================================
+// Tokio metrics of the runtime, e.g. to see why callbacks are late:
+// active_tasks - spawned tasks which haven't finished (event loops,
+// watchdogs...), queued_tasks - tasks waiting for a worker, worker_count -
+// threads running tasks (1 for a current-thread runtime).
+// queued_tasks counts only the global queue, unless the DLL is built with
+// tokio_unstable (see .cargo/config.toml), then the local queues of the
+// workers are added
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_runtime_stats(
+	rt_ptr: *mut Runtime,
+	active_tasks_out: *mut u64,
+	queued_tasks_out: *mut u64,
+	worker_count_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(active_tasks_out, ERR_NULL_POINTER);
+		check_null!(queued_tasks_out, ERR_NULL_POINTER);
+		check_null!(worker_count_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &*rt_ptr;
+			let metrics = rt.metrics();
+			#[allow(unused_mut)]
+			let mut queued_tasks = metrics.global_queue_depth();
+			#[cfg(tokio_unstable)]
+			{
+				queued_tasks += (0..metrics.num_workers())
+					.map(|worker| metrics.worker_local_queue_depth(worker))
+					.sum::<usize>();
+			}
+			*active_tasks_out = metrics.num_alive_tasks() as u64;
+			*queued_tasks_out = queued_tasks as u64;
+			*worker_count_out = metrics.num_workers() as u32;
+		}
+		NO_ERR
+	})
 }
@@ -12,41 +12,101 @@
 //==============================================================================
 
 use crate::errors::*;
+use crate::handles::{HandleKind, from_handle, into_handle};
+use crate::labview::{LStrHandle, string_to_lstr};
+use crate::server_audit::{reinstall_write_audit, unregister_write_audit};
+use crate::server_simulation::stop_simulations;
+use crate::server_users::{
+	LvAuthenticator, authenticator_of, register_authenticator, unregister_authenticator,
+};
 
 use std::{
-	sync::{Arc, Mutex},
+	collections::HashMap,
+	fs::File,
+	io::{BufWriter, Write},
+	path::{Path, PathBuf},
+	str::FromStr,
+	sync::{Arc, LazyLock, Mutex},
 	thread,
+	time::Duration,
 };
 
 use tokio::{
-	runtime::{Builder, Runtime},
+	runtime::{Builder, Handle, Runtime},
 	sync::oneshot,
 };
 
 use libc::c_char;
 use opcua::{
+	core::config::{Config, ConfigError},
+	crypto::{AlternateNames, CertificateStore, PrivateKey, X509, X509Data},
 	server::{
+		address_space::{
+			AddressSpace, EventNotifier, NodeType, ObjectBuilder, TypeTree, ViewBuilder,
+		},
 		node_manager::memory::{
 			InMemoryNodeManager, /* NamespaceMetadata, */ SimpleNodeManager,
 			SimpleNodeManagerImpl, simple_node_manager,
 		},
-		{Server, ServerBuilder, ServerHandle},
+		{Server, ServerBuilder, ServerConfig, ServerHandle},
 	},
-	types::{BuildInfo, DateTime, NodeId},
+	types::{BuildInfo, DateTime, NodeId, ObjectId, ObjectTypeId, ReferenceTypeId, StatusCode},
 };
 
 use opcua::server::diagnostics::node_manager::NamespaceMetadata;
 
 pub static mut SERVER_GLOBAL_RUNTIME: Option<Arc<Mutex<Runtime>>> = None;
 
+// Handle of SERVER_GLOBAL_RUNTIME, which the thread of lv_start_server keeps
+// locked while the server runs. Tasks spawned with it run with the server
+static SERVER_RUNTIME_HANDLE: Mutex<Option<Handle>> = Mutex::new(None);
+
+pub fn server_runtime_handle() -> Option<Handle> {
+	SERVER_RUNTIME_HANDLE.lock().unwrap().clone()
+}
+
+pub type SimpleManager = Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>;
+
+// Server handle -> server and node manager from the same lvServerBuilder call,
+// lv_server_set_pki_dir rebuilds all three (0 once freed)
+#[derive(Clone, Copy)]
+struct ServerParts {
+	server: usize,
+	manager: usize,
+}
+
+static SERVER_PARTS: LazyLock<Mutex<HashMap<usize, ServerParts>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Node manager of the server handle, None once freed
+pub fn manager_of(handle_ptr: *mut ServerHandle) -> Option<SimpleManager> {
+	let parts = SERVER_PARTS
+		.lock()
+		.unwrap()
+		.get(&(handle_ptr as usize))
+		.copied()?;
+	if parts.manager == 0 {
+		return None;
+	}
+	Some(unsafe { (*(parts.manager as *mut SimpleManager)).clone() })
+}
+
+// Server -> thread running it (lv_start_server), lv_free_server refuses
+// to free a running server
+static SERVER_THREADS: LazyLock<Mutex<HashMap<usize, Arc<thread::JoinHandle<()>>>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
 #[unsafe(no_mangle)]
 pub extern "C" fn lv_new_server_runtime() -> *mut Runtime {
-	let runtime = Builder::new_current_thread().enable_all().build().unwrap();
-	unsafe {
-		SERVER_GLOBAL_RUNTIME = Some(Arc::new(Mutex::new(runtime)));
-	}
+	catch_panic!(std::ptr::null_mut(), {
+		let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+		*SERVER_RUNTIME_HANDLE.lock().unwrap() = Some(runtime.handle().clone());
+		unsafe {
+			SERVER_GLOBAL_RUNTIME = Some(Arc::new(Mutex::new(runtime)));
+		}
 
-	Box::into_raw(Box::new(Runtime::new().unwrap()))
+		into_handle(Runtime::new().unwrap(), HandleKind::Runtime)
+	})
 }
 
 #[unsafe(no_mangle)]
@@ -55,28 +115,61 @@ pub extern "C" fn lvServerBuilder(
 	rt_ptr: *mut Runtime,
 	server_out: *mut *mut Server,
 	handle_out: *mut *mut ServerHandle,
-	manager_out: *mut *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	manager_out: *mut *mut SimpleManager,
 ) -> i32 {
-	check_null!(server_out, ERR_NULL_POINTER);
-	check_null!(handle_out, ERR_NULL_POINTER);
-	check_null!(manager_out, ERR_NULL_POINTER);
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(server_out, ERR_NULL_POINTER);
+		check_null!(handle_out, ERR_NULL_POINTER);
+		check_null!(manager_out, ERR_NULL_POINTER);
 
-	let config_path_str = cstr_to_string!(config_path_str);
-	// Execute the async connection logic
-	unsafe {
-		let rt1 = &mut *rt_ptr;
+		let config_path_str = cstr_to_string!(config_path_str);
+		let config = match load_server_config(&config_path_str, false) {
+			Ok(config) => config,
+			Err(err) => return err,
+		};
+		// Execute the async connection logic
+		unsafe {
+			let rt1 = &mut *rt_ptr;
 
-		let rt = unsafe { SERVER_GLOBAL_RUNTIME.as_ref().unwrap() };
+			let rt = unsafe { SERVER_GLOBAL_RUNTIME.as_ref().unwrap() };
 
-		rt.lock().unwrap().block_on(async move {
-			let (server, handle, manager) = ss(config_path_str).await;
-			*server_out = Box::into_raw(Box::new(server));
-			*handle_out = Box::into_raw(Box::new(handle));
-			*manager_out = Box::into_raw(Box::new(manager));
-		});
-	}
+			rt.lock().unwrap().block_on(async move {
+				match build_server(config).await {
+					Ok((server, handle, manager)) => {
+						*server_out = server;
+						*handle_out = handle;
+						*manager_out = manager;
+						NO_ERR
+					}
+					Err(e) => {
+						set_last_error_detail(e);
+						ERR_INVALID_SERVER_CONFIG
+					}
+				}
+			})
+		}
+	})
+}
 
-	0 // Success
+// Server, server handle and node manager of the config as lvServerBuilder
+// returns them, the user authentication of the config is registered
+pub(crate) async fn build_server(
+	config: ServerConfig,
+) -> Result<(*mut Server, *mut ServerHandle, *mut SimpleManager), String> {
+	let authenticator = LvAuthenticator::new(&config);
+	let (server, handle, manager) = ss(config, authenticator.clone()).await?;
+	let server_ptr = Box::into_raw(Box::new(server));
+	let handle_ptr = into_handle(handle, HandleKind::ServerHandle);
+	let manager_ptr = Box::into_raw(Box::new(manager));
+	SERVER_PARTS.lock().unwrap().insert(
+		handle_ptr as usize,
+		ServerParts {
+			server: server_ptr as usize,
+			manager: manager_ptr as usize,
+		},
+	);
+	register_authenticator(handle_ptr, authenticator);
+	Ok((server_ptr, handle_ptr, manager_ptr))
 }
 
 #[unsafe(no_mangle)]
@@ -85,39 +178,47 @@ pub extern "C" fn lv_stop_server(
 	handle_in: *mut ServerHandle,
 	join_handle_in: *mut Arc<std::thread::JoinHandle<()>>,
 ) -> i32 {
-	check_null!(handle_in, ERR_INVALID_SERVER_REF);
-	check_null!(rt_ptr, ERR_INVALID_RUNTIME);
-	check_null!(join_handle_in, ERR_INVALID_SERVER_REF);
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_in, ERR_INVALID_SERVER_REF);
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(join_handle_in, ERR_INVALID_SERVER_REF);
 
-	unsafe {
-		let rt1 = &mut *rt_ptr;
+		unsafe {
+			let rt1 = &mut *rt_ptr;
 
-		let rt = unsafe { SERVER_GLOBAL_RUNTIME.as_ref().unwrap() };
+			let rt = unsafe { SERVER_GLOBAL_RUNTIME.as_ref().unwrap() };
 
-		let handle = &mut *handle_in;
-		//let join_handle = &mut *join_handle_in;
+			let handle = &mut *handle_in;
+			//let join_handle = &mut *join_handle_in;
 
-		handle.cancel(); //as in provided example
+			stop_simulations(handle_in);
+			handle.cancel(); //as in provided example
 
-		let rt_handle = rt.lock().unwrap().handle().clone();
-		rt_handle.block_on(async move {
-			//	r.await;
-			//rt.shutdown_background();
-		});
-	}
+			let rt_handle = rt.lock().unwrap().handle().clone();
+			rt_handle.block_on(async move {
+				//	r.await;
+				//rt.shutdown_background();
+			});
+		}
 
-	return 0;
+		return 0;
+	})
 }
 
 async fn ss(
-	config_path_str: String,
-) -> (
-	Server,
-	ServerHandle,
-	Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
-) {
+	config: ServerConfig,
+	authenticator: Arc<LvAuthenticator>,
+) -> Result<
+	(
+		Server,
+		ServerHandle,
+		Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	),
+	String,
+> {
 	let (server, handle) = ServerBuilder::new()
-		.with_config_from(config_path_str)
+		.with_authenticator(authenticator)
+		.with_config(config)
 		.build_info(BuildInfo {
 			product_uri: "https://github.com/freeopcua/async-opcua".into(),
 			manufacturer_name: "Rust OPC-UA".into(),
@@ -134,8 +235,7 @@ async fn ss(
 			"simple",
 		))
 		.trust_client_certs(true)
-		.build()
-		.unwrap();
+		.build()?;
 	let node_manager = handle
 		.node_managers()
 		.get_of_type::<SimpleNodeManager>()
@@ -143,9 +243,401 @@ async fn ss(
 
 	let ns = handle.get_namespace_index("urn:SimpleServer").unwrap();
 
-	(server, handle, node_manager)
+	Ok((server, handle, node_manager))
+}
+
+// Load and validate the server config file, all problems at once.
+// validate_only (lv_validate_server_config) checks the TCP port too and
+// changes nothing on disk, lvServerBuilder creates the PKI dir and doesn't
+// need the port yet (lv_start_server checks it)
+fn load_server_config(path: &str, validate_only: bool) -> Result<ServerConfig, i32> {
+	let config: ServerConfig = ServerConfig::load(&PathBuf::from(path))
+		.map_err(|err| config_error(path, err, ERR_INVALID_SERVER_CONFIG))?;
+	let mut errors = config.validate().err().unwrap_or_default();
+	if validate_only {
+		errors.extend(check_pki_dir_writable(&config.pki_dir));
+	} else {
+		errors.extend(check_pki_dir(&config.pki_dir));
+	}
+	if validate_only {
+		errors.extend(check_tcp_port(
+			&config.tcp_config.host,
+			config.tcp_config.port,
+		));
+	}
+	if errors.is_empty() {
+		Ok(config)
+	} else {
+		let errors = ConfigError::ConfigInvalid(errors);
+		Err(config_error(path, errors, ERR_INVALID_SERVER_CONFIG))
+	}
+}
+
+// The server must be able to create the PKI dir and write its certificates
+fn check_pki_dir(pki_dir: &Path) -> Option<String> {
+	let probe = pki_dir.join(".lv_write_check");
+	std::fs::create_dir_all(pki_dir)
+		.and_then(|_| std::fs::write(&probe, b""))
+		.and_then(|_| std::fs::remove_file(&probe))
+		.err()
+		.map(|e| format!("PKI dir {} is not writable: {e}", pki_dir.display()))
+}
+
+// check_pki_dir without creating anything: the PKI dir, or the existing
+// directory it would be created in, must be writable
+fn check_pki_dir_writable(pki_dir: &Path) -> Option<String> {
+	let existing = pki_dir
+		.ancestors()
+		.map(|dir| match dir.as_os_str().is_empty() {
+			true => Path::new("."),
+			false => dir,
+		})
+		.find(|dir| dir.exists())?;
+	if !existing.is_dir() {
+		return Some(format!(
+			"PKI dir {} can't be created, {} is not a directory",
+			pki_dir.display(),
+			existing.display()
+		));
+	}
+	if !is_writable(existing) {
+		return Some(format!(
+			"PKI dir {} is not writable: no write access to {}",
+			pki_dir.display(),
+			existing.display()
+		));
+	}
+	None
+}
+
+#[cfg(unix)]
+fn is_writable(dir: &Path) -> bool {
+	use std::os::unix::ffi::OsStrExt;
+	let Ok(dir) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+		return false;
+	};
+	unsafe { libc::access(dir.as_ptr(), libc::W_OK) == 0 }
+}
+
+// The ACL isn't checked, the read-only attribute only
+#[cfg(windows)]
+fn is_writable(dir: &Path) -> bool {
+	std::fs::metadata(dir).is_ok_and(|m| !m.permissions().readonly())
+}
+
+// Nothing else may listen on the port of the server
+fn check_tcp_port(host: &str, port: u16) -> Option<String> {
+	std::net::TcpListener::bind((host, port))
+		.err()
+		.map(|e| format!("TCP port {port} on {host} is not available: {e}"))
+}
+
+//==============================================================================
+// Write the configuration of the server (from lvServerBuilder) to a YAML file,
+// which lvServerBuilder loads again
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_save_server_config(handle_ptr: *mut ServerHandle, path: *const c_char) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(path, ERR_NULL_POINTER);
+
+		let path = cstr_to_string!(path);
+		let handle = unsafe { &*handle_ptr };
+		match handle.info().config.save(&PathBuf::from(&path)) {
+			Ok(()) => NO_ERR,
+			Err(err) => config_error(&path, err, ERR_INVALID_SERVER_CONFIG),
+		}
+	})
+}
+
+//==============================================================================
+// Check a server config file before starting the server: the config itself
+// (endpoints and their security policies, user tokens, limits), if the PKI dir
+// can be created and written and if the TCP port is free. Nothing is created
+// or written, the write access of the PKI dir is checked only.
+// detail_out receives all problems found (one per line), empty if the file is
+// valid. ERR_IO if the file can't be read, ERR_INVALID_SERVER_CONFIG otherwise
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_validate_server_config(path: *const c_char, detail_out: LStrHandle) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(path, ERR_NULL_POINTER);
+		check_null!(detail_out, ERR_NULL_POINTER);
+
+		let path = cstr_to_string!(path);
+		let (result, detail) = match load_server_config(&path, true) {
+			Ok(_) => (NO_ERR, String::new()),
+			Err(err) => (err, last_error_detail()),
+		};
+		let err = unsafe { string_to_lstr(&detail, detail_out) };
+		if err != NO_ERR {
+			return err;
+		}
+		result
+	})
+}
+
+//==============================================================================
+// Use pki_dir for the certificates of the server (own certificate and key,
+// trusted and rejected client certificates) instead of the one of its config.
+// The server is rebuilt with the changed config, so call it right after
+// lvServerBuilder: before lv_start_server (ERR_INVALID_ARGUMENT for a started
+// server) and before adding nodes, the node manager is a new one as well.
+// The server, handle and node manager pointers stay valid
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_server_set_pki_dir(
+	handle_ptr: *mut ServerHandle,
+	pki_dir_str: *const c_char,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(pki_dir_str, ERR_NULL_POINTER);
+
+		let pki_dir = PathBuf::from(cstr_to_string!(pki_dir_str));
+		if pki_dir.as_os_str().is_empty() {
+			return ERR_INVALID_ARGUMENT;
+		}
+		let Some(parts) = SERVER_PARTS
+			.lock()
+			.unwrap()
+			.get(&(handle_ptr as usize))
+			.copied()
+		else {
+			return ERR_INVALID_SERVER_REF;
+		};
+		if parts.server == 0 {
+			set_last_error_detail("The server of the handle is freed");
+			return ERR_INVALID_SERVER_REF;
+		}
+		if SERVER_THREADS.lock().unwrap().contains_key(&parts.server) {
+			set_last_error_detail("The server is started, set the PKI dir before lv_start_server");
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let handle = &mut *handle_ptr;
+			let mut config = (*handle.info().config).clone();
+			config.pki_dir = pki_dir;
+			if let Some(e) = check_pki_dir(&config.pki_dir) {
+				set_last_error_detail(e);
+				return ERR_IO;
+			}
+
+			// The users added so far are kept
+			let authenticator =
+				authenticator_of(handle_ptr).unwrap_or_else(|| LvAuthenticator::new(&config));
+			let rt = SERVER_GLOBAL_RUNTIME.as_ref().unwrap();
+			let rebuilt = rt.lock().unwrap().block_on(ss(config, authenticator));
+			let (server, new_handle, manager) = match rebuilt {
+				Ok(rebuilt) => rebuilt,
+				Err(e) => {
+					set_last_error_detail(e);
+					return ERR_INVALID_SERVER_CONFIG;
+				}
+			};
+			reinstall_write_audit(handle_ptr, &manager);
+			*(parts.server as *mut Server) = server;
+			*handle = new_handle;
+			if parts.manager != 0 {
+				*(parts.manager as *mut SimpleManager) = manager;
+			}
+		}
+		NO_ERR
+	})
+}
+
+// Paths of the own certificate and private key the server uses
+fn own_cert_and_key_paths(config: &ServerConfig) -> (PathBuf, PathBuf) {
+	match (&config.certificate_path, &config.private_key_path) {
+		(Some(cert_path), Some(key_path)) => (
+			config.pki_dir.join(cert_path),
+			config.pki_dir.join(key_path),
+		),
+		_ => {
+			let store = CertificateStore::new(&config.pki_dir);
+			(store.own_certificate_path(), store.own_private_key_path())
+		}
+	}
+}
+
+//==============================================================================
+// Path of the own certificate (.der) / private key (.pem) of the server
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_server_get_own_cert_path(
+	handle_ptr: *mut ServerHandle,
+	cert_path_lv_str: LStrHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(cert_path_lv_str, ERR_NULL_POINTER);
+
+		let handle = unsafe { &*handle_ptr };
+		let (cert_path, _) = own_cert_and_key_paths(&handle.info().config);
+		unsafe { string_to_lstr(&cert_path.to_string_lossy(), cert_path_lv_str) }
+	})
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_server_get_own_key_path(
+	handle_ptr: *mut ServerHandle,
+	key_path_lv_str: LStrHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(key_path_lv_str, ERR_NULL_POINTER);
+
+		let handle = unsafe { &*handle_ptr };
+		let (_, key_path) = own_cert_and_key_paths(&handle.info().config);
+		unsafe { string_to_lstr(&key_path.to_string_lossy(), key_path_lv_str) }
+	})
+}
+
+// Renewed certificates waiting for the sessions of the server to close,
+// server handle -> certificate and key
+static PENDING_CERTIFICATES: LazyLock<Mutex<HashMap<usize, (X509, PrivateKey)>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Write the certificate and key over the old ones, new connections use them
+fn apply_server_certificate(
+	handle: &ServerHandle,
+	cert: X509,
+	pkey: PrivateKey,
+) -> Result<(), String> {
+	let (cert_path, key_path) = own_cert_and_key_paths(&handle.info().config);
+	CertificateStore::store_cert_and_pkey(&cert, &pkey, true, &cert_path, &key_path)?;
+	handle.info().set_server_certificate(cert, pkey);
+	Ok(())
+}
+
+// Apply the pending certificate once the server has no sessions (or is stopped)
+fn apply_when_no_sessions(handle: ServerHandle, key: usize) {
+	thread::spawn(move || {
+		while !handle.session_manager().read().is_empty() && !handle.token().is_cancelled() {
+			thread::sleep(Duration::from_secs(1));
+		}
+		let pending = PENDING_CERTIFICATES.lock().unwrap().remove(&key);
+		if let Some((cert, pkey)) = pending {
+			if let Err(e) = apply_server_certificate(&handle, cert, pkey) {
+				set_last_error_detail(format!("The renewed server certificate isn't saved: {e}"));
+			}
+		}
+	});
 }
 
+//==============================================================================
+// New certificate and private key of the server (key_size 2048 or 4096 bits,
+// valid for duration_days), e.g. before the old one expires. Alternate names
+// are app_uri_str and hostname_str, empty strings take the application URI /
+// host of the config, an empty common_name_str the application name.
+// Without sessions the pair replaces the own certificate and key files at once,
+// otherwise after the last session is closed (or timed out), the clients of
+// the open sessions keep the old one until then. Calling it again before
+// replaces the pending pair
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_regenerate_server_certificate(
+	handle_ptr: *mut ServerHandle,
+	key_size: u32,
+	duration_days: u32,
+	common_name_str: *const c_char,
+	app_uri_str: *const c_char,
+	hostname_str: *const c_char,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(common_name_str, ERR_NULL_POINTER);
+		check_null!(app_uri_str, ERR_NULL_POINTER);
+		check_null!(hostname_str, ERR_NULL_POINTER);
+
+		if key_size != 2048 && key_size != 4096 {
+			set_last_error_detail(format!("Key size {key_size}, must be 2048 or 4096"));
+			return ERR_INVALID_ARGUMENT;
+		}
+		if duration_days == 0 {
+			return ERR_INVALID_ARGUMENT;
+		}
+		let handle = unsafe { &*handle_ptr };
+		let config = &handle.info().config;
+		let or_default = |s: String, default: &str| {
+			if s.is_empty() { default.to_string() } else { s }
+		};
+		let common_name = or_default(cstr_to_string!(common_name_str), &config.application_name);
+		let app_uri = or_default(cstr_to_string!(app_uri_str), &config.application_uri);
+		let hostname = or_default(cstr_to_string!(hostname_str), &config.tcp_config.host);
+
+		let mut alt_host_names = AlternateNames::new();
+		alt_host_names.add_uri(&app_uri);
+		alt_host_names.add_address(&hostname);
+		let x509_data = X509Data {
+			key_size,
+			common_name,
+			organization: String::new(),
+			organizational_unit: String::new(),
+			country: String::new(),
+			state: String::new(),
+			alt_host_names,
+			certificate_duration_days: duration_days,
+			serial_number: None,
+			signature_algorithm: Default::default(),
+		};
+		let (cert, pkey) = match X509::cert_and_pkey(&x509_data) {
+			Ok(pair) => pair,
+			Err(e) => {
+				set_last_error_detail(e);
+				return ERR_INVALID_ARGUMENT;
+			}
+		};
+
+		if handle.session_manager().read().is_empty() {
+			return match apply_server_certificate(handle, cert, pkey) {
+				Ok(()) => NO_ERR,
+				Err(e) => {
+					set_last_error_detail(e);
+					ERR_IO
+				}
+			};
+		}
+		let already_pending = PENDING_CERTIFICATES
+			.lock()
+			.unwrap()
+			.insert(handle_ptr as usize, (cert, pkey))
+			.is_some();
+		if !already_pending {
+			apply_when_no_sessions(handle.clone(), handle_ptr as usize);
+		}
+		NO_ERR
+	})
+}
+
+// Nothing else may listen on the port of the server when it starts,
+// ERR_INVALID_SERVER_CONFIG otherwise
+fn check_server_port(server_ptr: *mut Server) -> i32 {
+	let handle_ptr = SERVER_PARTS
+		.lock()
+		.unwrap()
+		.iter()
+		.find(|(_, parts)| parts.server == server_ptr as usize)
+		.map(|(handle_ptr, _)| *handle_ptr as *mut ServerHandle);
+	let Some(handle_ptr) = handle_ptr else {
+		return NO_ERR; // not of lvServerBuilder
+	};
+	let config = unsafe { (*handle_ptr).info().config.clone() };
+	match check_tcp_port(&config.tcp_config.host, config.tcp_config.port) {
+		Some(detail) => {
+			set_last_error_detail(detail);
+			ERR_INVALID_SERVER_CONFIG
+		}
+		None => NO_ERR,
+	}
+}
+
+//==============================================================================
+// Run the server of lvServerBuilder on a thread of its own.
+// ERR_INVALID_SERVER_CONFIG if the TCP port of its config is in use
+//
 #[unsafe(no_mangle)]
 pub extern "C" fn lv_start_server(
 	rt_ptr: *mut Runtime,
@@ -153,52 +645,63 @@ pub extern "C" fn lv_start_server(
 	server_handle_out: *mut *mut (), //not needed in general
 	join_handle_out: *mut *mut Arc<std::thread::JoinHandle<()>>,
 ) -> i32 {
-	// Create a Tokio runtime
-	// let rt = Runtime::new()?;
-	if rt_ptr.is_null() {
-		return ERR_INVALID_RUNTIME;
-	}
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		// Create a Tokio runtime
+		// let rt = Runtime::new()?;
+		if rt_ptr.is_null() {
+			return ERR_INVALID_RUNTIME;
+		}
+		check_null!(lv_server, ERR_INVALID_SERVER_REF);
+		let err = check_server_port(lv_server);
+		if err != NO_ERR {
+			return err;
+		}
 
-	// Execute the async connection logic
-	unsafe {
-		let rt1 = &mut *rt_ptr;
+		// Execute the async connection logic
+		unsafe {
+			let rt1 = &mut *rt_ptr;
 
-		let rt = unsafe { SERVER_GLOBAL_RUNTIME.as_ref().unwrap() };
-		let server = &mut *lv_server;
+			let rt = unsafe { SERVER_GLOBAL_RUNTIME.as_ref().unwrap() };
+			let server = &mut *lv_server;
 
-		rt.lock().unwrap().block_on(async {
-			//server.run().await.unwrap();
-			//*server_out = Box::into_raw(Box::new(server));
-		});
+			rt.lock().unwrap().block_on(async {
+				//server.run().await.unwrap();
+				//*server_out = Box::into_raw(Box::new(server));
+			});
 
-		// Create a channel to send a signal to the server thread to start
-		let (tx, rx) = oneshot::channel();
+			// Create a channel to send a signal to the server thread to start
+			let (tx, rx) = oneshot::channel();
 
-		// Start the server in a separate thread
-		let server_handle = {
-			//let rt = rt.clone();
-			let handle = Arc::new(thread::spawn(move || {
-				// Clone the runtime to use in the thread
+			// Start the server in a separate thread
+			let server_handle = {
 				//let rt = rt.clone();
-				rt.lock().unwrap().block_on(async {
-					// Wait for the signal to start the server
-					rx.await.unwrap();
-					server.run().await.unwrap();
-					// server running
-				});
-			}));
-			*join_handle_out = Box::into_raw(Box::new(handle));
-		};
+				let handle = Arc::new(thread::spawn(move || {
+					// Clone the runtime to use in the thread
+					//let rt = rt.clone();
+					rt.lock().unwrap().block_on(async {
+						// Wait for the signal to start the server
+						rx.await.unwrap();
+						server.run().await.unwrap();
+						// server running
+					});
+				}));
+				SERVER_THREADS
+					.lock()
+					.unwrap()
+					.insert(lv_server as usize, handle.clone());
+				*join_handle_out = into_handle(handle, HandleKind::JoinHandle);
+			};
 
-		// Send the signal to start the server
-		tx.send(());
+			// Send the signal to start the server
+			tx.send(());
 
-		// Return the join handle to keep the thread running
-		//Ok(server_handle)
+			// Return the join handle to keep the thread running
+			//Ok(server_handle)
 
-		*server_handle_out = Box::into_raw(Box::new(server_handle));
-		return 0;
-	}
+			*server_handle_out = Box::into_raw(Box::new(server_handle));
+			return 0;
+		}
+	})
 }
 
 //==============================================================================
@@ -212,20 +715,91 @@ pub extern "C" fn lv_is_server_running(
 	rt_ptr: *mut Runtime,
 	join_handle_in: *mut Arc<std::thread::JoinHandle<()>>,
 ) -> i32 {
-	check_null!(join_handle_in, ERR_INVALID_SERVER_REF);
-	check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(join_handle_in, ERR_INVALID_SERVER_REF);
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
 
-	unsafe {
-		let rt1 = &mut *rt_ptr;
+		unsafe {
+			let rt1 = &mut *rt_ptr;
 
-		let rt = unsafe { SERVER_GLOBAL_RUNTIME.as_ref().unwrap() };
-		let handle = &mut *join_handle_in;
-		if !(handle.is_finished()) {
-			return 1;
-		} else {
-			return 0;
-		};
-	}
+			let rt = unsafe { SERVER_GLOBAL_RUNTIME.as_ref().unwrap() };
+			let handle = &mut *join_handle_in;
+			if !(handle.is_finished()) {
+				return 1;
+			} else {
+				return 0;
+			};
+		}
+	})
+}
+
+//==============================================================================
+// Free the server from lvServerBuilder. It must not be running: after
+// lv_stop_server wait until lv_is_server_running returns 0.
+// ERR_INVALID_SERVER_REF if the server thread is still running
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_free_server(server_ptr: *mut Server) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(server_ptr, ERR_INVALID_SERVER_REF);
+
+		let running = SERVER_THREADS
+			.lock()
+			.unwrap()
+			.get(&(server_ptr as usize))
+			.is_some_and(|t| !t.is_finished());
+		if running {
+			set_last_error_detail("The server is running, stop it first");
+			return ERR_INVALID_SERVER_REF;
+		}
+		SERVER_THREADS
+			.lock()
+			.unwrap()
+			.remove(&(server_ptr as usize));
+		for parts in SERVER_PARTS.lock().unwrap().values_mut() {
+			if parts.server == server_ptr as usize {
+				parts.server = 0;
+			}
+		}
+		unsafe { drop(from_handle(server_ptr)) };
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Free the server handle from lvServerBuilder (after lv_stop_server, the
+// handle is needed to cancel the server)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_free_server_handle(handle_ptr: *mut ServerHandle) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+		SERVER_PARTS.lock().unwrap().remove(&(handle_ptr as usize));
+		unregister_authenticator(handle_ptr);
+		unregister_write_audit(handle_ptr);
+		unsafe { drop(from_handle(handle_ptr)) };
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Free the node manager reference from lvServerBuilder, the server keeps
+// its own reference
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_free_node_manager(
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		for parts in SERVER_PARTS.lock().unwrap().values_mut() {
+			if parts.manager == manager_ptr as usize {
+				parts.manager = 0;
+			}
+		}
+		unsafe { drop(from_handle(manager_ptr)) };
+		NO_ERR
+	})
 }
 
 //==============================================================================
@@ -243,28 +817,818 @@ pub extern "C" fn lv_add_folder(
 	folder_id_out: *mut *mut NodeId,
 	//address_space_out: *mut *mut RwLockWriteGuard<'_, RawRwLock, AddressSpace>
 ) -> i32 {
-	unsafe {
-		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
-		check_null!(folder_id_out, ERR_NULL_POINTER);
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		unsafe {
+			check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+			check_null!(folder_id_out, ERR_NULL_POINTER);
 
-		let manager = &mut *manager_ptr;
+			let manager = &mut *manager_ptr;
 
-		let folder_node_str = cstr_to_string!(folder_node_str);
-		let folder_browse_str = cstr_to_string!(folder_browse_str);
-		let folder_display_str = cstr_to_string!(folder_display_str);
+			let folder_node_str = cstr_to_string!(folder_node_str);
+			let folder_browse_str = cstr_to_string!(folder_browse_str);
+			let folder_display_str = cstr_to_string!(folder_display_str);
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+
+			// Create a sample folder under objects folder
+			let sample_folder_id = NodeId::new(ns, folder_node_str); //was "folder"
+			let err = crate::server_variables::check_node_free(&address_space, &sample_folder_id);
+			if err != NO_ERR {
+				return err;
+			}
+			address_space.add_folder(
+				&sample_folder_id,
+				folder_browse_str,
+				folder_display_str,
+				&NodeId::objects_folder_id(),
+			);
+			//*address_space_out = Box::into_raw(Box::new(address_space)); //no need
+			*folder_id_out = Box::into_raw(Box::new(sample_folder_id));
+		}
+		0
+	})
+}
+
+//==============================================================================
+// Parent of a new folder/object: parent_id_ptr (folder_id from lv_add_folder*,
+// lv_add_object) if not null, else parent_node_str ("ns=2;s=Device1"),
+// if it is null or empty the Objects folder.
+// The parent must be an object (folders are objects too) of this manager
+//
+fn parent_node_id(
+	address_space: &AddressSpace,
+	parent_id_ptr: *mut NodeId,
+	parent_node_str: *const c_char,
+) -> Result<NodeId, i32> {
+	let parent_id = if !parent_id_ptr.is_null() {
+		unsafe { (*parent_id_ptr).clone() }
+	} else if parent_node_str.is_null() {
+		return Ok(NodeId::objects_folder_id());
+	} else {
+		let parent_node_str = cstr_to_string!(parent_node_str);
+		if parent_node_str.is_empty() {
+			return Ok(NodeId::objects_folder_id());
+		}
+		match NodeId::from_str(&parent_node_str) {
+			Ok(parent_id) => parent_id,
+			Err(_) => return Err(ERR_INVALID_ARGUMENT),
+		}
+	};
+
+	if parent_id == NodeId::objects_folder_id() {
+		return Ok(parent_id);
+	}
+	match address_space.find_node(&parent_id) {
+		Some(NodeType::Object(_)) => Ok(parent_id),
+		_ => {
+			set_last_error_detail(format!("Parent {} is not a folder or object", parent_id));
+			Err(StatusCode::BadParentNodeIdInvalid.bits() as i32)
+		}
+	}
+}
+
+fn add_object_node(
+	node_str: *const c_char,
+	browse_str: *const c_char,
+	display_str: *const c_char,
+	ns: u16,
+	parent_id_ptr: *mut NodeId,
+	parent_node_str: *const c_char,
+	is_folder: bool,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	node_id_out: *mut *mut NodeId,
+) -> i32 {
+	check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+	check_null!(node_id_out, ERR_NULL_POINTER);
+	check_null!(node_str, ERR_NULL_POINTER);
+	check_null!(browse_str, ERR_NULL_POINTER);
+	check_null!(display_str, ERR_NULL_POINTER);
+
+	unsafe {
+		let manager = &mut *manager_ptr;
 		let address_space = manager.address_space();
 		let mut address_space = address_space.write();
 
-		// Create a sample folder under objects folder
-		let sample_folder_id = NodeId::new(ns, folder_node_str); //was "folder"
-		address_space.add_folder(
-			&sample_folder_id,
+		let parent_id = match parent_node_id(&address_space, parent_id_ptr, parent_node_str) {
+			Ok(parent_id) => parent_id,
+			Err(err) => return err,
+		};
+		let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+		let err = crate::server_variables::check_node_free(&address_space, &node_id);
+		if err != NO_ERR {
+			return err;
+		}
+
+		let builder = ObjectBuilder::new(
+			&node_id,
+			cstr_to_string!(browse_str),
+			cstr_to_string!(display_str),
+		);
+		let builder = if is_folder {
+			builder.is_folder()
+		} else {
+			builder.has_type_definition(ObjectTypeId::BaseObjectType)
+		};
+		builder.organized_by(parent_id).insert(&mut *address_space);
+		*node_id_out = Box::into_raw(Box::new(node_id));
+	}
+	NO_ERR
+}
+
+//==============================================================================
+// Same as lv_add_folder, but under the given parent (see parent_node_id()),
+// for Device/Channel/Signal hierarchies
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_add_folder_ex(
+	folder_node_str: *const c_char,
+	folder_browse_str: *const c_char,
+	folder_display_str: *const c_char,
+	ns: u16,
+	parent_id_ptr: *mut NodeId,
+	parent_node_str: *const c_char,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	folder_id_out: *mut *mut NodeId,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		add_object_node(
+			folder_node_str,
 			folder_browse_str,
 			folder_display_str,
-			&NodeId::objects_folder_id(),
+			ns,
+			parent_id_ptr,
+			parent_node_str,
+			true,
+			manager_ptr,
+			folder_id_out,
+		)
+	})
+}
+
+//==============================================================================
+// Add BaseObjectType instance under the given parent (see parent_node_id()),
+// some clients show FolderType differently. object_id_out can be used as
+// folder for lv_add_variable* and as parent for lv_add_folder_ex/lv_add_object
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_add_object(
+	object_node_str: *const c_char,
+	object_browse_str: *const c_char,
+	object_display_str: *const c_char,
+	ns: u16,
+	parent_id_ptr: *mut NodeId,
+	parent_node_str: *const c_char,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	object_id_out: *mut *mut NodeId,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		add_object_node(
+			object_node_str,
+			object_browse_str,
+			object_display_str,
+			ns,
+			parent_id_ptr,
+			parent_node_str,
+			false,
+			manager_ptr,
+			object_id_out,
+		)
+	})
+}
+
+//==============================================================================
+// Add View node under Views folder, some clients show only the nodes of the
+// view (linked with lv_add_node_to_view). event_notifier: 1 - subscribe to
+// events, 4 - history read, 8 - history write (EventNotifier bits)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_add_view(
+	view_node_str: *const c_char,
+	browse_str: *const c_char,
+	display_str: *const c_char,
+	ns: u16,
+	contains_no_loops: u8,
+	event_notifier: u8,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	view_id_out: *mut *mut NodeId,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(view_id_out, ERR_NULL_POINTER);
+		check_null!(view_node_str, ERR_NULL_POINTER);
+		check_null!(browse_str, ERR_NULL_POINTER);
+		check_null!(display_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+
+			let view_id = NodeId::new(ns, cstr_to_string!(view_node_str));
+			let err = crate::server_variables::check_node_free(&address_space, &view_id);
+			if err != NO_ERR {
+				return err;
+			}
+			ViewBuilder::new(
+				&view_id,
+				cstr_to_string!(browse_str),
+				cstr_to_string!(display_str),
+			)
+			.contains_no_loops(contains_no_loops != 0)
+			.event_notifier(EventNotifier::from_bits_truncate(event_notifier))
+			.organized_by(ObjectId::ViewsFolder)
+			.insert(&mut *address_space);
+			*view_id_out = Box::into_raw(Box::new(view_id));
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Link the existing node into the view (Organizes reference from the view)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_add_node_to_view(
+	view_id_ptr: *mut NodeId,
+	target_ns: u16,
+	target_str: *const c_char,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(view_id_ptr, ERR_NULL_POINTER);
+		check_null!(target_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let view_id = &*view_id_ptr;
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+
+			match address_space.find_node(view_id) {
+				Some(NodeType::View(_)) => {}
+				_ => {
+					set_last_error_detail(format!("View {} not found", view_id));
+					return ERR_INVALID_SERVER_REF;
+				}
+			}
+			let target = match existing_node(&address_space, target_ns, target_str) {
+				Ok(target) => target,
+				Err(err) => return err,
+			};
+			address_space.insert_reference(view_id, &target, ReferenceTypeId::Organizes);
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Delete the folder (or object) at runtime, with references pointing to it.
+// recursive = 1 deletes all nodes below it too (subfolders, variables and
+// their properties), with recursive = 0 the folder must be empty
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_delete_folder(
+	folder_node_str: *const c_char,
+	ns: u16,
+	recursive: u8,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(folder_node_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let folder_node = NodeId::new(ns, cstr_to_string!(folder_node_str));
+
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			match address_space.find_node(&folder_node) {
+				Some(NodeType::Object(_)) => {}
+				Some(_) => {
+					set_last_error_detail(format!("{} is not a folder", folder_node));
+					return ERR_INVALID_TYPE;
+				}
+				None => {
+					set_last_error_detail(format!("Folder {} not found", folder_node));
+					return ERR_INVALID_SERVER_REF;
+				}
+			}
+			let child_refs = [
+				ReferenceTypeId::Organizes,
+				ReferenceTypeId::HasComponent,
+				ReferenceTypeId::HasProperty,
+			];
+			if recursive == 0
+				&& !crate::server_variables::child_nodes(&address_space, &folder_node, &child_refs)
+					.is_empty()
+			{
+				set_last_error_detail(format!(
+					"Folder {} is not empty, use recursive = 1",
+					folder_node
+				));
+				return ERR_INVALID_ARGUMENT;
+			}
+			crate::server_variables::delete_node_tree(
+				&mut address_space,
+				&folder_node,
+				&child_refs,
+				true,
+			);
+			NO_ERR
+		}
+	})
+}
+
+//==============================================================================
+// Node of lv_add_reference/lv_delete_reference, ERR_INVALID_SERVER_REF with
+// the last error detail if it doesn't exist
+//
+fn existing_node(
+	address_space: &AddressSpace,
+	ns: u16,
+	node_str: *const c_char,
+) -> Result<NodeId, i32> {
+	let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+	match address_space.find_node(&node_id) {
+		Some(_) => Ok(node_id),
+		None => {
+			set_last_error_detail(format!("Node {} not found", node_id));
+			Err(ERR_INVALID_SERVER_REF)
+		}
+	}
+}
+
+// Standard reference type of namespace 0 (see reference_types.rs), the
+// address space of the manager doesn't hold the nodes of namespace 0
+fn reference_type(ref_type_id: u32) -> Result<NodeId, i32> {
+	match ReferenceTypeId::try_from(ref_type_id) {
+		Ok(ref_type) => Ok(ref_type.into()),
+		Err(()) => {
+			set_last_error_detail(format!("{} is not a reference type", ref_type_id));
+			Err(ERR_INVALID_TYPE)
+		}
+	}
+}
+
+//==============================================================================
+// Add the reference of type ref_type_id (see reference_types.rs) from
+// source to target, is_forward = 0 adds it from target to source.
+// The inverse reference is added too, so both ends can browse it
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_add_reference(
+	source_ns: u16,
+	source_str: *const c_char,
+	ref_type_id: u32,
+	is_forward: u8,
+	target_ns: u16,
+	target_str: *const c_char,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(source_str, ERR_NULL_POINTER);
+		check_null!(target_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			let nodes = (
+				existing_node(&address_space, source_ns, source_str),
+				reference_type(ref_type_id),
+				existing_node(&address_space, target_ns, target_str),
+			);
+			let (source, ref_type, target) = match nodes {
+				(Ok(source), Ok(ref_type), Ok(target)) => (source, ref_type, target),
+				(Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => return err,
+			};
+			match is_forward {
+				0 => address_space.insert_reference(&target, &source, ref_type),
+				_ => address_space.insert_reference(&source, &target, ref_type),
+			}
+			NO_ERR
+		}
+	})
+}
+
+//==============================================================================
+// Delete the reference of type ref_type_id from source to target (and its
+// inverse), BadNotFound if there is no such reference
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_delete_reference(
+	source_ns: u16,
+	source_str: *const c_char,
+	ref_type_id: u32,
+	target_ns: u16,
+	target_str: *const c_char,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(source_str, ERR_NULL_POINTER);
+		check_null!(target_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let source = NodeId::new(source_ns, cstr_to_string!(source_str));
+			let target = NodeId::new(target_ns, cstr_to_string!(target_str));
+			let ref_type = match reference_type(ref_type_id) {
+				Ok(ref_type) => ref_type,
+				Err(err) => return err,
+			};
+
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			if address_space.delete_reference(&source, &target, ref_type.clone()) {
+				NO_ERR
+			} else {
+				set_last_error_detail(format!(
+					"No {} reference from {} to {}",
+					ref_type, source, target
+				));
+				StatusCode::BadNotFound.bits() as i32
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Set Server_ServiceLevel (0..255), clients may switch to a standby server
+// when the level drops, e.g. while the VI is in configuration mode.
+// The node belongs to the core node manager, which reads the level from
+// the server handle, so the value is set through it (manager is only checked)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_service_level(
+	handle_ptr: *mut ServerHandle,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	level: u8,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+
+		unsafe {
+			let handle = &*handle_ptr;
+			handle.set_service_level(level);
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Look up the index of a namespace registered on the server by its URI,
+// the index may differ from 1 when more namespaces are registered
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_server_get_namespace_index(
+	handle_ptr: *mut ServerHandle,
+	ns_uri_str: *const c_char,
+	ns_index_out: *mut u16,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(ns_uri_str, ERR_NULL_POINTER);
+		check_null!(ns_index_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let handle = &*handle_ptr;
+			let ns_uri = cstr_to_string!(ns_uri_str);
+			match handle.get_namespace_index(&ns_uri) {
+				Some(ns) => *ns_index_out = ns,
+				None => {
+					set_last_error_detail(ns_uri);
+					return ERR_NAMESPACE_NOT_FOUND;
+				}
+			}
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Server configuration for troubleshooting as JSON: bound address (the
+// actual port once running, also if the config said 0), endpoints with
+// their security, own certificate (thumbprint, validity) and namespace array
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_server_info(handle_ptr: *mut ServerHandle, lv_str_out: LStrHandle) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(lv_str_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let handle = &*handle_ptr;
+			let info = handle.info();
+			let config = &info.config;
+			// 0 until the listener is bound
+			let port = match info.port.load(std::sync::atomic::Ordering::Relaxed) {
+				0 => config.tcp_config.port,
+				port => port,
+			};
+			let base_endpoint = format!("opc.tcp://{}:{}", config.tcp_config.host, port);
+
+			let endpoints: Vec<serde_json::Value> = config
+				.endpoints
+				.iter()
+				.map(|(id, e)| {
+					serde_json::json!({
+						"id": id,
+						"url": e.endpoint_url(&base_endpoint),
+						"securityPolicy": e.security_policy,
+						"securityPolicyUri": e.security_policy().to_uri(),
+						"securityMode": e.security_mode,
+						"securityLevel": e.security_level,
+						"userTokenIds": e.user_token_ids,
+					})
+				})
+				.collect();
+
+			let certificate = match &*info.server_certificate.load() {
+				Some(cert) => serde_json::json!({
+					"subject": cert.subject_name(),
+					"thumbprint": cert.thumbprint().as_hex_string(),
+					"notBefore": cert.not_before().ok().map(|t| t.to_rfc3339()),
+					"notAfter": cert.not_after().ok().map(|t| t.to_rfc3339()),
+				}),
+				None => serde_json::Value::Null,
+			};
+
+			let mut namespaces: Vec<(String, u16)> = handle
+				.type_tree()
+				.read()
+				.namespaces()
+				.known_namespaces()
+				.iter()
+				.map(|(uri, ns)| (uri.clone(), *ns))
+				.collect();
+			namespaces.sort_by_key(|(_, ns)| *ns);
+			let namespaces: Vec<String> = namespaces.into_iter().map(|(uri, _)| uri).collect();
+
+			let server_info = serde_json::json!({
+				"applicationName": info.application_name.text.as_ref(),
+				"applicationUri": info.application_uri.as_ref(),
+				"productUri": info.product_uri.as_ref(),
+				"running": info.is_running(),
+				"host": config.tcp_config.host,
+				"port": port,
+				"configuredPort": config.tcp_config.port,
+				"endpoints": endpoints,
+				"discoveryUrls": config.discovery_urls,
+				"certificate": certificate,
+				"namespaces": namespaces,
+			});
+			string_to_lstr(&server_info.to_string(), lv_str_out)
+		}
+	})
+}
+
+//==============================================================================
+// Export all nodes of the manager's address space into a CSV file
+// NodeId,BrowseName,DisplayName,NodeClass,DataType,ValueRank
+// for documentation and diagnostics (this is not a NodeSet2 export).
+// DataType and ValueRank are empty for nodes other than variables
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_export_address_space_csv(
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	file_path_str: *const c_char,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(file_path_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &*manager_ptr;
+			let file_path = cstr_to_string!(file_path_str);
+
+			let mut rows: Vec<[String; 6]> = {
+				let address_space = manager.address_space().read();
+				address_space
+					.nodes()
+					.map(|node_type| {
+						let node = node_type.as_node();
+						let (data_type, value_rank) = match node_type {
+							NodeType::Variable(v) => {
+								(v.data_type().to_string(), v.value_rank().to_string())
+							}
+							NodeType::VariableType(v) => {
+								(v.data_type().to_string(), v.value_rank().to_string())
+							}
+							_ => (String::new(), String::new()),
+						};
+						[
+							node.node_id().to_string(),
+							node.browse_name().to_string(),
+							node.display_name().to_string(),
+							format!("{:?}", node.node_class()),
+							data_type,
+							value_rank,
+						]
+					})
+					.collect()
+			};
+			rows.sort();
+
+			if let Err(e) = write_csv(&file_path, &rows) {
+				set_last_error_detail(format!("{file_path}: {e}"));
+				return ERR_IO;
+			}
+		}
+		NO_ERR
+	})
+}
+
+fn write_csv(file_path: &str, rows: &[[String; 6]]) -> std::io::Result<()> {
+	let mut w = BufWriter::new(File::create(file_path)?);
+	writeln!(
+		w,
+		"NodeId,BrowseName,DisplayName,NodeClass,DataType,ValueRank"
+	)?;
+	for row in rows {
+		let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+		writeln!(w, "{}", fields.join(","))?;
+	}
+	w.flush()
+}
+
+// Quote the field if it contains separator, quotes or line breaks
+fn csv_field(field: &str) -> String {
+	if field.contains([',', '"', '\n', '\r']) {
+		format!("\"{}\"", field.replace('"', "\"\""))
+	} else {
+		field.to_string()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::labview::{DSDisposeHandleLStr, lstr_to_string, string_to_new_lstr};
+	use crate::test_server::{TestClient, TestServer, test_dir};
+	use std::{ffi::CString, time::Instant};
+
+	fn current_certificate(server: &TestServer) -> Arc<X509> {
+		server
+			.handle()
+			.info()
+			.server_certificate
+			.load_full()
+			.unwrap()
+	}
+
+	fn regenerate(server: &TestServer, key_size: u32, duration_days: u32) -> i32 {
+		lv_regenerate_server_certificate(
+			server.handle_ptr,
+			key_size,
+			duration_days,
+			c"".as_ptr(),
+			c"".as_ptr(),
+			c"".as_ptr(),
+		)
+	}
+
+	// Allocates and frees all handles of a server and a client session. For
+	// leaks and double frees run it with AddressSanitizer (the vendored crates
+	// build with the stable compiler only):
+	// RUSTC_BOOTSTRAP=1 RUSTFLAGS=-Zsanitizer=address cargo test
+	//	--target x86_64-unknown-linux-gnu handles_
+	#[test]
+	fn handles_are_freed() {
+		for _ in 0..3 {
+			let server = TestServer::start();
+			let client = TestClient::connect(&server);
+			assert!(client.session().is_connected());
+			// The drops free them with the lv_free_* functions
+			drop(client);
+			drop(server);
+		}
+	}
+
+	// The port of a running server is in use
+	#[test]
+	fn port_is_checked_at_start() {
+		let server = TestServer::start();
+		assert_eq!(
+			check_server_port(server.server_ptr),
+			ERR_INVALID_SERVER_CONFIG
+		);
+	}
+
+	// Validating changes nothing on disk, the PKI dir isn't created
+	#[test]
+	fn validated_server_config_creates_nothing() {
+		let server = TestServer::start();
+		let dir = test_dir("validate");
+		std::fs::create_dir_all(&dir).unwrap();
+		let file = dir.join("file");
+		std::fs::write(&file, b"").unwrap();
+		let config_path = dir.join("server.conf");
+		let validate = |pki_dir: PathBuf| -> (i32, String) {
+			let mut config = (*server.handle().info().config).clone();
+			config.pki_dir = pki_dir;
+			config.tcp_config.port = 0; // the port of the test server is in use
+			config.save(&config_path).unwrap();
+			let path = CString::new(config_path.to_str().unwrap()).unwrap();
+			unsafe {
+				let detail = string_to_new_lstr("");
+				let err = lv_validate_server_config(path.as_ptr(), detail);
+				let detail_text = lstr_to_string(detail);
+				DSDisposeHandleLStr(detail);
+				(err, detail_text)
+			}
+		};
+		let entries = || std::fs::read_dir(&dir).unwrap().count();
+
+		assert_eq!(validate(dir.join("new/pki")), (NO_ERR, String::new()));
+		assert!(!dir.join("new").exists());
+		assert_eq!(entries(), 2);
+
+		let (err, detail) = validate(file.join("pki"));
+		assert_eq!(err, ERR_INVALID_SERVER_CONFIG);
+		assert!(detail.contains("is not a directory"), "{detail}");
+		assert_eq!(entries(), 2);
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+
+	// The free functions return the error, no debug assertion panics
+	#[test]
+	fn handles_in_use_are_not_freed() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		assert_eq!(
+			crate::client::lv_free_session(client.session_ptr),
+			ERR_INVALID_CLIENT_REF
+		);
+		assert_eq!(
+			crate::client::lv_free_client(client.client_ptr),
+			ERR_INVALID_CLIENT_REF
+		);
+
+		// As if started by lv_start_server
+		let (tx, rx) = std::sync::mpsc::channel::<()>();
+		let thread = Arc::new(thread::spawn(move || {
+			let _ = rx.recv();
+		}));
+		SERVER_THREADS
+			.lock()
+			.unwrap()
+			.insert(server.server_ptr as usize, thread.clone());
+		assert_eq!(lv_free_server(server.server_ptr), ERR_INVALID_SERVER_REF);
+		drop(tx);
+		while !thread.is_finished() {
+			thread::sleep(Duration::from_millis(1));
+		}
+		// The drops free them
+	}
+
+	#[test]
+	fn regenerated_server_certificate() {
+		let server = TestServer::start();
+		let old_cert = current_certificate(&server);
+		assert_eq!(regenerate(&server, 1024, 30), ERR_INVALID_ARGUMENT);
+		assert_eq!(regenerate(&server, 2048, 0), ERR_INVALID_ARGUMENT);
+
+		// Without sessions it replaces the certificate and the file at once
+		assert_eq!(
+			regenerate(&server, 2048, 30),
+			NO_ERR,
+			"{}",
+			last_error_detail()
+		);
+		let cert = current_certificate(&server);
+		assert_ne!(cert.thumbprint(), old_cert.thumbprint());
+		let expected_not_after = chrono::Utc::now() + chrono::Duration::days(30);
+		assert!(
+			(cert.not_after().unwrap() - expected_not_after)
+				.num_minutes()
+				.abs() < 5
+		);
+		let (cert_path, _) = own_cert_and_key_paths(&server.handle().info().config);
+		let saved = X509::from_der(&std::fs::read(cert_path).unwrap()).unwrap();
+		assert_eq!(saved.thumbprint(), cert.thumbprint());
+
+		// With a session it waits for the session to close
+		let client = TestClient::connect(&server);
+		assert_eq!(
+			regenerate(&server, 2048, 30),
+			NO_ERR,
+			"{}",
+			last_error_detail()
 		);
-		//*address_space_out = Box::into_raw(Box::new(address_space)); //no need
-		*folder_id_out = Box::into_raw(Box::new(sample_folder_id));
+		assert_eq!(current_certificate(&server).thumbprint(), cert.thumbprint());
+		drop(client);
+		let start = Instant::now();
+		while current_certificate(&server).thumbprint() == cert.thumbprint() {
+			assert!(
+				start.elapsed() < Duration::from_secs(10),
+				"Pending certificate not applied"
+			);
+			thread::sleep(Duration::from_millis(100));
+		}
 	}
-	0
 }
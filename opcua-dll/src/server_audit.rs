@@ -0,0 +1,222 @@
+//==============================================================================
+//
+// Title:		Write audit of the embedded server
+// Purpose:		AuditWriteUpdateEventType events for the values written by
+//				the clients, the same records posted to LabVIEW for logging
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+//
+// The audit is a write observer of the node manager of the server, which holds
+// the variables of lv_add_variable*. The values written from LabVIEW
+// (lv_write_variable*) are not audited, only the Write service of the clients.
+// Disabled, the node manager has no observer and the write path is unchanged
+//
+use crate::errors::*;
+use crate::labview::{
+	LStrArrayHandle, PostLVUserEvent, dispose_lstr_array, strings_to_new_lstr_array,
+};
+use crate::server::{SimpleManager, manager_of};
+use crate::server_users::{LvAuthenticator, authenticator_of};
+use crate::subscription::variant_to_string;
+
+use libc::c_void;
+use opcua::{
+	core_namespace::events::{AuditEventType, AuditUpdateEventType},
+	nodes::BaseEventType,
+	server::{ServerHandle, node_manager::RequestContext},
+	types::{
+		AttributeId, ByteString, DataValue, DateTime, Guid, NodeId, NumericRange, ObjectId,
+		UAString, Variant,
+	},
+};
+use std::{
+	collections::HashMap,
+	sync::{
+		Arc, LazyLock, Mutex,
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+	},
+};
+
+// AuditWriteUpdateEventType with the values as they are written, the type of
+// the core namespace has ExtensionObject old/new values
+#[derive(Debug, opcua::Event)]
+#[opcua(identifier = "i=2100")]
+struct LvAuditWriteUpdateEvent {
+	base: AuditUpdateEventType,
+	attribute_id: u32,
+	index_range: NumericRange,
+	new_value: Variant,
+	old_value: Variant,
+}
+
+#[derive(Default)]
+struct WriteAudit {
+	enabled: AtomicBool,
+	// LabVIEW user event of the audit records, 0 = none
+	user_event_ref: AtomicUsize,
+}
+
+// Server handle -> its write audit
+static WRITE_AUDITS: LazyLock<Mutex<HashMap<usize, Arc<WriteAudit>>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn write_audit_of(handle_ptr: *mut ServerHandle) -> Arc<WriteAudit> {
+	WRITE_AUDITS
+		.lock()
+		.unwrap()
+		.entry(handle_ptr as usize)
+		.or_default()
+		.clone()
+}
+
+pub fn unregister_write_audit(handle_ptr: *mut ServerHandle) {
+	WRITE_AUDITS.lock().unwrap().remove(&(handle_ptr as usize));
+}
+
+// Raise the event on the Server object and post the record to LabVIEW
+fn audit_write(
+	audit: &WriteAudit,
+	authenticator: Option<&LvAuthenticator>,
+	context: &RequestContext,
+	node_id: &NodeId,
+	old_value: DataValue,
+	new_value: &DataValue,
+) {
+	let time = DateTime::now();
+	let session_id = context.session.read().session_id().to_string();
+	let user = match authenticator {
+		Some(authenticator) => authenticator.user_name(&context.token).to_string(),
+		None => context.token.0.clone(),
+	};
+	let old_value = old_value.value.unwrap_or_default();
+	let new_value = new_value.value.clone().unwrap_or_default();
+
+	if audit.user_event_ref.load(Ordering::Relaxed) != 0 {
+		let record = [
+			time.to_string(),
+			session_id.clone(),
+			user.clone(),
+			node_id.to_string(),
+			variant_to_string(&old_value),
+			variant_to_string(&new_value),
+		];
+		unsafe {
+			let mut lv_array = strings_to_new_lstr_array(&record);
+			if !lv_array.is_null() {
+				PostLVUserEvent(
+					audit.user_event_ref.load(Ordering::Relaxed) as *mut c_void,
+					&mut lv_array as *mut LStrArrayHandle as *mut c_void,
+				);
+				dispose_lstr_array(lv_array);
+			}
+		}
+	}
+
+	let event = LvAuditWriteUpdateEvent {
+		base: AuditUpdateEventType {
+			base: AuditEventType {
+				base: BaseEventType::new(
+					LvAuditWriteUpdateEvent::event_type_id(),
+					ByteString::from(Guid::new().as_bytes().to_vec()),
+					format!("Write of {node_id} by {user}"),
+					time,
+				)
+				.set_source_node(node_id.clone())
+				.set_source_name(UAString::from("Attribute/Write"))
+				.set_severity(100),
+				action_time_stamp: time,
+				// The request header audit entry isn't known here, the session is
+				client_audit_entry_id: UAString::from(session_id),
+				client_user_id: UAString::from(user),
+				server_id: context.info.application_uri.clone(),
+				status: true,
+			},
+		},
+		attribute_id: AttributeId::Value as u32,
+		index_range: NumericRange::None,
+		new_value,
+		old_value,
+	};
+	let server_id: NodeId = ObjectId::Server.into();
+	context
+		.subscriptions
+		.notify_events([(&event as &dyn opcua::nodes::Event, &server_id)].into_iter());
+}
+
+fn set_write_observer(
+	handle_ptr: *mut ServerHandle,
+	manager: &SimpleManager,
+	audit: Arc<WriteAudit>,
+) {
+	let authenticator = authenticator_of(handle_ptr);
+	manager
+		.inner()
+		.set_write_observer(move |context, node_id, old_value, new_value| {
+			audit_write(
+				&audit,
+				authenticator.as_deref(),
+				context,
+				node_id,
+				old_value,
+				new_value,
+			)
+		});
+}
+
+// Node manager rebuilt by lv_server_set_pki_dir, audit it like the old one
+pub fn reinstall_write_audit(handle_ptr: *mut ServerHandle, manager: &SimpleManager) {
+	let audit = write_audit_of(handle_ptr);
+	if audit.enabled.load(Ordering::Relaxed) {
+		set_write_observer(handle_ptr, manager, audit);
+	}
+}
+
+//==============================================================================
+// enabled != 0: every successful Write of a variable value by a client raises
+// an AuditWriteUpdateEvent on the Server object (ClientUserId - user name,
+// ClientAuditEntryId - session id, SourceNode - the variable, OldValue,
+// NewValue, ActionTimeStamp) and posts the record to the event of
+// lv_set_write_audit_record_event. Off by default
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_enable_write_audit(handle_ptr: *mut ServerHandle, enabled: u8) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+
+		let Some(manager) = manager_of(handle_ptr) else {
+			set_last_error_detail("The node manager of the handle is freed");
+			return ERR_INVALID_SERVER_REF;
+		};
+		let audit = write_audit_of(handle_ptr);
+		audit.enabled.store(enabled != 0, Ordering::Relaxed);
+		if enabled != 0 {
+			set_write_observer(handle_ptr, &manager, audit);
+		} else {
+			manager.inner().clear_write_observer();
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Post the audit records to user_event_ref (LabVIEW user event of a 1D string
+// array: time (RFC 3339), session id, user name, node id, old value,
+// new value), 0 stops posting. The OPC UA events don't depend on it
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_write_audit_record_event(
+	handle_ptr: *mut ServerHandle,
+	user_event_ref: *mut c_void,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+
+		write_audit_of(handle_ptr)
+			.user_event_ref
+			.store(user_event_ref as usize, Ordering::Relaxed); // raw pointers are not Send
+		NO_ERR
+	})
+}
@@ -0,0 +1,332 @@
+//==============================================================================
+//
+// Title:		Value simulation of the embedded server
+// Purpose:		Sine, ramp, random walk and toggle values of server variables,
+//				for testing client VIs without a LabVIEW write loop
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+//
+// Each simulated variable is a task on the server runtime, it runs while the
+// server runs (lv_start_server) and sets the value every period_ms as
+// lv_write_variable* does. The source timestamps are the scheduled times of
+// the updates, so trends of the clients stay smooth when the task is late.
+// lv_stop_server stops the tasks of the server
+//
+use crate::errors::*;
+use crate::server::{SimpleManager, server_runtime_handle};
+
+use libc::c_char;
+use opcua::{
+	server::{ServerHandle, address_space::NodeType},
+	types::{DataValue, DateTime, NodeId, StatusCode, Variant, VariantScalarTypeId},
+};
+use std::{
+	collections::HashMap,
+	f64::consts::TAU,
+	sync::{LazyLock, Mutex},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+// Updates per cycle of the sine and the ramp
+const SIMULATION_CYCLE_STEPS: u64 = 100;
+
+// Largest step of the random walk, in amplitudes
+const RANDOM_WALK_STEP: f64 = 0.1;
+
+#[derive(Clone, Copy, PartialEq)]
+enum SimulationMode {
+	Sine,
+	Ramp,
+	RandomWalk,
+	Toggle,
+}
+
+impl SimulationMode {
+	fn from_u32(mode: u32) -> Option<Self> {
+		match mode {
+			0 => Some(Self::Sine),
+			1 => Some(Self::Ramp),
+			2 => Some(Self::RandomWalk),
+			3 => Some(Self::Toggle),
+			_ => None,
+		}
+	}
+}
+
+// Server handle of the variable, ns and node. Servers in the same process
+// may have the same node ids
+type SimulationKey = (usize, u16, String);
+
+// Task of each simulated variable
+static SIMULATIONS: LazyLock<Mutex<HashMap<SimulationKey, JoinHandle<()>>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Values of the mode, offset..offset + amplitude (sine offset +- amplitude)
+struct Generator {
+	mode: SimulationMode,
+	amplitude: f64,
+	offset: f64,
+	value: f64,
+	rng: u64,
+}
+
+impl Generator {
+	fn new(mode: SimulationMode, amplitude: f64, offset: f64) -> Self {
+		let seed = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |d| d.as_nanos() as u64);
+		Generator {
+			mode,
+			amplitude,
+			offset,
+			value: offset,
+			rng: seed | 1, // xorshift state must not be 0
+		}
+	}
+
+	// Uniform in -1.0..1.0
+	fn next_random(&mut self) -> f64 {
+		self.rng ^= self.rng << 13;
+		self.rng ^= self.rng >> 7;
+		self.rng ^= self.rng << 17;
+		(self.rng >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+	}
+
+	fn value(&mut self, step: u64) -> f64 {
+		let phase = (step % SIMULATION_CYCLE_STEPS) as f64 / SIMULATION_CYCLE_STEPS as f64;
+		self.value = match self.mode {
+			SimulationMode::Sine => self.offset + self.amplitude * (TAU * phase).sin(),
+			SimulationMode::Ramp => self.offset + self.amplitude * phase,
+			SimulationMode::RandomWalk => {
+				let (low, high) = (self.offset - self.amplitude, self.offset + self.amplitude);
+				let walked = self.value + RANDOM_WALK_STEP * self.amplitude * self.next_random();
+				walked.clamp(low.min(high), low.max(high))
+			}
+			SimulationMode::Toggle => match step % 2 {
+				0 => self.offset,
+				_ => self.offset + self.amplitude,
+			},
+		};
+		self.value
+	}
+}
+
+// Value of the variable type, integers are rounded
+fn simulated_variant(value: f64, step: u64, scalar_type: VariantScalarTypeId) -> Variant {
+	match scalar_type {
+		VariantScalarTypeId::Boolean => Variant::Boolean(step % 2 == 1),
+		VariantScalarTypeId::Float | VariantScalarTypeId::Double => {
+			Variant::Double(value).cast(scalar_type)
+		}
+		_ => Variant::Double(value.round()).cast(scalar_type),
+	}
+}
+
+async fn run_simulation(
+	manager: SimpleManager,
+	server_handle: ServerHandle,
+	node_id: NodeId,
+	scalar_type: VariantScalarTypeId,
+	mut generator: Generator,
+	period: Duration,
+) {
+	let start = SystemTime::now();
+	let mut interval = tokio::time::interval(period);
+	// Late updates are sent at once, their source timestamps stay on the schedule
+	interval.set_missed_tick_behavior(MissedTickBehavior::Burst);
+	let mut step: u64 = 0;
+	loop {
+		tokio::select! {
+			_ = server_handle.token().cancelled() => return,
+			_ = interval.tick() => {}
+		}
+		let value = simulated_variant(generator.value(step), step, scalar_type);
+		let source_time = start + period.mul_f64(step as f64);
+		step += 1;
+		if value.is_empty() {
+			continue; // out of the range of the type
+		}
+		let data_value = DataValue {
+			value: Some(value),
+			status: Some(StatusCode::Good),
+			source_timestamp: Some(DateTime::from(chrono::DateTime::<chrono::Utc>::from(
+				source_time,
+			))),
+			server_timestamp: Some(DateTime::now()),
+			..Default::default()
+		};
+		let _ = manager.set_value(server_handle.subscriptions(), &node_id, None, data_value);
+	}
+}
+
+// Stop the simulations of the server (lv_stop_server)
+pub fn stop_simulations(handle_ptr: *mut ServerHandle) {
+	SIMULATIONS.lock().unwrap().retain(|(server, _, _), task| {
+		if *server != handle_ptr as usize {
+			return true;
+		}
+		task.abort();
+		false
+	});
+}
+
+//==============================================================================
+// Simulate the value of the variable: mode 0 - sine offset +- amplitude,
+// 1 - ramp offset..offset + amplitude, 2 - random walk within offset +-
+// amplitude, 3 - toggle between offset and offset + amplitude. Sine and ramp
+// have a cycle of 100 updates. The value is set every period_ms while the
+// server runs, cast to the type of the variable (integers rounded). Boolean
+// variables only toggle. A simulation of the same variable of the server is
+// replaced.
+// ERR_INVALID_TYPE if the variable isn't Boolean .. Double or the mode isn't
+// toggle for a Boolean, ERR_INVALID_ARGUMENT for an unknown mode or period_ms 0,
+// BadNodeIdUnknown if there is no such variable
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_start_simulation(
+	manager_ptr: *mut SimpleManager,
+	server_handle_ptr: *mut ServerHandle,
+	node_str: *const c_char,
+	ns: u16,
+	mode: u32,
+	period_ms: u32,
+	amplitude: f64,
+	offset: f64,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		let Some(mode) = SimulationMode::from_u32(mode) else {
+			set_last_error_detail(format!("Unknown simulation mode {mode}"));
+			return ERR_INVALID_ARGUMENT;
+		};
+		if period_ms == 0 || !amplitude.is_finite() || !offset.is_finite() {
+			return ERR_INVALID_ARGUMENT;
+		}
+		let Some(runtime) = server_runtime_handle() else {
+			set_last_error_detail("No server runtime, call lv_new_server_runtime first");
+			return ERR_INVALID_RUNTIME;
+		};
+
+		let node_str = cstr_to_string!(node_str);
+		let node_id = NodeId::new(ns, node_str.clone());
+		let manager = unsafe { (*manager_ptr).clone() };
+		let server_handle = unsafe { (*server_handle_ptr).clone() };
+
+		let data_type = match manager.address_space().read().find_node(&node_id) {
+			Some(NodeType::Variable(variable)) => variable.data_type(),
+			_ => {
+				set_last_error_detail(format!("Variable {node_id} not found"));
+				return StatusCode::BadNodeIdUnknown.bits() as i32;
+			}
+		};
+		let scalar_type = match VariantScalarTypeId::try_from(&data_type) {
+			Ok(t) if t as u32 <= VariantScalarTypeId::Double as u32 => t,
+			_ => {
+				set_last_error_detail(format!("{data_type} values can't be simulated"));
+				return ERR_INVALID_TYPE;
+			}
+		};
+		if scalar_type == VariantScalarTypeId::Boolean && mode != SimulationMode::Toggle {
+			set_last_error_detail("Boolean variables can only toggle (mode 3)");
+			return ERR_INVALID_TYPE;
+		}
+
+		let generator = Generator::new(mode, amplitude, offset);
+		let period = Duration::from_millis(period_ms as u64);
+		let task = runtime.spawn(run_simulation(
+			manager,
+			server_handle,
+			node_id,
+			scalar_type,
+			generator,
+			period,
+		));
+		let mut simulations = SIMULATIONS.lock().unwrap();
+		simulations.retain(|_, task| !task.is_finished());
+		let key = (server_handle_ptr as usize, ns, node_str);
+		if let Some(replaced) = simulations.insert(key, task) {
+			replaced.abort();
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Stop the simulation of the variable of the server, its last value stays.
+// ERR_INVALID_ARGUMENT if the variable isn't simulated
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_stop_simulation(
+	server_handle_ptr: *mut ServerHandle,
+	node_str: *const c_char,
+	ns: u16,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+
+		let node_str = cstr_to_string!(node_str);
+		let key = (server_handle_ptr as usize, ns, node_str.clone());
+		match SIMULATIONS.lock().unwrap().remove(&key) {
+			Some(task) => {
+				task.abort();
+				NO_ERR
+			}
+			None => {
+				set_last_error_detail(format!("ns={ns};s={node_str} is not simulated"));
+				ERR_INVALID_ARGUMENT
+			}
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::handles::from_handle;
+	use crate::test_server::TestServer;
+
+	fn simulated(server: &TestServer, node: &str) -> bool {
+		let key = (server.handle_ptr as usize, server.ns, node.to_string());
+		SIMULATIONS.lock().unwrap().contains_key(&key)
+	}
+
+	// The same node id in two servers of the process
+	#[test]
+	fn simulations_of_servers_are_separate() {
+		// The tasks are spawned on the server runtime, not run here
+		let rt_ptr = crate::server::lv_new_server_runtime();
+		let servers = [TestServer::start(), TestServer::start()];
+		for server in &servers {
+			assert_eq!(server.add_variable("Sim", 11), NO_ERR);
+			let err = lv_start_simulation(
+				server.manager_ptr,
+				server.handle_ptr,
+				c"Sim".as_ptr(),
+				server.ns,
+				0,
+				100,
+				1.0,
+				0.0,
+			);
+			assert_eq!(err, NO_ERR, "{}", last_error_detail());
+		}
+		assert!(servers.iter().all(|server| simulated(server, "Sim")));
+
+		let stop =
+			|server: &TestServer| lv_stop_simulation(server.handle_ptr, c"Sim".as_ptr(), server.ns);
+		assert_eq!(stop(&servers[0]), NO_ERR);
+		assert_eq!(stop(&servers[0]), ERR_INVALID_ARGUMENT);
+		assert!(!simulated(&servers[0], "Sim") && simulated(&servers[1], "Sim"));
+
+		stop_simulations(servers[1].handle_ptr);
+		assert!(!simulated(&servers[1], "Sim"));
+		unsafe { drop(from_handle(rt_ptr)) };
+	}
+}
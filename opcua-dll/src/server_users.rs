@@ -0,0 +1,404 @@
+//==============================================================================
+//
+// Title:		Users of the embedded server
+// Purpose:		Username/password users managed from LabVIEW, read-only or
+//				read-write, and anonymous access on/off while the server runs
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+//
+// The server authenticates with an LvAuthenticator: the users added from
+// LabVIEW are checked first, the user tokens of the config file after them.
+// Users and the anonymous flag can change at any time, the endpoints offer
+// the UserName policy as soon as there is a user.
+// Nodes with write roles are writable only by the listed users, denied
+// writes can be posted to a LabVIEW event for audit logging
+//
+use crate::{errors::*, labview::*};
+
+use async_trait::async_trait;
+use libc::{c_char, c_void};
+use opcua::{
+	crypto::Thumbprint,
+	server::{
+		ANONYMOUS_USER_TOKEN_ID, ServerConfig, ServerEndpoint, ServerHandle,
+		address_space::AccessLevel,
+		authenticator::{
+			AuthManager, CoreServerPermissions, DefaultAuthenticator, Password, UserToken,
+			user_pass_security_policy_id, user_pass_security_policy_uri,
+		},
+	},
+	types::{Error, NodeId, StatusCode, UAString, UserTokenPolicy, UserTokenType},
+};
+use std::{
+	collections::{HashMap, HashSet},
+	sync::{
+		Arc, LazyLock, Mutex, RwLock,
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+	},
+};
+
+// Token of a LabVIEW user is the prefix + user name, the config users keep
+// their token ids
+const LV_USER_PREFIX: &str = "lv_user:";
+
+struct LvUser {
+	password: String,
+	allow_write: bool,
+}
+
+pub struct LvAuthenticator {
+	config_users: DefaultAuthenticator,
+	// Token id of a config user -> its user name
+	config_user_names: HashMap<String, String>,
+	users: RwLock<HashMap<String, LvUser>>,
+	anonymous_allowed: AtomicBool,
+	// Node -> user names allowed to write it
+	node_write_roles: RwLock<HashMap<NodeId, HashSet<String>>>,
+	// LabVIEW user event of denied writes, 0 = none
+	audit_event_ref: AtomicUsize,
+}
+
+impl LvAuthenticator {
+	pub fn new(config: &ServerConfig) -> Arc<Self> {
+		Arc::new(Self {
+			config_users: DefaultAuthenticator::new(config.user_tokens.clone()),
+			config_user_names: config
+				.user_tokens
+				.iter()
+				.map(|(id, token)| (id.clone(), token.user.clone()))
+				.collect(),
+			users: RwLock::new(HashMap::new()),
+			anonymous_allowed: AtomicBool::new(true),
+			node_write_roles: RwLock::new(HashMap::new()),
+			audit_event_ref: AtomicUsize::new(0),
+		})
+	}
+
+	// User name of the session token, ANONYMOUS for anonymous sessions
+	pub fn user_name<'a>(&'a self, token: &'a UserToken) -> &'a str {
+		if let Some(username) = token.0.strip_prefix(LV_USER_PREFIX) {
+			username
+		} else if token.is_anonymous() {
+			ANONYMOUS_USER_TOKEN_ID
+		} else {
+			self.config_user_names
+				.get(&token.0)
+				.map_or(token.0.as_str(), String::as_str)
+		}
+	}
+
+	// False if the node has write roles without the user
+	fn role_allows_write(&self, token: &UserToken, node_id: &NodeId) -> bool {
+		match self.node_write_roles.read().unwrap().get(node_id) {
+			Some(users) => users.contains(self.user_name(token)),
+			None => true,
+		}
+	}
+
+	// A LabVIEW user allowed to write, false for read-only and removed users
+	fn may_write(&self, token: &UserToken) -> Option<bool> {
+		let username = token.0.strip_prefix(LV_USER_PREFIX)?;
+		Some(
+			self.users
+				.read()
+				.unwrap()
+				.get(username)
+				.is_some_and(|user| user.allow_write),
+		)
+	}
+}
+
+#[async_trait]
+impl AuthManager for LvAuthenticator {
+	async fn authenticate_anonymous_token(&self, endpoint: &ServerEndpoint) -> Result<(), Error> {
+		if !self.anonymous_allowed.load(Ordering::Relaxed) {
+			return Err(Error::new(
+				StatusCode::BadIdentityTokenRejected,
+				"Anonymous access is disabled",
+			));
+		}
+		self.config_users
+			.authenticate_anonymous_token(endpoint)
+			.await
+	}
+
+	async fn authenticate_username_identity_token(
+		&self,
+		endpoint: &ServerEndpoint,
+		username: &str,
+		password: &Password,
+	) -> Result<UserToken, Error> {
+		let lv_user_valid = self
+			.users
+			.read()
+			.unwrap()
+			.get(username)
+			.map(|user| user.password.as_bytes() == password.get().as_bytes());
+		match lv_user_valid {
+			Some(true) => Ok(UserToken(format!("{LV_USER_PREFIX}{username}"))),
+			Some(false) => Err(Error::new(
+				StatusCode::BadUserAccessDenied,
+				format!("Wrong password of user \"{username}\""),
+			)),
+			None => self
+				.config_users
+				.authenticate_username_identity_token(endpoint, username, password)
+				.await
+				.map_err(|e| Error::new(StatusCode::BadUserAccessDenied, e.to_string())),
+		}
+	}
+
+	async fn authenticate_x509_identity_token(
+		&self,
+		endpoint: &ServerEndpoint,
+		signing_thumbprint: &Thumbprint,
+	) -> Result<UserToken, Error> {
+		self.config_users
+			.authenticate_x509_identity_token(endpoint, signing_thumbprint)
+			.await
+	}
+
+	fn effective_user_access_level(
+		&self,
+		token: &UserToken,
+		user_access_level: AccessLevel,
+		node_id: &NodeId,
+	) -> AccessLevel {
+		let write_access = AccessLevel::CURRENT_WRITE | AccessLevel::HISTORY_WRITE;
+		let user_access_level = match self.may_write(token) {
+			Some(false) => user_access_level - write_access,
+			Some(true) => user_access_level,
+			None => {
+				self.config_users
+					.effective_user_access_level(token, user_access_level, node_id)
+			}
+		};
+		if self.role_allows_write(token, node_id) {
+			user_access_level
+		} else {
+			user_access_level - write_access
+		}
+	}
+
+	fn access_denied(&self, token: &UserToken, node_id: &NodeId, _access: AccessLevel) {
+		let user_event_ref = self.audit_event_ref.load(Ordering::Relaxed);
+		if user_event_ref == 0 {
+			return;
+		}
+		let strings = [self.user_name(token).to_string(), node_id.to_string()];
+		unsafe {
+			let mut lv_array = strings_to_new_lstr_array(&strings);
+			if !lv_array.is_null() {
+				PostLVUserEvent(
+					user_event_ref as *mut c_void,
+					&mut lv_array as *mut LStrArrayHandle as *mut c_void,
+				);
+				dispose_lstr_array(lv_array);
+			}
+		}
+	}
+
+	fn is_user_executable(&self, token: &UserToken, method_id: &NodeId) -> bool {
+		match self.may_write(token) {
+			Some(allow_write) => allow_write,
+			None => self.config_users.is_user_executable(token, method_id),
+		}
+	}
+
+	fn user_token_policies(&self, endpoint: &ServerEndpoint) -> Vec<UserTokenPolicy> {
+		let mut policies = self.config_users.user_token_policies(endpoint);
+		if !self.anonymous_allowed.load(Ordering::Relaxed) {
+			policies.retain(|p| p.token_type != UserTokenType::Anonymous);
+		}
+		if !self.users.read().unwrap().is_empty()
+			&& !policies
+				.iter()
+				.any(|p| p.token_type == UserTokenType::UserName)
+		{
+			policies.push(UserTokenPolicy {
+				policy_id: user_pass_security_policy_id(endpoint),
+				token_type: UserTokenType::UserName,
+				issued_token_type: UAString::null(),
+				issuer_endpoint_url: UAString::null(),
+				security_policy_uri: user_pass_security_policy_uri(endpoint),
+			});
+		}
+		policies
+	}
+
+	fn core_permissions(&self, token: &UserToken) -> CoreServerPermissions {
+		self.config_users.core_permissions(token)
+	}
+}
+
+// Server handle -> its authenticator (lvServerBuilder)
+static AUTHENTICATORS: LazyLock<Mutex<HashMap<usize, Arc<LvAuthenticator>>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub fn register_authenticator(handle_ptr: *mut ServerHandle, authenticator: Arc<LvAuthenticator>) {
+	AUTHENTICATORS
+		.lock()
+		.unwrap()
+		.insert(handle_ptr as usize, authenticator);
+}
+
+pub fn authenticator_of(handle_ptr: *mut ServerHandle) -> Option<Arc<LvAuthenticator>> {
+	AUTHENTICATORS
+		.lock()
+		.unwrap()
+		.get(&(handle_ptr as usize))
+		.cloned()
+}
+
+pub fn unregister_authenticator(handle_ptr: *mut ServerHandle) {
+	AUTHENTICATORS
+		.lock()
+		.unwrap()
+		.remove(&(handle_ptr as usize));
+}
+
+//==============================================================================
+// Add the user (or change the password and write access of an existing one)
+// on all endpoints of the server, before or after lv_start_server.
+// allow_write = 0: the user can read and browse, writes are BadUserAccessDenied
+// and methods aren't executable. A wrong password is BadUserAccessDenied
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_server_add_user(
+	handle_ptr: *mut ServerHandle,
+	username_str: *const c_char,
+	password_str: *const c_char,
+	allow_write: u8,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(username_str, ERR_NULL_POINTER);
+		check_null!(password_str, ERR_NULL_POINTER);
+
+		let Some(authenticator) = authenticator_of(handle_ptr) else {
+			return ERR_INVALID_SERVER_REF;
+		};
+		let username = cstr_to_string!(username_str);
+		if username.is_empty() {
+			return ERR_INVALID_ARGUMENT;
+		}
+		authenticator.users.write().unwrap().insert(
+			username,
+			LvUser {
+				password: cstr_to_string!(password_str),
+				allow_write: allow_write != 0,
+			},
+		);
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Remove a user added by lv_server_add_user, its active sessions stay open
+// read-only. ERR_INVALID_ARGUMENT if there is no such user
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_server_remove_user(
+	handle_ptr: *mut ServerHandle,
+	username_str: *const c_char,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(username_str, ERR_NULL_POINTER);
+
+		let Some(authenticator) = authenticator_of(handle_ptr) else {
+			return ERR_INVALID_SERVER_REF;
+		};
+		let username = cstr_to_string!(username_str);
+		match authenticator.users.write().unwrap().remove(&username) {
+			Some(_) => NO_ERR,
+			None => {
+				set_last_error_detail(format!("No user \"{username}\""));
+				ERR_INVALID_ARGUMENT
+			}
+		}
+	})
+}
+
+//==============================================================================
+// allow = 0 rejects anonymous sessions (BadIdentityTokenRejected) and removes
+// the Anonymous policy from the endpoints, 1 (default) allows them on the
+// endpoints whose config has the ANONYMOUS user token
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_server_set_anonymous_access(handle_ptr: *mut ServerHandle, allow: u8) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+
+		let Some(authenticator) = authenticator_of(handle_ptr) else {
+			return ERR_INVALID_SERVER_REF;
+		};
+		authenticator
+			.anonymous_allowed
+			.store(allow != 0, Ordering::Relaxed);
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Only the users of allowed_usernames_csv ("operator,admin") can write the
+// value and history of node ns;node_id, the others get BadUserAccessDenied.
+// Config file users are named by their user, anonymous sessions ANONYMOUS.
+// An empty list removes the rule, the node is writable by all users again
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_node_write_roles(
+	handle_ptr: *mut ServerHandle,
+	node_id_str: *const c_char,
+	ns: u16,
+	allowed_usernames_csv: *const c_char,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(node_id_str, ERR_NULL_POINTER);
+		check_null!(allowed_usernames_csv, ERR_NULL_POINTER);
+
+		let Some(authenticator) = authenticator_of(handle_ptr) else {
+			return ERR_INVALID_SERVER_REF;
+		};
+		let node_id = NodeId::new(ns, cstr_to_string!(node_id_str));
+		let users: HashSet<String> = cstr_to_string!(allowed_usernames_csv)
+			.split(',')
+			.map(str::trim)
+			.filter(|u| !u.is_empty())
+			.map(String::from)
+			.collect();
+		let mut node_write_roles = authenticator.node_write_roles.write().unwrap();
+		if users.is_empty() {
+			node_write_roles.remove(&node_id);
+		} else {
+			node_write_roles.insert(node_id, users);
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Post the denied writes to user_event_ref (LabVIEW user event of a 1D string
+// array: user name, node id), 0 stops posting
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_write_audit_event(
+	handle_ptr: *mut ServerHandle,
+	user_event_ref: *mut c_void,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(handle_ptr, ERR_INVALID_SERVER_REF);
+
+		let Some(authenticator) = authenticator_of(handle_ptr) else {
+			return ERR_INVALID_SERVER_REF;
+		};
+		authenticator
+			.audit_event_ref
+			.store(user_event_ref as usize, Ordering::Relaxed); // raw pointers are not Send
+		NO_ERR
+	})
+}
@@ -1,7 +1,7 @@
 //==============================================================================
 //
 // Title:		Server Variables, create and hold
-// Purpose:		Currently the only scalar Bool and U8...F64 supported
+// Purpose:		Currently the only scalar Bool, U8...F64 and String supported
 //
 // Created on:	14-MAR-2025 by AD.
 // License: MPL-2.0
@@ -10,15 +10,92 @@
 use libc::c_char;
 use opcua::{
 	server::{
-		ServerHandle,
-		address_space::VariableBuilder,
+		ServerHandle, SubscriptionCache,
+		address_space::{AccessLevel, AddressSpace, DefaultTypeTree, NodeType, VariableBuilder},
 		node_manager::memory::{InMemoryNodeManager, SimpleNodeManagerImpl},
 	},
-	types::{DataTypeId, DataValue, NodeId},
+	types::{
+		AttributeId, BrowseDirection, ByteString, DataTypeId, DataValue, DateTime, EUInformation,
+		ExtensionObject, Guid, LocalizedText, NodeId, Range, ReferenceTypeId, StatusCode,
+		TimestampsToReturn, VariableTypeId, Variant, VariantScalarTypeId,
+	},
 };
-use std::sync::Arc;
+#[cfg(not(test))]
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::{num::TryFromIntError, str::FromStr, sync::Arc};
 
 use crate::errors::*;
+use crate::labview::{
+	LStrArrayHandle, LStrHandle, LvArrayHandle, TVariant, lstr_array_to_strings, lstr_to_string,
+	lv_array_as_slice, lv_variant_to_variant,
+};
+use crate::utils::{cocoa_to_date_time, cocoa_to_date_time_checked};
+
+// Namespace of the UNECE unit codes, used for EUInformation
+const UNECE_UNITS_NAMESPACE: &str = "http://www.opcfoundation.org/UA/units/un/cefact";
+
+//==============================================================================
+// NO_ERR if the node id is free, otherwise ERR_NODE_EXISTS and the existing
+// node is reported in the last error detail. Same id in other namespace is free
+//
+pub fn check_node_free(address_space: &AddressSpace, node_id: &NodeId) -> i32 {
+	match address_space.find_node(node_id) {
+		Some(node) => {
+			set_last_error_detail(format!(
+				"Node {} already exists with browse name '{}'",
+				node_id,
+				node.as_node().browse_name()
+			));
+			ERR_NODE_EXISTS
+		}
+		None => NO_ERR,
+	}
+}
+
+//==============================================================================
+// Nodes below the node, targets of its child_refs references
+//
+pub fn child_nodes(
+	address_space: &AddressSpace,
+	node_id: &NodeId,
+	child_refs: &[ReferenceTypeId],
+) -> Vec<NodeId> {
+	let type_tree = DefaultTypeTree::new();
+	child_refs
+		.iter()
+		.flat_map(|reference_type| {
+			address_space
+				.find_references(
+					node_id,
+					Some((*reference_type, false)),
+					&type_tree,
+					BrowseDirection::Forward,
+				)
+				.map(|r| r.target_node.clone())
+				.collect::<Vec<_>>()
+		})
+		.collect()
+}
+
+//==============================================================================
+// Delete the node and the nodes below it (see child_nodes()),
+// the nodes below always lose all their references
+//
+pub fn delete_node_tree(
+	address_space: &mut AddressSpace,
+	node_id: &NodeId,
+	child_refs: &[ReferenceTypeId],
+	delete_reverse_refs: bool,
+) {
+	let children = child_nodes(address_space, node_id, child_refs);
+	address_space.delete(node_id, delete_reverse_refs);
+	for child in children {
+		// Already gone if it is reachable twice
+		if address_space.find_node(&child).is_some() {
+			delete_node_tree(address_space, &child, child_refs, true);
+		}
+	}
+}
 
 #[unsafe(no_mangle)]
 pub extern "C" fn lv_add_variable(
@@ -30,138 +107,2089 @@ pub extern "C" fn lv_add_variable(
 	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
 	folder_id_ptr: *mut NodeId,
 ) -> i32 {
-	unsafe {
-		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
-		check_null!(folder_id_ptr, ERR_INVALID_SERVER_REF);
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		unsafe {
+			check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+			check_null!(folder_id_ptr, ERR_INVALID_SERVER_REF);
 
-		let manager = &mut *manager_ptr;
-		let folder_id = &mut *folder_id_ptr;
-		let variable_node_str = cstr_to_string!(variable_node_str);
-		let variable_browse_str = cstr_to_string!(variable_browse_str);
-		let variable_display_str = cstr_to_string!(variable_display_str);
-		let address_space = manager.address_space();
-		let mut address_space = address_space.write();
-		let variable_node = NodeId::new(ns, variable_node_str);
-		//#ToDo: Refactor to get writable, etc and organized_by from LabVIEW
-		match var_type {
-			1 => VariableBuilder::new(&variable_node, variable_browse_str, variable_display_str)
-				.data_type(DataTypeId::Boolean)
-				.value(false)
-				.writable()
-				.organized_by(&*folder_id)
-				.insert(&mut *address_space),
-			2 => VariableBuilder::new(&variable_node, variable_browse_str, variable_display_str)
-				.data_type(DataTypeId::SByte)
-				.value(0)
-				.writable()
-				.organized_by(&*folder_id)
-				.insert(&mut *address_space),
-			3 => VariableBuilder::new(&variable_node, variable_browse_str, variable_display_str)
-				.data_type(DataTypeId::Byte)
-				.value(0)
-				.writable()
-				.organized_by(&*folder_id)
-				.insert(&mut *address_space),
-			4 => VariableBuilder::new(&variable_node, variable_browse_str, variable_display_str)
-				.data_type(DataTypeId::Int16)
-				.value(0)
-				.writable()
-				.organized_by(&*folder_id)
-				.insert(&mut *address_space),
-			5 => VariableBuilder::new(&variable_node, variable_browse_str, variable_display_str)
-				.data_type(DataTypeId::UInt16)
-				.value(0)
-				.writable()
-				.organized_by(&*folder_id)
-				.insert(&mut *address_space),
-			6 => VariableBuilder::new(&variable_node, variable_browse_str, variable_display_str)
-				.data_type(DataTypeId::Int32)
-				.value(0)
-				.writable()
-				.organized_by(&*folder_id)
-				.insert(&mut *address_space),
-			7 => VariableBuilder::new(&variable_node, variable_browse_str, variable_display_str)
-				.data_type(DataTypeId::UInt32)
-				.value(0)
-				.writable()
-				.organized_by(&*folder_id)
-				.insert(&mut *address_space),
-			8 => VariableBuilder::new(&variable_node, variable_browse_str, variable_display_str)
-				.data_type(DataTypeId::Int64)
-				.value(0)
-				.writable()
-				.organized_by(&*folder_id)
-				.insert(&mut *address_space),
-			9 => VariableBuilder::new(&variable_node, variable_browse_str, variable_display_str)
-				.data_type(DataTypeId::Int64)
-				.value(0)
-				.writable()
-				.organized_by(&*folder_id)
-				.insert(&mut *address_space),
-			10 => VariableBuilder::new(&variable_node, variable_browse_str, variable_display_str)
-				.data_type(DataTypeId::Float)
-				.value(0)
-				.writable()
-				.organized_by(&*folder_id)
-				.insert(&mut *address_space),
-			11 => VariableBuilder::new(&variable_node, variable_browse_str, variable_display_str)
-				.data_type(DataTypeId::Double)
-				.value(0)
+			let manager = &mut *manager_ptr;
+			let folder_id = &mut *folder_id_ptr;
+			let variable_node_str = cstr_to_string!(variable_node_str);
+			let variable_browse_str = cstr_to_string!(variable_browse_str);
+			let variable_display_str = cstr_to_string!(variable_display_str);
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			let variable_node = NodeId::new(ns, variable_node_str);
+			let err = check_node_free(&address_space, &variable_node);
+			if err != NO_ERR {
+				return err;
+			}
+			let Some((data_type, initial_value)) = lv_data_type(var_type) else {
+				return ERR_INVALID_TYPE;
+			};
+			//#ToDo: Refactor to get writable, etc and organized_by from LabVIEW
+			VariableBuilder::new(&variable_node, variable_browse_str, variable_display_str)
+				.data_type(data_type)
+				.value(initial_value.clone())
 				.writable()
 				.organized_by(&*folder_id)
-				.insert(&mut *address_space),
+				.insert(&mut *address_space);
+			set_waiting_for_initial_data(&mut address_space, &variable_node, initial_value);
+		}
 
-			_ => return ERR_INVALID_TYPE,
-		};
+		0
+	})
+}
+
+// Value of a variable that was never written: the zero of its DataType with
+// BadWaitingForInitialData, so that subscribers can tell it from a written 0
+fn set_waiting_for_initial_data(
+	address_space: &mut AddressSpace,
+	node_id: &NodeId,
+	initial_value: Variant,
+) {
+	if let Some(NodeType::Variable(variable)) = address_space.find_node_mut(node_id) {
+		let now = DateTime::now();
+		variable.set_data_value(DataValue {
+			value: Some(initial_value),
+			status: Some(StatusCode::BadWaitingForInitialData),
+			source_timestamp: Some(now),
+			server_timestamp: Some(now),
+			..Default::default()
+		});
+	}
+}
+
+//==============================================================================
+// LabVIEW type id to OPC UA data type and initial value. The ids are those of
+// LVDataTypeId, i.e. the OPC UA built-in type ids, and the DataType node id for
+// Enumeration, which isn't a built-in type:
+//
+//    1 Boolean    5 UInt16    9 UInt64 (Int64)   13 DateTime
+//    2 SByte      6 Int32    10 Float            14 Guid
+//    3 Byte       7 UInt32   11 Double           15 ByteString
+//    4 Int16      8 Int64    12 String           29 Enumeration (Int32)
+//
+// DateTime, Guid, ByteString and Enumeration keep their OPC UA ids instead of
+// being numbered 12..15 in the order they were added, 12 was String already
+// and 13..15 are taken by LVDataTypeId.
+// 9 (U64) has been Int64 since the first lv_add_variable, the U64 writes to
+// it are range checked
+//
+pub fn lv_data_type(var_type: u16) -> Option<(DataTypeId, Variant)> {
+	match var_type {
+		1 => Some((DataTypeId::Boolean, Variant::Boolean(false))),
+		2 => Some((DataTypeId::SByte, Variant::SByte(0))),
+		3 => Some((DataTypeId::Byte, Variant::Byte(0))),
+		4 => Some((DataTypeId::Int16, Variant::Int16(0))),
+		5 => Some((DataTypeId::UInt16, Variant::UInt16(0))),
+		6 => Some((DataTypeId::Int32, Variant::Int32(0))),
+		7 => Some((DataTypeId::UInt32, Variant::UInt32(0))),
+		8 => Some((DataTypeId::Int64, Variant::Int64(0))),
+		9 => Some((DataTypeId::Int64, Variant::Int64(0))),
+		10 => Some((DataTypeId::Float, Variant::Float(0.0))),
+		11 => Some((DataTypeId::Double, Variant::Double(0.0))),
+		12 => Some((DataTypeId::String, Variant::from(""))),
+		13 => Some((DataTypeId::DateTime, Variant::from(DateTime::null()))),
+		14 => Some((DataTypeId::Guid, Variant::from(Guid::null()))),
+		15 => Some((DataTypeId::ByteString, Variant::from(ByteString::null()))),
+		29 => Some((DataTypeId::Enumeration, Variant::Int32(0))),
+		_ => None,
 	}
+}
 
-	0
+//==============================================================================
+// Parse the text as a value of the LabVIEW type id, None if it doesn't fit.
+// Boolean accepts true/false and 1/0, String is taken as is
+//
+pub fn lv_parse_value(var_type: u16, text: &str) -> Option<Variant> {
+	let t = text.trim();
+	match var_type {
+		1 => match t.to_ascii_lowercase().as_str() {
+			"true" | "1" => Some(Variant::Boolean(true)),
+			"false" | "0" => Some(Variant::Boolean(false)),
+			_ => None,
+		},
+		2 => t.parse::<i8>().ok().map(Variant::SByte),
+		3 => t.parse::<u8>().ok().map(Variant::Byte),
+		4 => t.parse::<i16>().ok().map(Variant::Int16),
+		5 => t.parse::<u16>().ok().map(Variant::UInt16),
+		6 => t.parse::<i32>().ok().map(Variant::Int32),
+		7 => t.parse::<u32>().ok().map(Variant::UInt32),
+		8 => t.parse::<i64>().ok().map(Variant::Int64),
+		9 => t.parse::<u64>().ok().map(Variant::UInt64),
+		10 => t.parse::<f32>().ok().map(Variant::Float),
+		11 => t.parse::<f64>().ok().map(Variant::Double),
+		12 => Some(Variant::from(text)),
+		13 => DateTime::parse_from_rfc3339(t).ok().map(Variant::from),
+		14 => Guid::from_str(t).ok().map(Variant::from),
+		15 => ByteString::from_base64(t).map(Variant::from),
+		29 => t.parse::<i32>().ok().map(Variant::Int32),
+		_ => None,
+	}
 }
 
-macro_rules! create_lv_write_variable {
-	($fn_name:ident, $value_type:ty) => {
+//==============================================================================
+// Same as lv_add_variable, but the variable starts with the value given as text,
+// e.g. "3.14" for Float, "true" for Boolean or "2026-10-16T12:00:00Z" for
+// DateTime.
+// ERR_INVALID_ARGUMENT if the text doesn't parse as var_type
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_add_variable_with_initial_value(
+	variable_node_str: *const c_char,
+	variable_browse_str: *const c_char,
+	variable_display_str: *const c_char,
+	ns: u16,
+	var_type: u16,
+	initial_value_str: *const c_char,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	folder_id_ptr: *mut NodeId,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(folder_id_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+		check_null!(variable_browse_str, ERR_NULL_POINTER);
+		check_null!(variable_display_str, ERR_NULL_POINTER);
+		check_null!(initial_value_str, ERR_NULL_POINTER);
+
+		let Some((data_type, _)) = lv_data_type(var_type) else {
+			return ERR_INVALID_TYPE;
+		};
+		let initial_value_str = cstr_to_string!(initial_value_str);
+		let initial_value = lv_parse_value(var_type, &initial_value_str).and_then(|value| {
+			match cast_integer(&value, data_type) {
+				Some(cast) => cast.ok(), // U64 of type 9
+				None => Some(value),
+			}
+		});
+		let Some(initial_value) = initial_value else {
+			set_last_error_detail(format!(
+				"'{}' is not a valid value of type {}",
+				initial_value_str, var_type
+			));
+			return ERR_INVALID_ARGUMENT;
+		};
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let folder_id = &mut *folder_id_ptr;
+			let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			let err = check_node_free(&address_space, &variable_node);
+			if err != NO_ERR {
+				return err;
+			}
+			VariableBuilder::new(
+				&variable_node,
+				cstr_to_string!(variable_browse_str),
+				cstr_to_string!(variable_display_str),
+			)
+			.data_type(data_type)
+			.value(initial_value)
+			.writable()
+			.organized_by(&*folder_id)
+			.insert(&mut *address_space);
+		}
+
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Same as lv_add_variable, but with explicit access level (OPC UA AccessLevel
+// bits: 1 - read, 2 - write, 4 - history read, 8 - history write) for all
+// and for the current user, a description, and optional engineering units
+// (empty string - none), added as EngineeringUnits (EUInformation) property
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_add_variable_ex(
+	variable_node_str: *const c_char,
+	variable_browse_str: *const c_char,
+	variable_display_str: *const c_char,
+	description_str: *const c_char,
+	eu_str: *const c_char,
+	ns: u16,
+	var_type: u16,
+	access_flags: u8,
+	user_access_flags: u8,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	folder_id_ptr: *mut NodeId,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(folder_id_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+		check_null!(variable_browse_str, ERR_NULL_POINTER);
+		check_null!(variable_display_str, ERR_NULL_POINTER);
+		check_null!(description_str, ERR_NULL_POINTER);
+		check_null!(eu_str, ERR_NULL_POINTER);
+
+		let Some((data_type, initial_value)) = lv_data_type(var_type) else {
+			return ERR_INVALID_TYPE;
+		};
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let folder_id = &mut *folder_id_ptr;
+			let variable_node_str = cstr_to_string!(variable_node_str);
+			let eu_str = cstr_to_string!(eu_str);
+			let variable_node = NodeId::new(ns, variable_node_str.clone());
+
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			let err = check_node_free(&address_space, &variable_node);
+			if err != NO_ERR {
+				return err;
+			}
+			VariableBuilder::new(
+				&variable_node,
+				cstr_to_string!(variable_browse_str),
+				cstr_to_string!(variable_display_str),
+			)
+			.description(cstr_to_string!(description_str))
+			.data_type(data_type)
+			.value(initial_value.clone())
+			.access_level(AccessLevel::from_bits_truncate(access_flags))
+			.user_access_level(AccessLevel::from_bits_truncate(user_access_flags))
+			.historizing(access_flags & AccessLevel::HISTORY_READ.bits() != 0)
+			.organized_by(&*folder_id)
+			.insert(&mut *address_space);
+			set_waiting_for_initial_data(&mut address_space, &variable_node, initial_value);
+
+			if !eu_str.is_empty() {
+				let eu = EUInformation {
+					namespace_uri: UNECE_UNITS_NAMESPACE.into(),
+					unit_id: -1, // not a UNECE code
+					display_name: LocalizedText::new("", &eu_str),
+					description: LocalizedText::new("", &eu_str),
+				};
+				set_variable_property(
+					&mut address_space,
+					ns,
+					&variable_node_str,
+					"EngineeringUnits",
+					DataTypeId::EUInformation,
+					ExtensionObject::from_message(eu).into(),
+				);
+			}
+		}
+
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// One-dimensional array variable of array_length elements, all 0 (false),
+// otherwise as lv_add_variable. Written with lv_write_variable_array*
+//
+macro_rules! create_lv_add_array_variable {
+	($fn_name:ident, $value_type:ty, $data_type:ident) => {
+		#[allow(non_snake_case)]
 		#[unsafe(no_mangle)]
 		pub extern "C" fn $fn_name(
 			variable_node_str: *const c_char,
+			variable_browse_str: *const c_char,
+			variable_display_str: *const c_char,
 			ns: u16,
-			value: $value_type,
+			array_length: i32,
 			manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
-			server_handle_ptr: *mut ServerHandle,
+			folder_id_ptr: *mut NodeId,
 		) -> i32 {
-			unsafe {
-				let variable_node_str = cstr_to_string!(variable_node_str);
-				let variable_node = NodeId::new(ns, variable_node_str);
-
+			catch_panic!(ERR_INTERNAL_PANIC, {
 				check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
-				check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+				check_null!(folder_id_ptr, ERR_INVALID_SERVER_REF);
+				check_null!(variable_node_str, ERR_NULL_POINTER);
+				check_null!(variable_browse_str, ERR_NULL_POINTER);
+				check_null!(variable_display_str, ERR_NULL_POINTER);
+				if array_length < 0 {
+					return ERR_INVALID_ARGUMENT;
+				}
 
-				let manager = &mut *manager_ptr;
-				let server_handle = &mut *server_handle_ptr;
+				unsafe {
+					let manager = &mut *manager_ptr;
+					let folder_id = &mut *folder_id_ptr;
+					let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
 
-				let address_space = manager.address_space();
-				let subscriptions = server_handle.subscriptions().clone();
+					let address_space = manager.address_space();
+					let mut address_space = address_space.write();
+					let err = check_node_free(&address_space, &variable_node);
+					if err != NO_ERR {
+						return err;
+					}
+					VariableBuilder::new(
+						&variable_node,
+						cstr_to_string!(variable_browse_str),
+						cstr_to_string!(variable_display_str),
+					)
+					.data_type(DataTypeId::$data_type)
+					.value_rank(1)
+					.array_dimensions(&[array_length as u32])
+					.value(vec![<$value_type>::default(); array_length as usize])
+					.writable()
+					.organized_by(&*folder_id)
+					.insert(&mut *address_space);
+				}
+				NO_ERR
+			})
+		}
+	};
+}
 
-				address_space.force_unlock_write();
-				let data_value = DataValue::new_now(value);
-				manager
-					.set_value(&subscriptions, &variable_node, None, data_value)
-					.unwrap();
+create_lv_add_array_variable!(lv_add_variable_array_Boolean, bool, Boolean); // 1
+create_lv_add_array_variable!(lv_add_variable_array_SByte, i8, SByte); // 2
+create_lv_add_array_variable!(lv_add_variable_array_Byte, u8, Byte); // 3
+create_lv_add_array_variable!(lv_add_variable_array_Int16, i16, Int16); //...
+create_lv_add_array_variable!(lv_add_variable_array_UInt16, u16, UInt16);
+create_lv_add_array_variable!(lv_add_variable_array_Int32, i32, Int32);
+create_lv_add_array_variable!(lv_add_variable_array_UInt32, u32, UInt32);
+create_lv_add_array_variable!(lv_add_variable_array_Int64, i64, Int64);
+create_lv_add_array_variable!(lv_add_variable_array_UInt64, u64, UInt64);
+create_lv_add_array_variable!(lv_add_variable_array_Float, f32, Float);
+create_lv_add_array_variable!(lv_add_variable_array_Double, f64, Double); // 11
+
+//==============================================================================
+// One-dimensional String array variable, initially empty. max_length is
+// announced as ArrayDimensions (0 - unknown), otherwise as lv_add_variable.
+// Written with lv_write_variable_array_String
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_add_string_array_variable(
+	variable_node_str: *const c_char,
+	variable_browse_str: *const c_char,
+	variable_display_str: *const c_char,
+	ns: u16,
+	max_length: i32,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	folder_id_ptr: *mut NodeId,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(folder_id_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+		check_null!(variable_browse_str, ERR_NULL_POINTER);
+		check_null!(variable_display_str, ERR_NULL_POINTER);
+		if max_length < 0 {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let folder_id = &mut *folder_id_ptr;
+			let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			let err = check_node_free(&address_space, &variable_node);
+			if err != NO_ERR {
+				return err;
 			}
-			return 0;
+			VariableBuilder::new(
+				&variable_node,
+				cstr_to_string!(variable_browse_str),
+				cstr_to_string!(variable_display_str),
+			)
+			.data_type(DataTypeId::String)
+			.value_rank(1)
+			.array_dimensions(&[max_length as u32])
+			.value(Vec::<String>::new())
+			.writable()
+			.organized_by(&*folder_id)
+			.insert(&mut *address_space);
 		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Create or update the property (HasProperty, PropertyType) of the variable,
+// node id of the property is "<variable node>.<browse name>".
+// Returns NO_ERR or BadNodeIdUnknown if the variable doesn't exist
+//
+fn set_variable_property(
+	address_space: &mut AddressSpace,
+	ns: u16,
+	variable_node_str: &str,
+	browse_name: &str,
+	data_type: DataTypeId,
+	value: Variant,
+) -> i32 {
+	let variable_node = NodeId::new(ns, variable_node_str.to_string());
+	if !matches!(
+		address_space.find_node(&variable_node),
+		Some(NodeType::Variable(_))
+	) {
+		set_last_error_detail(format!("Variable {} not found", variable_node));
+		return StatusCode::BadNodeIdUnknown.bits() as i32;
+	}
+
+	let property_node = NodeId::new(ns, format!("{}.{}", variable_node_str, browse_name));
+	if let Some(NodeType::Variable(property)) = address_space.find_node_mut(&property_node) {
+		property.set_data_value(DataValue::new_now(value));
+		return NO_ERR;
+	}
+	let value_rank = match value {
+		Variant::Array(_) => 1,
+		_ => -1,
 	};
+	VariableBuilder::new(&property_node, browse_name, browse_name)
+		.data_type(data_type)
+		.value_rank(value_rank)
+		.value(value)
+		.has_type_definition(VariableTypeId::PropertyType)
+		.property_of(&variable_node)
+		.insert(address_space);
+	NO_ERR
 }
 
-// Create functions for different variable types
-create_lv_write_variable!(lv_write_variableBoolean, bool); // 1
-create_lv_write_variable!(lv_write_variableSByte, i8); // 2
-create_lv_write_variable!(lv_write_variableByte, u8); // 3
-create_lv_write_variable!(lv_write_variableInt16, i16); //...
-create_lv_write_variable!(lv_write_variableUInt16, u16);
-create_lv_write_variable!(lv_write_variableInt32, i32);
-create_lv_write_variable!(lv_write_variableUInt32, u32);
-create_lv_write_variable!(lv_write_variableInt64, i64);
-create_lv_write_variable!(lv_write_variableUInt64, u64);
-create_lv_write_variable!(lv_write_variableFloat, f32);
-create_lv_write_variable!(lv_write_variableDouble, f64); // 11
-// too tired to write the rest
+//==============================================================================
+// EURange property (OPC UA Part 8, Data Access) of the variable, the range
+// of the value HMIs use for scaling. Called again updates the range
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_variable_eu_range(
+	variable_node_str: *const c_char,
+	ns: u16,
+	eu_low: f64,
+	eu_high: f64,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+		if !(eu_low <= eu_high) {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let variable_node_str = cstr_to_string!(variable_node_str);
+			let range = Range {
+				low: eu_low,
+				high: eu_high,
+			};
+
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			set_variable_property(
+				&mut address_space,
+				ns,
+				&variable_node_str,
+				"EURange",
+				DataTypeId::Range,
+				ExtensionObject::from_message(range).into(),
+			)
+		}
+	})
+}
+
+//==============================================================================
+// EnumStrings property of the Enumeration variable (lv_add_variable 29), the
+// labels of the values 0, 1, 2... one per line of enum_str_lv_str, so that
+// clients show "Idle" instead of 0. Called again replaces the labels
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_variable_enum_strings(
+	variable_node_str: *const c_char,
+	ns: u16,
+	enum_str_lv_str: LStrHandle,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+		check_null!(enum_str_lv_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let variable_node_str = cstr_to_string!(variable_node_str);
+			let labels: Vec<LocalizedText> = lstr_to_string(enum_str_lv_str)
+				.lines()
+				.map(|label| LocalizedText::new("", label))
+				.collect();
+			if labels.is_empty() {
+				set_last_error_detail("No enumeration labels");
+				return ERR_INVALID_ARGUMENT;
+			}
+
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			set_variable_property(
+				&mut address_space,
+				ns,
+				&variable_node_str,
+				"EnumStrings",
+				DataTypeId::LocalizedText,
+				labels.into(),
+			)
+		}
+	})
+}
+
+//==============================================================================
+// EngineeringUnits property (OPC UA Part 8, Data Access) of the variable.
+// unit_id is the UNECE code (e.g. 4408652 for degree Celsius), -1 if none
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_variable_eu_information(
+	variable_node_str: *const c_char,
+	ns: u16,
+	display_name_str: *const c_char,
+	description_str: *const c_char,
+	unit_id: i32,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+		check_null!(display_name_str, ERR_NULL_POINTER);
+		check_null!(description_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let variable_node_str = cstr_to_string!(variable_node_str);
+			let eu = EUInformation {
+				namespace_uri: UNECE_UNITS_NAMESPACE.into(),
+				unit_id,
+				display_name: LocalizedText::new("", &cstr_to_string!(display_name_str)),
+				description: LocalizedText::new("", &cstr_to_string!(description_str)),
+			};
+
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			set_variable_property(
+				&mut address_space,
+				ns,
+				&variable_node_str,
+				"EngineeringUnits",
+				DataTypeId::EUInformation,
+				ExtensionObject::from_message(eu).into(),
+			)
+		}
+	})
+}
+
+//==============================================================================
+// Change AccessLevel and UserAccessLevel of existing variable (same bits as
+// in lv_add_variable_ex), e.g. 1 to make it read-only
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_variable_access(
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	variable_node_str: *const c_char,
+	ns: u16,
+	access_flags: u8,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		lv_set_variable_access_level(variable_node_str, ns, access_flags, manager_ptr)
+	})
+}
+
+//==============================================================================
+// Set AccessLevel and UserAccessLevel attributes of existing variable,
+// 1 = CurrentRead, 2 = CurrentWrite, 4 = HistoryRead, 8 = HistoryWrite.
+// Client writes to a variable without CurrentWrite get BadNotWritable
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_variable_access_level(
+	variable_node_str: *const c_char,
+	ns: u16,
+	access_level_bits: u8,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			let Some(node) = address_space.find_node_mut(&variable_node) else {
+				set_last_error_detail(format!("Variable {} not found", variable_node));
+				return StatusCode::BadNodeIdUnknown.bits() as i32;
+			};
+			for attribute_id in [AttributeId::AccessLevel, AttributeId::UserAccessLevel] {
+				if let Err(status) = node
+					.as_mut_node()
+					.set_attribute(attribute_id, Variant::Byte(access_level_bits))
+				{
+					set_last_error_detail(format!("{} is not a variable", variable_node));
+					return status.bits() as i32;
+				}
+			}
+			NO_ERR
+		}
+	})
+}
+
+//==============================================================================
+// Set Description attribute of the node (variable, folder, ...), shown by
+// clients like UA Expert. locale_str may be empty
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_variable_description(
+	variable_node_str: *const c_char,
+	ns: u16,
+	description_str: *const c_char,
+	locale_str: *const c_char,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+		check_null!(description_str, ERR_NULL_POINTER);
+		check_null!(locale_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+			let description = LocalizedText::new(
+				&cstr_to_string!(locale_str),
+				&cstr_to_string!(description_str),
+			);
+
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			let Some(node) = address_space.find_node_mut(&variable_node) else {
+				set_last_error_detail(format!("Node {} not found", variable_node));
+				return ERR_INVALID_SERVER_REF;
+			};
+			match node.as_mut_node().set_attribute(
+				AttributeId::Description,
+				Variant::LocalizedText(Box::new(description)),
+			) {
+				Ok(()) => NO_ERR,
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Delete the variable with its properties (EURange, ...) at runtime.
+// delete_reverse_refs != 0 also deletes the references pointing to it
+// (from the parent folder), otherwise only its own references
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_delete_variable(
+	variable_node_str: *const c_char,
+	ns: u16,
+	delete_reverse_refs: u8,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			match address_space.find_node(&variable_node) {
+				Some(NodeType::Variable(_)) => {}
+				Some(_) => {
+					set_last_error_detail(format!("{} is not a variable", variable_node));
+					return ERR_INVALID_TYPE;
+				}
+				None => {
+					set_last_error_detail(format!("Variable {} not found", variable_node));
+					return ERR_INVALID_SERVER_REF;
+				}
+			}
+			delete_node_tree(
+				&mut address_space,
+				&variable_node,
+				&[ReferenceTypeId::HasProperty, ReferenceTypeId::HasComponent],
+				delete_reverse_refs != 0,
+			);
+			NO_ERR
+		}
+	})
+}
+
+//==============================================================================
+// Bulk creation and update of variables, the address space is locked once.
+// LabVIEW arrays of clusters, results_out is allocated by LabVIEW with
+// the same number of elements as the input array
+//
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+pub struct LvVariableDef {
+	node_id: LStrHandle,
+	browse_name: LStrHandle,
+	display_name: LStrHandle,
+	var_type: u16,
+}
+
+#[cfg(target_arch = "x86")]
+#[repr(C, packed(1))]
+pub struct LvVariableDef {
+	node_id: LStrHandle,
+	browse_name: LStrHandle,
+	display_name: LStrHandle,
+	var_type: u16,
+}
+
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+pub struct LvVariableValue {
+	node_id: LStrHandle,
+	value: f64, // converted to the DataType of the node
+}
+
+#[cfg(target_arch = "x86")]
+#[repr(C, packed(1))]
+pub struct LvVariableValue {
+	node_id: LStrHandle,
+	value: f64,
+}
+
+// Per-variable results: NO_ERR, ERR_NODE_EXISTS, ERR_INVALID_TYPE or ERR_INVALID_ARGUMENT.
+// ERR_NAMESPACE_NOT_FOUND if ns isn't a namespace of the node manager
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_add_variables_bulk(
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	folder_id_ptr: *mut NodeId,
+	ns: u16,
+	defs: LvArrayHandle<LvVariableDef>,
+	results_out: *mut i32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(folder_id_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(defs, ERR_NULL_POINTER);
+		check_null!(results_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let folder_id = &*folder_id_ptr;
+			let defs = lv_array_as_slice(defs);
+			let results = std::slice::from_raw_parts_mut(results_out, defs.len());
+
+			let address_space = manager.address_space();
+			let mut address_space = address_space.write();
+			if !address_space.namespaces().contains_key(&ns) {
+				set_last_error_detail(format!("Namespace {ns} isn't in the address space"));
+				return ERR_NAMESPACE_NOT_FOUND;
+			}
+			for (def, result) in defs.iter().zip(results.iter_mut()) {
+				let variable_node = NodeId::new(ns, lstr_to_string(def.node_id));
+				let Some((data_type, initial_value)) = lv_data_type(def.var_type) else {
+					*result = ERR_INVALID_TYPE;
+					continue;
+				};
+				// Also a node id given twice in defs, the first one is added
+				let err = check_node_free(&address_space, &variable_node);
+				if err != NO_ERR {
+					*result = err;
+					continue;
+				}
+				let inserted = VariableBuilder::new(
+					&variable_node,
+					lstr_to_string(def.browse_name),
+					lstr_to_string(def.display_name),
+				)
+				.data_type(data_type)
+				.value(initial_value.clone())
+				.writable()
+				.organized_by(folder_id)
+				.insert(&mut *address_space);
+				*result = if inserted {
+					set_waiting_for_initial_data(&mut address_space, &variable_node, initial_value);
+					NO_ERR
+				} else {
+					ERR_INVALID_ARGUMENT
+				};
+			}
+		}
+		NO_ERR
+	})
+}
+
+// Per-variable results: NO_ERR or status code of the failure
+// (BadNodeIdUnknown, BadTypeMismatch if the value can't be cast to node DataType),
+// validated as Double values (lv_set_write_validation)
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_variables_bulk(
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	server_handle_ptr: *mut ServerHandle,
+	ns: u16,
+	values: LvArrayHandle<LvVariableValue>,
+	results_out: *mut i32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(values, ERR_NULL_POINTER);
+		check_null!(results_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let manager = &mut *manager_ptr;
+			let server_handle = &mut *server_handle_ptr;
+			let values = lv_array_as_slice(values);
+			let results = std::slice::from_raw_parts_mut(results_out, values.len());
+
+			// Resolve the nodes first, set_values() stops at the first unknown node
+			let mut data_values = Vec::with_capacity(values.len());
+			{
+				let address_space = manager.address_space().read();
+				for (value, result) in values.iter().zip(results.iter_mut()) {
+					let node_id = NodeId::new(ns, lstr_to_string(value.node_id));
+					let data_type = match address_space.find_node(&node_id) {
+						Some(NodeType::Variable(variable)) => variable.data_type(),
+						_ => {
+							*result = StatusCode::BadNodeIdUnknown.bits() as i32;
+							continue;
+						}
+					};
+					let err = check_write(&{ value.value }, DataTypeId::Double, || {
+						Some(data_type.clone())
+					});
+					if err != NO_ERR {
+						*result = err;
+						continue;
+					}
+					let variant = match VariantScalarTypeId::try_from(&data_type) {
+						Ok(type_id) => Variant::Double(value.value).cast(type_id),
+						Err(_) => Variant::Empty,
+					};
+					if variant.is_empty() {
+						*result = StatusCode::BadTypeMismatch.bits() as i32;
+						continue;
+					}
+					*result = NO_ERR;
+					data_values.push((node_id, DataValue::new_now(variant)));
+				}
+			}
+
+			let subscriptions = server_handle.subscriptions().clone();
+			if let Err(status) = manager.set_values(
+				&subscriptions,
+				data_values.iter().map(|(id, dv)| (id, None, dv.clone())),
+			) {
+				return status.bits() as i32;
+			}
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Optional validation of the values written from LabVIEW, off by default
+//
+pub const WRITE_VALIDATION_NONE: u32 = 0;
+pub const WRITE_VALIDATION_REJECT_NAN_INF: u32 = 1; // NaN/Inf for Float/Double
+pub const WRITE_VALIDATION_CHECK_DATA_TYPE: u32 = 2; // node DataType must match the function
+
+#[cfg(not(test))]
+static WRITE_VALIDATION: AtomicU32 = AtomicU32::new(WRITE_VALIDATION_NONE);
+
+// Per thread in the unit tests, which run in parallel
+#[cfg(test)]
+thread_local! {
+	static WRITE_VALIDATION: std::cell::Cell<u32> =
+		const { std::cell::Cell::new(WRITE_VALIDATION_NONE) };
+}
+
+fn write_validation() -> u32 {
+	#[cfg(not(test))]
+	return WRITE_VALIDATION.load(Ordering::Relaxed);
+	#[cfg(test)]
+	return WRITE_VALIDATION.get();
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_write_validation(flags: u32) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		if flags & !(WRITE_VALIDATION_REJECT_NAN_INF | WRITE_VALIDATION_CHECK_DATA_TYPE) != 0 {
+			return ERR_INVALID_ARGUMENT;
+		}
+		#[cfg(not(test))]
+		WRITE_VALIDATION.store(flags, Ordering::Relaxed);
+		#[cfg(test)]
+		WRITE_VALIDATION.set(flags);
+		NO_ERR
+	})
+}
+
+// Only Float/Double have special values, which can't be written with validation on
+trait SpecialValue {
+	fn is_special(&self) -> bool {
+		false
+	}
+}
+
+impl SpecialValue for bool {}
+impl SpecialValue for i8 {}
+impl SpecialValue for u8 {}
+impl SpecialValue for i16 {}
+impl SpecialValue for u16 {}
+impl SpecialValue for i32 {}
+impl SpecialValue for u32 {}
+impl SpecialValue for i64 {}
+impl SpecialValue for u64 {}
+impl SpecialValue for String {}
+impl SpecialValue for DateTime {}
+impl SpecialValue for Guid {}
+impl SpecialValue for ByteString {}
+
+impl SpecialValue for f32 {
+	fn is_special(&self) -> bool {
+		!self.is_finite()
+	}
+}
+
+impl SpecialValue for f64 {
+	fn is_special(&self) -> bool {
+		!self.is_finite()
+	}
+}
+
+impl SpecialValue for Variant {
+	fn is_special(&self) -> bool {
+		match self {
+			Variant::Float(v) => v.is_special(),
+			Variant::Double(v) => v.is_special(),
+			_ => false,
+		}
+	}
+}
+
+impl<T: SpecialValue> SpecialValue for [T] {
+	fn is_special(&self) -> bool {
+		self.iter().any(|v| v.is_special())
+	}
+}
+
+// Check the value of data_type against the validation flags, variable_type
+// is the DataType of the variable (None if the node isn't one), only called
+// for the DataType check. U64 may be written to the Int64 variables of
+// type 9. Returns NO_ERR, ERR_INVALID_ARGUMENT or
+// BadTypeMismatch/BadNodeIdUnknown
+fn check_write(
+	value: &(impl SpecialValue + ?Sized),
+	data_type: DataTypeId,
+	variable_type: impl FnOnce() -> Option<NodeId>,
+) -> i32 {
+	let flags = write_validation();
+	if flags & WRITE_VALIDATION_REJECT_NAN_INF != 0 && value.is_special() {
+		set_last_error_detail("NaN or Inf value");
+		return ERR_INVALID_ARGUMENT;
+	}
+	if flags & WRITE_VALIDATION_CHECK_DATA_TYPE != 0 {
+		let Some(variable_type) = variable_type() else {
+			return StatusCode::BadNodeIdUnknown.bits() as i32;
+		};
+		let type_9 = data_type == DataTypeId::UInt64 && variable_type == DataTypeId::Int64;
+		if variable_type != data_type && !type_9 {
+			return StatusCode::BadTypeMismatch.bits() as i32;
+		}
+	}
+	NO_ERR
+}
+
+// check_write of the value written to the node
+fn validate_write(
+	manager: &InMemoryNodeManager<SimpleNodeManagerImpl>,
+	node_id: &NodeId,
+	value: &(impl SpecialValue + ?Sized),
+	data_type: DataTypeId,
+) -> i32 {
+	check_write(value, data_type, || {
+		match manager.address_space().read().find_node(node_id) {
+			Some(NodeType::Variable(variable)) => Some(variable.data_type()),
+			_ => None,
+		}
+	})
+}
+
+// Integer value as the integer DataType, None if either isn't an integer,
+// Err if the value is out of the range of the DataType
+fn cast_integer(
+	value: &Variant,
+	data_type: DataTypeId,
+) -> Option<Result<Variant, TryFromIntError>> {
+	let v: i128 = match *value {
+		Variant::SByte(v) => v.into(),
+		Variant::Byte(v) => v.into(),
+		Variant::Int16(v) => v.into(),
+		Variant::UInt16(v) => v.into(),
+		Variant::Int32(v) => v.into(),
+		Variant::UInt32(v) => v.into(),
+		Variant::Int64(v) => v.into(),
+		Variant::UInt64(v) => v.into(),
+		_ => return None,
+	};
+	Some(match data_type {
+		DataTypeId::SByte => i8::try_from(v).map(Variant::from),
+		DataTypeId::Byte => u8::try_from(v).map(Variant::from),
+		DataTypeId::Int16 => i16::try_from(v).map(Variant::from),
+		DataTypeId::UInt16 => u16::try_from(v).map(Variant::from),
+		DataTypeId::Int32 => i32::try_from(v).map(Variant::from),
+		DataTypeId::UInt32 => u32::try_from(v).map(Variant::from),
+		DataTypeId::Int64 => i64::try_from(v).map(Variant::from),
+		DataTypeId::UInt64 => u64::try_from(v).map(Variant::from),
+		_ => return None,
+	})
+}
+
+// Set the value of the variable (validate_write first), an integer value is
+// cast to the integer DataType of the variable if that is another one (e.g.
+// U64 written to the Int64 variables of type 9), ERR_INVALID_ARGUMENT if the
+// value is out of its range. As set_value, but the DataType is looked up under
+// the same write lock, so the unchecked writes take no other lock
+fn set_variable_value(
+	manager: &InMemoryNodeManager<SimpleNodeManagerImpl>,
+	subscriptions: &SubscriptionCache,
+	node_id: &NodeId,
+	mut data_value: DataValue,
+) -> i32 {
+	let mut address_space = manager.address_space().write();
+	let Some(NodeType::Variable(variable)) = address_space.find_mut(node_id) else {
+		return StatusCode::BadNodeIdUnknown.bits() as i32;
+	};
+	let variable_type = variable.data_type().as_data_type_id();
+	if let (Some(value), Ok(variable_type)) = (&data_value.value, variable_type) {
+		match cast_integer(value, variable_type) {
+			Some(Ok(cast)) => data_value.value = Some(cast),
+			Some(Err(_)) => {
+				set_last_error_detail(format!(
+					"{value} is out of the range of {variable_type:?} variable {node_id}"
+				));
+				return ERR_INVALID_ARGUMENT;
+			}
+			None => {}
+		}
+	}
+	variable.set_data_value(data_value);
+
+	subscriptions.maybe_notify(
+		[(node_id, AttributeId::Value)].into_iter(),
+		|node_id, attribute_id, index_range, data_encoding| {
+			address_space.find(node_id)?.as_node().get_attribute(
+				TimestampsToReturn::Both,
+				attribute_id,
+				index_range,
+				data_encoding,
+			)
+		},
+	);
+	NO_ERR
+}
+
+macro_rules! create_lv_write_variable {
+	($fn_name:ident, $value_type:ty, $data_type:ident) => {
+		#[unsafe(no_mangle)]
+		pub extern "C" fn $fn_name(
+			variable_node_str: *const c_char,
+			ns: u16,
+			value: $value_type,
+			manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+			server_handle_ptr: *mut ServerHandle,
+		) -> i32 {
+			catch_panic!(ERR_INTERNAL_PANIC, {
+				unsafe {
+					let variable_node_str = cstr_to_string!(variable_node_str);
+					let variable_node = NodeId::new(ns, variable_node_str);
+
+					check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+					check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+
+					let manager = &mut *manager_ptr;
+					let server_handle = &mut *server_handle_ptr;
+
+					// No lock here, set_variable_value takes the address space write
+					// lock itself
+					let subscriptions = server_handle.subscriptions();
+
+					let err =
+						validate_write(manager, &variable_node, &value, DataTypeId::$data_type);
+					if err != NO_ERR {
+						return err;
+					}
+					let data_value = DataValue::new_now(Variant::from(value));
+					return set_variable_value(manager, subscriptions, &variable_node, data_value);
+				}
+			})
+		}
+	};
+}
+
+// Create functions for different variable types
+create_lv_write_variable!(lv_write_variableBoolean, bool, Boolean); // 1
+create_lv_write_variable!(lv_write_variableSByte, i8, SByte); // 2
+create_lv_write_variable!(lv_write_variableByte, u8, Byte); // 3
+create_lv_write_variable!(lv_write_variableInt16, i16, Int16); //...
+create_lv_write_variable!(lv_write_variableUInt16, u16, UInt16);
+create_lv_write_variable!(lv_write_variableInt32, i32, Int32);
+create_lv_write_variable!(lv_write_variableUInt32, u32, UInt32);
+create_lv_write_variable!(lv_write_variableInt64, i64, Int64);
+create_lv_write_variable!(lv_write_variableUInt64, u64, UInt64);
+create_lv_write_variable!(lv_write_variableFloat, f32, Float);
+create_lv_write_variable!(lv_write_variableDouble, f64, Double); // 11
+// too tired to write the rest
+
+//==============================================================================
+// DateTime variable (lv_add_variable 13) from the LabVIEW timestamp (Cocoa).
+// ERR_INVALID_ARGUMENT for NaN and timestamps before 1601-01-01, which
+// OPC UA can't represent
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_variableDateTime(
+	variable_node_str: *const c_char,
+	ns: u16,
+	cocoa_timestamp: f64,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	server_handle_ptr: *mut ServerHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+
+		let Some(value) = cocoa_to_date_time_checked(cocoa_timestamp) else {
+			set_last_error_detail(format!(
+				"{cocoa_timestamp} is out of the OPC UA DateTime range"
+			));
+			return ERR_INVALID_ARGUMENT;
+		};
+
+		unsafe {
+			let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+			let manager = &mut *manager_ptr;
+			let server_handle = &mut *server_handle_ptr;
+
+			let err = validate_write(manager, &variable_node, &value, DataTypeId::DateTime);
+			if err != NO_ERR {
+				return err;
+			}
+			if let Err(status) = manager.set_value(
+				server_handle.subscriptions(),
+				&variable_node,
+				None,
+				DataValue::new_now(value),
+			) {
+				return status.bits() as i32;
+			}
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Enumeration variable (lv_add_variable 29), value is the index of its label
+// in EnumStrings (see lv_set_variable_enum_strings)
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_variableEnum(
+	variable_node_str: *const c_char,
+	ns: u16,
+	value: i32,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	server_handle_ptr: *mut ServerHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+			let manager = &mut *manager_ptr;
+			let server_handle = &mut *server_handle_ptr;
+
+			let err = validate_write(manager, &variable_node, &value, DataTypeId::Enumeration);
+			if err != NO_ERR {
+				return err;
+			}
+			if let Err(status) = manager.set_value(
+				server_handle.subscriptions(),
+				&variable_node,
+				None,
+				DataValue::new_now(value),
+			) {
+				return status.bits() as i32;
+			}
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Guid variable (lv_add_variable 14) from guid_str as
+// {xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx}, the format of lv_read_variableGuid.
+// ERR_INVALID_ARGUMENT if guid_str isn't a GUID in braces
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_variableGuid(
+	variable_node_str: *const c_char,
+	ns: u16,
+	guid_str: *const c_char,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	server_handle_ptr: *mut ServerHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+		check_null!(guid_str, ERR_NULL_POINTER);
+
+		let text = cstr_to_string!(guid_str);
+		let braced = text
+			.trim()
+			.strip_prefix('{')
+			.and_then(|t| t.strip_suffix('}'));
+		let Some(Ok(value)) = braced.map(Guid::from_str) else {
+			set_last_error_detail(format!(
+				"'{text}' is not a GUID as {{xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx}}"
+			));
+			return ERR_INVALID_ARGUMENT;
+		};
+
+		unsafe {
+			let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+			let manager = &mut *manager_ptr;
+			let server_handle = &mut *server_handle_ptr;
+
+			let err = validate_write(manager, &variable_node, &value, DataTypeId::Guid);
+			if err != NO_ERR {
+				return err;
+			}
+			if let Err(status) = manager.set_value(
+				server_handle.subscriptions(),
+				&variable_node,
+				None,
+				DataValue::new_now(value),
+			) {
+				return status.bits() as i32;
+			}
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// ByteString variable (lv_add_variable 15) from data_len bytes of data_ptr,
+// e.g. a calibration blob or a firmware image. data_len 0 writes an empty
+// ByteString, data_ptr may be null then
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_variableByteString(
+	variable_node_str: *const c_char,
+	ns: u16,
+	data_ptr: *const u8,
+	data_len: u32,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	server_handle_ptr: *mut ServerHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+		if data_len > 0 && data_ptr.is_null() {
+			return ERR_NULL_POINTER;
+		}
+
+		unsafe {
+			let value = match data_len {
+				0 => ByteString::from(Vec::new()),
+				len => ByteString::from(std::slice::from_raw_parts(data_ptr, len as usize)),
+			};
+			let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+			let manager = &mut *manager_ptr;
+			let server_handle = &mut *server_handle_ptr;
+
+			let err = validate_write(manager, &variable_node, &value, DataTypeId::ByteString);
+			if err != NO_ERR {
+				return err;
+			}
+			if let Err(status) = manager.set_value(
+				server_handle.subscriptions(),
+				&variable_node,
+				None,
+				DataValue::new_now(Variant::ByteString(value)),
+			) {
+				return status.bits() as i32;
+			}
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Same as lv_write_variable*, with the value of the LabVIEW Variant, type_id is
+// the LVDataTypeId of its data (1 Boolean .. 12 String).
+// ERR_INVALID_TYPE if the Variant holds another type
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_variable_from_lv_variant(
+	variable_node_str: *const c_char,
+	ns: u16,
+	lv_variant_hdl: TVariant,
+	type_id: u16,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	server_handle_ptr: *mut ServerHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+		check_null!(lv_variant_hdl, ERR_NULL_POINTER);
+
+		unsafe {
+			let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+			let manager = &mut *manager_ptr;
+			let server_handle = &mut *server_handle_ptr;
+
+			let value = match lv_variant_to_variant(lv_variant_hdl, type_id) {
+				Ok(value) => value,
+				Err(err) => return err,
+			};
+			let Some((data_type, _)) = lv_data_type(type_id) else {
+				return ERR_INVALID_TYPE;
+			};
+			let err = validate_write(manager, &variable_node, &value, data_type);
+			if err != NO_ERR {
+				return err;
+			}
+			set_variable_value(
+				manager,
+				server_handle.subscriptions(),
+				&variable_node,
+				DataValue::new_now(value),
+			)
+		}
+	})
+}
+
+//==============================================================================
+// Write count values from values_in to the array variable of
+// lv_add_variable_array*, the array takes the length count
+//
+macro_rules! create_lv_write_array_variable {
+	($fn_name:ident, $value_type:ty, $data_type:ident) => {
+		#[allow(non_snake_case)]
+		#[unsafe(no_mangle)]
+		pub extern "C" fn $fn_name(
+			variable_node_str: *const c_char,
+			ns: u16,
+			values_in: *const $value_type,
+			count: i32,
+			manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+			server_handle_ptr: *mut ServerHandle,
+		) -> i32 {
+			catch_panic!(ERR_INTERNAL_PANIC, {
+				check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+				check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+				check_null!(variable_node_str, ERR_NULL_POINTER);
+				if count < 0 || (count > 0 && values_in.is_null()) {
+					return ERR_INVALID_ARGUMENT;
+				}
+
+				unsafe {
+					let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+					let manager = &mut *manager_ptr;
+					let server_handle = &mut *server_handle_ptr;
+					let values = match count {
+						0 => &[][..],
+						n => std::slice::from_raw_parts(values_in, n as usize),
+					};
+
+					let err =
+						validate_write(manager, &variable_node, values, DataTypeId::$data_type);
+					if err != NO_ERR {
+						return err;
+					}
+					let data_value = DataValue::new_now(Variant::from(values.to_vec()));
+					if let Err(status) = manager.set_value(
+						server_handle.subscriptions(),
+						&variable_node,
+						None,
+						data_value,
+					) {
+						return status.bits() as i32;
+					}
+				}
+				NO_ERR
+			})
+		}
+	};
+}
+
+create_lv_write_array_variable!(lv_write_variable_array_Boolean, bool, Boolean); // 1
+create_lv_write_array_variable!(lv_write_variable_array_SByte, i8, SByte); // 2
+create_lv_write_array_variable!(lv_write_variable_array_Byte, u8, Byte); // 3
+create_lv_write_array_variable!(lv_write_variable_array_Int16, i16, Int16); //...
+create_lv_write_array_variable!(lv_write_variable_array_UInt16, u16, UInt16);
+create_lv_write_array_variable!(lv_write_variable_array_Int32, i32, Int32);
+create_lv_write_array_variable!(lv_write_variable_array_UInt32, u32, UInt32);
+create_lv_write_array_variable!(lv_write_variable_array_Int64, i64, Int64);
+create_lv_write_array_variable!(lv_write_variable_array_UInt64, u64, UInt64);
+create_lv_write_array_variable!(lv_write_variable_array_Float, f32, Float);
+create_lv_write_array_variable!(lv_write_variable_array_Double, f64, Double); // 11
+
+//==============================================================================
+// Write the first count strings of the LabVIEW 1D string array to the array
+// variable of lv_add_string_array_variable, the array takes the length count.
+// ERR_INVALID_ARGUMENT if the LabVIEW array has fewer elements
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_variable_array_String(
+	variable_node_str: *const c_char,
+	ns: u16,
+	lstr_array_in: LStrArrayHandle,
+	count: i32,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	server_handle_ptr: *mut ServerHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+
+		unsafe {
+			let mut values = lstr_array_to_strings(lstr_array_in);
+			if count < 0 || count as usize > values.len() {
+				set_last_error_detail(format!(
+					"count {count}, the array has {} strings",
+					values.len()
+				));
+				return ERR_INVALID_ARGUMENT;
+			}
+			values.truncate(count as usize);
+
+			let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+			let manager = &mut *manager_ptr;
+			let server_handle = &mut *server_handle_ptr;
+
+			let err = validate_write(manager, &variable_node, &values[..], DataTypeId::String);
+			if err != NO_ERR {
+				return err;
+			}
+			let data_value = DataValue::new_now(Variant::from(values));
+			if let Err(status) = manager.set_value(
+				server_handle.subscriptions(),
+				&variable_node,
+				None,
+				data_value,
+			) {
+				return status.bits() as i32;
+			}
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Same as lv_write_variable*, but with source timestamp (Cocoa, e.g. t0 of the
+// LabVIEW waveform) and status code (0 - Good, 0x40000000 - Uncertain, ...)
+//
+fn timestamped_data_value(value: Variant, source_timestamp_cocoa: f64, status: u32) -> DataValue {
+	DataValue {
+		value: Some(value),
+		status: Some(StatusCode::from(status)),
+		source_timestamp: Some(cocoa_to_date_time(source_timestamp_cocoa)),
+		server_timestamp: Some(DateTime::now()),
+		..Default::default()
+	}
+}
+
+macro_rules! create_lv_write_variable_with_timestamp {
+	($fn_name:ident, $value_type:ty, $data_type:ident) => {
+		#[unsafe(no_mangle)]
+		pub extern "C" fn $fn_name(
+			variable_node_str: *const c_char,
+			ns: u16,
+			value: $value_type,
+			source_timestamp_cocoa: f64,
+			status: u32,
+			manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+			server_handle_ptr: *mut ServerHandle,
+		) -> i32 {
+			catch_panic!(ERR_INTERNAL_PANIC, {
+				check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+				check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+				check_null!(variable_node_str, ERR_NULL_POINTER);
+
+				unsafe {
+					let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+					let manager = &mut *manager_ptr;
+					let server_handle = &mut *server_handle_ptr;
+
+					let err =
+						validate_write(manager, &variable_node, &value, DataTypeId::$data_type);
+					if err != NO_ERR {
+						return err;
+					}
+					let data_value = timestamped_data_value(
+						Variant::from(value),
+						source_timestamp_cocoa,
+						status,
+					);
+					set_variable_value(
+						manager,
+						server_handle.subscriptions(),
+						&variable_node,
+						data_value,
+					)
+				}
+			})
+		}
+	};
+}
+
+create_lv_write_variable_with_timestamp!(lv_write_variable_with_timestamp_Boolean, bool, Boolean);
+create_lv_write_variable_with_timestamp!(lv_write_variable_with_timestamp_SByte, i8, SByte);
+create_lv_write_variable_with_timestamp!(lv_write_variable_with_timestamp_Byte, u8, Byte);
+create_lv_write_variable_with_timestamp!(lv_write_variable_with_timestamp_Int16, i16, Int16);
+create_lv_write_variable_with_timestamp!(lv_write_variable_with_timestamp_UInt16, u16, UInt16);
+create_lv_write_variable_with_timestamp!(lv_write_variable_with_timestamp_Int32, i32, Int32);
+create_lv_write_variable_with_timestamp!(lv_write_variable_with_timestamp_UInt32, u32, UInt32);
+create_lv_write_variable_with_timestamp!(lv_write_variable_with_timestamp_Int64, i64, Int64);
+create_lv_write_variable_with_timestamp!(lv_write_variable_with_timestamp_UInt64, u64, UInt64);
+create_lv_write_variable_with_timestamp!(lv_write_variable_with_timestamp_Float, f32, Float);
+create_lv_write_variable_with_timestamp!(lv_write_variable_with_timestamp_Double, f64, Double);
+
+//==============================================================================
+// Publish the LabVIEW waveform Y values as Double array, the source timestamp
+// is t0 of the waveform. values_in is array of count elements
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_write_variable_with_timestamp_DoubleArray(
+	variable_node_str: *const c_char,
+	ns: u16,
+	values_in: *const f64,
+	count: i32,
+	source_timestamp_cocoa: f64,
+	status: u32,
+	manager_ptr: *mut Arc<InMemoryNodeManager<SimpleNodeManagerImpl>>,
+	server_handle_ptr: *mut ServerHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(manager_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(server_handle_ptr, ERR_INVALID_SERVER_REF);
+		check_null!(variable_node_str, ERR_NULL_POINTER);
+		if count < 0 || (count > 0 && values_in.is_null()) {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let variable_node = NodeId::new(ns, cstr_to_string!(variable_node_str));
+			let manager = &mut *manager_ptr;
+			let server_handle = &mut *server_handle_ptr;
+			let values = match count {
+				0 => &[][..],
+				n => std::slice::from_raw_parts(values_in, n as usize),
+			};
+
+			let err = validate_write(manager, &variable_node, values, DataTypeId::Double);
+			if err != NO_ERR {
+				return err;
+			}
+			let data_value = timestamped_data_value(
+				Variant::from(values.to_vec()),
+				source_timestamp_cocoa,
+				status,
+			);
+			if let Err(status) = manager.set_value(
+				server_handle.subscriptions(),
+				&variable_node,
+				None,
+				data_value,
+			) {
+				return status.bits() as i32;
+			}
+		}
+		NO_ERR
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::client_variables::{lv_read_variableDouble, lv_read_variableGuid};
+	use crate::labview::{DSDisposeHandle, DSDisposeHandleLStr, new_lv_array, string_to_new_lstr};
+	use crate::test_server::{TestClient, TestServer};
+
+	use opcua::{
+		client::DataChangeCallback,
+		types::{
+			MonitoredItemCreateRequest, MonitoringMode, MonitoringParameters, ReadValueId,
+			TimestampsToReturn,
+		},
+	};
+	use std::{ffi::CString, time::Duration};
+	use tokio::{sync::mpsc, time::timeout};
+
+	#[test]
+	fn new_variables_read_back_their_type() {
+		use VariantScalarTypeId as V;
+		let types = [
+			(1, DataTypeId::Boolean, V::Boolean),
+			(2, DataTypeId::SByte, V::SByte),
+			(3, DataTypeId::Byte, V::Byte),
+			(4, DataTypeId::Int16, V::Int16),
+			(5, DataTypeId::UInt16, V::UInt16),
+			(6, DataTypeId::Int32, V::Int32),
+			(7, DataTypeId::UInt32, V::UInt32),
+			(8, DataTypeId::Int64, V::Int64),
+			(9, DataTypeId::Int64, V::Int64),
+			(10, DataTypeId::Float, V::Float),
+			(11, DataTypeId::Double, V::Double),
+			(12, DataTypeId::String, V::String),
+			(13, DataTypeId::DateTime, V::DateTime),
+			(14, DataTypeId::Guid, V::Guid),
+			(15, DataTypeId::ByteString, V::ByteString),
+			(29, DataTypeId::Enumeration, V::Int32),
+		];
+		let known: Vec<u16> = (0..=u16::MAX)
+			.filter(|t| lv_data_type(*t).is_some())
+			.collect();
+		assert_eq!(
+			known,
+			types.map(|t| t.0),
+			"a type of lv_data_type isn't tested"
+		);
+
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		for (var_type, data_type, scalar_type) in types {
+			let node = format!("Var{var_type}");
+			assert_eq!(server.add_variable(&node, var_type), NO_ERR);
+			let node_id = NodeId::new(server.ns, node);
+			let read = |attribute_id: AttributeId| ReadValueId {
+				node_id: node_id.clone(),
+				attribute_id: attribute_id as u32,
+				..Default::default()
+			};
+			let session = client.session();
+			let values = client
+				.runtime()
+				.block_on(session.read(
+					&[read(AttributeId::DataType), read(AttributeId::Value)],
+					TimestampsToReturn::Neither,
+					0.0,
+				))
+				.unwrap();
+			assert_eq!(
+				values[0].value,
+				Some(Variant::NodeId(Box::new(data_type.into()))),
+				"DataType of type {var_type}"
+			);
+			assert_eq!(
+				values[1].status,
+				Some(StatusCode::BadWaitingForInitialData),
+				"status of type {var_type}"
+			);
+			let value_type = values[1].value.as_ref().and_then(|v| v.scalar_type_id());
+			assert_eq!(value_type, Some(scalar_type), "Value of type {var_type}");
+		}
+	}
+
+	#[test]
+	fn bulk_added_variables_are_unique_per_namespace() {
+		let server = TestServer::start();
+		assert_eq!(server.add_variable("Tank1", 11), NO_ERR);
+		// (error, results) of adding Double variables of the ids
+		let add = |ns: u16, nodes: &[&str]| unsafe {
+			let defs = new_lv_array(nodes.iter().map(|node| LvVariableDef {
+				node_id: string_to_new_lstr(node),
+				browse_name: string_to_new_lstr(node),
+				display_name: string_to_new_lstr(node),
+				var_type: 11,
+			}));
+			let mut results = vec![-1; nodes.len()];
+			let mut folder = server.objects_folder.clone();
+			let err = lv_add_variables_bulk(
+				server.manager_ptr,
+				&mut folder,
+				ns,
+				defs,
+				results.as_mut_ptr(),
+			);
+			for def in lv_array_as_slice(defs) {
+				for s in [def.node_id, def.browse_name, def.display_name] {
+					DSDisposeHandleLStr(s);
+				}
+			}
+			DSDisposeHandle(defs);
+			(err, results)
+		};
+
+		let (err, results) = add(server.ns, &["Tank1", "Tank2", "Tank2", "Tank3"]);
+		assert_eq!(err, NO_ERR);
+		assert_eq!(results, [ERR_NODE_EXISTS, NO_ERR, ERR_NODE_EXISTS, NO_ERR]);
+
+		// The same ids in another namespace of the manager are other nodes
+		let other_ns = server.ns + 1;
+		assert_eq!(add(other_ns, &["Tank1"]).0, ERR_NAMESPACE_NOT_FOUND);
+		let manager = unsafe { &*server.manager_ptr };
+		manager
+			.address_space()
+			.write()
+			.add_namespace("urn:opcua-dll-test-other", other_ns);
+		let (err, results) = add(other_ns, &["Tank1", "Tank2", "Tank2"]);
+		assert_eq!(err, NO_ERR);
+		assert_eq!(results, [NO_ERR, NO_ERR, ERR_NODE_EXISTS]);
+	}
+
+	#[test]
+	fn narrowing_writes_are_range_checked() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		assert_eq!(server.add_variable("U64Var", 9), NO_ERR);
+		assert_eq!(server.add_variable("I8Var", 2), NO_ERR);
+		let (u64_node, i8_node) = (
+			CString::new("U64Var").unwrap(),
+			CString::new("I8Var").unwrap(),
+		);
+		let read = |node: &str| {
+			let read_value_id = ReadValueId {
+				node_id: NodeId::new(server.ns, node.to_string()),
+				attribute_id: AttributeId::Value as u32,
+				..Default::default()
+			};
+			let session = client.session();
+			let values = client
+				.runtime()
+				.block_on(session.read(&[read_value_id], TimestampsToReturn::Neither, 0.0))
+				.unwrap();
+			values[0].value.clone()
+		};
+		let (manager, handle) = (server.manager_ptr, server.handle_ptr);
+
+		let max = i64::MAX as u64;
+		assert_eq!(
+			lv_write_variableUInt64(u64_node.as_ptr(), server.ns, max, manager, handle),
+			NO_ERR
+		);
+		assert_eq!(read("U64Var"), Some(Variant::Int64(i64::MAX)));
+		let err = lv_write_variableUInt64(u64_node.as_ptr(), server.ns, max + 1, manager, handle);
+		assert_eq!(err, ERR_INVALID_ARGUMENT);
+		let err = lv_write_variable_with_timestamp_UInt64(
+			u64_node.as_ptr(),
+			server.ns,
+			u64::MAX,
+			0.0,
+			0,
+			manager,
+			handle,
+		);
+		assert_eq!(err, ERR_INVALID_ARGUMENT);
+		assert_eq!(read("U64Var"), Some(Variant::Int64(i64::MAX)));
+
+		assert_eq!(
+			lv_write_variableInt32(i8_node.as_ptr(), server.ns, -128, manager, handle),
+			NO_ERR
+		);
+		assert_eq!(read("I8Var"), Some(Variant::SByte(-128)));
+		let err = lv_write_variableInt32(i8_node.as_ptr(), server.ns, 128, manager, handle);
+		assert_eq!(err, ERR_INVALID_ARGUMENT);
+		assert_eq!(read("I8Var"), Some(Variant::SByte(-128)));
+	}
+
+	// The flags are per thread in the tests, reset on return
+	fn with_write_validation(flags: u32, f: impl FnOnce()) {
+		assert_eq!(lv_set_write_validation(flags), NO_ERR);
+		f();
+		assert_eq!(lv_set_write_validation(WRITE_VALIDATION_NONE), NO_ERR);
+	}
+
+	// (error, results) of lv_write_variables_bulk
+	fn write_bulk(server: &TestServer, values: &[(&str, f64)]) -> (i32, Vec<i32>) {
+		unsafe {
+			let values_in = new_lv_array(values.iter().map(|(node, value)| LvVariableValue {
+				node_id: string_to_new_lstr(node),
+				value: *value,
+			}));
+			let mut results = vec![-1; values.len()];
+			let err = lv_write_variables_bulk(
+				server.manager_ptr,
+				server.handle_ptr,
+				server.ns,
+				values_in,
+				results.as_mut_ptr(),
+			);
+			for value in lv_array_as_slice(values_in) {
+				DSDisposeHandleLStr(value.node_id);
+			}
+			DSDisposeHandle(values_in);
+			(err, results)
+		}
+	}
+
+	#[test]
+	fn write_validation_rejects_nan_and_inf() {
+		let server = TestServer::start();
+		assert_eq!(server.add_variable("DoubleVar", 11), NO_ERR);
+		assert_eq!(server.add_variable("FloatVar", 10), NO_ERR);
+		let (double_node, float_node) = (
+			CString::new("DoubleVar").unwrap(),
+			CString::new("FloatVar").unwrap(),
+		);
+		let (manager, handle) = (server.manager_ptr, server.handle_ptr);
+		let write_double = |value: f64| {
+			lv_write_variableDouble(double_node.as_ptr(), server.ns, value, manager, handle)
+		};
+		let write_float = |value: f32| {
+			lv_write_variableFloat(float_node.as_ptr(), server.ns, value, manager, handle)
+		};
+
+		assert_eq!(lv_set_write_validation(4), ERR_INVALID_ARGUMENT);
+		// Unchecked by default
+		assert_eq!(write_double(f64::NAN), NO_ERR);
+		assert_eq!(write_float(f32::INFINITY), NO_ERR);
+
+		with_write_validation(WRITE_VALIDATION_REJECT_NAN_INF, || {
+			for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+				assert_eq!(write_double(value), ERR_INVALID_ARGUMENT, "{value}");
+			}
+			assert_eq!(write_float(f32::NAN), ERR_INVALID_ARGUMENT);
+			assert_eq!(write_double(1.5), NO_ERR);
+			assert_eq!(write_float(-1.5), NO_ERR);
+
+			let (err, results) = write_bulk(&server, &[("DoubleVar", f64::NAN), ("FloatVar", 2.5)]);
+			assert_eq!(err, NO_ERR);
+			assert_eq!(results, [ERR_INVALID_ARGUMENT, NO_ERR]);
+		});
+	}
+
+	#[test]
+	fn write_validation_checks_the_data_type() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		for (node, var_type) in [
+			("I8Var", 2),
+			("I32Var", 6),
+			("U64Var", 9),
+			("DoubleVar", 11),
+		] {
+			assert_eq!(server.add_variable(node, var_type), NO_ERR);
+		}
+		let node = |node: &str| CString::new(node).unwrap();
+		let (i8_node, i32_node, u64_node, double_node) = (
+			node("I8Var"),
+			node("I32Var"),
+			node("U64Var"),
+			node("DoubleVar"),
+		);
+		let (manager, handle) = (server.manager_ptr, server.handle_ptr);
+		let read = |node: &str| {
+			let read_value_id = ReadValueId::from(NodeId::new(server.ns, node.to_string()));
+			let session = client.session();
+			let values = client
+				.runtime()
+				.block_on(session.read(&[read_value_id], TimestampsToReturn::Neither, 0.0))
+				.unwrap();
+			values[0].value.clone()
+		};
+		let mismatch = StatusCode::BadTypeMismatch.bits() as i32;
+
+		with_write_validation(WRITE_VALIDATION_CHECK_DATA_TYPE, || {
+			// Not narrowed to the SByte of the variable
+			let err = lv_write_variableInt32(i8_node.as_ptr(), server.ns, 5, manager, handle);
+			assert_eq!(err, mismatch);
+			let err = lv_write_variableDouble(i32_node.as_ptr(), server.ns, 5.0, manager, handle);
+			assert_eq!(err, mismatch);
+			let err = lv_write_variableInt32(double_node.as_ptr(), server.ns, 5, manager, handle);
+			assert_eq!(err, mismatch);
+			assert_eq!(read("I8Var"), Some(Variant::SByte(0)));
+			assert_eq!(read("I32Var"), Some(Variant::Int32(0)));
+
+			let err = lv_write_variableInt32(i32_node.as_ptr(), server.ns, 7, manager, handle);
+			assert_eq!(err, NO_ERR);
+			assert_eq!(read("I32Var"), Some(Variant::Int32(7)));
+			// U64 is the type of the Int64 variables of type 9
+			let err = lv_write_variableUInt64(u64_node.as_ptr(), server.ns, 9, manager, handle);
+			assert_eq!(err, NO_ERR);
+			assert_eq!(read("U64Var"), Some(Variant::Int64(9)));
+			let unknown = node("NoSuchVar");
+			let err = lv_write_variableInt32(unknown.as_ptr(), server.ns, 1, manager, handle);
+			assert_eq!(err, StatusCode::BadNodeIdUnknown.bits() as i32);
+
+			let (err, results) = write_bulk(&server, &[("I32Var", 8.0), ("DoubleVar", 8.0)]);
+			assert_eq!(err, NO_ERR);
+			assert_eq!(results, [mismatch, NO_ERR]);
+		});
+
+		// Cast to the DataType of the variable without the check
+		let (err, results) = write_bulk(&server, &[("I32Var", 8.0)]);
+		assert_eq!((err, results[0]), (NO_ERR, NO_ERR));
+		assert_eq!(read("I32Var"), Some(Variant::Int32(8)));
+		let err = lv_write_variableInt32(i8_node.as_ptr(), server.ns, 5, manager, handle);
+		assert_eq!(err, NO_ERR);
+		assert_eq!(read("I8Var"), Some(Variant::SByte(5)));
+	}
+
+	#[test]
+	fn guid_round_trip() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		assert_eq!(server.add_variable("GuidVar", 14), NO_ERR);
+		let node = CString::new("GuidVar").unwrap();
+		let write = |text: &str| {
+			let text = CString::new(text).unwrap();
+			lv_write_variableGuid(
+				node.as_ptr(),
+				server.ns,
+				text.as_ptr(),
+				server.manager_ptr,
+				server.handle_ptr,
+			)
+		};
+
+		let text = "{72962B91-FA75-4AE6-8D28-B404DC7DAF63}";
+		assert_eq!(write(text), NO_ERR);
+		for invalid in [
+			"72962b91-fa75-4ae6-8d28-b404dc7daf63",
+			"{72962b91-fa75-4ae6-8d28-b404dc7daf63",
+			"72962b91-fa75-4ae6-8d28-b404dc7daf63}",
+			"{72962b91-fa75-4ae6-8d28}",
+			"{}",
+		] {
+			assert_eq!(write(invalid), ERR_INVALID_ARGUMENT, "{invalid}");
+		}
+
+		unsafe {
+			let read = string_to_new_lstr("");
+			let err = lv_read_variableGuid(
+				client.rt_ptr,
+				client.session_ptr,
+				server.ns,
+				node.as_ptr(),
+				read,
+			);
+			assert_eq!(err, NO_ERR);
+			let read_text = lstr_to_string(read);
+			DSDisposeHandleLStr(read);
+			assert!(read_text.eq_ignore_ascii_case(text), "{read_text}");
+			// Reads back what it writes
+			let again = CString::new(read_text).unwrap();
+			assert_eq!(
+				lv_write_variableGuid(
+					node.as_ptr(),
+					server.ns,
+					again.as_ptr(),
+					server.manager_ptr,
+					server.handle_ptr,
+				),
+				NO_ERR
+			);
+		}
+	}
+
+	#[test]
+	fn concurrent_writes_with_subscription() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		assert_eq!(server.add_variable("StressVar", 11), NO_ERR);
+		let node_id = NodeId::new(server.ns, "StressVar");
+
+		let (tx, mut rx) = mpsc::unbounded_channel();
+		let session = client.session().clone();
+		client.runtime().block_on(async {
+			let sub_id = session
+				.create_subscription(
+					Duration::from_millis(50),
+					100,
+					20,
+					1000,
+					0,
+					true,
+					DataChangeCallback::new(move |dv, _| {
+						let _ = tx.send(dv.value);
+					}),
+				)
+				.await
+				.unwrap();
+			let res = session
+				.create_monitored_items(
+					sub_id,
+					TimestampsToReturn::Both,
+					vec![MonitoredItemCreateRequest {
+						item_to_monitor: ReadValueId {
+							node_id: node_id.clone(),
+							attribute_id: AttributeId::Value as u32,
+							..Default::default()
+						},
+						monitoring_mode: MonitoringMode::Reporting,
+						requested_parameters: MonitoringParameters {
+							sampling_interval: 0.0,
+							queue_size: 100,
+							discard_oldest: true,
+							..Default::default()
+						},
+					}],
+				)
+				.await
+				.unwrap();
+			assert_eq!(res[0].status_code, StatusCode::Good);
+		});
+
+		// Two LabVIEW loops writing the variable at once
+		const WRITES: i32 = 1000;
+		let writers: Vec<_> = [1.0, -1.0]
+			.into_iter()
+			.map(|sign| {
+				let (manager, handle) = (server.manager_ptr as usize, server.handle_ptr as usize);
+				let ns = server.ns;
+				std::thread::spawn(move || {
+					let node = CString::new("StressVar").unwrap();
+					for i in 1..=WRITES {
+						let err = lv_write_variableDouble(
+							node.as_ptr(),
+							ns,
+							sign * i as f64,
+							manager as *mut _,
+							handle as *mut _,
+						);
+						assert_eq!(err, NO_ERR);
+					}
+				})
+			})
+			.collect();
+		for writer in writers {
+			writer.join().unwrap();
+		}
+
+		// The final value is the last write of one of the writers,
+		// and the subscription catches up to it
+		let node = CString::new("StressVar").unwrap();
+		let mut last = 0.0;
+		let err = unsafe {
+			lv_read_variableDouble(
+				client.rt_ptr,
+				client.session_ptr,
+				node.as_ptr(),
+				server.ns,
+				&mut last,
+			)
+		};
+		assert_eq!(err, NO_ERR);
+		assert!(last == WRITES as f64 || last == -WRITES as f64);
+		client.runtime().block_on(async {
+			loop {
+				let value = timeout(Duration::from_secs(2), rx.recv())
+					.await
+					.expect("Last value of the writes not notified")
+					.unwrap();
+				if value == Some(Variant::Double(last)) {
+					break;
+				}
+			}
+		});
+	}
+}
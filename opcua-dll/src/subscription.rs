@@ -0,0 +1,2406 @@
+//==============================================================================
+//
+// Title:		OPC UA Subscriptions wrapper
+// Purpose:		Monitored items and subscription management
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+use crate::client::read_value_id;
+use crate::errors::*;
+use crate::labview::{
+	DSDisposeHandle, DSDisposeHandleLStr, LStrArrayHandle, LStrHandle, LvArrayHandle, M_FULL_ERR,
+	PostLVUserEvent, bytes_to_new_lstr, dispose_lstr_array, lstr_array_to_strings,
+	lv_array_as_slice, new_lv_array, string_to_new_lstr, strings_to_new_lstr_array, write_lv_array,
+};
+use crate::server_variables::lv_data_type;
+use crate::utils::date_time_to_cocoa;
+
+use libc::c_char;
+use opcua::{
+	client::{
+		MonitoredItem, OnSubscriptionNotification, Session, SubscriptionTransferResult, UARequest,
+		services::ModifySubscription,
+	},
+	types::{
+		AttributeId, ByteString, ContentFilter, ContentFilterElement, DataChangeFilter,
+		DataChangeTrigger, DataValue, DeadbandType, EventFilter, ExtensionObject, FilterOperator,
+		LocalizedText, MethodId, ModifySubscriptionResponse, MonitoredItemCreateRequest,
+		MonitoredItemModifyRequest, MonitoringMode, MonitoringParameters, NodeId, ObjectTypeId,
+		Operand, ReadValueId, SimpleAttributeOperand, StatusCode, TimestampsToReturn, Variant,
+	},
+};
+use std::{
+	collections::{HashMap, HashSet, VecDeque},
+	ffi::c_void,
+	sync::{
+		Arc, LazyLock, Mutex,
+		atomic::{AtomicU32, Ordering},
+	},
+	time::Duration,
+};
+use tokio::runtime::Runtime;
+
+// Well-known BaseEventType fields used when LabVIEW doesn't supply the list
+const DEFAULT_EVENT_FIELDS: [&str; 5] = ["EventId", "EventType", "Severity", "Message", "Time"];
+
+//==============================================================================
+// Notification routing.
+// The callback is given per subscription, so each subscription created by
+// lv_create_subscription() holds a table client_handle -> LabVIEW user event.
+// Items not found in the table are posted to the subscription's own event.
+//
+#[derive(Clone, Copy)]
+enum ValueKind {
+	// LabVIEW type id of lv_add_variable, see the table of lv_data_type()
+	LvType(u16),
+	String,
+	DateTime,
+	NodeId,
+}
+
+#[derive(Clone)]
+enum ItemSink {
+	Event {
+		user_event_ref: usize,
+		field_count: usize,
+	},
+	// Values of one type posted to the item's own event
+	Value {
+		user_event_ref: usize,
+		kind: ValueKind,
+	},
+}
+
+struct SubscriptionSinks {
+	user_event_ref: usize, // data changes, 0 if not used
+	items: HashMap<u32, ItemSink>,
+	batch: Option<NotificationBatch>,
+}
+
+// Data change queued in the batching mode
+struct QueuedNotification {
+	node_id: String,
+	value: String,
+	status: u32,
+	timestamp: f64, // Cocoa
+}
+
+//==============================================================================
+// Batching mode of lv_set_subscription_batching: the data changes, which
+// would be posted one by one to the subscription's event, are queued in a ring
+// buffer instead, and either posted at the end of each publish cycle
+// (user_event_ref != 0) or dequeued by LabVIEW.
+// dropped counts the notifications lost to overflow since last reported
+//
+struct NotificationBatch {
+	queue: VecDeque<QueuedNotification>,
+	capacity: usize,
+	drop_oldest: bool,
+	dropped: u64,
+	user_event_ref: usize,
+}
+
+impl NotificationBatch {
+	fn push(&mut self, notification: QueuedNotification) {
+		if self.queue.len() >= self.capacity {
+			self.dropped += 1;
+			if !self.drop_oldest {
+				return;
+			}
+			self.queue.pop_front();
+		}
+		self.queue.push_back(notification);
+	}
+
+	// A smaller capacity drops the notifications a full queue would
+	fn set_capacity(&mut self, capacity: usize) {
+		self.capacity = capacity;
+		let excess = self.queue.len().saturating_sub(capacity);
+		if self.drop_oldest {
+			self.queue.drain(..excess);
+		} else {
+			self.queue.truncate(capacity);
+		}
+		self.dropped += excess as u64;
+	}
+
+	fn take_dropped(&mut self) -> u32 {
+		let dropped = self.dropped.min(u32::MAX as u64) as u32;
+		self.dropped -= dropped as u64;
+		dropped
+	}
+}
+
+type SinksRef = Arc<Mutex<SubscriptionSinks>>;
+
+// Handles given by the DLL, far from the ones the library assigns (from 1000)
+static NEXT_CLIENT_HANDLE: AtomicU32 = AtomicU32::new(0x8000_0000);
+
+// (session, subscription id) -> sinks, ids are unique per session only
+static SUBSCRIPTIONS: LazyLock<Mutex<HashMap<(usize, u32), SinksRef>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// (session, subscription id given to LabVIEW) -> id after lv_reconnect_session
+// created the subscription again
+static RECREATED_IDS: LazyLock<Mutex<HashMap<(usize, u32), u32>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn session_key(session: &Arc<Session>) -> usize {
+	Arc::as_ptr(session) as usize
+}
+
+fn subscription_sinks(session: &Arc<Session>, sub_id: u32) -> Option<SinksRef> {
+	SUBSCRIPTIONS
+		.lock()
+		.unwrap()
+		.get(&(session_key(session), sub_id))
+		.cloned()
+}
+
+// Client handles of the monitored items of the subscription, also the ones
+// without a sink of their own
+fn monitored_client_handles(session: &Session, sub_id: u32) -> HashSet<u32> {
+	let state = session.subscription_state().lock();
+	state
+		.get(sub_id)
+		.map(|sub| {
+			sub.monitored_items()
+				.values()
+				.map(|item| item.client_handle())
+				.collect()
+		})
+		.unwrap_or_default()
+}
+
+pub fn unregister_subscription(session: &Arc<Session>, sub_id: u32) {
+	SUBSCRIPTIONS
+		.lock()
+		.unwrap()
+		.remove(&(session_key(session), sub_id));
+}
+
+// All subscriptions of the closed session, its address may be reused
+pub fn unregister_session(session: &Arc<Session>) {
+	let key = session_key(session);
+	SUBSCRIPTIONS
+		.lock()
+		.unwrap()
+		.retain(|(session, _), _| *session != key);
+	RECREATED_IDS
+		.lock()
+		.unwrap()
+		.retain(|(session, _), _| *session != key);
+}
+
+// Move the routing of the subscription to the new session (and id)
+fn move_subscription(
+	old_session: &Arc<Session>,
+	new_session: &Arc<Session>,
+	sub_id: u32,
+	new_id: u32,
+) {
+	let mut subscriptions = SUBSCRIPTIONS.lock().unwrap();
+	if let Some(sinks) = subscriptions.remove(&(session_key(old_session), sub_id)) {
+		subscriptions.insert((session_key(new_session), new_id), sinks);
+	}
+}
+
+// Follow the subscriptions which the library created again with new ids on
+// reconnect: routing and the ids for lv_get_reconnected_subscription_id()
+pub fn remap_recreated_subscriptions(session: &Arc<Session>) {
+	let key = session_key(session);
+	let mut recreated_ids = RECREATED_IDS.lock().unwrap();
+	for (old_id, new_id) in session.take_recreated_subscriptions() {
+		move_subscription(session, session, old_id, new_id);
+		// Ids of earlier reconnects
+		for ((session, _), id) in recreated_ids.iter_mut() {
+			if *session == key && *id == old_id {
+				*id = new_id;
+			}
+		}
+		recreated_ids.entry((key, old_id)).or_insert(new_id);
+	}
+}
+
+// Data change posted to the subscription's user event
+#[repr(C)]
+struct LvDataChange {
+	client_handle: u32,
+	status: u32,
+	value: f64,
+	timestamp: f64, // Cocoa
+}
+
+// Value as text, as shown in LabVIEW
+pub fn variant_to_string(v: &Variant) -> String {
+	match v {
+		Variant::Empty => String::new(),
+		Variant::ByteString(b) => b.as_base64(),
+		Variant::LocalizedText(t) => t.text.to_string(),
+		Variant::QualifiedName(q) => q.name.to_string(),
+		Variant::StatusCode(s) => format!("{}", s),
+		v => format!("{}", v),
+	}
+}
+
+pub fn variant_to_f64(v: &Variant) -> f64 {
+	match v {
+		Variant::Boolean(v) => *v as u8 as f64,
+		Variant::SByte(v) => *v as f64,
+		Variant::Byte(v) => *v as f64,
+		Variant::Int16(v) => *v as f64,
+		Variant::UInt16(v) => *v as f64,
+		Variant::Int32(v) => *v as f64,
+		Variant::UInt32(v) => *v as f64,
+		Variant::Int64(v) => *v as f64,
+		Variant::UInt64(v) => *v as f64,
+		Variant::Float(v) => *v as f64,
+		Variant::Double(v) => *v,
+		_ => f64::NAN,
+	}
+}
+
+fn post_data_change(user_event_ref: usize, dv: &DataValue, item: &MonitoredItem) {
+	if user_event_ref == 0 {
+		return;
+	}
+	let mut data = LvDataChange {
+		client_handle: item.client_handle(),
+		status: dv.status().bits(),
+		value: dv.value.as_ref().map(variant_to_f64).unwrap_or(f64::NAN),
+		timestamp: dv
+			.source_timestamp
+			.or(dv.server_timestamp)
+			.map(|t| date_time_to_cocoa(&t))
+			.unwrap_or(0.0),
+	};
+	unsafe {
+		PostLVUserEvent(
+			user_event_ref as *mut c_void,
+			&mut data as *mut LvDataChange as *mut c_void,
+		);
+	}
+}
+
+// PostLVUserEvent copies the string into the event data, LabVIEW owns the copy
+// and the handle is disposed here
+fn post_string(user_event_ref: usize, s: &str) {
+	post_bytes(user_event_ref, s.as_bytes());
+}
+
+// Bytes as LabVIEW string (ByteString values)
+fn post_bytes(user_event_ref: usize, bytes: &[u8]) {
+	unsafe {
+		let mut handle = bytes_to_new_lstr(bytes);
+		if !handle.is_null() {
+			PostLVUserEvent(
+				user_event_ref as *mut c_void,
+				&mut handle as *mut LStrHandle as *mut c_void,
+			);
+			DSDisposeHandleLStr(handle);
+		}
+	}
+}
+
+fn post_scalar<T: Copy>(user_event_ref: usize, mut value: T) {
+	unsafe {
+		PostLVUserEvent(
+			user_event_ref as *mut c_void,
+			&mut value as *mut T as *mut c_void,
+		);
+	}
+}
+
+// Value as the LabVIEW type id of lv_add_variable (Boolean as U8, String as
+// string), values of another type are not posted
+fn post_lv_typed(user_event_ref: usize, var_type: u16, v: &Variant) {
+	match (var_type, v) {
+		(1, Variant::Boolean(v)) => post_scalar(user_event_ref, *v as u8),
+		(2, Variant::SByte(v)) => post_scalar(user_event_ref, *v),
+		(3, Variant::Byte(v)) => post_scalar(user_event_ref, *v),
+		(4, Variant::Int16(v)) => post_scalar(user_event_ref, *v),
+		(5, Variant::UInt16(v)) => post_scalar(user_event_ref, *v),
+		(6, Variant::Int32(v)) => post_scalar(user_event_ref, *v),
+		(7, Variant::UInt32(v)) => post_scalar(user_event_ref, *v),
+		(8, Variant::Int64(v)) => post_scalar(user_event_ref, *v),
+		(9, Variant::UInt64(v)) => post_scalar(user_event_ref, *v),
+		(9, Variant::Int64(v)) if *v >= 0 => post_scalar(user_event_ref, *v as u64),
+		(10, Variant::Float(v)) => post_scalar(user_event_ref, *v),
+		(11, Variant::Double(v)) => post_scalar(user_event_ref, *v),
+		(12, Variant::String(s)) => post_string(user_event_ref, s.as_ref()),
+		(13, Variant::DateTime(t)) => post_scalar(user_event_ref, date_time_to_cocoa(t)),
+		(14, Variant::Guid(g)) => post_string(user_event_ref, &format!("{{{g}}}")),
+		(15, Variant::ByteString(b)) => post_bytes(user_event_ref, b.as_ref()),
+		(29, Variant::Int32(v)) => post_scalar(user_event_ref, *v),
+		_ => {}
+	}
+}
+
+// Values of another type (Bad status without value too) are not posted
+fn post_value(user_event_ref: usize, kind: ValueKind, dv: &DataValue) {
+	match (kind, dv.value.as_ref()) {
+		(ValueKind::LvType(var_type), Some(v)) => post_lv_typed(user_event_ref, var_type, v),
+		(ValueKind::String, Some(Variant::String(s))) => post_string(user_event_ref, s.as_ref()),
+		(ValueKind::NodeId, Some(Variant::NodeId(id))) => {
+			post_string(user_event_ref, &id.to_string())
+		}
+		(ValueKind::DateTime, Some(Variant::DateTime(t))) => {
+			post_scalar(user_event_ref, date_time_to_cocoa(t))
+		}
+		_ => {}
+	}
+}
+
+// Event fields in the order of select clauses, missing ones as empty strings
+fn post_event(user_event_ref: usize, field_count: usize, fields: Option<Vec<Variant>>) {
+	let fields = fields.unwrap_or_default();
+	let strings: Vec<String> = (0..field_count)
+		.map(|i| fields.get(i).map(variant_to_string).unwrap_or_default())
+		.collect();
+	unsafe {
+		let mut lv_array = strings_to_new_lstr_array(&strings);
+		if !lv_array.is_null() {
+			PostLVUserEvent(
+				user_event_ref as *mut c_void,
+				&mut lv_array as *mut LStrArrayHandle as *mut c_void,
+			);
+			dispose_lstr_array(lv_array);
+		}
+	}
+}
+
+fn queued_notification(dv: &DataValue, item: &MonitoredItem) -> QueuedNotification {
+	QueuedNotification {
+		node_id: item.item_to_monitor().node_id.to_string(),
+		value: dv.value.as_ref().map(variant_to_string).unwrap_or_default(),
+		status: dv.status().bits(),
+		timestamp: dv
+			.source_timestamp
+			.or(dv.server_timestamp)
+			.map(|t| date_time_to_cocoa(&t))
+			.unwrap_or(0.0),
+	}
+}
+
+// Notification of lv_dequeue_notifications and the batch events
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LvNotification {
+	node_id: LStrHandle,
+	value: LStrHandle, // as text
+	status: u32,
+	timestamp: f64, // Cocoa
+}
+
+#[cfg(target_arch = "x86")]
+#[repr(C, packed(1))]
+#[derive(Clone, Copy)]
+pub struct LvNotification {
+	node_id: LStrHandle,
+	value: LStrHandle,
+	status: u32,
+	timestamp: f64,
+}
+
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+struct LvNotificationBatch {
+	dropped: u32,
+	notifications: LvArrayHandle<LvNotification>,
+}
+
+#[cfg(target_arch = "x86")]
+#[repr(C, packed(1))]
+struct LvNotificationBatch {
+	dropped: u32,
+	notifications: LvArrayHandle<LvNotification>,
+}
+
+fn to_lv_notification(notification: &QueuedNotification) -> LvNotification {
+	unsafe {
+		LvNotification {
+			node_id: string_to_new_lstr(&notification.node_id),
+			value: string_to_new_lstr(&notification.value),
+			status: notification.status,
+			timestamp: notification.timestamp,
+		}
+	}
+}
+
+unsafe fn dispose_notification(notification: &LvNotification) {
+	unsafe {
+		for s in [notification.node_id, notification.value] {
+			if !s.is_null() {
+				DSDisposeHandleLStr(s);
+			}
+		}
+	}
+}
+
+// Strings of the notifications, the array itself is kept
+unsafe fn dispose_notification_strings(handle: LvArrayHandle<LvNotification>) {
+	unsafe {
+		lv_array_as_slice(handle)
+			.iter()
+			.for_each(|n| dispose_notification(n))
+	}
+}
+
+// All strings allocated up front, None on a memory error (with none left)
+fn to_lv_notifications<'a>(
+	notifications: impl Iterator<Item = &'a QueuedNotification>,
+) -> Option<Vec<LvNotification>> {
+	let lv_notifications: Vec<_> = notifications.map(to_lv_notification).collect();
+	if lv_notifications
+		.iter()
+		.all(|n| !n.node_id.is_null() && !n.value.is_null())
+	{
+		return Some(lv_notifications);
+	}
+	unsafe {
+		lv_notifications
+			.iter()
+			.for_each(|n| dispose_notification(n))
+	};
+	None
+}
+
+// All queued notifications as one event, posted even if only dropped ones
+fn post_batch(batch: &mut NotificationBatch) {
+	if batch.queue.is_empty() && batch.dropped == 0 {
+		return;
+	}
+	// On a LabVIEW memory error the queue is posted with the next cycle
+	let Some(items) = to_lv_notifications(batch.queue.iter()) else {
+		return;
+	};
+	unsafe {
+		let notifications = new_lv_array(items.iter().copied());
+		if notifications.is_null() {
+			items.iter().for_each(|n| dispose_notification(n));
+			return;
+		}
+		batch.queue.clear();
+		let mut data = LvNotificationBatch {
+			dropped: batch.take_dropped(),
+			notifications,
+		};
+		PostLVUserEvent(
+			batch.user_event_ref as *mut c_void,
+			&mut data as *mut LvNotificationBatch as *mut c_void,
+		);
+		dispose_notification_strings(notifications);
+		DSDisposeHandle(notifications);
+	}
+}
+
+struct LvSubscriptionCallbacks {
+	sinks: SinksRef,
+}
+
+impl OnSubscriptionNotification for LvSubscriptionCallbacks {
+	fn on_data_value(&mut self, dv: DataValue, item: &MonitoredItem) {
+		let mut guard = self.sinks.lock().unwrap();
+		let sinks = &mut *guard;
+		match sinks.items.get(&item.client_handle()) {
+			Some(ItemSink::Event { .. }) => {}
+			Some(ItemSink::Value {
+				user_event_ref,
+				kind,
+			}) => post_value(*user_event_ref, *kind, &dv),
+			None => match sinks.batch.as_mut() {
+				Some(batch) => batch.push(queued_notification(&dv, item)),
+				None => post_data_change(sinks.user_event_ref, &dv, item),
+			},
+		}
+	}
+
+	fn on_event(&mut self, fields: Option<Vec<Variant>>, item: &MonitoredItem) {
+		let sink = self
+			.sinks
+			.lock()
+			.unwrap()
+			.items
+			.get(&item.client_handle())
+			.cloned();
+		if let Some(ItemSink::Event {
+			user_event_ref,
+			field_count,
+		}) = sink
+		{
+			post_event(user_event_ref, field_count, fields);
+		}
+	}
+
+	// End of the publish cycle
+	fn on_notification_end(&mut self) {
+		let mut sinks = self.sinks.lock().unwrap();
+		if let Some(batch) = sinks.batch.as_mut() {
+			if batch.user_event_ref != 0 {
+				post_batch(batch);
+			}
+		}
+	}
+}
+
+//==============================================================================
+// Copy u32 values into the malloc'ed buffer, which shall be released
+// with lv_free_u32_array() (LabVIEW can't free Rust allocations)
+//
+fn to_malloc_u32_array(values: &[u32]) -> *mut u32 {
+	unsafe {
+		let size = std::cmp::max(values.len(), 1) * std::mem::size_of::<u32>();
+		let ptr = libc::malloc(size) as *mut u32;
+		if !ptr.is_null() {
+			std::ptr::copy_nonoverlapping(values.as_ptr(), ptr, values.len());
+		}
+		ptr
+	}
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_free_u32_array(ptr: *mut u32) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(ptr, ERR_NULL_POINTER);
+		unsafe {
+			libc::free(ptr as *mut libc::c_void);
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Get monitored items of the subscription (Server.GetMonitoredItems method)
+// Both arrays are allocated here, call lv_free_u32_array() for each of them
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_monitored_items(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	sub_id: u32,
+	server_handles_out: *mut *mut u32,
+	client_handles_out: *mut *mut u32,
+	count_out: *mut i32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(server_handles_out, ERR_NULL_POINTER);
+		check_null!(client_handles_out, ERR_NULL_POINTER);
+		check_null!(count_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+
+			let r = rt.block_on(async { session.call_get_monitored_items(sub_id).await });
+			match r {
+				Ok((server_handles, client_handles)) => {
+					*server_handles_out = to_malloc_u32_array(&server_handles);
+					*client_handles_out = to_malloc_u32_array(&client_handles);
+					*count_out = server_handles.len() as i32;
+					NO_ERR
+				}
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Create subscription, data changes of its items are posted to user_event_ref
+// as cluster {client_handle: U32, status: U32, value: DBL, timestamp: DBL}.
+// user_event_ref may be null if only events are monitored
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_create_subscription(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	publishing_interval_ms: f64,
+	user_event_ref: *mut c_void,
+	sub_id_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(sub_id_out, ERR_NULL_POINTER);
+		if !(publishing_interval_ms > 0.0) {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+
+			let sinks = Arc::new(Mutex::new(SubscriptionSinks {
+				user_event_ref: user_event_ref as usize, // raw pointers are not Send
+				items: HashMap::new(),
+				batch: None,
+			}));
+			let callbacks = LvSubscriptionCallbacks {
+				sinks: sinks.clone(),
+			};
+
+			let r = rt.block_on(async {
+				session
+					.create_subscription(
+						Duration::from_secs_f64(publishing_interval_ms / 1000.0),
+						30,
+						10,
+						0,
+						0,
+						true,
+						callbacks,
+					)
+					.await
+			});
+			match r {
+				Ok(sub_id) => {
+					SUBSCRIPTIONS
+						.lock()
+						.unwrap()
+						.insert((session_key(session), sub_id), sinks);
+					*sub_id_out = sub_id;
+					NO_ERR
+				}
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Batching mode of the subscription created with lv_create_subscription():
+// enabled != 0 queues the data changes of the items without own event (as of
+// lv_subscribe_data_with_deadband) in a ring buffer of capacity notifications
+// instead of posting each to the subscription's event.
+// user_event_ref != 0 posts the queue at the end of each publish cycle as
+// cluster {dropped: U32, notifications: array of {node_id: string,
+// value: string, status: U32, timestamp: DBL}}, null leaves the queue to
+// lv_dequeue_notifications.
+// A full queue drops the oldest (drop_oldest != 0) or the new notification,
+// counted in dropped, a smaller capacity drops the oldest or newest ones.
+// enabled = 0 discards the queue and posts one by one again
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_subscription_batching(
+	session_in: *mut Arc<Session>,
+	sub_id: u32,
+	enabled: u8,
+	capacity: u32,
+	drop_oldest: u8,
+	user_event_ref: *mut c_void,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		if enabled != 0 && capacity == 0 {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		let session = unsafe { &*session_in };
+		let Some(sinks) = subscription_sinks(session, sub_id) else {
+			return ERR_INVALID_ARGUMENT; // not created by lv_create_subscription
+		};
+		let mut sinks = sinks.lock().unwrap();
+		if enabled == 0 {
+			sinks.batch = None;
+			return NO_ERR;
+		}
+		// Changing the settings keeps what is queued
+		let batch = sinks.batch.get_or_insert_with(|| NotificationBatch {
+			queue: VecDeque::new(),
+			capacity: 0,
+			drop_oldest: true,
+			dropped: 0,
+			user_event_ref: 0,
+		});
+		batch.drop_oldest = drop_oldest != 0;
+		batch.set_capacity(capacity as usize);
+		batch.user_event_ref = user_event_ref as usize; // raw pointers are not Send
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Take up to max_items (0 - all) queued notifications of the subscription in
+// batching mode, oldest first, into array_out (array of {node_id: string,
+// value: string, status: U32, timestamp: DBL}, the strings of its previous
+// content are disposed). dropped_count_out receives the notifications lost
+// to overflow since the last call, growing counts mean polling too seldom.
+// ERR_INVALID_ARGUMENT if the subscription isn't in batching mode
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_dequeue_notifications(
+	session_in: *mut Arc<Session>,
+	sub_id: u32,
+	max_items: u32,
+	array_out: LvArrayHandle<LvNotification>,
+	dropped_count_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(array_out, ERR_NULL_POINTER);
+		check_null!(dropped_count_out, ERR_NULL_POINTER);
+
+		let session = unsafe { &*session_in };
+		let Some(sinks) = subscription_sinks(session, sub_id) else {
+			return ERR_INVALID_ARGUMENT; // not created by lv_create_subscription
+		};
+		let mut sinks = sinks.lock().unwrap();
+		let Some(batch) = sinks.batch.as_mut() else {
+			set_last_error_detail(format!("Subscription {sub_id} isn't in batching mode"));
+			return ERR_INVALID_ARGUMENT;
+		};
+
+		let n = match max_items {
+			0 => batch.queue.len(),
+			max_items => batch.queue.len().min(max_items as usize),
+		};
+		// On a LabVIEW memory error the notifications stay queued
+		let Some(items) = to_lv_notifications(batch.queue.iter().take(n)) else {
+			return M_FULL_ERR;
+		};
+		unsafe {
+			if !(*array_out).is_null() {
+				dispose_notification_strings(array_out);
+				(**array_out).dim_size = 0;
+			}
+			let err = write_lv_array(array_out, items.iter().copied());
+			if err != 0 {
+				items.iter().for_each(|n| dispose_notification(n));
+				return err;
+			}
+			batch.queue.drain(..n);
+			*dropped_count_out = batch.take_dropped();
+		}
+		NO_ERR
+	})
+}
+
+// Parameters of ModifySubscription, None keeps the current value
+#[derive(Default)]
+struct SubscriptionParams {
+	publishing_interval: Option<Duration>,
+	max_keep_alive_count: Option<u32>,
+	max_lifetime_count: Option<u32>,
+	max_notifications_per_publish: Option<u32>,
+	priority: Option<u8>,
+}
+
+//==============================================================================
+// ModifySubscription keeping the current parameters, which are not given.
+// Returns the server response with the revised values.
+// Fails with BadNotConnected at once while the session is reconnecting
+//
+async fn modify_subscription(
+	session: &Session,
+	sub_id: u32,
+	params: SubscriptionParams,
+) -> Result<ModifySubscriptionResponse, StatusCode> {
+	if !session.is_connected() {
+		return Err(StatusCode::BadNotConnected);
+	}
+	let modify = {
+		let state = session.subscription_state().lock();
+		let Some(sub) = state.get(sub_id) else {
+			return Err(StatusCode::BadSubscriptionIdInvalid);
+		};
+		ModifySubscription::new(sub_id, session)
+			.publishing_interval(
+				params
+					.publishing_interval
+					.unwrap_or(sub.publishing_interval()),
+			)
+			.max_keep_alive_count(
+				params
+					.max_keep_alive_count
+					.unwrap_or(sub.max_keep_alive_count()),
+			)
+			.max_lifetime_count(params.max_lifetime_count.unwrap_or(sub.lifetime_count()))
+			.max_notifications_per_publish(
+				params
+					.max_notifications_per_publish
+					.unwrap_or(sub.max_notifications_per_publish()),
+			)
+			.priority(params.priority.unwrap_or(sub.priority()))
+	};
+	modify.send(session.channel()).await
+}
+
+//==============================================================================
+// Change the publishing interval of the running subscription,
+// revised_interval_out receives the interval accepted by the server
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_modify_subscription_interval(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	sub_id: u32,
+	publishing_interval_ms: f64,
+	revised_interval_out: *mut f64,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(revised_interval_out, ERR_NULL_POINTER);
+		if !(publishing_interval_ms > 0.0) {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+
+			let interval = Duration::from_secs_f64(publishing_interval_ms / 1000.0);
+			let r = rt.block_on(async {
+				let params = SubscriptionParams {
+					publishing_interval: Some(interval),
+					..Default::default()
+				};
+				modify_subscription(session, sub_id, params).await
+			});
+			match r {
+				Ok(response) => {
+					*revised_interval_out = response.revised_publishing_interval;
+					NO_ERR
+				}
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Change keep-alive and lifetime counts of the running subscription.
+// The server requires lifetime >= 3 * keep-alive and may revise both
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_modify_subscription_keep_alive(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	sub_id: u32,
+	max_keep_alive_count: u32,
+	max_lifetime_count: u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+
+			let r = rt.block_on(async {
+				let params = SubscriptionParams {
+					max_keep_alive_count: Some(max_keep_alive_count),
+					max_lifetime_count: Some(max_lifetime_count),
+					..Default::default()
+				};
+				modify_subscription(session, sub_id, params).await
+			});
+			match r {
+				Ok(_) => NO_ERR,
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Change all parameters of the running subscription at once.
+// The revised_*_out receive the values accepted by the server.
+// BadSubscriptionIdInvalid for unknown sub_id,
+// BadNotConnected while the session is reconnecting
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_modify_subscription(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	sub_id: u32,
+	publishing_interval_ms: f64,
+	lifetime_count: u32,
+	keep_alive_count: u32,
+	max_notifications: u32,
+	priority: u8,
+	revised_interval_out: *mut f64,
+	revised_lifetime_out: *mut u32,
+	revised_keep_alive_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(revised_interval_out, ERR_NULL_POINTER);
+		check_null!(revised_lifetime_out, ERR_NULL_POINTER);
+		check_null!(revised_keep_alive_out, ERR_NULL_POINTER);
+		if !(publishing_interval_ms > 0.0) {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+
+			let params = SubscriptionParams {
+				publishing_interval: Some(Duration::from_secs_f64(publishing_interval_ms / 1000.0)),
+				max_keep_alive_count: Some(keep_alive_count),
+				max_lifetime_count: Some(lifetime_count),
+				max_notifications_per_publish: Some(max_notifications),
+				priority: Some(priority),
+			};
+			let r = rt.block_on(async { modify_subscription(session, sub_id, params).await });
+			match r {
+				Ok(response) => {
+					*revised_interval_out = response.revised_publishing_interval;
+					*revised_lifetime_out = response.revised_lifetime_count;
+					*revised_keep_alive_out = response.revised_max_keep_alive_count;
+					NO_ERR
+				}
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Enable/disable publishing of the subscriptions, e.g. to pause notifications
+// while the LabVIEW UI is reconfigured. sub_ids_in and results_out are arrays
+// of count elements, results_out receives the status code per subscription
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_set_publishing_mode(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	sub_ids_in: *const u32,
+	count: i32,
+	enabled: u8,
+	results_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(sub_ids_in, ERR_NULL_POINTER);
+		check_null!(results_out, ERR_NULL_POINTER);
+		if count <= 0 {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let sub_ids = std::slice::from_raw_parts(sub_ids_in, count as usize);
+			let results = std::slice::from_raw_parts_mut(results_out, count as usize);
+
+			if !session.is_connected() {
+				return StatusCode::BadNotConnected.bits() as i32;
+			}
+			let r = rt.block_on(async { session.set_publishing_mode(sub_ids, enabled != 0).await });
+			match r {
+				Ok(statuses) => {
+					for (result, status) in results.iter_mut().zip(statuses) {
+						*result = status.bits();
+					}
+					NO_ERR
+				}
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Current id of the subscription sub_id after lv_reconnect_session, which
+// changes if the subscription had to be created again on the server.
+// The same id if it was kept
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_reconnected_subscription_id(
+	session_in: *mut Arc<Session>,
+	sub_id: u32,
+	new_sub_id_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(new_sub_id_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let session = &*session_in;
+			*new_sub_id_out = RECREATED_IDS
+				.lock()
+				.unwrap()
+				.get(&(session_key(session), sub_id))
+				.copied()
+				.unwrap_or(sub_id);
+		}
+		NO_ERR
+	})
+}
+
+// Per-subscription results of lv_transfer_subscriptions(), other values are StatusCodes
+pub const TRANSFER_RESULT_TRANSFERRED: u32 = 0;
+pub const TRANSFER_RESULT_RECREATED: u32 = 1;
+
+//==============================================================================
+// Move subscriptions of old_session (e.g. to the failed server of a redundant
+// pair) to new_session. Each subscription is transferred on the server, or
+// created again with its monitored items if the transfer fails.
+// sub_ids_in, results_out and new_sub_ids_out are arrays of count elements.
+// results_out: TRANSFER_RESULT_TRANSFERRED, TRANSFER_RESULT_RECREATED or StatusCode,
+// new_sub_ids_out: id of the subscription in new_session (changes if recreated).
+// LabVIEW user events keep firing, no need to register them again
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_transfer_subscriptions(
+	rt_ptr: *mut Runtime,
+	old_session_in: *mut Arc<Session>,
+	new_session_in: *mut Arc<Session>,
+	sub_ids_in: *const u32,
+	count: i32,
+	results_out: *mut u32,
+	new_sub_ids_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(old_session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(new_session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(sub_ids_in, ERR_NULL_POINTER);
+		check_null!(results_out, ERR_NULL_POINTER);
+		check_null!(new_sub_ids_out, ERR_NULL_POINTER);
+		if count <= 0 {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let old_session = &*old_session_in;
+			let new_session = &*new_session_in;
+			let sub_ids = std::slice::from_raw_parts(sub_ids_in, count as usize);
+			let results = std::slice::from_raw_parts_mut(results_out, count as usize);
+			let new_sub_ids = std::slice::from_raw_parts_mut(new_sub_ids_out, count as usize);
+
+			let r =
+				rt.block_on(async { new_session.take_subscriptions(old_session, sub_ids).await });
+			let transfer_results = match r {
+				Ok(transfer_results) => transfer_results,
+				Err(status) => return status.bits() as i32,
+			};
+
+			for (i, transfer_result) in transfer_results.into_iter().enumerate() {
+				let sub_id = sub_ids[i];
+				(results[i], new_sub_ids[i]) = match transfer_result {
+					SubscriptionTransferResult::Transferred => {
+						move_subscription(old_session, new_session, sub_id, sub_id);
+						(TRANSFER_RESULT_TRANSFERRED, sub_id)
+					}
+					SubscriptionTransferResult::Recreated(new_id) => {
+						move_subscription(old_session, new_session, sub_id, new_id);
+						(TRANSFER_RESULT_RECREATED, new_id)
+					}
+					SubscriptionTransferResult::Failed(status) => {
+						unregister_subscription(old_session, sub_id);
+						(status.bits(), 0)
+					}
+				};
+			}
+		}
+		NO_ERR
+	})
+}
+
+//==============================================================================
+// Move subscriptions of old_session to new_session with TransferSubscriptions
+// only, the ids stay the same. send_initial_values != 0: the server sends the
+// current values of the monitored items again.
+// sub_ids_in and results_out are arrays of count elements, results_out: the
+// StatusCode of each transfer. Subscriptions which failed stay with
+// old_session, e.g. to move them with lv_transfer_subscriptions()
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_session_transfer_subscriptions(
+	rt_ptr: *mut Runtime,
+	old_session_in: *mut Arc<Session>,
+	new_session_in: *mut Arc<Session>,
+	sub_ids_in: *const u32,
+	count: i32,
+	send_initial_values: u8,
+	results_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(old_session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(new_session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(sub_ids_in, ERR_NULL_POINTER);
+		check_null!(results_out, ERR_NULL_POINTER);
+		if count <= 0 {
+			return ERR_INVALID_ARGUMENT;
+		}
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let old_session = &*old_session_in;
+			let new_session = &*new_session_in;
+			let sub_ids = std::slice::from_raw_parts(sub_ids_in, count as usize);
+			let results = std::slice::from_raw_parts_mut(results_out, count as usize);
+
+			let r = rt.block_on(async {
+				new_session
+					.transfer_subscriptions_from(old_session, sub_ids, send_initial_values != 0)
+					.await
+			});
+			let statuses = match r {
+				Ok(statuses) => statuses,
+				Err(status) => return status.bits() as i32,
+			};
+
+			for (i, status) in statuses.into_iter().enumerate() {
+				if status.is_good() {
+					move_subscription(old_session, new_session, sub_ids[i], sub_ids[i]);
+				}
+				results[i] = status.bits();
+			}
+		}
+		NO_ERR
+	})
+}
+
+// DataChangeFilter for deadband_type: 0 - no filter, 1 - Absolute,
+// 2 - Percent (of the EURange), Err is the return code
+fn deadband_filter(deadband_type: u32, deadband_value: f64) -> Result<ExtensionObject, i32> {
+	let deadband = match deadband_type {
+		0 => DeadbandType::None,
+		1 => DeadbandType::Absolute,
+		2 => DeadbandType::Percent,
+		_ => {
+			set_last_error_detail(format!("Unknown deadband type {deadband_type}"));
+			return Err(ERR_INVALID_ARGUMENT);
+		}
+	};
+	if deadband_value < 0.0
+		|| deadband_value.is_nan()
+		|| (deadband == DeadbandType::Percent && deadband_value > 100.0)
+	{
+		set_last_error_detail(format!("Invalid deadband value {deadband_value}"));
+		return Err(ERR_INVALID_ARGUMENT);
+	}
+	Ok(match deadband {
+		DeadbandType::None => ExtensionObject::null(),
+		_ => ExtensionObject::from_message(DataChangeFilter {
+			trigger: DataChangeTrigger::StatusValue,
+			deadband_type: deadband as u32,
+			deadband_value,
+		}),
+	})
+}
+
+// Detail of the status of a monitored item with a deadband filter, the
+// filter errors say why the server didn't accept it
+fn set_filter_error_detail(node: &str, status: StatusCode) {
+	let reason = match status {
+		StatusCode::BadFilterNotAllowed => " (Percent needs the EURange property of the node)",
+		StatusCode::BadDeadbandFilterInvalid | StatusCode::BadMonitoredItemFilterUnsupported => {
+			" (deadband filter not accepted)"
+		}
+		_ => "",
+	};
+	set_last_error_detail(format!("Monitored item {node}: {status}{reason}"));
+}
+
+// Revised values of the monitored item, the out pointers may be null
+unsafe fn write_revised(
+	sampling_interval: f64,
+	queue_size: u32,
+	revised_sampling_interval_out: *mut f64,
+	revised_queue_size_out: *mut u32,
+) {
+	unsafe {
+		if !revised_sampling_interval_out.is_null() {
+			*revised_sampling_interval_out = sampling_interval;
+		}
+		if !revised_queue_size_out.is_null() {
+			*revised_queue_size_out = queue_size;
+		}
+	}
+}
+
+//==============================================================================
+// Monitor Value of the variable in the subscription created with
+// lv_create_subscription(), changes are posted to the subscription's user event.
+// deadband_type: 0 - no filter, 1 - Absolute, 2 - Percent (of the EURange,
+// deadband_value 0..100)
+// client_handle = 0 lets the library assign the handle
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_subscribe_data_with_deadband(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	sub_id: u32,
+	deadband_type: u32,
+	deadband_value: f64,
+	client_handle: u32,
+	monitored_item_id_out: *mut u32,
+) -> i32 {
+	lv_subscribe_data_with_deadband_ex(
+		rt_ptr,
+		session_in,
+		ns,
+		node_str,
+		sub_id,
+		deadband_type,
+		deadband_value,
+		client_handle,
+		monitored_item_id_out,
+		std::ptr::null_mut(),
+		std::ptr::null_mut(),
+	)
+}
+
+//==============================================================================
+// Same as lv_subscribe_data_with_deadband, with the sampling interval and
+// queue size the server revised (the out pointers may be null).
+// Success means the server applies the filter. A filter it doesn't accept
+// fails the item with its status code, e.g. BadFilterNotAllowed for
+// Percent on a node without EURange, the reason is in the last error detail
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_subscribe_data_with_deadband_ex(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	sub_id: u32,
+	deadband_type: u32,
+	deadband_value: f64,
+	client_handle: u32,
+	monitored_item_id_out: *mut u32,
+	revised_sampling_interval_out: *mut f64,
+	revised_queue_size_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(monitored_item_id_out, ERR_NULL_POINTER);
+
+		let filter = match deadband_filter(deadband_type, deadband_value) {
+			Ok(filter) => filter,
+			Err(err) => return err,
+		};
+		let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+
+			let item = MonitoredItemCreateRequest {
+				item_to_monitor: node_id.clone().into(),
+				monitoring_mode: MonitoringMode::Reporting,
+				requested_parameters: MonitoringParameters {
+					client_handle,
+					sampling_interval: 0.0,
+					queue_size: 1,
+					discard_oldest: true,
+					filter,
+				},
+			};
+
+			let r = rt.block_on(async {
+				session
+					.create_monitored_items(sub_id, TimestampsToReturn::Both, vec![item])
+					.await
+			});
+			match r {
+				Ok(results) => match results.into_iter().next() {
+					Some(result) if result.status_code.is_good() => {
+						*monitored_item_id_out = result.monitored_item_id;
+						write_revised(
+							result.revised_sampling_interval,
+							result.revised_queue_size,
+							revised_sampling_interval_out,
+							revised_queue_size_out,
+						);
+						NO_ERR
+					}
+					Some(result) => {
+						set_filter_error_detail(&node_id.to_string(), result.status_code);
+						result.status_code.bits() as i32
+					}
+					None => StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+// Monitor Value of the variable, posted to user_event_ref as long as it is of
+// the kind. The item is routed by client_handle, 0 lets the DLL assign one,
+// ERR_INVALID_ARGUMENT if an item of the subscription uses it already.
+// initial_value reads and posts the current value first
+fn subscribe_value(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	sub_id: u32,
+	client_handle: u32,
+	user_event_ref: *mut c_void,
+	monitored_item_id_out: *mut u32,
+	kind: ValueKind,
+	initial_value: bool,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(node_str, ERR_NULL_POINTER);
+		check_null!(user_event_ref, ERR_NULL_POINTER);
+		check_null!(monitored_item_id_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let sinks = match subscription_sinks(session, sub_id) {
+				Some(sinks) => sinks,
+				None => return ERR_INVALID_ARGUMENT, // not created by lv_create_subscription
+			};
+			let node_id = NodeId::new(ns, cstr_to_string!(node_str));
+
+			// Register before creation, the first value may arrive before create returns
+			let client_handle = match client_handle {
+				0 => NEXT_CLIENT_HANDLE.fetch_add(1, Ordering::Relaxed),
+				client_handle => {
+					// Not under the sinks lock, see lv_subscribe_batch
+					let in_use = monitored_client_handles(session, sub_id).contains(&client_handle);
+					if in_use || sinks.lock().unwrap().items.contains_key(&client_handle) {
+						set_last_error_detail(format!(
+							"Client handle {client_handle} is already in use"
+						));
+						return ERR_INVALID_ARGUMENT;
+					}
+					client_handle
+				}
+			};
+			sinks.lock().unwrap().items.insert(
+				client_handle,
+				ItemSink::Value {
+					user_event_ref: user_event_ref as usize, // raw pointers are not Send
+					kind,
+				},
+			);
+
+			let item = MonitoredItemCreateRequest {
+				item_to_monitor: node_id.clone().into(),
+				monitoring_mode: MonitoringMode::Reporting,
+				requested_parameters: MonitoringParameters {
+					client_handle,
+					sampling_interval: 0.0,
+					queue_size: 1,
+					discard_oldest: true,
+					filter: ExtensionObject::null(),
+				},
+			};
+
+			let r = rt.block_on(async {
+				// Read before the item exists, so no notification of a later
+				// change is posted before the older value read
+				if initial_value {
+					let read = session
+						.read(
+							&[read_value_id(AttributeId::Value, &node_id)],
+							TimestampsToReturn::Both,
+							0.0,
+						)
+						.await;
+					if let Some(dv) = read.ok().and_then(|values| values.into_iter().next()) {
+						post_value(user_event_ref as usize, kind, &dv);
+					}
+				}
+				session
+					.create_monitored_items(sub_id, TimestampsToReturn::Both, vec![item])
+					.await
+			});
+			let status = match r {
+				Ok(results) => match results.into_iter().next() {
+					Some(result) if result.status_code.is_good() => {
+						*monitored_item_id_out = result.monitored_item_id;
+						return NO_ERR;
+					}
+					Some(result) => result.status_code,
+					None => StatusCode::BadUnexpectedError,
+				},
+				Err(status) => status,
+			};
+			sinks.lock().unwrap().items.remove(&client_handle);
+			status.bits() as i32
+		}
+	})
+}
+
+//==============================================================================
+// Monitor the String variable in the subscription created with
+// lv_create_subscription(), each value is posted to user_event_ref (LabVIEW
+// user event of a string). The DLL disposes its string handle after posting,
+// the event data is LabVIEW's own copy and nothing is freed by the VI.
+// Values which are not String (e.g. Bad status without value) are not posted.
+// client_handle = 0 lets the DLL assign the handle
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_subscribe_string(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	sub_id: u32,
+	client_handle: u32,
+	user_event_ref: *mut c_void,
+	monitored_item_id_out: *mut u32,
+) -> i32 {
+	subscribe_value(
+		rt_ptr,
+		session_in,
+		ns,
+		node_str,
+		sub_id,
+		client_handle,
+		user_event_ref,
+		monitored_item_id_out,
+		ValueKind::String,
+		false,
+	)
+}
+
+//==============================================================================
+// Monitor the Int32 variable in the subscription created with
+// lv_create_subscription(), each value is posted to user_event_ref (LabVIEW
+// user event of I32). The current value is read and posted before the item is
+// created, so the indicators have a value at startup without waiting for
+// a change. A failed read isn't an error, the item is created anyway.
+// client_handle = 0 lets the DLL assign the handle
+//
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_subscribe_with_initial_value_Int32(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	sub_id: u32,
+	client_handle: u32,
+	user_event_ref: *mut c_void,
+	monitored_item_id_out: *mut u32,
+) -> i32 {
+	subscribe_value(
+		rt_ptr,
+		session_in,
+		ns,
+		node_str,
+		sub_id,
+		client_handle,
+		user_event_ref,
+		monitored_item_id_out,
+		ValueKind::LvType(6),
+		true,
+	)
+}
+
+//==============================================================================
+// Monitor count variables in the subscription created with
+// lv_create_subscription() with a single CreateMonitoredItems call.
+// Item i is node_str_array[i] in ns_array[i], its values are posted to
+// user_event_refs_array[i] as the LabVIEW type var_type_array[i] (as with
+// lv_add_variable: 1 Boolean (U8) .. 11 Double, 12 String, 13 DateTime as
+// DBL timestamp, 14 Guid as {xxxxxxxx-...} string, 15 ByteString as string of
+// the bytes, 29 Enumeration as I32), values of another type are not posted.
+// client_handles_array may be null, its 0 entries let the DLL assign the
+// handle. ERR_INVALID_ARGUMENT if a handle is given twice or already used by
+// an item of the subscription.
+// monitored_item_ids_out (count elements) receives the item ids, 0 for the
+// items the server rejected. Returns NO_ERR if all items are created, else
+// the status code of the first rejected item
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_subscribe_batch(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	sub_id: u32,
+	node_str_array: LStrArrayHandle,
+	ns_array: *const u16,
+	var_type_array: *const u16,
+	client_handles_array: *const u32,
+	count: i32,
+	user_event_refs_array: *mut *mut c_void,
+	monitored_item_ids_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(ns_array, ERR_NULL_POINTER);
+		check_null!(var_type_array, ERR_NULL_POINTER);
+		check_null!(user_event_refs_array, ERR_NULL_POINTER);
+		check_null!(monitored_item_ids_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let sinks = match subscription_sinks(session, sub_id) {
+				Some(sinks) => sinks,
+				None => return ERR_INVALID_ARGUMENT, // not created by lv_create_subscription
+			};
+			let node_strs = lstr_array_to_strings(node_str_array);
+			if count <= 0 || node_strs.len() < count as usize {
+				set_last_error_detail(format!(
+					"count {count} doesn't fit the {} node ids",
+					node_strs.len()
+				));
+				return ERR_INVALID_ARGUMENT;
+			}
+			let count = count as usize;
+			let ns = std::slice::from_raw_parts(ns_array, count);
+			let var_types = std::slice::from_raw_parts(var_type_array, count);
+			let user_event_refs = std::slice::from_raw_parts(user_event_refs_array, count);
+			if let Some(i) = var_types.iter().position(|t| lv_data_type(*t).is_none()) {
+				set_last_error_detail(format!("Unknown type {} of item {i}", var_types[i]));
+				return ERR_INVALID_TYPE;
+			}
+
+			let requested: Vec<u32> = match client_handles_array.is_null() {
+				true => vec![0; count],
+				false => std::slice::from_raw_parts(client_handles_array, count).to_vec(),
+			};
+			// Not under the sinks lock, the library calls back with the
+			// subscription state locked
+			let mut used = monitored_client_handles(session, sub_id);
+
+			// Register before creation, values may arrive before create returns
+			let mut client_handles = Vec::with_capacity(count);
+			let mut items = Vec::with_capacity(count);
+			{
+				let mut sinks = sinks.lock().unwrap();
+				// A handle in use would take over the sink of the other item,
+				// and remove it if the creation fails
+				used.extend(sinks.items.keys());
+				if let Some(i) = requested.iter().position(|h| *h != 0 && !used.insert(*h)) {
+					set_last_error_detail(format!(
+						"Client handle {} of item {i} is already in use",
+						requested[i]
+					));
+					return ERR_INVALID_ARGUMENT;
+				}
+				for i in 0..count {
+					let client_handle = match requested[i] {
+						0 => NEXT_CLIENT_HANDLE.fetch_add(1, Ordering::Relaxed),
+						client_handle => client_handle,
+					};
+					sinks.items.insert(
+						client_handle,
+						ItemSink::Value {
+							user_event_ref: user_event_refs[i] as usize, // raw pointers are not Send
+							kind: ValueKind::LvType(var_types[i]),
+						},
+					);
+					client_handles.push(client_handle);
+					items.push(MonitoredItemCreateRequest {
+						item_to_monitor: NodeId::new(ns[i], node_strs[i].clone()).into(),
+						monitoring_mode: MonitoringMode::Reporting,
+						requested_parameters: MonitoringParameters {
+							client_handle,
+							sampling_interval: 0.0,
+							queue_size: 1,
+							discard_oldest: true,
+							filter: ExtensionObject::null(),
+						},
+					});
+				}
+			}
+
+			let r = rt.block_on(async {
+				session
+					.create_monitored_items(sub_id, TimestampsToReturn::Both, items)
+					.await
+			});
+			let results = match r {
+				Ok(results) => results,
+				Err(status) => {
+					let mut sinks = sinks.lock().unwrap();
+					for client_handle in &client_handles {
+						sinks.items.remove(client_handle);
+					}
+					return status.bits() as i32;
+				}
+			};
+
+			let mut first_error = None;
+			let mut sinks = sinks.lock().unwrap();
+			for (i, client_handle) in client_handles.iter().enumerate() {
+				let status = results
+					.get(i)
+					.map_or(StatusCode::BadUnexpectedError, |r| r.status_code);
+				if status.is_good() {
+					*monitored_item_ids_out.add(i) = results[i].monitored_item_id;
+				} else {
+					*monitored_item_ids_out.add(i) = 0;
+					sinks.items.remove(client_handle);
+					if first_error.is_none() {
+						set_last_error_detail(format!(
+							"Item {i} ({}) failed: {status}",
+							node_strs[i]
+						));
+						first_error = Some(status);
+					}
+				}
+			}
+			match first_error {
+				Some(status) => status.bits() as i32,
+				None => NO_ERR,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Same as lv_subscribe_string for a DateTime variable, posted as DBL
+// (seconds since 1904-01-01 UTC, LabVIEW timestamp via To Time Stamp)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_subscribe_date_time(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	sub_id: u32,
+	client_handle: u32,
+	user_event_ref: *mut c_void,
+	monitored_item_id_out: *mut u32,
+) -> i32 {
+	subscribe_value(
+		rt_ptr,
+		session_in,
+		ns,
+		node_str,
+		sub_id,
+		client_handle,
+		user_event_ref,
+		monitored_item_id_out,
+		ValueKind::DateTime,
+		false,
+	)
+}
+
+//==============================================================================
+// Same as lv_subscribe_string for a NodeId variable, posted as string,
+// e.g. "ns=2;s=Line1" (string handle ownership as with lv_subscribe_string)
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_subscribe_node_id(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	ns: u16,
+	node_str: *const c_char,
+	sub_id: u32,
+	client_handle: u32,
+	user_event_ref: *mut c_void,
+	monitored_item_id_out: *mut u32,
+) -> i32 {
+	subscribe_value(
+		rt_ptr,
+		session_in,
+		ns,
+		node_str,
+		sub_id,
+		client_handle,
+		user_event_ref,
+		monitored_item_id_out,
+		ValueKind::NodeId,
+		false,
+	)
+}
+
+//==============================================================================
+// Change the deadband filter of a monitored item (deadband_type and
+// deadband_value as with lv_subscribe_data_with_deadband), the other
+// parameters of the item are kept. The revised sampling interval and queue
+// size are written to the out pointers (may be null). A filter the server
+// doesn't accept is returned as its status code and the item keeps the
+// old filter
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_modify_monitored_item_deadband(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	sub_id: u32,
+	monitored_item_id: u32,
+	deadband_type: u32,
+	deadband_value: f64,
+	revised_sampling_interval_out: *mut f64,
+	revised_queue_size_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+
+		let filter = match deadband_filter(deadband_type, deadband_value) {
+			Ok(filter) => filter,
+			Err(err) => return err,
+		};
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+
+			let (node, request) = {
+				let state = session.subscription_state().lock();
+				let Some(sub) = state.get(sub_id) else {
+					return StatusCode::BadSubscriptionIdInvalid.bits() as i32;
+				};
+				let Some(item) = sub.monitored_items().get(&monitored_item_id) else {
+					return StatusCode::BadMonitoredItemIdInvalid.bits() as i32;
+				};
+				(
+					item.item_to_monitor().node_id.to_string(),
+					MonitoredItemModifyRequest {
+						monitored_item_id,
+						requested_parameters: MonitoringParameters {
+							client_handle: item.client_handle(),
+							sampling_interval: item.sampling_interval(),
+							queue_size: item.queue_size() as u32,
+							discard_oldest: item.discard_oldest(),
+							filter,
+						},
+					},
+				)
+			};
+
+			let r = rt.block_on(async {
+				session
+					.modify_monitored_items(sub_id, TimestampsToReturn::Both, &[request])
+					.await
+			});
+			match r {
+				Ok(results) => match results.into_iter().next() {
+					Some(result) if result.status_code.is_good() => {
+						write_revised(
+							result.revised_sampling_interval,
+							result.revised_queue_size,
+							revised_sampling_interval_out,
+							revised_queue_size_out,
+						);
+						NO_ERR
+					}
+					Some(result) => {
+						set_filter_error_detail(&node, result.status_code);
+						result.status_code.bits() as i32
+					}
+					None => StatusCode::BadUnexpectedError.bits() as i32,
+				},
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+//==============================================================================
+// Build event filter selecting the fields by their browse names (BaseEventType).
+// Browse path of nested fields is separated by "/", e.g. "EnabledState/Id"
+//
+fn event_filter(field_names: &[String]) -> EventFilter {
+	let select_clauses = field_names
+		.iter()
+		.map(|name| {
+			SimpleAttributeOperand::new(
+				ObjectTypeId::BaseEventType,
+				name,
+				AttributeId::Value,
+				Default::default(),
+			)
+		})
+		.collect();
+
+	EventFilter {
+		select_clauses: Some(select_clauses),
+		where_clause: ContentFilter::default(),
+	}
+}
+
+// Where clause OfType(event_type_id), limits events to the type and its subtypes
+fn of_type_filter(event_type_id: NodeId) -> ContentFilter {
+	let operand = Operand::literal(Variant::from(event_type_id));
+	ContentFilter {
+		elements: Some(vec![ContentFilterElement {
+			filter_operator: FilterOperator::OfType,
+			filter_operands: Some(vec![ExtensionObject::from(&operand)]),
+		}]),
+	}
+}
+
+//==============================================================================
+// Monitor events of the notifier (usually the Server object, ns=0;i=2253) in the
+// subscription created with lv_create_subscription().
+// select_fields is 1D string array of BaseEventType fields, an empty array
+// means EventId, EventType, Severity, Message, Time.
+// Each event is posted to user_event_ref as 1D string array in the order of
+// the selected fields, fields missing in the event are empty strings.
+// event_type_id_u32 != 0 delivers only events of this type (ns=0) and subtypes.
+// EventId is delivered as Base64, as expected by lv_acknowledge_event()
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_subscribe_events(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	sub_id: u32,
+	notifier_ns: u16,
+	notifier_str: *const c_char,
+	event_type_id_u32: u32,
+	select_fields: LStrArrayHandle,
+	user_event_ref: *mut c_void,
+	monitored_item_out: *mut u32,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(notifier_str, ERR_NULL_POINTER);
+		check_null!(user_event_ref, ERR_NULL_POINTER);
+		check_null!(monitored_item_out, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let sinks = match subscription_sinks(session, sub_id) {
+				Some(sinks) => sinks,
+				None => return ERR_INVALID_ARGUMENT, // not created by lv_create_subscription
+			};
+			let notifier_id = NodeId::new(notifier_ns, cstr_to_string!(notifier_str));
+
+			let mut field_names: Vec<String> = lstr_array_to_strings(select_fields)
+				.iter()
+				.map(|f| f.trim().to_string())
+				.filter(|f| !f.is_empty())
+				.collect();
+			if field_names.is_empty() {
+				field_names = DEFAULT_EVENT_FIELDS.iter().map(|f| f.to_string()).collect();
+			}
+			let mut filter = event_filter(&field_names);
+			if event_type_id_u32 != 0 {
+				filter.where_clause = of_type_filter(NodeId::new(0, event_type_id_u32));
+			}
+
+			// Register before creation, events may arrive before create returns
+			let client_handle = NEXT_CLIENT_HANDLE.fetch_add(1, Ordering::Relaxed);
+			sinks.lock().unwrap().items.insert(
+				client_handle,
+				ItemSink::Event {
+					user_event_ref: user_event_ref as usize,
+					field_count: field_names.len(),
+				},
+			);
+
+			let item = MonitoredItemCreateRequest {
+				item_to_monitor: ReadValueId {
+					node_id: notifier_id,
+					attribute_id: AttributeId::EventNotifier as u32,
+					..Default::default()
+				},
+				monitoring_mode: MonitoringMode::Reporting,
+				requested_parameters: MonitoringParameters {
+					client_handle,
+					sampling_interval: 0.0,
+					queue_size: 100,
+					discard_oldest: true,
+					filter: ExtensionObject::from_message(filter),
+				},
+			};
+
+			let r = rt.block_on(async {
+				session
+					.create_monitored_items(sub_id, TimestampsToReturn::Both, vec![item])
+					.await
+			});
+			let status = match r {
+				Ok(results) => match results.into_iter().next() {
+					Some(result) if result.status_code.is_good() => {
+						*monitored_item_out = result.monitored_item_id;
+						return NO_ERR;
+					}
+					Some(result) => result.status_code,
+					None => StatusCode::BadUnexpectedError,
+				},
+				Err(status) => status,
+			};
+			sinks.lock().unwrap().items.remove(&client_handle);
+			status.bits() as i32
+		}
+	})
+}
+
+//==============================================================================
+// Acknowledge the alarm condition (AcknowledgeableConditionType.Acknowledge)
+// event_id_base64 is the EventId field as delivered by lv_subscribe_events()
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_acknowledge_event(
+	rt_ptr: *mut Runtime,
+	session_in: *mut Arc<Session>,
+	condition_ns: u16,
+	condition_str: *const c_char,
+	event_id_base64: *const c_char,
+	comment_str: *const c_char,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(rt_ptr, ERR_INVALID_RUNTIME);
+		check_null!(session_in, ERR_INVALID_CLIENT_REF);
+		check_null!(condition_str, ERR_NULL_POINTER);
+		check_null!(event_id_base64, ERR_NULL_POINTER);
+
+		unsafe {
+			let rt = &mut *rt_ptr;
+			let session = &mut *session_in;
+			let condition_id = NodeId::new(condition_ns, cstr_to_string!(condition_str));
+			let event_id = match ByteString::from_base64(&cstr_to_string!(event_id_base64)) {
+				Some(id) => id,
+				None => return ERR_INVALID_ARGUMENT,
+			};
+			let comment = if comment_str.is_null() {
+				String::new()
+			} else {
+				cstr_to_string!(comment_str)
+			};
+
+			let method_id: NodeId = MethodId::AcknowledgeableConditionType_Acknowledge.into();
+			let args = Some(vec![
+				Variant::from(event_id),
+				Variant::from(LocalizedText::new("", &comment)),
+			]);
+
+			let r = rt.block_on(async { session.call_one((condition_id, method_id, args)).await });
+			match r {
+				Ok(result) if result.status_code.is_good() => NO_ERR,
+				Ok(result) => result.status_code.bits() as i32,
+				Err(status) => status.bits() as i32,
+			}
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::labview::record_i32_events;
+	use crate::test_server::{TestClient, TestServer};
+	use std::{ffi::CString, time::Instant};
+
+	fn batch(capacity: usize, drop_oldest: bool) -> NotificationBatch {
+		NotificationBatch {
+			queue: VecDeque::new(),
+			capacity,
+			drop_oldest,
+			dropped: 0,
+			user_event_ref: 0,
+		}
+	}
+
+	fn push_values(batch: &mut NotificationBatch, values: std::ops::Range<u32>) {
+		for i in values {
+			batch.push(QueuedNotification {
+				node_id: "ns=2;s=Tank1".into(),
+				value: i.to_string(),
+				status: 0,
+				timestamp: 0.0,
+			});
+		}
+	}
+
+	fn queued_values(batch: &NotificationBatch) -> Vec<String> {
+		batch.queue.iter().map(|n| n.value.clone()).collect()
+	}
+
+	#[test]
+	fn full_queue_drops_oldest() {
+		let mut batch = batch(3, true);
+		push_values(&mut batch, 0..5);
+		assert_eq!(queued_values(&batch), ["2", "3", "4"]);
+		assert_eq!(batch.take_dropped(), 2);
+		assert_eq!(batch.take_dropped(), 0);
+	}
+
+	#[test]
+	fn full_queue_drops_new() {
+		let mut batch = batch(3, false);
+		push_values(&mut batch, 0..5);
+		assert_eq!(queued_values(&batch), ["0", "1", "2"]);
+		assert_eq!(batch.take_dropped(), 2);
+	}
+
+	#[test]
+	fn dropped_count_saturates_per_take() {
+		let mut batch = batch(1, true);
+		batch.dropped = u32::MAX as u64 + 10;
+		assert_eq!(batch.take_dropped(), u32::MAX);
+		assert_eq!(batch.take_dropped(), 10);
+	}
+
+	#[test]
+	fn smaller_capacity_drops_as_the_policy() {
+		let mut oldest = batch(5, true);
+		push_values(&mut oldest, 0..5);
+		oldest.set_capacity(2);
+		assert_eq!(queued_values(&oldest), ["3", "4"]);
+		assert_eq!(oldest.take_dropped(), 3);
+
+		let mut new = batch(5, false);
+		push_values(&mut new, 0..5);
+		new.set_capacity(2);
+		assert_eq!(queued_values(&new), ["0", "1"]);
+		assert_eq!(new.take_dropped(), 3);
+
+		// A larger capacity keeps the queue
+		new.set_capacity(10);
+		push_values(&mut new, 5..7);
+		assert_eq!(queued_values(&new), ["0", "1", "5", "6"]);
+		assert_eq!(new.take_dropped(), 0);
+	}
+
+	fn create_subscription(client: &TestClient) -> u32 {
+		let mut sub_id = 0;
+		let err = lv_create_subscription(
+			client.rt_ptr,
+			client.session_ptr,
+			100.0,
+			std::ptr::null_mut(),
+			&mut sub_id,
+		);
+		assert_eq!(err, NO_ERR);
+		sub_id
+	}
+
+	// lv_subscribe_batch of String variables, the error and the item ids
+	fn subscribe_batch(
+		client: &TestClient,
+		sub_id: u32,
+		ns: u16,
+		nodes: &[String],
+		client_handles: Option<&[u32]>,
+	) -> (i32, Vec<u32>) {
+		let count = nodes.len();
+		let node_strs = unsafe { strings_to_new_lstr_array(nodes) };
+		// Without LabVIEW the events go nowhere, any non-null reference does
+		let mut user_event = 0u32;
+		let mut user_event_refs = vec![&mut user_event as *mut u32 as *mut c_void; count];
+		let mut ids = vec![0; count];
+		let err = lv_subscribe_batch(
+			client.rt_ptr,
+			client.session_ptr,
+			sub_id,
+			node_strs,
+			vec![ns; count].as_ptr(),
+			vec![12; count].as_ptr(),
+			client_handles.map_or(std::ptr::null(), |h| h.as_ptr()),
+			count as i32,
+			user_event_refs.as_mut_ptr(),
+			ids.as_mut_ptr(),
+		);
+		unsafe { dispose_lstr_array(node_strs) };
+		(err, ids)
+	}
+
+	fn string_variables(server: &TestServer, count: usize) -> Vec<String> {
+		let nodes: Vec<String> = (0..count).map(|i| format!("Text{i}")).collect();
+		for node in &nodes {
+			assert_eq!(server.add_variable(node, 12), NO_ERR);
+		}
+		nodes
+	}
+
+	#[test]
+	fn batch_rejects_client_handles_in_use() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		let nodes = string_variables(&server, 2);
+		let sub_id = create_subscription(&client);
+		let routed = |client_handle: u32| {
+			let sinks = subscription_sinks(client.session(), sub_id).unwrap();
+			sinks.lock().unwrap().items.contains_key(&client_handle)
+		};
+
+		let (err, _) = subscribe_batch(&client, sub_id, server.ns, &nodes, Some(&[7, 7]));
+		assert_eq!(err, ERR_INVALID_ARGUMENT);
+		assert!(!routed(7));
+		let (err, ids) = subscribe_batch(&client, sub_id, server.ns, &nodes[..1], Some(&[7]));
+		assert_eq!(err, NO_ERR);
+		assert_ne!(ids[0], 0);
+
+		// Neither taking over nor removing the sink of the item
+		let (err, ids) = subscribe_batch(&client, sub_id, server.ns, &nodes[1..], Some(&[7]));
+		assert_eq!((err, ids[0]), (ERR_INVALID_ARGUMENT, 0));
+		assert!(routed(7));
+		// No node manager owns ns 77, the server rejects the item
+		let (err, _) = subscribe_batch(&client, sub_id, 77, &nodes[1..], Some(&[8]));
+		assert_eq!(err, StatusCode::BadNodeIdUnknown.bits() as i32);
+		assert!(routed(7) && !routed(8));
+
+		// Items of the library without a sink use their handles too
+		let session = client.session();
+		let item = MonitoredItemCreateRequest {
+			item_to_monitor: NodeId::new(server.ns, nodes[1].clone()).into(),
+			monitoring_mode: MonitoringMode::Reporting,
+			requested_parameters: MonitoringParameters {
+				client_handle: 9,
+				..Default::default()
+			},
+		};
+		let results = client
+			.runtime()
+			.block_on(session.create_monitored_items(sub_id, TimestampsToReturn::Both, vec![item]))
+			.unwrap();
+		assert!(results[0].status_code.is_good());
+		let (err, _) = subscribe_batch(&client, sub_id, server.ns, &nodes[1..], Some(&[9]));
+		assert_eq!(err, ERR_INVALID_ARGUMENT);
+	}
+
+	// Server handle -> client handle of the items (lv_get_monitored_items)
+	fn monitored_items(client: &TestClient, sub_id: u32) -> Result<HashMap<u32, u32>, i32> {
+		let mut server_handles = std::ptr::null_mut();
+		let mut client_handles = std::ptr::null_mut();
+		let mut count = -1;
+		let err = lv_get_monitored_items(
+			client.rt_ptr,
+			client.session_ptr,
+			sub_id,
+			&mut server_handles,
+			&mut client_handles,
+			&mut count,
+		);
+		if err != NO_ERR {
+			return Err(err);
+		}
+		let items = unsafe {
+			let count = count as usize;
+			std::slice::from_raw_parts(server_handles, count)
+				.iter()
+				.copied()
+				.zip(
+					std::slice::from_raw_parts(client_handles, count)
+						.iter()
+						.copied(),
+				)
+				.collect()
+		};
+		assert_eq!(lv_free_u32_array(server_handles), NO_ERR);
+		assert_eq!(lv_free_u32_array(client_handles), NO_ERR);
+		Ok(items)
+	}
+
+	#[test]
+	fn monitored_items_have_the_client_handles_of_creation() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		let nodes = string_variables(&server, 3);
+		let sub_id = create_subscription(&client);
+		assert_eq!(monitored_items(&client, sub_id), Ok(HashMap::new()));
+
+		let (err, ids) = subscribe_batch(&client, sub_id, server.ns, &nodes[..2], Some(&[11, 12]));
+		assert_eq!(err, NO_ERR);
+		let node = CString::new(nodes[2].as_str()).unwrap();
+		let mut user_event = 0u32;
+		let mut id = 0;
+		let err = lv_subscribe_string(
+			client.rt_ptr,
+			client.session_ptr,
+			server.ns,
+			node.as_ptr(),
+			sub_id,
+			13,
+			&mut user_event as *mut u32 as *mut c_void,
+			&mut id,
+		);
+		assert_eq!(err, NO_ERR);
+
+		let expected = HashMap::from([(ids[0], 11), (ids[1], 12), (id, 13)]);
+		assert_eq!(monitored_items(&client, sub_id), Ok(expected));
+		// Status of the failed method call
+		assert!(monitored_items(&client, sub_id + 1000).is_err_and(|err| err < 0));
+	}
+
+	// A noisy Double variable with an absolute deadband of 5 and without
+	// filter, their notifications queued in batching mode
+	#[test]
+	fn deadband_filters_the_noise() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		let sub_id = create_subscription(&client);
+		let batching = lv_set_subscription_batching(
+			client.session_ptr,
+			sub_id,
+			1,
+			10_000,
+			1,
+			std::ptr::null_mut(),
+		);
+		assert_eq!(batching, NO_ERR);
+
+		let nodes = ["Filtered", "Unfiltered"].map(|node| CString::new(node).unwrap());
+		let write = |value: f64| {
+			for node in &nodes {
+				let err = crate::server_variables::lv_write_variableDouble(
+					node.as_ptr(),
+					server.ns,
+					value,
+					server.manager_ptr,
+					server.handle_ptr,
+				);
+				assert_eq!(err, NO_ERR);
+			}
+		};
+		for node in &nodes {
+			assert_eq!(server.add_variable(node.to_str().unwrap(), 11), NO_ERR);
+		}
+		// Written once, else the first write is a change of the status
+		// BadWaitingForInitialData and notified despite the deadband
+		write(0.0);
+
+		for (node, deadband_type) in nodes.iter().zip([1, 0]) {
+			let mut id = 0;
+			let err = lv_subscribe_data_with_deadband(
+				client.rt_ptr,
+				client.session_ptr,
+				server.ns,
+				node.as_ptr(),
+				sub_id,
+				deadband_type,
+				5.0,
+				0,
+				&mut id,
+			);
+			assert_eq!(err, NO_ERR, "{}", last_error_detail());
+		}
+		assert_eq!(
+			lv_subscribe_data_with_deadband(
+				client.rt_ptr,
+				client.session_ptr,
+				server.ns,
+				nodes[0].as_ptr(),
+				sub_id,
+				1,
+				-1.0,
+				0,
+				&mut 0,
+			),
+			ERR_INVALID_ARGUMENT
+		);
+
+		let sinks = subscription_sinks(client.session(), sub_id).unwrap();
+		let values_of = |node: &CString| -> Vec<String> {
+			let node_id = NodeId::new(server.ns, node.to_str().unwrap().to_string()).to_string();
+			let sinks = sinks.lock().unwrap();
+			let queue = &sinks.batch.as_ref().unwrap().queue;
+			queue
+				.iter()
+				.filter(|n| n.node_id == node_id)
+				.map(|n| n.value.clone())
+				.collect()
+		};
+		let wait_for = |value: &str| {
+			let start = Instant::now();
+			while nodes
+				.iter()
+				.any(|node| values_of(node).last().map(String::as_str) != Some(value))
+			{
+				assert!(
+					start.elapsed() < Duration::from_secs(5),
+					"{value} not notified"
+				);
+				std::thread::sleep(Duration::from_millis(10));
+			}
+		};
+
+		// Noise around the initial 0 over several publishing intervals, then a step
+		wait_for("0");
+		for i in 0..40 {
+			write(if i % 2 == 0 { 0.5 } else { -0.5 });
+			std::thread::sleep(Duration::from_millis(20));
+		}
+		write(100.0);
+		wait_for("100");
+		assert_eq!(values_of(&nodes[0]), ["0", "100"]);
+		assert!(values_of(&nodes[1]).len() > 4, "{:?}", values_of(&nodes[1]));
+	}
+
+	// The current value is posted on the calling thread before the call
+	// returns, not by the initial notification of the item
+	#[test]
+	fn initial_value_is_posted_before_returning() {
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		let sub_id = create_subscription(&client);
+		assert_eq!(server.add_variable("Counter", 6), NO_ERR);
+		let node = CString::new("Counter").unwrap();
+		let err = crate::server_variables::lv_write_variableInt32(
+			node.as_ptr(),
+			server.ns,
+			42,
+			server.manager_ptr,
+			server.handle_ptr,
+		);
+		assert_eq!(err, NO_ERR);
+
+		let mut user_event = 0u32;
+		let user_event_ref = &mut user_event as *mut u32 as *mut c_void;
+		let mut id = 0;
+		let (err, posted) = record_i32_events(|| {
+			lv_subscribe_with_initial_value_Int32(
+				client.rt_ptr,
+				client.session_ptr,
+				server.ns,
+				node.as_ptr(),
+				sub_id,
+				0,
+				user_event_ref,
+				&mut id,
+			)
+		});
+		assert_eq!(err, NO_ERR);
+		assert_ne!(id, 0);
+		assert_eq!(posted, [(user_event_ref as usize, 42)]);
+	}
+
+	// cargo test subscribe_500 -- --ignored --nocapture
+	#[test]
+	#[ignore = "benchmark"]
+	fn subscribe_500_items_in_a_batch_and_one_by_one() {
+		const ITEMS: usize = 500;
+		let server = TestServer::start();
+		let client = TestClient::connect(&server);
+		let nodes = string_variables(&server, ITEMS);
+
+		let sub_id = create_subscription(&client);
+		let start = Instant::now();
+		let (err, ids) = subscribe_batch(&client, sub_id, server.ns, &nodes, None);
+		let batch_time = start.elapsed();
+		assert_eq!(err, NO_ERR);
+		assert!(ids.iter().all(|id| *id != 0));
+
+		let sub_id = create_subscription(&client);
+		let mut user_event = 0u32;
+		let start = Instant::now();
+		for node in &nodes {
+			let node = CString::new(node.as_str()).unwrap();
+			let mut id = 0;
+			let err = lv_subscribe_string(
+				client.rt_ptr,
+				client.session_ptr,
+				server.ns,
+				node.as_ptr(),
+				sub_id,
+				0,
+				&mut user_event as *mut u32 as *mut c_void,
+				&mut id,
+			);
+			assert_eq!(err, NO_ERR);
+		}
+		let single_time = start.elapsed();
+		eprintln!(
+			"{ITEMS} items: lv_subscribe_batch {batch_time:?}, lv_subscribe_string {single_time:?}"
+		);
+	}
+}
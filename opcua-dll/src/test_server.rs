@@ -0,0 +1,242 @@
+//==============================================================================
+//
+// Title:		Server and client of the unit tests
+// Purpose:		In-process server and client session with the handles the
+//				exported functions get from LabVIEW
+//
+// Created on:	16-OCT-2026 by agent.
+// License: MPL-2.0
+//
+//==============================================================================
+//
+// The server runs on a runtime of its own (not SERVER_GLOBAL_RUNTIME of
+// lv_start_server), so the tests can run in parallel. Each server gets a free
+// port and a PKI dir in the temp dir, which is removed again
+//
+use crate::errors::*;
+use crate::handles::{HandleKind, from_handle, into_handle};
+use crate::server::{
+	SimpleManager, build_server, lv_free_node_manager, lv_free_server, lv_free_server_handle,
+};
+
+use opcua::{
+	client::{Client, ClientBuilder, Session},
+	core::config::Config,
+	crypto::{CertificateStore, SecurityPolicy},
+	server::{ANONYMOUS_USER_TOKEN_ID, Server, ServerBuilder, ServerHandle},
+	types::{MessageSecurityMode, NodeId, ObjectId, StatusCode},
+};
+use std::{
+	ffi::CString,
+	path::PathBuf,
+	sync::{
+		Arc, LazyLock,
+		atomic::{AtomicU32, Ordering},
+	},
+};
+use tokio::{net::TcpListener, runtime::Runtime, task::JoinHandle};
+
+static TEST_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+// Dir of its own in the temp dir for each server and client
+pub fn test_dir(prefix: &str) -> PathBuf {
+	let n = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+	std::env::temp_dir().join(format!("opcua-dll-{prefix}-{}-{n}", std::process::id()))
+}
+
+// Key generation takes seconds, the servers share one certificate (kept for
+// the next run as the certs of the async-opcua integration tests)
+static SERVER_CERT_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+	let dir = std::env::temp_dir().join("opcua-dll-test-certs");
+	if !dir.join("own/cert.der").exists() {
+		let config = server_builder(&dir, 0).config().clone();
+		std::fs::create_dir_all(dir.join("own")).unwrap();
+		std::fs::create_dir_all(dir.join("private")).unwrap();
+		CertificateStore::create_certificate_and_key(
+			&config.application_description().into(),
+			true,
+			&dir.join("own/cert.der"),
+			&dir.join("private/private.pem"),
+		)
+		.unwrap();
+	}
+	dir
+});
+
+fn server_builder(pki_dir: &PathBuf, port: u16) -> ServerBuilder {
+	ServerBuilder::new()
+		.application_name("opcua-dll test server")
+		.application_uri("urn:opcua-dll-test-server")
+		.product_uri("urn:opcua-dll-test-server")
+		.pki_dir(pki_dir)
+		.host("127.0.0.1")
+		.port(port)
+		.discovery_urls(vec![format!("opc.tcp://127.0.0.1:{port}/")])
+		.add_endpoint(
+			"none",
+			(
+				"/",
+				SecurityPolicy::None,
+				MessageSecurityMode::None,
+				&[ANONYMOUS_USER_TOKEN_ID] as &[&str],
+			),
+		)
+}
+
+pub struct TestServer {
+	pub server_ptr: *mut Server,
+	pub handle_ptr: *mut ServerHandle,
+	pub manager_ptr: *mut SimpleManager,
+	pub ns: u16,
+	pub url: String,
+	pub objects_folder: NodeId,
+	pki_dir: PathBuf,
+	task: Option<JoinHandle<()>>,
+	runtime: Option<Runtime>,
+}
+
+impl TestServer {
+	pub fn start() -> Self {
+		let runtime = Runtime::new().unwrap();
+		let pki_dir = test_dir("server");
+		let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+		let port = listener.local_addr().unwrap().port();
+
+		for file in ["own/cert.der", "private/private.pem"] {
+			let path = pki_dir.join(file);
+			std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+			std::fs::copy(SERVER_CERT_DIR.join(file), path).unwrap();
+		}
+		let config = server_builder(&pki_dir, port).config().clone();
+		let (server_ptr, handle_ptr, manager_ptr) = runtime.block_on(build_server(config)).unwrap();
+		let ns = unsafe { (*handle_ptr).get_namespace_index("urn:SimpleServer") }.unwrap();
+
+		// The server is freed by drop only after the task has ended
+		let server = unsafe { &mut *server_ptr };
+		let task = runtime.spawn(async move {
+			server.run_with(listener).await.unwrap();
+		});
+
+		TestServer {
+			server_ptr,
+			handle_ptr,
+			manager_ptr,
+			ns,
+			url: format!("opc.tcp://127.0.0.1:{port}/"),
+			objects_folder: ObjectId::ObjectsFolder.into(),
+			pki_dir,
+			task: Some(task),
+			runtime: Some(runtime),
+		}
+	}
+
+	// Variable of the LabVIEW type id in the Objects folder (lv_add_variable)
+	pub fn add_variable(&self, node: &str, var_type: u16) -> i32 {
+		let node = CString::new(node).unwrap();
+		let mut folder = self.objects_folder.clone();
+		crate::server_variables::lv_add_variable(
+			node.as_ptr(),
+			node.as_ptr(),
+			node.as_ptr(),
+			self.ns,
+			var_type,
+			self.manager_ptr,
+			&mut folder,
+		)
+	}
+
+	pub fn handle(&self) -> &ServerHandle {
+		unsafe { &*self.handle_ptr }
+	}
+}
+
+impl Drop for TestServer {
+	fn drop(&mut self) {
+		self.handle().cancel();
+		if let (Some(runtime), Some(task)) = (self.runtime.take(), self.task.take()) {
+			let _ = runtime.block_on(task);
+			drop(runtime);
+		}
+		assert_eq!(lv_free_node_manager(self.manager_ptr), NO_ERR);
+		assert_eq!(lv_free_server(self.server_ptr), NO_ERR);
+		assert_eq!(lv_free_server_handle(self.handle_ptr), NO_ERR);
+		let _ = std::fs::remove_dir_all(&self.pki_dir);
+	}
+}
+
+// Client session on the test server, connected with lv_connect_to_endpoint
+pub struct TestClient {
+	pub rt_ptr: *mut Runtime,
+	pub client_ptr: *mut Client,
+	pub session_ptr: *mut Arc<Session>,
+	event_loop_ptr: *mut JoinHandle<StatusCode>,
+	pki_dir: PathBuf,
+}
+
+impl TestClient {
+	pub fn connect(server: &TestServer) -> Self {
+		let pki_dir = test_dir("client");
+		let client = ClientBuilder::new()
+			.application_name("opcua-dll test client")
+			.application_uri("urn:opcua-dll-test-client")
+			.product_uri("urn:opcua-dll-test-client")
+			.pki_dir(&pki_dir)
+			.trust_server_certs(true)
+			.session_retry_limit(1)
+			.client()
+			.unwrap();
+		let client_ptr: *mut Client = into_handle(client, HandleKind::Client);
+		let rt_ptr = crate::runtime::lv_new_runtime();
+
+		let url = CString::new(server.url.clone()).unwrap();
+		let mut session_ptr = std::ptr::null_mut();
+		let mut event_loop_ptr = std::ptr::null_mut();
+		let mut loop_handle_ptr = std::ptr::null_mut();
+		let err = crate::client::lv_connect_to_endpoint(
+			rt_ptr,
+			client_ptr,
+			url.as_ptr(),
+			c"None".as_ptr(),
+			c"None".as_ptr(),
+			std::ptr::null(),
+			std::ptr::null(),
+			std::ptr::null(),
+			std::ptr::null(),
+			0,
+			&mut session_ptr,
+			&mut event_loop_ptr,
+			&mut loop_handle_ptr,
+		);
+		assert_eq!(err, NO_ERR, "{}", last_error_detail());
+
+		TestClient {
+			rt_ptr,
+			client_ptr,
+			session_ptr,
+			event_loop_ptr: loop_handle_ptr,
+			pki_dir,
+		}
+	}
+
+	pub fn session(&self) -> &Arc<Session> {
+		unsafe { &*self.session_ptr }
+	}
+
+	pub fn runtime(&self) -> &Runtime {
+		unsafe { &*self.rt_ptr }
+	}
+}
+
+impl Drop for TestClient {
+	fn drop(&mut self) {
+		let session = self.session().clone();
+		let _ = self.runtime().block_on(session.disconnect());
+		crate::client::lv_free_session(self.session_ptr);
+		crate::client::lv_free_client(self.client_ptr);
+		unsafe {
+			drop(from_handle(self.event_loop_ptr));
+			from_handle(self.rt_ptr).shutdown_background();
+		}
+		let _ = std::fs::remove_dir_all(&self.pki_dir);
+	}
+}
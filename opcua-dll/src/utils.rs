@@ -1,16 +1,193 @@
+use crate::errors::*;
+use crate::labview::{LStrHandle, string_to_lstr};
+
 use chrono::Utc;
 use libc::c_double;
+use opcua::client::Session;
+use opcua::types::{ByteString, DateTime, Guid, NodeId, TimestampsToReturn, VariableId, Variant};
+use std::{os::raw::c_char, str::FromStr, time::Duration};
 
 const MAC_EPOCH_OFFSET: f64 = 2082844800.0; // 1904-01-01 to 1970-01-01 in seconds
+const COCOA_EPOCH_TICKS: i64 = 9561628800 * TICKS_PER_SECOND; // 1601-01-01 to 1904-01-01
+const TICKS_PER_SECOND: i64 = 10_000_000; // OPC UA DateTime, 100 ns
+
+// Version of the crate, checked when building: a version part which isn't a
+// number or version 0.0.0 fails the build
+const VERSION_MAJOR: u32 = parse_version_part(env!("CARGO_PKG_VERSION_MAJOR"));
+const VERSION_MINOR: u32 = parse_version_part(env!("CARGO_PKG_VERSION_MINOR"));
+const VERSION_PATCH: u32 = parse_version_part(env!("CARGO_PKG_VERSION_PATCH"));
+const _: () = assert!(
+	VERSION_MAJOR != 0 || VERSION_MINOR != 0 || VERSION_PATCH != 0,
+	"The crate version must not be 0.0.0"
+);
+
+const fn parse_version_part(part: &str) -> u32 {
+	let digits = part.as_bytes();
+	assert!(!digits.is_empty(), "Empty version part");
+	let mut value = 0;
+	let mut i = 0;
+	while i < digits.len() {
+		assert!(digits[i].is_ascii_digit(), "Version part is not a number");
+		value = value * 10 + (digits[i] - b'0') as u32;
+		i += 1;
+	}
+	value
+}
 
 //==============================================================================
 // Will be used later to get TimeStaps in LabVIEW
 //
 #[unsafe(no_mangle)]
 pub extern "C" fn get_current_cocoa_timestamp() -> c_double {
-	let now = Utc::now();
-	let unix_seconds = now.timestamp() as f64;
-	let nanos_fraction = now.timestamp_subsec_nanos() as f64 / 1e9;
+	catch_panic!(0.0, {
+		let now = Utc::now();
+		let unix_seconds = now.timestamp() as f64;
+		let nanos_fraction = now.timestamp_subsec_nanos() as f64 / 1e9;
+
+		(unix_seconds + nanos_fraction) + MAC_EPOCH_OFFSET
+	})
+}
+
+//==============================================================================
+// LabVIEW (Cocoa, 1904 epoch) timestamp to OPC UA DateTime and back
+//
+pub fn cocoa_to_date_time(cocoa_timestamp: f64) -> DateTime {
+	let unix_seconds = cocoa_timestamp - MAC_EPOCH_OFFSET;
+	let seconds = unix_seconds.floor();
+	let nanos = ((unix_seconds - seconds) * 1e9) as u32;
+
+	match chrono::DateTime::from_timestamp(seconds as i64, nanos) {
+		Some(date_time) => DateTime::from(date_time),
+		None => DateTime::null(),
+	}
+}
+
+// As cocoa_to_date_time, in the OPC UA ticks (100 ns since 1601-01-01):
+// whole seconds and the fraction are converted apart, so the fraction keeps
+// the precision of the f64. None for NaN/Inf and timestamps before 1601-01-01
+// or after 9999-12-31, which OPC UA can't represent
+pub fn cocoa_to_date_time_checked(cocoa_timestamp: f64) -> Option<DateTime> {
+	if !cocoa_timestamp.is_finite() {
+		return None;
+	}
+	let seconds = cocoa_timestamp.floor();
+	let fraction_ticks = ((cocoa_timestamp - seconds) * TICKS_PER_SECOND as f64).round() as i64;
+	let ticks = (seconds as i64)
+		.checked_mul(TICKS_PER_SECOND)?
+		.checked_add(COCOA_EPOCH_TICKS)?
+		.checked_add(fraction_ticks)?;
+	(0..=DateTime::endtimes_ticks())
+		.contains(&ticks)
+		.then(|| DateTime::from(ticks))
+}
+
+pub fn date_time_to_cocoa(date_time: &DateTime) -> f64 {
+	let utc = date_time.as_chrono();
+	let unix_seconds = utc.timestamp() as f64;
+	let nanos_fraction = utc.timestamp_subsec_nanos() as f64 / 1e9;
 
 	(unix_seconds + nanos_fraction) + MAC_EPOCH_OFFSET
 }
+
+//==============================================================================
+// NodeId of the id inputs of lvBrowser* and lv_get_node_info by id_type:
+// 1 - numeric id_u32 (namespace 0), 2 - string id_str, 3 - GUID id_str
+// ("72962b91-fa75-4ae6-8d28-b404dc7daf63"), 4 - opaque ByteString id_str as
+// base64, 5 - id_str as the node_uid of lvBrowser ("i=85", "s=Tank1",
+// "g=72962b91-...", "b=AQID") or a full node id ("ns=3;g=72962b91-...").
+// 2..5 are in namespace ns unless id_str has "ns=".
+// ERR_INVALID_TYPE for another id_type, ERR_INVALID_ARGUMENT if id_str
+// doesn't parse as the id_type
+//
+pub fn lv_node_id(
+	id_u32: u32,
+	id_str: *const c_char,
+	ns: u16,
+	id_type: u32,
+) -> Result<NodeId, i32> {
+	if id_type == 1 {
+		return Ok(NodeId::new(0, id_u32));
+	}
+	if !(2..=5).contains(&id_type) {
+		return Err(ERR_INVALID_TYPE);
+	}
+	check_null!(id_str, Err(ERR_NULL_POINTER));
+	let text = cstr_to_string!(id_str);
+	let node_id = match id_type {
+		2 => Some(NodeId::new(ns, text.clone())),
+		3 => Guid::from_str(text.trim())
+			.ok()
+			.map(|guid| NodeId::new(ns, guid)),
+		4 => ByteString::from_base64(text.trim()).map(|bytes| NodeId::new(ns, bytes)),
+		_ => match NodeId::from_str(text.trim()) {
+			Ok(node_id) if text.trim_start().starts_with("ns=") => Some(node_id),
+			Ok(node_id) => Some(NodeId::new(ns, node_id.identifier)),
+			Err(_) => None,
+		},
+	};
+	node_id.ok_or_else(|| {
+		set_last_error_detail(format!("'{text}' is not a node id of id type {id_type}"));
+		ERR_INVALID_ARGUMENT
+	})
+}
+
+//==============================================================================
+// Await the request for at most timeout_ms (0 - no limit, the session
+// request timeout applies), Err(ERR_TIMEOUT) if it takes longer.
+// The dropped request doesn't harm the session, a late response is discarded
+//
+pub async fn with_timeout<F: Future>(timeout_ms: u32, request: F) -> Result<F::Output, i32> {
+	if timeout_ms == 0 {
+		return Ok(request.await);
+	}
+	tokio::time::timeout(Duration::from_millis(timeout_ms as u64), request)
+		.await
+		.map_err(|_| {
+			set_last_error_detail(format!("No response within {timeout_ms} ms"));
+			ERR_TIMEOUT
+		})
+}
+
+//==============================================================================
+// Query an OperationLimits variable of the server (e.g.
+// MaxNodesPerHistoryUpdateData), default if not available or 0 ("no limit")
+//
+pub async fn operation_limit(session: &Session, limit: VariableId, default: usize) -> usize {
+	let limit_id: NodeId = limit.into();
+	match session
+		.read(&[limit_id.into()], TimestampsToReturn::Neither, 0.0)
+		.await
+	{
+		Ok(values) => match values.first().and_then(|v| v.value.as_ref()) {
+			Some(Variant::UInt32(limit)) if *limit > 0 => *limit as usize,
+			_ => default,
+		},
+		Err(_) => default,
+	}
+}
+
+//==============================================================================
+// Version of the DLL (Cargo.toml of opcua-dll), e.g. 0.2.0 and "0.2.0",
+// to check which DLL the VIs use. Needs no runtime
+//
+#[unsafe(no_mangle)]
+pub extern "C" fn lv_get_dll_version(
+	major_out: *mut u32,
+	minor_out: *mut u32,
+	patch_out: *mut u32,
+	build_str_out: LStrHandle,
+) -> i32 {
+	catch_panic!(ERR_INTERNAL_PANIC, {
+		check_null!(major_out, ERR_NULL_POINTER);
+		check_null!(minor_out, ERR_NULL_POINTER);
+		check_null!(patch_out, ERR_NULL_POINTER);
+		check_null!(build_str_out, ERR_NULL_POINTER);
+
+		unsafe {
+			*major_out = VERSION_MAJOR;
+			*minor_out = VERSION_MINOR;
+			*patch_out = VERSION_PATCH;
+			string_to_lstr(env!("CARGO_PKG_VERSION"), build_str_out)
+		}
+	})
+}
@@ -11,6 +11,14 @@ pub struct ClientBuilder {
     config: ClientConfig,
 }
 
+impl From<ClientConfig> for ClientBuilder {
+    /// Creates a `ClientBuilder` using an existing configuration as the initial state, e.g.
+    /// the config of a client to change some of its settings.
+    fn from(config: ClientConfig) -> Self {
+        ClientBuilder { config }
+    }
+}
+
 impl ClientBuilder {
     /// Creates a `ClientBuilder`
     pub fn new() -> ClientBuilder {
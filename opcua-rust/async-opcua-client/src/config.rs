@@ -16,7 +16,7 @@ use chrono::TimeDelta;
 use serde::{Deserialize, Serialize};
 use tracing::warn;
 
-use opcua_core::config::Config;
+use opcua_core::{comms::url::is_valid_opc_ua_url, config::Config};
 use opcua_crypto::SecurityPolicy;
 use opcua_types::{ApplicationType, EndpointDescription, MessageSecurityMode, UAString};
 
@@ -311,6 +311,14 @@ impl Config for ClientConfig {
         if self.application_uri.is_empty() {
             errors.push("Application uri is empty".to_owned());
         }
+        if self.pki_dir.as_os_str().is_empty() {
+            errors.push("PKI dir is empty".to_owned());
+        } else if self.pki_dir.exists() && !self.pki_dir.is_dir() {
+            errors.push(format!(
+                "PKI dir {} is not a directory",
+                self.pki_dir.display()
+            ));
+        }
         if self.user_tokens.contains_key(ANONYMOUS_USER_TOKEN_ID) {
             errors.push(format!(
                 "User tokens contains the reserved \"{}\" id",
@@ -340,8 +348,11 @@ impl Config for ClientConfig {
                     self.default_endpoint
                 ));
             }
-            // Check for invalid security policy and modes in endpoints
+            // Check for invalid urls, security policy and modes in endpoints
             self.endpoints.iter().for_each(|(id, e)| {
+                if !is_valid_opc_ua_url(&e.url) {
+                    errors.push(format!("Endpoint {} url {} is malformed", id, e.url));
+                }
                 if SecurityPolicy::from_str(&e.security_policy).unwrap() != SecurityPolicy::Unknown
                 {
                     if MessageSecurityMode::Invalid
@@ -747,6 +758,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn client_malformed_endpoint_url_config() {
+        let mut config = default_sample_config();
+        config.endpoints = BTreeMap::new();
+        config.endpoints.insert(
+            String::from("sample_none"),
+            ClientEndpoint {
+                url: String::from("http://127.0.0.1:4855"),
+                security_policy: String::from(SecurityPolicy::None.to_str()),
+                security_mode: String::from(MessageSecurityMode::None),
+                user_token_id: ANONYMOUS_USER_TOKEN_ID.to_string(),
+            },
+        );
+        assert_eq!(
+            config.validate().unwrap_err().join(", "),
+            "Endpoint sample_none url http://127.0.0.1:4855 is malformed"
+        );
+    }
+
+    #[test]
+    fn client_pki_dir_not_a_directory_config() {
+        let path = make_test_file("client_pki_dir_not_a_directory");
+        std::fs::write(&path, b"not a directory").unwrap();
+        let mut config = default_sample_config();
+        config.pki_dir = path.clone();
+        assert_eq!(
+            config.validate().unwrap_err().join(", "),
+            format!("PKI dir {} is not a directory", path.display())
+        );
+    }
+
     #[test]
     fn client_anonymous_user_tokens_id() {
         let mut config = default_sample_config();
@@ -130,7 +130,8 @@ pub use session::{
     Client, DataChangeCallback, DefaultRetryPolicy, EventCallback, HistoryReadAction,
     HistoryUpdateAction, MonitoredItem, OnSubscriptionNotification, RequestRetryPolicy, Session,
     SessionActivity, SessionBuilder, SessionConnectMode, SessionEventLoop, SessionPollResult,
-    Subscription, SubscriptionActivity, SubscriptionCallbacks, UARequest,
+    Subscription, SubscriptionActivity, SubscriptionCallbacks, SubscriptionTransferResult,
+    UARequest,
 };
 pub use transport::AsyncSecureChannel;
 
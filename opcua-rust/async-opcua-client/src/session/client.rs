@@ -755,4 +755,9 @@ impl Client {
     pub fn certificate_store(&self) -> &Arc<RwLock<CertificateStore>> {
         &self.certificate_store
     }
+
+    /// Get the configuration of the client, e.g. to save it to a file.
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
 }
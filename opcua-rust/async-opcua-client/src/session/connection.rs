@@ -19,6 +19,7 @@ struct SessionBuilderInner {
     user_identity_token: IdentityToken,
     connector: Box<dyn Connector>,
     type_loaders: Vec<Arc<dyn TypeLoader>>,
+    verify_server_hostname: bool,
 }
 
 /// Type-state builder for a session and session event loop.
@@ -44,6 +45,7 @@ impl<'a> SessionBuilder<'a, (), ()> {
                 user_identity_token: IdentityToken::Anonymous,
                 connector: Box::new(TcpConnector),
                 type_loaders: Vec::new(),
+                verify_server_hostname: true,
             },
         }
     }
@@ -89,6 +91,15 @@ impl<T, R> SessionBuilder<'_, T, R> {
         self
     }
 
+    /// Set whether the hostname in the server certificate must match the endpoint URL.
+    /// Turn this off only for a server reached through an address that is not in its
+    /// certificate, e.g. behind NAT. The other certificate checks are still done.
+    /// Default is `true`.
+    pub fn verify_server_hostname(mut self, verify_server_hostname: bool) -> Self {
+        self.inner.verify_server_hostname = verify_server_hostname;
+        self
+    }
+
     fn endpoint_supports_token(&self, endpoint: &EndpointDescription) -> bool {
         match &self.inner.user_identity_token {
             IdentityToken::Anonymous => {
@@ -284,6 +295,7 @@ impl<R> SessionBuilder<'_, EndpointDescription, R> {
             self.inner.session_id,
             self.inner.connector,
             self.inner.type_loaders,
+            self.inner.verify_server_hostname,
         )
     }
 }
@@ -272,6 +272,12 @@ impl SessionEventLoop {
     }
 }
 
+impl Drop for SessionEventLoop {
+    fn drop(&mut self) {
+        self.inner.has_event_loop.store(false, Ordering::Release);
+    }
+}
+
 /// Periodic activity performed by the session.
 #[derive(Debug, Clone)]
 pub enum SessionActivity {
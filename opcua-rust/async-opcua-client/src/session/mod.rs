@@ -64,7 +64,7 @@ pub use services::subscriptions::{
     CreateMonitoredItems, CreateSubscription, DataChangeCallback, DeleteMonitoredItems,
     DeleteSubscriptions, EventCallback, ModifyMonitoredItems, ModifySubscription, MonitoredItem,
     OnSubscriptionNotification, SetMonitoringMode, SetPublishingMode, SetTriggering, Subscription,
-    SubscriptionActivity, SubscriptionCallbacks, TransferSubscriptions,
+    SubscriptionActivity, SubscriptionCallbacks, SubscriptionTransferResult, TransferSubscriptions,
 };
 pub use services::view::{
     Browse, BrowseNext, RegisterNodes, TranslateBrowsePaths, UnregisterNodes,
@@ -107,7 +107,7 @@ macro_rules! session_trace {
 #[allow(unused)]
 pub(crate) use session_trace;
 
-use opcua_core::ResponseMessage;
+use opcua_core::{trace_lock, ResponseMessage};
 use opcua_types::{
     ApplicationDescription, ContextOwned, DecodingOptions, EndpointDescription, Error, IntegerId,
     NamespaceMap, NodeId, ReadValueId, RequestHeader, ResponseHeader, StatusCode,
@@ -181,6 +181,7 @@ pub struct Session {
     pub(super) publish_timeout: Duration,
     pub(super) recreate_monitored_items_chunk: usize,
     pub(super) recreate_subscriptions: bool,
+    pub(super) verify_server_hostname: bool,
     pub(super) should_reconnect: AtomicBool,
     pub(super) session_timeout: f64,
     /// Reference to the subscription cache for the client.
@@ -189,6 +190,13 @@ pub struct Session {
     pub(super) publish_limits_watch_tx: tokio::sync::watch::Sender<PublishLimits>,
     pub(super) monitored_item_handle: AtomicHandle,
     pub(super) trigger_publish_tx: tokio::sync::watch::Sender<Instant>,
+    pub(super) session_retry_policy: SessionRetryPolicy,
+    pub(super) keep_alive_interval: Duration,
+    pub(super) max_failed_keep_alive_count: u64,
+    /// Set while a [`SessionEventLoop`] of the session exists.
+    pub(super) has_event_loop: AtomicBool,
+    /// Subscriptions created again with a new id on reconnect, as (old id, new id).
+    pub(super) recreated_subscriptions: Mutex<Vec<(u32, u32)>>,
     decoding_options: DecodingOptions,
     pub(super) encoding_context: Arc<RwLock<ContextOwned>>,
 }
@@ -206,6 +214,7 @@ impl Session {
         session_id: Option<NodeId>,
         connector: Box<dyn Connector>,
         extra_type_loaders: Vec<Arc<dyn TypeLoader>>,
+        verify_server_hostname: bool,
     ) -> (Arc<Self>, SessionEventLoop) {
         let auth_token: Arc<ArcSwap<NodeId>> = Arc::default();
         let (publish_limits_watch_tx, publish_limits_watch_rx) =
@@ -255,6 +264,7 @@ impl Session {
             publish_timeout: config.publish_timeout,
             recreate_monitored_items_chunk: config.performance.recreate_monitored_items_chunk,
             recreate_subscriptions: config.recreate_subscriptions,
+            verify_server_hostname,
             should_reconnect: AtomicBool::new(true),
             subscription_state: Mutex::new(SubscriptionState::new(
                 config.min_publish_interval,
@@ -264,6 +274,11 @@ impl Session {
             publish_limits_watch_rx,
             publish_limits_watch_tx,
             trigger_publish_tx,
+            session_retry_policy: session_retry_policy.clone(),
+            keep_alive_interval: config.keep_alive_interval,
+            max_failed_keep_alive_count: config.max_failed_keep_alive_count,
+            has_event_loop: AtomicBool::new(true),
+            recreated_subscriptions: Mutex::new(Vec::new()),
             decoding_options,
             encoding_context,
         });
@@ -280,6 +295,37 @@ impl Session {
         )
     }
 
+    /// Create a new event loop for the session after the previous one has ended, e.g. because
+    /// it failed to reconnect after a loss of connection or reconnects were disabled. Polling
+    /// it connects the session again, like the first event loop, and reconnects are enabled.
+    /// The subscriptions of the session are transferred to the new server session, or created
+    /// again if that fails, see [`Session::take_recreated_subscriptions`].
+    ///
+    /// # Errors
+    ///
+    /// `BadInvalidState` if an event loop of the session still exists, only one may drive the
+    /// session.
+    pub fn new_event_loop(self: &Arc<Self>) -> Result<SessionEventLoop, StatusCode> {
+        if self.has_event_loop.swap(true, Ordering::AcqRel) {
+            return Err(StatusCode::BadInvalidState);
+        }
+        self.enable_reconnects();
+        Ok(SessionEventLoop::new(
+            self.clone(),
+            self.session_retry_policy.clone(),
+            self.trigger_publish_tx.subscribe(),
+            self.keep_alive_interval,
+            self.max_failed_keep_alive_count,
+        ))
+    }
+
+    /// Take the subscriptions which could not be transferred on reconnect and were created
+    /// again on the server, as pairs of (old subscription id, new subscription id) in the
+    /// order they were created. The list is emptied.
+    pub fn take_recreated_subscriptions(&self) -> Vec<(u32, u32)> {
+        std::mem::take(&mut *trace_lock!(self.recreated_subscriptions))
+    }
+
     /// Create a request header with the default timeout.
     pub(super) fn make_request_header(&self) -> RequestHeader {
         self.channel.make_request_header(self.request_timeout)
@@ -330,6 +376,27 @@ impl Session {
         self.wait_for_state(true).await
     }
 
+    /// Wait until the session is no longer connected, i.e. it is disconnected or in the middle
+    /// of a reconnect. Returns immediately if the session isn't connected.
+    pub async fn wait_for_connection_lost(&self) -> bool {
+        let mut rx = self.state_watch_rx.clone();
+
+        let res = rx
+            .wait_for(|s| !matches!(*s, SessionState::Connected))
+            .await
+            .is_ok();
+
+        // Compiler limitation
+        #[allow(clippy::let_and_return)]
+        res
+    }
+
+    /// Check whether the session is currently connected, i.e. not disconnected
+    /// or in the middle of a reconnect.
+    pub fn is_connected(&self) -> bool {
+        matches!(*self.state_watch_rx.borrow(), SessionState::Connected)
+    }
+
     /// Disable automatic reconnects.
     /// This will make the event loop quit the next time
     /// it disconnects for whatever reason.
@@ -392,6 +459,17 @@ impl Session {
         &self.channel
     }
 
+    /// Get the endpoint the session was created for, including the
+    /// application description of the server.
+    pub fn endpoint(&self) -> &EndpointDescription {
+        &self.session_info.endpoint
+    }
+
+    /// Get the certificate store holding the own certificate and key of the session.
+    pub fn certificate_store(&self) -> &Arc<RwLock<CertificateStore>> {
+        &self.certificate_store
+    }
+
     /// Get the next request handle.
     pub fn request_handle(&self) -> IntegerId {
         self.channel.request_handle()
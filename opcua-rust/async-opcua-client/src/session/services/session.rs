@@ -49,6 +49,7 @@ pub struct CreateSession<'a> {
     max_response_message_size: u32,
     certificate_store: &'a RwLock<CertificateStore>,
     endpoint: &'a EndpointDescription,
+    verify_server_hostname: bool,
 
     header: RequestHeaderBuilder,
 }
@@ -75,6 +76,7 @@ impl<'a> CreateSession<'a> {
             },
             endpoint: &session.session_info.endpoint,
             certificate_store: &session.certificate_store,
+            verify_server_hostname: session.verify_server_hostname,
             session_timeout: session.session_timeout,
             max_response_message_size: 0,
             header: RequestHeaderBuilder::new_from_session(session),
@@ -100,6 +102,7 @@ impl<'a> CreateSession<'a> {
             max_response_message_size: 0,
             certificate_store,
             endpoint,
+            verify_server_hostname: true,
             header: RequestHeaderBuilder::new(session_id, timeout, auth_token, request_handle),
         }
     }
@@ -156,6 +159,13 @@ impl<'a> CreateSession<'a> {
         self.max_response_message_size = max_response_message_size;
         self
     }
+
+    /// Set whether the hostname in the server certificate must match the endpoint URL.
+    /// The other certificate checks are still done. Default is `true`.
+    pub fn verify_server_hostname(mut self, verify_server_hostname: bool) -> Self {
+        self.verify_server_hostname = verify_server_hostname;
+        self
+    }
 }
 
 impl UARequest for CreateSession<'_> {
@@ -189,15 +199,21 @@ impl UARequest for CreateSession<'_> {
                     opcua_crypto::X509::from_byte_string(&response.server_certificate)
                 {
                     // Validate server certificate against hostname and application_uri
-                    let hostname = hostname_from_url(self.endpoint.endpoint_url.as_ref())
-                        .map_err(|_| StatusCode::BadUnexpectedError)?;
+                    let hostname = if self.verify_server_hostname {
+                        Some(
+                            hostname_from_url(self.endpoint.endpoint_url.as_ref())
+                                .map_err(|_| StatusCode::BadUnexpectedError)?,
+                        )
+                    } else {
+                        None
+                    };
                     let application_uri = self.endpoint.server.application_uri.as_ref();
 
                     let certificate_store = trace_write_lock!(self.certificate_store);
                     certificate_store.validate_or_reject_application_instance_cert(
                         &server_certificate,
                         security_policy,
-                        Some(&hostname),
+                        hostname.as_deref(),
                         Some(application_uri),
                     )?;
                 } else {
@@ -18,7 +18,7 @@ use opcua_types::{
 pub use service::{
     CreateMonitoredItems, CreateSubscription, DeleteMonitoredItems, DeleteSubscriptions,
     ModifyMonitoredItems, ModifySubscription, SetMonitoringMode, SetPublishingMode, SetTriggering,
-    TransferSubscriptions,
+    SubscriptionTransferResult, TransferSubscriptions,
 };
 
 pub(crate) struct CreateMonitoredItem {
@@ -36,6 +36,7 @@ pub(crate) struct ModifyMonitoredItem {
     pub id: u32,
     pub sampling_interval: f64,
     pub queue_size: u32,
+    pub filter: ExtensionObject,
 }
 
 /// A set of callbacks for notifications on a subscription.
@@ -53,6 +54,11 @@ pub trait OnSubscriptionNotification: Send + Sync {
     /// Called for each received event.
     #[allow(unused)]
     fn on_event(&mut self, event_fields: Option<Vec<Variant>>, item: &MonitoredItem) {}
+
+    /// Called after the data values, events and status changes of one
+    /// notification message were delivered, e.g. to pass on what was
+    /// collected in one publishing cycle at once.
+    fn on_notification_end(&mut self) {}
 }
 
 type StatusChangeCallbackFun = dyn FnMut(StatusChangeNotification) + Send + Sync;
@@ -222,6 +228,11 @@ impl MonitoredItem {
         self.discard_oldest
     }
 
+    /// Filter of the monitored item, as last accepted by the server.
+    pub fn filter(&self) -> &ExtensionObject {
+        &self.filter
+    }
+
     pub(crate) fn set_sampling_interval(&mut self, value: f64) {
         self.sampling_interval = value;
     }
@@ -230,6 +241,10 @@ impl MonitoredItem {
         self.queue_size = value;
     }
 
+    pub(crate) fn set_filter(&mut self, filter: ExtensionObject) {
+        self.filter = filter;
+    }
+
     pub(crate) fn set_monitoring_mode(&mut self, monitoring_mode: MonitoringMode) {
         self.monitoring_mode = monitoring_mode;
     }
@@ -398,6 +413,7 @@ impl Subscription {
             if let Some(ref mut monitored_item) = self.monitored_items.get_mut(&i.id) {
                 monitored_item.set_sampling_interval(i.sampling_interval);
                 monitored_item.set_queue_size(i.queue_size as usize);
+                monitored_item.set_filter(i.filter.clone());
             }
         });
     }
@@ -458,6 +474,7 @@ impl Subscription {
                 }
             )
         }
+        self.callback.on_notification_end();
     }
 }
 
@@ -30,6 +30,17 @@ use tracing::{debug, enabled};
 
 use super::{state::SubscriptionState, OnSubscriptionNotification};
 
+/// Outcome of moving a subscription to another session with [`Session::take_subscriptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionTransferResult {
+    /// The subscription was transferred on the server and keeps its id.
+    Transferred,
+    /// The transfer failed, so the subscription was created again with this new id.
+    Recreated(u32),
+    /// The subscription could neither be transferred nor created again.
+    Failed(StatusCode),
+}
+
 /// Create a subscription by sending a [`CreateSubscriptionRequest`] to the server.
 ///
 /// See OPC UA Part 4 - Services 5.13.2 for complete description of the service and error responses.
@@ -948,10 +959,10 @@ impl UARequest for ModifyMonitoredItems<'_> {
             );
             return Err(StatusCode::BadNothingToDo);
         }
-        let ids = self
+        let requested = self
             .items_to_modify
             .iter()
-            .map(|i| i.monitored_item_id)
+            .map(|i| (i.monitored_item_id, i.requested_parameters.filter.clone()))
             .collect::<Vec<_>>();
         let request = ModifyMonitoredItemsRequest {
             request_header: self.header.header,
@@ -966,22 +977,25 @@ impl UARequest for ModifyMonitoredItems<'_> {
                 builder_error!(self, "modify_monitored_items, got empty response");
                 return Err(StatusCode::BadUnexpectedError);
             };
-            if results.len() != ids.len() {
+            if results.len() != requested.len() {
                 builder_error!(
                     self,
                     "modify_monitored_items, unexpected number of results. Expected {}, got {}",
-                    ids.len(),
+                    requested.len(),
                     results.len()
                 );
                 return Err(StatusCode::BadUnexpectedError);
             }
-            let items_to_modify = ids
-                .iter()
+            // Items the server rejected keep their previous parameters
+            let items_to_modify = requested
+                .into_iter()
                 .zip(results.iter())
-                .map(|(id, r)| ModifyMonitoredItem {
-                    id: *id,
+                .filter(|(_, r)| r.status_code.is_good())
+                .map(|((id, filter), r)| ModifyMonitoredItem {
+                    id,
                     queue_size: r.revised_queue_size,
                     sampling_interval: r.revised_sampling_interval,
+                    filter,
                 })
                 .collect::<Vec<ModifyMonitoredItem>>();
             {
@@ -1670,6 +1684,145 @@ impl Session {
         Ok(r)
     }
 
+    /// Move subscriptions from another session, typically one to a failed server in a
+    /// redundant setup, to this session. The subscriptions are removed from the other session,
+    /// transferred with [`Session::transfer_subscriptions`], and created again with their
+    /// monitored items if the transfer fails. Callbacks of the subscriptions are kept, so
+    /// notifications continue to be delivered to the same place.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SubscriptionTransferResult>)` - The outcome for each subscription id.
+    /// * `Err(StatusCode)` - Request failed, [Status code](StatusCode) is the reason for failure.
+    ///
+    pub async fn take_subscriptions(
+        &self,
+        other: &Session,
+        subscription_ids: &[u32],
+    ) -> Result<Vec<SubscriptionTransferResult>, StatusCode> {
+        if subscription_ids.is_empty() {
+            session_error!(
+                self,
+                "take_subscriptions, no subscription ids were provided"
+            );
+            return Err(StatusCode::BadNothingToDo);
+        }
+
+        let mut results =
+            vec![
+                SubscriptionTransferResult::Failed(StatusCode::BadSubscriptionIdInvalid);
+                subscription_ids.len()
+            ];
+        let to_transfer = self.move_subscription_states(other, subscription_ids);
+        if to_transfer.is_empty() {
+            return Ok(results);
+        }
+
+        let ids = to_transfer
+            .iter()
+            .map(|i| subscription_ids[*i])
+            .collect::<Vec<u32>>();
+        let transfer_results = self.transfer_subscriptions(&ids, true).await;
+        for (n, i) in to_transfer.into_iter().enumerate() {
+            let transferred = match &transfer_results {
+                Ok(r) => r.get(n).is_some_and(|r| r.status_code.is_good()),
+                Err(_) => false,
+            };
+            if transferred {
+                results[i] = SubscriptionTransferResult::Transferred;
+                continue;
+            }
+
+            let deleted_subscription = {
+                let mut subscription_state = trace_lock!(self.subscription_state);
+                subscription_state.delete_subscription(subscription_ids[i])
+            };
+            let Some(subscription) = deleted_subscription else {
+                continue;
+            };
+            results[i] = match self.recreate_subscription(subscription).await {
+                Ok(new_id) => SubscriptionTransferResult::Recreated(new_id),
+                Err(status) => SubscriptionTransferResult::Failed(status),
+            };
+        }
+
+        Ok(results)
+    }
+
+    /// Move subscriptions from another session to this session with
+    /// [`Session::transfer_subscriptions`], like [`Session::take_subscriptions`] but without
+    /// creating them again. Subscriptions which fail to transfer are left with the other session.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The session which owns the subscriptions.
+    /// * `subscription_ids` - The subscriptions to move.
+    /// * `send_initial_values` - Have the server send the current values of the monitored items
+    ///   of each transferred subscription.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<StatusCode>)` - The transfer status for each subscription id,
+    ///   `BadSubscriptionIdInvalid` if the other session doesn't have the subscription.
+    /// * `Err(StatusCode)` - Request failed, [Status code](StatusCode) is the reason for failure.
+    ///
+    pub async fn transfer_subscriptions_from(
+        &self,
+        other: &Session,
+        subscription_ids: &[u32],
+        send_initial_values: bool,
+    ) -> Result<Vec<StatusCode>, StatusCode> {
+        if subscription_ids.is_empty() {
+            session_error!(
+                self,
+                "transfer_subscriptions_from, no subscription ids were provided"
+            );
+            return Err(StatusCode::BadNothingToDo);
+        }
+
+        let mut results = vec![StatusCode::BadSubscriptionIdInvalid; subscription_ids.len()];
+        let to_transfer = self.move_subscription_states(other, subscription_ids);
+        if to_transfer.is_empty() {
+            return Ok(results);
+        }
+
+        let ids = to_transfer
+            .iter()
+            .map(|i| subscription_ids[*i])
+            .collect::<Vec<u32>>();
+        let transfer_results = self.transfer_subscriptions(&ids, send_initial_values).await;
+        for (n, i) in to_transfer.into_iter().enumerate() {
+            results[i] = match &transfer_results {
+                Ok(r) => r
+                    .get(n)
+                    .map(|r| r.status_code)
+                    .unwrap_or(StatusCode::BadUnexpectedError),
+                Err(status) => *status,
+            };
+            if results[i].is_bad() {
+                // Give it back
+                other.move_subscription_states(self, &subscription_ids[i..=i]);
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Moves the client side state of the subscriptions from the other session to this one,
+    /// returns the indices of the ids which were moved.
+    fn move_subscription_states(&self, other: &Session, subscription_ids: &[u32]) -> Vec<usize> {
+        let mut moved = Vec::with_capacity(subscription_ids.len());
+        let mut other_state = trace_lock!(other.subscription_state);
+        let mut subscription_state = trace_lock!(self.subscription_state);
+        for (i, id) in subscription_ids.iter().enumerate() {
+            if let Some(subscription) = other_state.delete_subscription(*id) {
+                subscription_state.add_subscription(subscription);
+                moved.push(i);
+            }
+        }
+        moved
+    }
+
     /// Deletes a subscription by sending a [`DeleteSubscriptionsRequest`] to the server.
     ///
     /// See OPC UA Part 4 - Services 5.13.8 for complete description of the service and error responses.
@@ -2041,67 +2194,79 @@ impl Session {
                 continue;
             };
 
-            let Ok(subscription_id) = self
-                .create_subscription_inner(
-                    subscription.publishing_interval,
-                    subscription.lifetime_count,
-                    subscription.max_keep_alive_count,
-                    subscription.max_notifications_per_publish,
-                    subscription.publishing_enabled,
-                    subscription.priority,
-                    subscription.callback,
-                )
-                .await
-            else {
-                session_warn!(
-                    self,
-                    "Could not create a subscription from the existing subscription {}",
-                    subscription_id
-                );
-                continue;
-            };
+            match self.recreate_subscription(subscription).await {
+                Ok(new_subscription_id) => {
+                    trace_lock!(self.recreated_subscriptions)
+                        .push((subscription_id, new_subscription_id));
+                }
+                Err(_) => {
+                    session_warn!(
+                        self,
+                        "Could not create a subscription from the existing subscription {}",
+                        subscription_id
+                    );
+                }
+            }
+        }
+    }
 
-            let items_to_create = subscription
-                .monitored_items
-                .values()
-                .map(|item| MonitoredItemCreateRequest {
-                    item_to_monitor: item.item_to_monitor().clone(),
-                    monitoring_mode: item.monitoring_mode,
-                    requested_parameters: MonitoringParameters {
-                        client_handle: item.client_handle(),
-                        sampling_interval: item.sampling_interval(),
-                        filter: item.filter.clone(),
-                        queue_size: item.queue_size() as u32,
-                        discard_oldest: item.discard_oldest(),
-                    },
-                })
-                .collect::<Vec<MonitoredItemCreateRequest>>();
+    /// Create the subscription again on the server, with its monitored items and triggering
+    /// links. Returns the new subscription id.
+    async fn recreate_subscription(&self, subscription: Subscription) -> Result<u32, StatusCode> {
+        let subscription_id = self
+            .create_subscription_inner(
+                subscription.publishing_interval,
+                subscription.lifetime_count,
+                subscription.max_keep_alive_count,
+                subscription.max_notifications_per_publish,
+                subscription.publishing_enabled,
+                subscription.priority,
+                subscription.callback,
+            )
+            .await?;
 
-            let mut iter = items_to_create.into_iter();
+        let items_to_create = subscription
+            .monitored_items
+            .values()
+            .map(|item| MonitoredItemCreateRequest {
+                item_to_monitor: item.item_to_monitor().clone(),
+                monitoring_mode: item.monitoring_mode,
+                requested_parameters: MonitoringParameters {
+                    client_handle: item.client_handle(),
+                    sampling_interval: item.sampling_interval(),
+                    filter: item.filter.clone(),
+                    queue_size: item.queue_size() as u32,
+                    discard_oldest: item.discard_oldest(),
+                },
+            })
+            .collect::<Vec<MonitoredItemCreateRequest>>();
+
+        let mut iter = items_to_create.into_iter();
+
+        loop {
+            let chunk = (&mut iter)
+                .take(self.recreate_monitored_items_chunk)
+                .collect::<Vec<_>>();
 
-            loop {
-                let chunk = (&mut iter)
-                    .take(self.recreate_monitored_items_chunk)
-                    .collect::<Vec<_>>();
+            if chunk.is_empty() {
+                break;
+            }
 
-                if chunk.is_empty() {
-                    break;
-                }
+            let _ = self
+                .create_monitored_items(subscription_id, TimestampsToReturn::Both, chunk)
+                .await;
+        }
 
+        for item in subscription.monitored_items.values() {
+            let triggered_items = item.triggered_items();
+            if !triggered_items.is_empty() {
+                let links_to_add = triggered_items.iter().copied().collect::<Vec<u32>>();
                 let _ = self
-                    .create_monitored_items(subscription_id, TimestampsToReturn::Both, chunk)
+                    .set_triggering(subscription_id, item.id(), links_to_add.as_slice(), &[])
                     .await;
             }
-
-            for item in subscription.monitored_items.values() {
-                let triggered_items = item.triggered_items();
-                if !triggered_items.is_empty() {
-                    let links_to_add = triggered_items.iter().copied().collect::<Vec<u32>>();
-                    let _ = self
-                        .set_triggering(subscription_id, item.id(), links_to_add.as_slice(), &[])
-                        .await;
-                }
-            }
         }
+
+        Ok(subscription_id)
     }
 }
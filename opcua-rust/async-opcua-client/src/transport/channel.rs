@@ -7,7 +7,7 @@ use opcua_core::{
     sync::RwLock,
     trace_read_lock, trace_write_lock, RequestMessage, ResponseMessage,
 };
-use opcua_crypto::{CertificateStore, SecurityPolicy};
+use opcua_crypto::{CertificateStore, SecurityPolicy, X509};
 use opcua_types::{
     ByteString, CloseSecureChannelRequest, ContextOwned, IntegerId, NodeId, RequestHeader,
     SecurityTokenRequestType, StatusCode,
@@ -81,6 +81,17 @@ impl AsyncSecureChannel {
         Ok(())
     }
 
+    /// Get the certificate of the server, `None` if the channel has no security, as no
+    /// certificate is used then.
+    pub fn remote_cert(&self) -> Option<X509> {
+        let secure_channel = trace_read_lock!(self.secure_channel);
+        if secure_channel.security_policy() == SecurityPolicy::None {
+            None
+        } else {
+            secure_channel.remote_cert()
+        }
+    }
+
     pub(crate) fn security_policy(&self) -> SecurityPolicy {
         let secure_channel = trace_read_lock!(self.secure_channel);
         secure_channel.security_policy()
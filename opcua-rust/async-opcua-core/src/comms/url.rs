@@ -34,6 +34,19 @@ pub fn url_with_replaced_hostname(url: &str, hostname: &str) -> Result<String, u
     Ok(url.into())
 }
 
+/// Replace the hostname and port in the supplied url and return a new url, e.g. to reach
+/// a server behind NAT whose endpoints advertise an internal address.
+pub fn url_with_replaced_host_port(
+    url: &str,
+    hostname: &str,
+    port: u16,
+) -> Result<String, url::ParseError> {
+    let mut url = opc_url_from_str(url)?;
+    url.set_host(Some(hostname))?;
+    let _ = url.set_port(Some(port));
+    Ok(url.into())
+}
+
 /// Test if the two urls match except for the hostname. Can be used by a server whose endpoint doesn't
 /// exactly match the incoming connection, e.g. 127.0.0.1 vs localhost.
 pub fn url_matches_except_host(url1: &str, url2: &str) -> bool {
@@ -186,4 +199,17 @@ mod tests {
             "opc.tcp://127.0.0.1:123/x"
         );
     }
+
+    #[test]
+    fn url_with_replaced_host_port_test() {
+        assert_eq!(
+            url_with_replaced_host_port("opc.tcp://internal:4840/x", "public.example.com", 4841)
+                .unwrap(),
+            "opc.tcp://public.example.com:4841/x"
+        );
+        assert_eq!(
+            url_with_replaced_host_port("opc.tcp://internal/x", "10.0.0.1", 4840).unwrap(),
+            "opc.tcp://10.0.0.1:4840/x"
+        );
+    }
 }
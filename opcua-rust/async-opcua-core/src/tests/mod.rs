@@ -5,7 +5,7 @@ use std::io::Cursor;
 use opcua_crypto::{
     pkey::PrivateKey,
     security_policy::SecurityPolicy,
-    x509::{X509Data, X509},
+    x509::{SignatureAlgorithm, X509Data, X509},
 };
 use opcua_types::{
     status_code::StatusCode, BinaryDecodable, BinaryEncodable, ByteString, ChannelSecurityToken,
@@ -151,6 +151,8 @@ fn make_test_cert(key_size: u32) -> (X509, PrivateKey) {
         ]
         .into(),
         certificate_duration_days: 60,
+        serial_number: None,
+        signature_algorithm: SignatureAlgorithm::default(),
     };
     let cert = X509::cert_and_pkey(&args);
     cert.unwrap()
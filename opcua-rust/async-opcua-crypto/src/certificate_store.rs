@@ -30,6 +30,13 @@ const OWN_PRIVATE_KEY_PATH: &str = "private/private.pem";
 const TRUSTED_CERTS_DIR: &str = "trusted";
 /// The directory holding rejected certificates
 const REJECTED_CERTS_DIR: &str = "rejected";
+/// The directory holding the certificates of CAs which complete certificate chains but aren't
+/// trusted themselves
+const ISSUER_CERTS_DIR: &str = "issuers";
+/// Limits the certificate chain from a cert up to its root
+const MAX_CHAIN_LENGTH: usize = 10;
+/// Default tolerance of the not before / after checks for the clock difference to the other end
+const DEFAULT_CLOCK_SKEW_TOLERANCE_SECS: i64 = 60;
 
 /// The certificate store manages the storage of a server/client's own certificate & private key
 /// and the trust / rejection of certificates from the other end.
@@ -43,6 +50,9 @@ pub struct CertificateStore {
     /// Timestamps of the cert are normally checked on the cert to ensure it cannot be used before
     /// or after its limits, but this check can be disabled.
     check_time: bool,
+    /// The not before / after values of the cert may be off by this much, as the clocks of the
+    /// two ends are rarely in sync.
+    clock_skew_tolerance: chrono::Duration,
     /// This option lets you skip additional certificate validations (e.g. hostname, application
     /// uri and the not before / after values). Certificates are always checked to see if they are
     /// trusted and have a valid key length.
@@ -63,6 +73,7 @@ impl CertificateStore {
             own_private_key_path: PathBuf::from(OWN_PRIVATE_KEY_PATH),
             pki_path: pki_path.to_path_buf(),
             check_time: true,
+            clock_skew_tolerance: chrono::Duration::seconds(DEFAULT_CLOCK_SKEW_TOLERANCE_SECS),
             skip_verify_certs: false,
             trust_unknown_certs: false,
         }
@@ -135,6 +146,12 @@ impl CertificateStore {
         self.check_time = check_time;
     }
 
+    /// Set the tolerance of the expiration time check for a clock difference to the other end,
+    /// 60 seconds by default.
+    pub fn set_clock_skew_tolerance(&mut self, tolerance: chrono::Duration) {
+        self.clock_skew_tolerance = tolerance;
+    }
+
     /// Reads a private key from a path on disk.
     pub fn read_pkey(path: &Path) -> Result<PrivateKey, String> {
         if let Ok(pkey) = PrivateKey::read_pem_file(path) {
@@ -172,9 +189,20 @@ impl CertificateStore {
         pkey_path: &Path,
     ) -> Result<(X509, PrivateKey), String> {
         let (cert, pkey) = X509::cert_and_pkey(args)?;
+        CertificateStore::store_cert_and_pkey(&cert, &pkey, overwrite, cert_path, pkey_path)?;
+        Ok((cert, pkey))
+    }
 
+    /// Write a certificate (.der) and its private key (.pem) to the specified locations
+    pub fn store_cert_and_pkey(
+        cert: &X509,
+        pkey: &PrivateKey,
+        overwrite: bool,
+        cert_path: &Path,
+        pkey_path: &Path,
+    ) -> Result<(), String> {
         // Write the public cert
-        let _ = CertificateStore::store_cert(&cert, cert_path, overwrite)?;
+        let _ = CertificateStore::store_cert(cert, cert_path, overwrite)?;
 
         // Write the private key
         use rsa::pkcs8;
@@ -184,7 +212,7 @@ impl CertificateStore {
             .to_pem(rsa::pkcs8::PrivateKeyInfo::PEM_LABEL, pkcs8::LineEnding::CR)
             .unwrap();
         let _ = CertificateStore::write_to_file(pem.as_bytes(), pkey_path, overwrite)?;
-        Ok((cert, pkey))
+        Ok(())
     }
 
     /// This function will use the supplied arguments to create an Application Instance Certificate
@@ -203,6 +231,22 @@ impl CertificateStore {
         )
     }
 
+    /// Install a cert signed by a CA (.der or .pem) as the own certificate. The cert must be
+    /// for the own private key, i.e. signed for a request created with [`X509::create_csr`].
+    pub fn install_own_cert(&self, cert_path: &Path) -> Result<X509, String> {
+        let cert = CertificateStore::read_cert(cert_path)?;
+        let pkey = self.read_own_pkey()?;
+        if !cert.matches_pkey(&pkey) {
+            return Err(format!(
+                "Cert {} is not for the private key {}",
+                cert_path.display(),
+                self.own_private_key_path().display()
+            ));
+        }
+        let _ = CertificateStore::store_cert(&cert, &self.own_certificate_path(), true)?;
+        Ok(cert)
+    }
+
     /// Validates the cert as trusted and valid. If the cert is unknown, it will be written to
     /// the rejected folder so that the administrator can manually move it to the trusted folder.
     ///
@@ -318,23 +362,36 @@ impl CertificateStore {
             }
             cert_path.push(&cert_file_name);
 
-            // Check if cert is in the trusted folder
+            // Check if cert is in the trusted folder, or else is trusted through its issuers
+            let mut chain = None;
             if !cert_path.exists() {
-                // ... trust checks based on ca could be added here to add cert straight to trust folder
-                if self.trust_unknown_certs {
-                    // Put the unknown cert into the trusted folder
-                    warn!("Certificate {} is unknown but policy will store it into the trusted directory", cert_file_name);
-                    let _ = self.store_trusted_cert(cert);
-                // Note that we drop through and still check the cert for validity
-                } else {
-                    warn!("Certificate {} is unknown and untrusted so it will be stored in rejected directory", cert_file_name);
-                    let _ = self.store_rejected_cert(cert);
-                    return Err(StatusCode::BadCertificateUntrusted);
+                match self.trusted_chain(cert) {
+                    Ok(trusted_chain) => {
+                        debug!(
+                            "Certificate {} is trusted through a chain of {} certificates",
+                            cert_file_name,
+                            trusted_chain.len()
+                        );
+                        chain = Some(trusted_chain);
+                    }
+                    Err(status_code) if self.trust_unknown_certs => {
+                        // Put the unknown cert into the trusted folder
+                        warn!("Certificate {} is unknown ({}) but policy will store it into the trusted directory", cert_file_name, status_code);
+                        let _ = self.store_trusted_cert(cert);
+                        // Note that we drop through and still check the cert for validity
+                    }
+                    Err(status_code) => {
+                        warn!("Certificate {} is unknown and untrusted ({}) so it will be stored in rejected directory", cert_file_name, status_code);
+                        let _ = self.store_rejected_cert(cert);
+                        return Err(StatusCode::BadCertificateUntrusted);
+                    }
                 }
             }
 
             // Read the cert from the trusted folder to make sure it matches the one supplied
-            if !CertificateStore::ensure_cert_and_file_are_the_same(cert, &cert_path) {
+            if chain.is_none()
+                && !CertificateStore::ensure_cert_and_file_are_the_same(cert, &cert_path)
+            {
                 error!("Certificate in memory does not match the one on disk {} so cert will automatically be treated as untrusted", cert_path.display());
                 return Err(StatusCode::BadUnexpectedError);
             }
@@ -368,7 +425,15 @@ impl CertificateStore {
             if self.check_time {
                 use chrono::Utc;
                 let now = Utc::now();
-                cert.is_time_valid(&now)?;
+                cert.is_time_valid_with_tolerance(&now, &self.clock_skew_tolerance)?;
+                // The issuers of a chain must be valid too
+                if let Some(chain) = &chain {
+                    for issuer in chain.iter().skip(1) {
+                        issuer
+                            .is_time_valid_with_tolerance(&now, &self.clock_skew_tolerance)
+                            .map_err(|_| StatusCode::BadCertificateIssuerTimeInvalid)?;
+                    }
+                }
             }
 
             // Compare the hostname of the cert against the cert supplied
@@ -382,13 +447,67 @@ impl CertificateStore {
             }
 
             // Other tests that we might do with trust lists
-            // ... issuer
-            // ... trust (self-signed, ca etc.)
             // ... revocation
         }
         Ok(())
     }
 
+    /// Builds the chain from the cert up to a self-signed root with the certs of the trusted and
+    /// issuers folders. The chain is trusted if any cert of it, e.g. the root or the cert itself in
+    /// a multi-cert pem file, is in the trusted folder. The signature of each cert is verified
+    /// with the public key of its issuer, the validity times are checked by the caller.
+    ///
+    /// # Errors
+    ///
+    /// `BadCertificateChainIncomplete` if the issuer of a cert can't be found and
+    /// `BadCertificateUntrusted` if no cert of the chain is trusted.
+    ///
+    fn trusted_chain(&self, cert: &X509) -> Result<Vec<X509>, StatusCode> {
+        let trusted = CertificateStore::read_certs_in_dir(&self.trusted_certs_dir());
+        let issuers = CertificateStore::read_certs_in_dir(&self.issuer_certs_dir());
+        let chain = CertificateStore::build_chain(cert, trusted.iter().chain(issuers.iter()))?;
+
+        let trusted_thumbprints = trusted.iter().map(|c| c.thumbprint()).collect::<Vec<_>>();
+        if chain
+            .iter()
+            .any(|c| trusted_thumbprints.contains(&c.thumbprint()))
+        {
+            Ok(chain)
+        } else {
+            Err(StatusCode::BadCertificateUntrusted)
+        }
+    }
+
+    /// Walks from the cert to a self-signed root, taking the issuer of each cert from the
+    /// candidates. The chain starts with the cert and ends with the root.
+    fn build_chain<'a>(
+        cert: &X509,
+        candidates: impl Iterator<Item = &'a X509> + Clone,
+    ) -> Result<Vec<X509>, StatusCode> {
+        let mut chain = vec![cert.clone()];
+        loop {
+            let current = chain.last().unwrap();
+            if current.is_self_signed() {
+                return Ok(chain);
+            }
+            if chain.len() >= MAX_CHAIN_LENGTH {
+                error!("Certificate chain is longer than {}", MAX_CHAIN_LENGTH);
+                return Err(StatusCode::BadCertificateChainIncomplete);
+            }
+            let issuer = candidates
+                .clone()
+                .find(|c| current.is_issued_by(c))
+                .cloned();
+            match issuer {
+                Some(issuer) => chain.push(issuer),
+                None => {
+                    debug!("No issuer found for certificate {}", current.subject_name());
+                    return Err(StatusCode::BadCertificateChainIncomplete);
+                }
+            }
+        }
+    }
+
     /// Returns a certificate file name from the cert's issuer and thumbprint fields.
     /// File name is either "prefix - \[thumbprint\].der" or "thumbprint.der" depending on
     /// the cert's common name being empty or not
@@ -415,7 +534,7 @@ impl CertificateStore {
     ///
     pub fn ensure_pki_path(&self) -> Result<(), String> {
         let mut path = self.pki_path.clone();
-        let subdirs = [TRUSTED_CERTS_DIR, REJECTED_CERTS_DIR];
+        let subdirs = [TRUSTED_CERTS_DIR, REJECTED_CERTS_DIR, ISSUER_CERTS_DIR];
         for subdir in &subdirs {
             path.push(subdir);
             CertificateStore::ensure_dir(&path)?;
@@ -471,6 +590,13 @@ impl CertificateStore {
         path
     }
 
+    /// Get the path to the issuer certs dir
+    pub fn issuer_certs_dir(&self) -> PathBuf {
+        let mut path = PathBuf::from(&self.pki_path);
+        path.push(ISSUER_CERTS_DIR);
+        path
+    }
+
     /// Write a cert to the rejected directory. If the write succeeds, the function
     /// returns a path to the written file.
     ///
@@ -515,13 +641,28 @@ impl CertificateStore {
         CertificateStore::write_to_file(&der, path, overwrite)
     }
 
-    /// Reads an X509 certificate in .def or .pem format from disk
+    /// Reads an X509 certificate in .der or .pem format from disk. Of a pem file with more than
+    /// one certificate the first one is returned.
     ///
     /// # Errors
     ///
     /// A string description of any failure
     ///
     pub fn read_cert(path: &Path) -> Result<X509, String> {
+        CertificateStore::read_certs(path)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("No cert in cert file {}", path.display()))
+    }
+
+    /// Reads all X509 certificates of a .der file (one) or a .pem file (one or more, e.g. a
+    /// cert followed by its issuers)
+    ///
+    /// # Errors
+    ///
+    /// A string description of any failure
+    ///
+    pub fn read_certs(path: &Path) -> Result<Vec<X509>, String> {
         let file = File::open(path);
         if file.is_err() {
             return Err(format!("Could not open cert file {}", path.display()));
@@ -537,13 +678,13 @@ impl CertificateStore {
             ));
         }
 
-        let cert = match path.extension() {
-            Some(v) if v == "der" => X509::from_der(&cert),
-            Some(v) if v == "pem" => X509::from_pem(&cert),
+        let certs = match path.extension() {
+            Some(v) if v == "der" => X509::from_der(&cert).map(|cert| vec![cert]),
+            Some(v) if v == "pem" => X509::from_pem_chain(&cert),
             _ => return Err("Only .der and .pem certificates are supported".to_string()),
         };
 
-        match cert {
+        match certs {
             Err(_) => Err(format!(
                 "Could not read cert from cert file {}",
                 path.display()
@@ -552,6 +693,26 @@ impl CertificateStore {
         }
     }
 
+    /// Reads the certificates of all .der and .pem files in the directory. Files which can't be
+    /// read are skipped with a warning.
+    fn read_certs_in_dir(dir: &Path) -> Vec<X509> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut certs = Vec::new();
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            let is_cert = matches!(path.extension(), Some(v) if v == "der" || v == "pem");
+            if !is_cert || !path.is_file() {
+                continue;
+            }
+            match CertificateStore::read_certs(&path) {
+                Ok(mut file_certs) => certs.append(&mut file_certs),
+                Err(err) => warn!("{}", err),
+            }
+        }
+        certs
+    }
+
     /// Writes bytes to file and returns the size written, or an error reason for failure.
     ///
     /// # Errors
@@ -10,11 +10,11 @@ use crate::{
     pkey::{KeySize, PrivateKey, RsaPadding},
     random,
     tests::{
-        make_certificate_store, make_test_cert_1024, make_test_cert_2048, APPLICATION_HOSTNAME,
-        APPLICATION_URI,
+        make_certificate_store, make_signed_cert, make_test_cert_1024, make_test_cert_2048,
+        APPLICATION_HOSTNAME, APPLICATION_URI,
     },
     user_identity::{legacy_password_decrypt, legacy_password_encrypt},
-    x509::{X509Data, X509},
+    x509::{AltName, AlternateNames, SignatureAlgorithm, X509Data, X509},
     SecurityPolicy, SHA1_SIZE, SHA256_SIZE,
 };
 
@@ -91,6 +91,8 @@ fn create_own_cert_in_pki() {
         state: "London".to_string(),
         alt_host_names: vec!["host1".to_string(), "host2".to_string()].into(),
         certificate_duration_days: 60,
+        serial_number: None,
+        signature_algorithm: SignatureAlgorithm::default(),
     };
 
     let (tmp_dir, cert_store) = make_certificate_store();
@@ -107,6 +109,257 @@ fn create_own_cert_in_pki() {
     drop(tmp_dir)
 }
 
+#[test]
+fn create_csr() {
+    use x509_cert::der::Decode;
+
+    let (cert, pkey) = make_test_cert_2048();
+    let args = X509Data {
+        key_size: 2048,
+        common_name: "x".to_string(),
+        organization: "x.org".to_string(),
+        organizational_unit: String::new(),
+        country: String::new(),
+        state: String::new(),
+        alt_host_names: cert.alternate_names().unwrap(),
+        certificate_duration_days: 0,
+        serial_number: None,
+        signature_algorithm: SignatureAlgorithm::default(),
+    };
+    let der = X509::create_csr(&pkey, &args).unwrap();
+    let csr = x509_cert::request::CertReq::from_der(&der).unwrap();
+    let subject = csr.info.subject.to_string();
+    assert!(subject.contains("CN=x"));
+    assert!(subject.contains("O=x.org"));
+    assert_eq!(csr.info.public_key, pkey.public_key_to_info().unwrap());
+    // The extension request with the subject alternative names
+    assert_eq!(csr.info.attributes.len(), 1);
+}
+
+#[test]
+fn install_own_cert_in_pki() {
+    let (tmp_dir, cert_store) = make_certificate_store();
+    let (_, pkey) = cert_store
+        .create_and_store_application_instance_cert(&X509Data::sample_cert(), false)
+        .unwrap();
+
+    // A cert for another key is refused
+    let (other_cert, _) = make_test_cert_1024();
+    let other_path = tmp_dir.path().join("other.der");
+    File::create(&other_path)
+        .unwrap()
+        .write_all(&other_cert.to_der().unwrap())
+        .unwrap();
+    assert!(cert_store.install_own_cert(&other_path).is_err());
+
+    // A cert for the own key replaces the own cert
+    let mut args = X509Data::sample_cert();
+    args.common_name = "signed".to_string();
+    let signed_cert = X509::from_pkey(&pkey, &args).unwrap();
+    assert!(signed_cert.matches_pkey(&pkey));
+    let signed_path = tmp_dir.path().join("signed.der");
+    File::create(&signed_path)
+        .unwrap()
+        .write_all(&signed_cert.to_der().unwrap())
+        .unwrap();
+    cert_store.install_own_cert(&signed_path).unwrap();
+    let own_cert = cert_store.read_own_cert().unwrap();
+    assert_eq!(own_cert.common_name().unwrap(), "CN=signed");
+}
+
+#[test]
+fn random_serial_number() {
+    let (tmp_dir, cert_store) = make_certificate_store();
+    let (cert, _) = make_test_cert_1024();
+    let (cert2, _) = make_test_cert_1024();
+
+    assert_ne!(cert.serial_number(), cert2.serial_number());
+    for cert in [&cert, &cert2] {
+        // Positive and at least 64 bits
+        assert!(cert.serial_number().len() >= 8);
+        assert!(cert.serial_number().len() <= 20);
+        assert_eq!(cert.serial_number()[0] & 0x80, 0);
+        assert!(cert.is_self_signed());
+
+        let mut cert_trusted_path = cert_store.trusted_certs_dir();
+        cert_trusted_path.push(CertificateStore::cert_file_name(cert));
+        let mut file = File::create(cert_trusted_path).unwrap();
+        assert!(file.write(&cert.to_der().unwrap()).is_ok());
+        assert!(cert_store
+            .validate_or_reject_application_instance_cert(
+                cert,
+                SecurityPolicy::Basic128Rsa15,
+                None,
+                None,
+            )
+            .is_ok());
+    }
+
+    drop(tmp_dir);
+}
+
+#[test]
+fn explicit_serial_number() {
+    let mut args = X509Data::sample_cert();
+    args.serial_number = Some(vec![0x01, 0x02, 0x03]);
+    let (cert, _) = X509::cert_and_pkey(&args).unwrap();
+    assert_eq!(cert.serial_number(), &[0x01, 0x02, 0x03]);
+
+    // Longer than the 20 bytes allowed by RFC 5280
+    args.serial_number = Some(vec![0x01; 21]);
+    assert!(X509::cert_and_pkey(&args).is_err());
+}
+
+#[test]
+fn unsupported_key_size() {
+    let mut args = X509Data::sample_cert();
+    for key_size in [0, 1024, 2047, 8192] {
+        args.key_size = key_size;
+        let err = X509::cert_and_pkey(&args).err().unwrap();
+        assert!(err.contains(&key_size.to_string()));
+    }
+}
+
+/// Creates a cert, encodes it to DER and checks the reloaded cert
+fn round_trip_cert(key_size: u32, signature_algorithm: SignatureAlgorithm) {
+    let mut args = X509Data::sample_cert();
+    args.key_size = key_size;
+    args.signature_algorithm = signature_algorithm;
+    let (cert, pkey) = X509::cert_and_pkey(&args).unwrap();
+
+    let reloaded = X509::from_der(&cert.to_der().unwrap()).unwrap();
+    assert_eq!(reloaded.thumbprint(), cert.thumbprint());
+    assert_eq!(reloaded.key_length().unwrap(), key_size as usize);
+    assert_eq!(
+        reloaded.public_key().unwrap().bit_length(),
+        key_size as usize
+    );
+    assert!(reloaded.matches_pkey(&pkey));
+    assert!(reloaded.is_self_signed());
+    assert!(reloaded.is_time_valid(&chrono::Utc::now()).is_ok());
+}
+
+#[test]
+fn round_trip_cert_sizes() {
+    for key_size in [2048, 3072, 4096] {
+        round_trip_cert(key_size, SignatureAlgorithm::Sha256Pkcs1v15);
+    }
+}
+
+#[test]
+fn round_trip_cert_pss() {
+    round_trip_cert(2048, SignatureAlgorithm::Sha256Pss);
+    round_trip_cert(3072, SignatureAlgorithm::Sha256Pss);
+}
+
+#[test]
+fn application_uri() {
+    let (cert, _) = make_test_cert_2048();
+    assert_eq!(cert.application_uri().as_deref(), Some(APPLICATION_URI));
+
+    // A uri entry wins over the first name
+    let mut args = X509Data::sample_cert();
+    args.alt_host_names = vec!["host1".to_string()].into();
+    args.alt_host_names.add_uri("urn:other");
+    let (cert, _) = X509::cert_and_pkey(&args).unwrap();
+    assert_eq!(cert.application_uri().as_deref(), Some("urn:other"));
+}
+
+#[test]
+fn alt_names_typed() {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    let mut alt_host_names = AlternateNames::new();
+    alt_host_names.add_dns("host1");
+    alt_host_names.add_uri("urn:app");
+    alt_host_names.add_address("192.168.1.1");
+    alt_host_names.add_address("::1");
+    assert_eq!(
+        alt_host_names.iter_typed().collect::<Vec<_>>(),
+        vec![
+            AltName::Dns("host1".to_string()),
+            AltName::Uri("urn:app".to_string()),
+            AltName::Ip(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1))),
+            AltName::Ip(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+        ]
+    );
+
+    // The uri isn't the first entry, as in certs of some other stacks
+    let mut args = X509Data::sample_cert();
+    args.alt_host_names = alt_host_names;
+    let (cert, _) = X509::cert_and_pkey(&args).unwrap();
+    cert.is_application_uri_valid("urn:app").unwrap();
+    assert!(cert.is_application_uri_valid("host1").is_err());
+    cert.is_hostname_valid("host1").unwrap();
+    cert.is_hostname_valid("HOST1").unwrap();
+    cert.is_hostname_valid("192.168.1.1").unwrap();
+    // Any form of the address
+    cert.is_hostname_valid("0:0:0:0:0:0:0:1").unwrap();
+    // A uri is never a host name
+    assert_eq!(
+        cert.is_hostname_valid("urn:app"),
+        Err(StatusCode::BadCertificateHostNameInvalid)
+    );
+}
+
+#[test]
+fn application_uri_in_any_position() {
+    // URIs last, after the host names, and more than one
+    let mut alt_host_names = AlternateNames::new();
+    alt_host_names.add_dns("host1");
+    alt_host_names.add_address("10.0.0.1");
+    alt_host_names.add_uri("urn:first");
+    alt_host_names.add_uri("urn:second");
+    let mut args = X509Data::sample_cert();
+    args.alt_host_names = alt_host_names;
+    let (cert, _) = X509::cert_and_pkey(&args).unwrap();
+
+    cert.is_application_uri_valid("urn:first").unwrap();
+    cert.is_application_uri_valid("urn:second").unwrap();
+    assert_eq!(
+        cert.is_application_uri_valid("urn:third"),
+        Err(StatusCode::BadCertificateUriInvalid)
+    );
+    assert_eq!(
+        cert.is_application_uri_valid("host1"),
+        Err(StatusCode::BadCertificateUriInvalid)
+    );
+    assert_eq!(cert.application_uri().as_deref(), Some("urn:first"));
+
+    // The first entry is a host name like the others
+    cert.is_hostname_valid("host1").unwrap();
+    cert.is_hostname_valid("10.0.0.1").unwrap();
+}
+
+#[test]
+fn time_valid_with_tolerance() {
+    let (cert, _) = make_test_cert_2048();
+    let tolerance = chrono::Duration::seconds(60);
+
+    // The cert starts 30 seconds in the future of the other clock
+    let now = cert.not_before().unwrap() - chrono::Duration::seconds(30);
+    assert_eq!(
+        cert.is_time_valid(&now),
+        Err(StatusCode::BadCertificateTimeInvalid)
+    );
+    assert!(cert.is_time_valid_with_tolerance(&now, &tolerance).is_ok());
+
+    // Expired 30 seconds ago
+    let now = cert.not_after().unwrap() + chrono::Duration::seconds(30);
+    assert_eq!(
+        cert.is_time_valid(&now),
+        Err(StatusCode::BadCertificateTimeInvalid)
+    );
+    assert!(cert.is_time_valid_with_tolerance(&now, &tolerance).is_ok());
+
+    // Outside of the tolerance
+    let now = cert.not_before().unwrap() - chrono::Duration::seconds(90);
+    assert_eq!(
+        cert.is_time_valid_with_tolerance(&now, &tolerance),
+        Err(StatusCode::BadCertificateTimeInvalid)
+    );
+}
+
 #[test]
 fn create_rejected_cert_in_pki() {
     let (tmp_dir, cert_store) = make_certificate_store();
@@ -196,6 +449,107 @@ fn test_and_reject_thumbprint_mismatch() {
     drop(tmp_dir);
 }
 
+fn to_pem(certs: &[&X509]) -> String {
+    use x509_cert::der::{pem::LineEnding, Decode, EncodePem};
+
+    certs
+        .iter()
+        .map(|cert| {
+            x509_cert::Certificate::from_der(&cert.to_der().unwrap())
+                .unwrap()
+                .to_pem(LineEnding::LF)
+                .unwrap()
+        })
+        .collect()
+}
+
+fn write_cert_file(path: std::path::PathBuf, bytes: &[u8]) {
+    let mut file = File::create(path).unwrap();
+    assert!(file.write(bytes).is_ok());
+}
+
+#[test]
+fn x509_from_pem_chain() {
+    let (ca_cert, ca_pkey) = make_test_cert_1024();
+    let (cert, _) = make_signed_cert(&ca_cert, &ca_pkey, "leaf");
+
+    let certs = X509::from_pem_chain(to_pem(&[&cert, &ca_cert]).as_bytes()).unwrap();
+    assert_eq!(certs.len(), 2);
+    assert_eq!(certs[0].thumbprint(), cert.thumbprint());
+    assert_eq!(certs[1].thumbprint(), ca_cert.thumbprint());
+
+    assert!(cert.is_issued_by(&ca_cert));
+    assert!(!cert.is_self_signed());
+    assert!(ca_cert.is_self_signed());
+    assert!(!ca_cert.is_issued_by(&cert));
+
+    // Same subject, other key
+    let (other_ca_cert, _) = make_test_cert_1024();
+    assert!(!cert.is_issued_by(&other_ca_cert));
+}
+
+#[test]
+fn test_and_trust_ca_signed_cert() {
+    let (root_cert, root_pkey) = make_test_cert_1024();
+    let (ca_cert, ca_pkey) = make_signed_cert(&root_cert, &root_pkey, "intermediate");
+    let (cert, _) = make_signed_cert(&ca_cert, &ca_pkey, "leaf");
+
+    let validate = |cert_store: &CertificateStore| {
+        cert_store.validate_or_reject_application_instance_cert(
+            &cert,
+            SecurityPolicy::Basic128Rsa15,
+            None,
+            None,
+        )
+    };
+
+    // Trusted root, intermediate CA in the issuers folder
+    let (tmp_dir, cert_store) = make_certificate_store();
+    write_cert_file(
+        cert_store.trusted_certs_dir().join("root.der"),
+        &root_cert.to_der().unwrap(),
+    );
+    write_cert_file(
+        cert_store.issuer_certs_dir().join("intermediate.der"),
+        &ca_cert.to_der().unwrap(),
+    );
+    assert!(validate(&cert_store).is_ok());
+    drop(tmp_dir);
+
+    // Both CAs in one pem file of the trusted folder
+    let (tmp_dir, cert_store) = make_certificate_store();
+    write_cert_file(
+        cert_store.trusted_certs_dir().join("chain.pem"),
+        to_pem(&[&ca_cert, &root_cert]).as_bytes(),
+    );
+    assert!(validate(&cert_store).is_ok());
+    drop(tmp_dir);
+
+    // Incomplete chain
+    let (tmp_dir, cert_store) = make_certificate_store();
+    write_cert_file(
+        cert_store.trusted_certs_dir().join("root.der"),
+        &root_cert.to_der().unwrap(),
+    );
+    assert_eq!(
+        validate(&cert_store).unwrap_err(),
+        StatusCode::BadCertificateUntrusted
+    );
+    drop(tmp_dir);
+
+    // The root is only an issuer, nothing of the chain is trusted
+    let (tmp_dir, cert_store) = make_certificate_store();
+    write_cert_file(
+        cert_store.issuer_certs_dir().join("chain.pem"),
+        to_pem(&[&ca_cert, &root_cert]).as_bytes(),
+    );
+    assert_eq!(
+        validate(&cert_store).unwrap_err(),
+        StatusCode::BadCertificateUntrusted
+    );
+    drop(tmp_dir);
+}
+
 fn test_asymmetric_encrypt_and_decrypt(
     cert: &X509,
     key: &PrivateKey,
@@ -591,6 +945,33 @@ fn certificate_with_application_uri_mismatch() {
     cert.is_application_uri_valid(APPLICATION_URI).unwrap();
 }
 
+#[test]
+fn certificate_alternate_names() {
+    let (cert, _) = make_test_cert_2048();
+    let names: Vec<String> = cert.alternate_names().unwrap().iter().collect();
+    assert_eq!(
+        names,
+        vec![APPLICATION_URI, "foo", "foo2", APPLICATION_HOSTNAME, "foo3"]
+    );
+
+    // A cert created from the names is valid for the same application uri and host
+    let args = X509Data {
+        key_size: 2048,
+        common_name: "x".to_string(),
+        organization: String::new(),
+        organizational_unit: String::new(),
+        country: String::new(),
+        state: String::new(),
+        alt_host_names: cert.alternate_names().unwrap(),
+        certificate_duration_days: 30,
+        serial_number: None,
+        signature_algorithm: SignatureAlgorithm::default(),
+    };
+    let (renewed, _) = X509::cert_and_pkey(&args).unwrap();
+    renewed.is_application_uri_valid(APPLICATION_URI).unwrap();
+    renewed.is_hostname_valid(APPLICATION_HOSTNAME).unwrap();
+}
+
 #[test]
 fn encrypt_decrypt_password() {
     let password = String::from("abcdef123456");
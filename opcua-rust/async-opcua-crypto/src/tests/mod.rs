@@ -4,7 +4,7 @@ use crate::CertificateStore;
 
 use crate::{
     pkey::PrivateKey,
-    x509::{X509Data, X509},
+    x509::{AlternateNames, SignatureAlgorithm, X509Data, X509},
 };
 
 const APPLICATION_URI: &str = "urn:testapplication";
@@ -34,9 +34,13 @@ fn make_test_cert(key_size: u32) -> (X509, PrivateKey) {
         ]
         .into(),
         certificate_duration_days: 60,
+        serial_number: None,
+        signature_algorithm: SignatureAlgorithm::default(),
     };
-    let cert = X509::cert_and_pkey(&args);
-    cert.unwrap()
+    // From the key, as the 1024 bit keys of the older policies aren't accepted by cert_and_pkey
+    let pkey = PrivateKey::new(key_size).unwrap();
+    let cert = X509::from_pkey(&pkey, &args).unwrap();
+    (cert, pkey)
 }
 
 fn make_test_cert_1024() -> (X509, PrivateKey) {
@@ -47,6 +51,45 @@ fn make_test_cert_2048() -> (X509, PrivateKey) {
     make_test_cert(2048)
 }
 
+/// Makes a cert for a new key, signed by the issuer
+fn make_signed_cert(
+    issuer: &X509,
+    issuer_pkey: &PrivateKey,
+    common_name: &str,
+) -> (X509, PrivateKey) {
+    use std::{str::FromStr, time::Duration};
+    use x509_cert::{
+        builder::{Builder, CertificateBuilder, Profile},
+        der::{Decode, Encode},
+        name::Name,
+        serial_number::SerialNumber,
+        time::Validity,
+        Certificate,
+    };
+
+    let pkey = PrivateKey::new(1024).unwrap();
+    let issuer_name = Certificate::from_der(&issuer.to_der().unwrap())
+        .unwrap()
+        .tbs_certificate
+        .subject;
+    let signing_key = rsa::pkcs1v15::SigningKey::<sha2::Sha256>::new(issuer_pkey.value.clone());
+    let mut builder = CertificateBuilder::new(
+        Profile::Manual {
+            issuer: Some(issuer_name),
+        },
+        SerialNumber::from(7u32),
+        Validity::from_now(Duration::from_secs(86400)).unwrap(),
+        Name::from_str(&format!("CN={}", common_name)).unwrap(),
+        pkey.public_key_to_info().unwrap(),
+        &signing_key,
+    )
+    .unwrap();
+    let alt_host_names: AlternateNames = vec![APPLICATION_URI.to_string()].into();
+    builder.add_extension(&alt_host_names.names).unwrap();
+    let der = builder.build().unwrap().to_der().unwrap();
+    (X509::from_der(&der).unwrap(), pkey)
+}
+
 mod authentication;
 mod crypto;
 mod security_policy;
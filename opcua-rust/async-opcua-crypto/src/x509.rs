@@ -8,7 +8,7 @@ use std::{
     self,
     collections::HashSet,
     fmt::{self, Debug, Formatter},
-    net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs},
     result::Result,
 };
 
@@ -18,6 +18,7 @@ type ChronoUtc = DateTime<Utc>;
 
 use rsa;
 use rsa::pkcs1v15;
+use rsa::pss;
 use rsa::RsaPublicKey;
 use x509_cert::{
     self as x509,
@@ -41,6 +42,19 @@ const DEFAULT_KEYSIZE: u32 = 2048;
 const DEFAULT_COUNTRY: &str = "IE";
 const DEFAULT_STATE: &str = "Dublin";
 
+/// Key sizes in bits accepted for new certificates.
+pub const SUPPORTED_KEY_SIZES: [u32; 3] = [2048, 3072, 4096];
+
+/// Signature algorithm of a new certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignatureAlgorithm {
+    /// RSA PKCS#1 v1.5 with SHA-256 (sha256WithRSAEncryption), accepted by every security policy.
+    #[default]
+    Sha256Pkcs1v15,
+    /// RSASSA-PSS with SHA-256, e.g. for the Aes256Sha256RsaPss policy.
+    Sha256Pss,
+}
+
 #[derive(Debug, Default)]
 /// Alternate names for an X509 certificate.
 pub struct AlternateNames {
@@ -130,32 +144,13 @@ impl AlternateNames {
     }
 
     fn convert_name(name: &x509::ext::pkix::name::GeneralName) -> Option<String> {
-        match name {
-            GeneralName::DnsName(val) => Some(val.to_string()),
-            GeneralName::DirectoryName(val) => Some(val.to_string()),
-            GeneralName::Rfc822Name(val) => Some(val.to_string()),
-            GeneralName::UniformResourceIdentifier(val) => Some(val.to_string()),
-            GeneralName::IpAddress(val) => {
-                let bytes = val.as_bytes();
-                match bytes.len() {
-                    4 => Some(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]).to_string()),
-
-                    16 => {
-                        let a = ((bytes[0] as u16) << 8) | bytes[1] as u16;
-                        let b = ((bytes[2] as u16) << 8) | bytes[3] as u16;
-                        let c = ((bytes[4] as u16) << 8) | bytes[5] as u16;
-                        let d = ((bytes[6] as u16) << 8) | bytes[7] as u16;
-                        let e = ((bytes[8] as u16) << 8) | bytes[9] as u16;
-                        let f = ((bytes[10] as u16) << 8) | bytes[11] as u16;
-                        let g = ((bytes[12] as u16) << 8) | bytes[13] as u16;
-                        let h = ((bytes[14] as u16) << 8) | bytes[15] as u16;
-                        Some(Ipv6Addr::new(a, b, c, d, e, f, g, h).to_string())
-                    }
-                    _ => None,
-                }
-            }
-
-            _ => None,
+        match AltName::from(name) {
+            AltName::Uri(val)
+            | AltName::Dns(val)
+            | AltName::Email(val)
+            | AltName::Directory(val) => Some(val),
+            AltName::Ip(val) => Some(val.to_string()),
+            AltName::Other => None,
         }
     }
 
@@ -166,6 +161,87 @@ impl AlternateNames {
             index: 0,
         }
     }
+
+    /// Iterate over all the registered names, keeping their type.
+    pub fn iter_typed(&self) -> impl Iterator<Item = AltName> + '_ {
+        self.names.0.iter().map(AltName::from)
+    }
+
+    /// The application uri, the first uri entry. Names from other stacks may have the
+    /// application uri as the first entry of another type, which is used if there is no uri.
+    fn application_uri(&self) -> Option<String> {
+        self.application_uris().into_iter().next()
+    }
+
+    /// The uri entries, or the first entry if there is no uri entry.
+    fn application_uris(&self) -> Vec<String> {
+        let uris: Vec<String> = self
+            .iter_typed()
+            .filter_map(|name| match name {
+                AltName::Uri(uri) => Some(uri),
+                _ => None,
+            })
+            .collect();
+        if uris.is_empty() {
+            self.names
+                .0
+                .first()
+                .and_then(Self::convert_name)
+                .into_iter()
+                .collect()
+        } else {
+            uris
+        }
+    }
+
+    /// Tests if one of the DNS or IP entries is the hostname, wherever it is in the list.
+    fn has_hostname(&self, hostname: &str) -> bool {
+        let ip = hostname.parse::<IpAddr>().ok();
+        self.iter_typed().any(|name| match name {
+            AltName::Dns(dns) => dns.eq_ignore_ascii_case(hostname),
+            AltName::Ip(addr) => ip == Some(addr),
+            _ => false,
+        })
+    }
+}
+
+/// A subject alternative name of a certificate with its type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AltName {
+    /// Uniform resource identifier, e.g. the application uri.
+    Uri(String),
+    /// DNS host name.
+    Dns(String),
+    /// IPv4 or IPv6 address.
+    Ip(IpAddr),
+    /// RFC 822 email address.
+    Email(String),
+    /// Directory name.
+    Directory(String),
+    /// A type which isn't supported, or an invalid IP address.
+    Other,
+}
+
+impl From<&GeneralName> for AltName {
+    fn from(name: &GeneralName) -> Self {
+        match name {
+            GeneralName::UniformResourceIdentifier(val) => AltName::Uri(val.to_string()),
+            GeneralName::DnsName(val) => AltName::Dns(val.to_string()),
+            GeneralName::Rfc822Name(val) => AltName::Email(val.to_string()),
+            GeneralName::DirectoryName(val) => AltName::Directory(val.to_string()),
+            GeneralName::IpAddress(val) => {
+                let bytes = val.as_bytes();
+                if let Ok(octets) = <[u8; 4]>::try_from(bytes) {
+                    AltName::Ip(IpAddr::V4(Ipv4Addr::from(octets)))
+                } else if let Ok(octets) = <[u8; 16]>::try_from(bytes) {
+                    AltName::Ip(IpAddr::V6(Ipv6Addr::from(octets)))
+                } else {
+                    AltName::Other
+                }
+            }
+            _ => AltName::Other,
+        }
+    }
 }
 
 struct AlternateNamesStringIterator<'a> {
@@ -220,6 +296,12 @@ pub struct X509Data {
     pub alt_host_names: AlternateNames,
     /// The number of days the certificate is valid for, i.e. it will be valid from now until now + duration_days.
     pub certificate_duration_days: u32,
+    /// Serial number as big-endian bytes (positive, at most 20 bytes), e.g. to create the same
+    /// certificate again. `None` for a random serial number, which is unique for all practical
+    /// purposes.
+    pub serial_number: Option<Vec<u8>>,
+    /// The algorithm the certificate is signed with.
+    pub signature_algorithm: SignatureAlgorithm,
 }
 
 impl From<(ApplicationDescription, Option<Vec<String>>)> for X509Data {
@@ -244,6 +326,8 @@ impl From<(ApplicationDescription, Option<Vec<String>>)> for X509Data {
             state: DEFAULT_STATE.to_string(),
             alt_host_names,
             certificate_duration_days: 365,
+            serial_number: None,
+            signature_algorithm: SignatureAlgorithm::default(),
         }
     }
 }
@@ -371,6 +455,20 @@ impl X509Data {
             state: DEFAULT_STATE.to_string(),
             alt_host_names,
             certificate_duration_days: 365,
+            serial_number: None,
+            signature_algorithm: SignatureAlgorithm::default(),
+        }
+    }
+
+    /// Checks that `key_size` is one of the [`SUPPORTED_KEY_SIZES`].
+    pub fn check_key_size(&self) -> Result<(), String> {
+        if SUPPORTED_KEY_SIZES.contains(&self.key_size) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Key size {} is not supported, use one of {:?}",
+                self.key_size, SUPPORTED_KEY_SIZES
+            ))
         }
     }
 }
@@ -418,9 +516,13 @@ impl X509 {
         let val = x509::certificate::Certificate::decode(&mut reader)?;
         let valf = reader.finish(val)?;
         Ok(X509 { value: valf })
+    }
 
-        //keep certificate chain for another story
-        //let r = x509::certificate::Certificate::load_pem_chain(data);
+    /// Load all X509 certificates from a pem file, e.g. a cert followed by the certs of its
+    /// issuers. The certificates are returned in the order of the file.
+    pub fn from_pem_chain(data: &[u8]) -> Result<Vec<Self>, X509Error> {
+        let values = x509::certificate::Certificate::load_pem_chain(data)?;
+        Ok(values.into_iter().map(|value| X509 { value }).collect())
     }
 
     /// Load an X509 certificate from a der file.
@@ -459,6 +561,8 @@ impl X509 {
     /// In particular, application instance cert requires subjectAltName to specify alternate
     /// hostnames / ip addresses that the host runs on.
     pub fn cert_and_pkey(x509_data: &X509Data) -> Result<(Self, PrivateKey), String> {
+        x509_data.check_key_size()?;
+
         // Create a key pair
 
         let pkey = PrivateKey::new(x509_data.key_size)
@@ -481,6 +585,39 @@ impl X509 {
         }
     }
 
+    fn subject_from(x509_data: &X509Data) -> Result<x509::name::Name, x509::der::Error> {
+        use std::str::FromStr;
+
+        let mut subject = String::new();
+        Self::append_to_name(&mut subject, "CN", &x509_data.common_name);
+        Self::append_to_name(&mut subject, "O", &x509_data.organization);
+        Self::append_to_name(&mut subject, "OU", &x509_data.organizational_unit);
+        Self::append_to_name(&mut subject, "C", &x509_data.country);
+        Self::append_to_name(&mut subject, "ST", &x509_data.state);
+        x509::name::Name::from_str(&subject)
+    }
+
+    /// Create a DER encoded PKCS#10 certificate signing request for the private key, to be
+    /// signed by a CA. The subject and the subject alternative names are the same as those of
+    /// a self-signed cert created from `x509_data`, the request is signed with SHA-256.
+    /// `key_size`, `certificate_duration_days`, `serial_number` and `signature_algorithm` are not
+    /// used, the CA decides on those.
+    pub fn create_csr(pkey: &PrivateKey, x509_data: &X509Data) -> Result<Vec<u8>, X509Error> {
+        use x509::builder::{Builder, RequestBuilder};
+        use x509::der::Encode;
+
+        let subject = Self::subject_from(x509_data)?;
+        let signing_key = pkcs1v15::SigningKey::<sha2::Sha256>::new(pkey.value.clone());
+        let mut builder = RequestBuilder::new(subject, &signing_key).map_err(|_| X509Error)?;
+        if !x509_data.alt_host_names.is_empty() {
+            builder
+                .add_extension(&x509_data.alt_host_names.names)
+                .map_err(|_| X509Error)?;
+        }
+        let csr = builder.build().map_err(|_| X509Error)?;
+        Ok(csr.to_der()?)
+    }
+
     /// Create a certificate from a private key and certificate description.
     pub fn from_pkey(pkey: &PrivateKey, x509_data: &X509Data) -> Result<Self, String> {
         let result = Self::create_from_pkey(pkey, x509_data);
@@ -497,9 +634,34 @@ impl X509 {
     }
 
     fn create_from_pkey(pkey: &PrivateKey, x509_data: &X509Data) -> Result<Self, BuilderError> {
+        match x509_data.signature_algorithm {
+            SignatureAlgorithm::Sha256Pkcs1v15 => Self::create_signed(
+                pkey,
+                x509_data,
+                &pkcs1v15::SigningKey::<sha2::Sha256>::new(pkey.value.clone()),
+            ),
+            SignatureAlgorithm::Sha256Pss => Self::create_signed(
+                pkey,
+                x509_data,
+                &pss::SigningKey::<sha2::Sha256>::new(pkey.value.clone()),
+            ),
+        }
+    }
+
+    fn create_signed<S, Signature>(
+        pkey: &PrivateKey,
+        x509_data: &X509Data,
+        signing_key: &S,
+    ) -> Result<Self, BuilderError>
+    where
+        S: x509::spki::DynSignatureAlgorithmIdentifier
+            + rsa::signature::Keypair
+            + rsa::signature::RandomizedSigner<Signature>,
+        S::VerifyingKey: x509::spki::EncodePublicKey,
+        Signature: x509::spki::SignatureBitStringEncoding,
+    {
         use std::time::Duration;
         use x509_cert::builder::{CertificateBuilder, Profile};
-        use x509_cert::name::Name;
         use x509_cert::serial_number::SerialNumber;
         use x509_cert::time::Validity;
 
@@ -518,23 +680,12 @@ impl X509 {
         ))
         .unwrap();
 
-        let signing_key = pkcs1v15::SigningKey::<sha2::Sha256>::new(pkey.value.clone());
-
-        let serial_number = SerialNumber::from(42u32);
-
-        let subject;
-
-        {
-            let mut issuer = String::new();
-            Self::append_to_name(&mut issuer, "CN", &x509_data.common_name);
-            Self::append_to_name(&mut issuer, "O", &x509_data.organization);
-            Self::append_to_name(&mut issuer, "OU", &x509_data.organizational_unit);
-            Self::append_to_name(&mut issuer, "C", &x509_data.country);
-            Self::append_to_name(&mut issuer, "ST", &x509_data.state);
+        let serial_number = match &x509_data.serial_number {
+            Some(serial_number) => SerialNumber::new(serial_number)?,
+            None => Self::random_serial_number()?,
+        };
 
-            use std::str::FromStr;
-            subject = Name::from_str(&issuer)?;
-        }
+        let subject = Self::subject_from(x509_data)?;
 
         // Issuer and subject shall be the same for self-signed cert
         let profile = Profile::Manual {
@@ -559,7 +710,7 @@ impl X509 {
             validity,
             subject.clone(),
             pub_key,
-            &signing_key,
+            signing_key,
         )?;
 
         builder.add_extension(&x509::ext::pkix::SubjectKeyIdentifier(
@@ -603,11 +754,71 @@ impl X509 {
         }
 
         use x509_cert::builder::Builder;
-        let built = builder.build()?;
+        let built = builder.build_with_rng(&mut rand::thread_rng())?;
 
         Ok(X509 { value: built })
     }
 
+    /// A random serial number of 16 bytes (127 bits). The first byte has the highest bit clear so
+    /// the number is positive without a leading sign byte, and another high bit set so it doesn't
+    /// get shorter.
+    fn random_serial_number() -> Result<x509_cert::serial_number::SerialNumber, x509::der::Error> {
+        let mut bytes = [0u8; 16];
+        super::random::bytes(&mut bytes);
+        bytes[0] = (bytes[0] & 0x7f) | 0x40;
+        x509_cert::serial_number::SerialNumber::new(&bytes)
+    }
+
+    /// Tests if the public key of the cert is the one of the private key, e.g. before
+    /// installing a cert signed by a CA for a request made with [`X509::create_csr`].
+    pub fn matches_pkey(&self, pkey: &PrivateKey) -> bool {
+        match pkey.public_key_to_info() {
+            Ok(info) => info == self.value.tbs_certificate.subject_public_key_info,
+            Err(_) => false,
+        }
+    }
+
+    /// Tests if the cert was issued by `issuer`, i.e. the issuer of the cert is the subject of
+    /// `issuer` and the signature of the cert verifies with the public key of `issuer`. Only
+    /// RSA PKCS#1 v1.5 signatures with SHA-256 or SHA-1 and RSASSA-PSS signatures with SHA-256 are
+    /// supported, others fail the test.
+    pub fn is_issued_by(&self, issuer: &X509) -> bool {
+        use x509::der::Encode;
+
+        let tbs_certificate = &self.value.tbs_certificate;
+        if tbs_certificate.issuer != issuer.value.tbs_certificate.subject {
+            return false;
+        }
+        let Ok(public_key) = issuer.public_key() else {
+            return false;
+        };
+        let Ok(data) = tbs_certificate.to_der() else {
+            return false;
+        };
+        let Some(signature) = self.value.signature.as_bytes() else {
+            return false;
+        };
+        let verified = match self.value.signature_algorithm.oid {
+            const_oid::db::rfc5912::SHA_256_WITH_RSA_ENCRYPTION => {
+                public_key.verify_sha256(&data, signature)
+            }
+            const_oid::db::rfc5912::SHA_1_WITH_RSA_ENCRYPTION => {
+                public_key.verify_sha1(&data, signature)
+            }
+            const_oid::db::rfc5912::ID_RSASSA_PSS => public_key.verify_sha256_pss(&data, signature),
+            oid => {
+                warn!("Certificate signature algorithm {} is not supported", oid);
+                return false;
+            }
+        };
+        verified.unwrap_or(false)
+    }
+
+    /// Tests if the cert is signed with its own key, i.e. it is a root or a self-signed cert.
+    pub fn is_self_signed(&self) -> bool {
+        self.is_issued_by(self)
+    }
+
     /// Load a certificate from a der byte string.
     pub fn from_byte_string(data: &ByteString) -> Result<X509, Error> {
         if data.is_null() {
@@ -675,6 +886,11 @@ impl X509 {
         r.replace(";", "/")
     }
 
+    /// Gets the serial number of the cert as big-endian bytes
+    pub fn serial_number(&self) -> &[u8] {
+        self.value.tbs_certificate.serial_number.as_bytes()
+    }
+
     /// Gets the common name out of the cert
     pub fn common_name(&self) -> Result<String, X509Error> {
         self.get_subject_entry(const_oid::db::rfc4519::COMMON_NAME)
@@ -683,11 +899,25 @@ impl X509 {
     /// Tests if the certificate is valid for the supplied time using the not before and not
     /// after values on the cert.
     pub fn is_time_valid(&self, now: &DateTime<Utc>) -> Result<(), StatusCode> {
+        self.is_time_valid_with_tolerance(now, &chrono::Duration::zero())
+    }
+
+    /// Like [`X509::is_time_valid`], but the validity window of the cert is widened by
+    /// `tolerance` on both ends, for clocks of the two sides which are a bit apart.
+    pub fn is_time_valid_with_tolerance(
+        &self,
+        now: &DateTime<Utc>,
+        tolerance: &chrono::Duration,
+    ) -> Result<(), StatusCode> {
         // Issuer time
         let not_before = self.not_before();
         if let Ok(not_before) = not_before {
-            if now.lt(&not_before) {
-                error!("Certificate < before date)");
+            if *now + *tolerance < not_before {
+                error!(
+                    "Certificate < before date, it is valid in {} s (tolerance {} s)",
+                    (not_before - *now).num_seconds(),
+                    tolerance.num_seconds()
+                );
                 return Err(StatusCode::BadCertificateTimeInvalid);
             }
         } else {
@@ -699,8 +929,12 @@ impl X509 {
         // Expiration time
         let not_after = self.not_after();
         if let Ok(not_after) = not_after {
-            if now.gt(&not_after) {
-                error!("Certificate has expired (> after date)");
+            if *now - *tolerance > not_after {
+                error!(
+                    "Certificate has expired (> after date) {} s ago (tolerance {} s)",
+                    (*now - not_after).num_seconds(),
+                    tolerance.num_seconds()
+                );
                 return Err(StatusCode::BadCertificateTimeInvalid);
             }
         } else {
@@ -713,6 +947,16 @@ impl X509 {
         Ok(())
     }
 
+    /// Gets the subject alternative names of the cert, the first is expected to be the
+    /// application uri. Can be put into [`X509Data`] to create a new cert for the same application.
+    pub fn alternate_names(&self) -> Option<AlternateNames> {
+        use x509::ext::pkix::SubjectAltName;
+
+        self.get_alternate_names().map(|names| AlternateNames {
+            names: SubjectAltName(names),
+        })
+    }
+
     fn get_alternate_names(&self) -> Option<x509::ext::pkix::name::GeneralNames> {
         use x509::ext::pkix::SubjectAltName;
 
@@ -728,29 +972,23 @@ impl X509 {
         }
     }
 
-    /// Tests if the supplied hostname matches any of the dns alt subject name entries on the cert
+    /// Tests if the supplied hostname matches any of the dns or ip alt subject name entries on
+    /// the cert. Uri entries never match.
     pub fn is_hostname_valid(&self, hostname: &str) -> Result<(), StatusCode> {
         trace!("is_hostname_valid against {} on cert", hostname);
         // Look through alt subject names for a matching entry
         if hostname.is_empty() {
             error!("Hostname is empty");
             Err(StatusCode::BadCertificateHostNameInvalid)
-        } else if let Some(subject_alt_names) = self.get_alternate_names() {
-            let found = subject_alt_names
-                .iter()
-                .skip(1) //skip the application uri
-                .any(|n| {
-                    let name = AlternateNames::convert_name(n);
-                    match name {
-                        Some(val) => val.eq_ignore_ascii_case(hostname),
-                        _ => false,
-                    }
-                });
-            if found {
+        } else if let Some(alt_names) = self.alternate_names() {
+            if alt_names.has_hostname(hostname) {
                 info!("Certificate host name {} is good", hostname);
                 Ok(())
             } else {
-                warn!("Did not find hostname {hostname} in alt names {subject_alt_names:?}");
+                warn!(
+                    "Did not find hostname {hostname} in alt names {:?}",
+                    alt_names.iter_typed().collect::<Vec<_>>()
+                );
                 Err(StatusCode::BadCertificateHostNameInvalid)
             }
         } else {
@@ -760,41 +998,35 @@ impl X509 {
         }
     }
 
-    /// Tests if the supplied application uri matches the uri alt subject name entry on the cert
+    /// Tests if the supplied application uri matches one of the uri alt subject name entries on
+    /// the cert, wherever it is in the list. A cert without uri entries is taken to have the
+    /// application uri as its first entry, whatever its type.
     pub fn is_application_uri_valid(&self, application_uri: &str) -> Result<(), StatusCode> {
-        // Expecting the first subject alternative name to be a uri that matches with the supplied
-        // application uri
-        if let Some(alt_names) = self.get_alternate_names() {
-            if !alt_names.is_empty() {
-                match AlternateNames::convert_name(&alt_names[0]) {
-                    Some(val) => {
-                        if val == application_uri {
-                            Ok(())
-                        } else {
-                            error!(
-                                "Application uri {} does not match first alt name {}",
-                                application_uri, val
-                            );
-                            Err(StatusCode::BadCertificateUriInvalid)
-                        }
-                    }
-
-                    _ => {
-                        error!("Alternate name {:?} cannot be converted", alt_names[0]);
-                        Err(StatusCode::BadCertificateUriInvalid)
-                    }
-                }
-            } else {
-                error!("Cert has zero subject alt names");
-                Err(StatusCode::BadCertificateUriInvalid)
-            }
+        let uris = self
+            .alternate_names()
+            .map(|alt_names| alt_names.application_uris())
+            .unwrap_or_default();
+        if uris.iter().any(|uri| uri == application_uri) {
+            Ok(())
+        } else if uris.is_empty() {
+            error!("Cert has no subject alt name for the application uri");
+            Err(StatusCode::BadCertificateUriInvalid)
         } else {
-            error!("Cert has no subject alt names at all");
-            // No alt names
+            error!(
+                "Application uri {} does not match the uris {:?} of the cert",
+                application_uri, uris
+            );
             Err(StatusCode::BadCertificateUriInvalid)
         }
     }
 
+    /// Gets the application uri of the cert, the first uniform resource identifier of the subject
+    /// alternative names. Without a uri entry the first of the names is taken, as some stacks
+    /// use the first entry whatever its type.
+    pub fn application_uri(&self) -> Option<String> {
+        self.alternate_names()?.application_uri()
+    }
+
     /// OPC UA Part 6 MessageChunk structure
     ///
     /// The thumbprint is the SHA1 digest of the DER form of the certificate. The hash is 160 bits
@@ -872,7 +1104,7 @@ mod tests {
     #[test]
     fn alt_hostnames() {
         let mut alt_host_names = AlternateNames::new();
-        alt_host_names.add_dns("uri:foo"); //the application uri
+        alt_host_names.add_uri("uri:foo"); //the application uri
         alt_host_names.add_address("host2");
         alt_host_names.add_address("www.google.com");
         alt_host_names.add_address("192.168.1.1");
@@ -888,6 +1120,8 @@ mod tests {
             state: "London".to_string(),
             alt_host_names,
             certificate_duration_days: 60,
+            serial_number: None,
+            signature_algorithm: SignatureAlgorithm::default(),
         };
 
         let (x509, _pkey) = X509::cert_and_pkey(&args).unwrap();
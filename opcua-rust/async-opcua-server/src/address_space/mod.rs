@@ -349,6 +349,11 @@ impl AddressSpace {
         &self.namespaces
     }
 
+    /// Iterate over all nodes in the address space, in no particular order.
+    pub fn nodes(&self) -> impl Iterator<Item = &NodeType> {
+        self.node_map.values()
+    }
+
     /// Find node by something that can be turned into a node id and return a reference to it.
     pub fn find<N>(&self, node_id: N) -> Option<&NodeType>
     where
@@ -25,8 +25,17 @@ pub fn is_writable(
     node: &NodeType,
     attribute_id: AttributeId,
 ) -> Result<(), StatusCode> {
-    if let (NodeType::Variable(_), AttributeId::Value) = (node, attribute_id) {
+    if let (NodeType::Variable(variable), AttributeId::Value) = (node, attribute_id) {
+        // The access level applies to everyone, the user access level to this user.
+        if !variable.access_level().contains(AccessLevel::CURRENT_WRITE) {
+            return Err(StatusCode::BadNotWritable);
+        }
         if !user_access_level(context, node).contains(AccessLevel::CURRENT_WRITE) {
+            context.authenticator.access_denied(
+                &context.token,
+                node.node_id(),
+                AccessLevel::CURRENT_WRITE,
+            );
             return Err(StatusCode::BadUserAccessDenied);
         }
 
@@ -136,6 +136,10 @@ pub trait AuthManager: Send + Sync + 'static {
         user_access_level
     }
 
+    /// Called when the effective user access level denies the user an access to a node,
+    /// e.g. a write of its value, to audit failed attempts. The default does nothing.
+    fn access_denied(&self, token: &UserToken, node_id: &NodeId, access: AccessLevel) {}
+
     /// Return whether a method is actually user executable, overriding whatever is returned by the
     /// node manager.
     fn is_user_executable(&self, token: &UserToken, method_id: &NodeId) -> bool {
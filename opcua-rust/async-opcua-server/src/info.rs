@@ -7,7 +7,7 @@
 use std::sync::atomic::{AtomicU16, AtomicU8, Ordering};
 use std::sync::Arc;
 
-use arc_swap::ArcSwap;
+use arc_swap::{ArcSwap, ArcSwapOption};
 use opcua_nodes::DefaultTypeTree;
 use tracing::{debug, error, warn};
 
@@ -51,9 +51,9 @@ pub struct ServerInfo {
     /// Server configuration
     pub config: Arc<ServerConfig>,
     /// Server public certificate read from config location or null if there is none
-    pub server_certificate: Option<X509>,
+    pub server_certificate: ArcSwapOption<X509>,
     /// Server private key
-    pub server_pkey: Option<PrivateKey>,
+    pub server_pkey: ArcSwapOption<PrivateKey>,
     /// Operational limits
     pub(crate) operational_limits: OperationalLimits,
     /// Current state
@@ -284,9 +284,17 @@ impl ServerInfo {
         )
     }
 
+    /// Replace the server certificate and private key, e.g. after renewing them.
+    /// New secure channels and sessions use them, the open ones keep the old pair,
+    /// so the pair should only be replaced while there are no sessions.
+    pub fn set_server_certificate(&self, certificate: X509, pkey: PrivateKey) {
+        self.server_pkey.store(Some(Arc::new(pkey)));
+        self.server_certificate.store(Some(Arc::new(certificate)));
+    }
+
     /// Get the server certificate as a byte string.
     pub fn server_certificate_as_byte_string(&self) -> ByteString {
-        if let Some(ref server_certificate) = self.server_certificate {
+        if let Some(server_certificate) = &*self.server_certificate.load() {
             server_certificate.as_byte_string()
         } else {
             ByteString::null()
@@ -353,7 +361,7 @@ impl ServerInfo {
                     self.authenticate_username_identity_token(
                         endpoint,
                         &token,
-                        &self.server_pkey,
+                        self.server_pkey.load_full().as_deref(),
                         server_nonce,
                     )
                     .await
@@ -363,7 +371,7 @@ impl ServerInfo {
                         endpoint,
                         &token,
                         &request.user_token_signature,
-                        &self.server_certificate,
+                        self.server_certificate.load_full().as_deref(),
                         server_nonce,
                     )
                     .await
@@ -417,7 +425,7 @@ impl ServerInfo {
         &self,
         endpoint: &ServerEndpoint,
         token: &UserNameIdentityToken,
-        server_key: &Option<PrivateKey>,
+        server_key: Option<&PrivateKey>,
         server_nonce: &ByteString,
     ) -> Result<UserToken, Error> {
         if !self.authenticator.supports_user_pass(endpoint) {
@@ -442,7 +450,7 @@ impl ServerInfo {
                 token.encryption_algorithm.as_ref()
             );
             let token_password = if !token.encryption_algorithm.is_null() {
-                if let Some(ref server_key) = server_key {
+                if let Some(server_key) = server_key {
                     user_identity::decrypt_user_identity_token_password(
                         token,
                         server_nonce.as_ref(),
@@ -476,7 +484,7 @@ impl ServerInfo {
         endpoint: &ServerEndpoint,
         token: &X509IdentityToken,
         user_token_signature: &SignatureData,
-        server_certificate: &Option<X509>,
+        server_certificate: Option<&X509>,
         server_nonce: &ByteString,
     ) -> Result<UserToken, Error> {
         if !self.authenticator.supports_x509(endpoint) {
@@ -493,7 +501,7 @@ impl ServerInfo {
             ))
         } else {
             match server_certificate {
-                Some(ref server_certificate) => {
+                Some(server_certificate) => {
                     // Find the security policy used for verifying tokens
                     let user_identity_tokens = self.authenticator.user_token_policies(endpoint);
                     let security_policy = user_identity_tokens
@@ -524,6 +524,11 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
                 let user_access_level = user_access_level(context, node);
 
                 if !user_access_level.contains(AccessLevel::HISTORY_WRITE) {
+                    context.authenticator.access_denied(
+                        &context.token,
+                        node.as_node().node_id(),
+                        AccessLevel::HISTORY_WRITE,
+                    );
                     history_node.set_status(StatusCode::BadUserAccessDenied);
                     continue;
                 }
@@ -35,6 +35,8 @@ type ReadCB = Arc<
         + 'static,
 >;
 type MethodCB = Arc<dyn Fn(&[Variant]) -> Result<Vec<Variant>, StatusCode> + Send + Sync + 'static>;
+type WriteObserver =
+    Arc<dyn Fn(&RequestContext, &NodeId, DataValue, &DataValue) + Send + Sync + 'static>;
 
 /// Builder for the [SimpleNodeManager].
 pub struct SimpleNodeManagerBuilder {
@@ -120,6 +122,7 @@ pub struct SimpleNodeManagerImpl {
     write_cbs: RwLock<HashMap<NodeId, WriteCB>>,
     read_cbs: RwLock<HashMap<NodeId, ReadCB>>,
     method_cbs: RwLock<HashMap<NodeId, MethodCB>>,
+    write_observer: RwLock<Option<WriteObserver>>,
     namespaces: Vec<NamespaceMetadata>,
     #[allow(unused)]
     node_managers: NodeManagersRef,
@@ -270,9 +273,17 @@ impl InMemoryNodeManagerImpl for SimpleNodeManagerImpl {
         let mut address_space = trace_write_lock!(address_space);
         let type_tree = trace_read_lock!(context.type_tree);
         let cbs = trace_read_lock!(self.write_cbs);
+        let observer = trace_read_lock!(self.write_observer).clone();
 
         for write in nodes_to_write {
-            self.write_node_value(&cbs, context, &mut address_space, &type_tree, write);
+            self.write_node_value(
+                &cbs,
+                observer.as_ref(),
+                context,
+                &mut address_space,
+                &type_tree,
+                write,
+            );
         }
 
         Ok(())
@@ -307,6 +318,7 @@ impl SimpleNodeManagerImpl {
             write_cbs: Default::default(),
             read_cbs: Default::default(),
             method_cbs: Default::default(),
+            write_observer: Default::default(),
             namespaces,
             name: name.to_owned(),
             node_managers,
@@ -351,6 +363,7 @@ impl SimpleNodeManagerImpl {
     fn write_node_value(
         &self,
         cbs: &HashMap<NodeId, WriteCB>,
+        observer: Option<&WriteObserver>,
         context: &RequestContext,
         address_space: &mut AddressSpace,
         type_tree: &DefaultTypeTree,
@@ -371,6 +384,18 @@ impl SimpleNodeManagerImpl {
             return;
         }
 
+        // The previous value is only needed by the observer.
+        let old_value = observer.map(|_| {
+            node.as_node()
+                .get_attribute(
+                    TimestampsToReturn::Both,
+                    AttributeId::Value,
+                    &NumericRange::None,
+                    &opcua_types::DataEncoding::Binary,
+                )
+                .unwrap_or_default()
+        });
+
         if let Some(cb) = cbs.get(node.as_node().node_id()) {
             // If there is a callback registered, call that.
             write.set_status(cb(write.value().value.clone(), &write.value().index_range));
@@ -395,6 +420,9 @@ impl SimpleNodeManagerImpl {
                     [(val, node.node_id(), write.value().attribute_id)].into_iter(),
                 );
             }
+            if let (Some(observer), Some(old_value)) = (observer, old_value) {
+                observer(context, node.node_id(), old_value, &write.value().value);
+            }
         }
     }
 
@@ -421,6 +449,23 @@ impl SimpleNodeManagerImpl {
         cbs.insert(id, Arc::new(cb));
     }
 
+    /// Set a callback called after every successful `Write` of a value, with the
+    /// request context, the node ID, the value before the write and the written value.
+    /// Replaces the previous observer.
+    pub fn set_write_observer(
+        &self,
+        observer: impl Fn(&RequestContext, &NodeId, DataValue, &DataValue) + Send + Sync + 'static,
+    ) {
+        let mut lck = trace_write_lock!(self.write_observer);
+        *lck = Some(Arc::new(observer));
+    }
+
+    /// Remove the callback set with `set_write_observer`.
+    pub fn clear_write_observer(&self) {
+        let mut lck = trace_write_lock!(self.write_observer);
+        *lck = None;
+    }
+
     /// Add a callback for `Call` on the method given by `id`.
     pub fn add_method_callback(
         &self,
@@ -51,6 +51,16 @@ impl SessionManager {
         Self::find_by_token_int(&self.sessions, authentication_token)
     }
 
+    /// Number of sessions on the server, including the ones not activated yet.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// `true` if the server has no sessions.
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
     fn find_by_token_int(
         sessions: &HashMap<NodeId, Arc<RwLock<Session>>>,
         authentication_token: &NodeId,
@@ -109,7 +119,7 @@ impl SessionManager {
             .min(request.requested_session_timeout.floor() as u64);
         let max_request_message_size = self.info.config.limits.max_message_size as u32;
 
-        let server_signature = if let Some(ref pkey) = self.info.server_pkey {
+        let server_signature = if let Some(pkey) = &*self.info.server_pkey.load() {
             opcua_crypto::create_signature_data(
                 pkey,
                 security_policy,
@@ -183,7 +193,7 @@ impl SessionManager {
         client_signature: &SignatureData,
     ) -> Result<(), Error> {
         if let Some(client_certificate) = session.client_certificate() {
-            if let Some(ref server_certificate) = info.server_certificate {
+            if let Some(server_certificate) = &*info.server_certificate.load() {
                 opcua_crypto::verify_signature_data(
                     client_signature,
                     security_policy,
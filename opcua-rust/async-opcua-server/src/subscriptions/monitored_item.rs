@@ -6,8 +6,8 @@ use tracing::error;
 use super::MonitoredItemHandle;
 use crate::{info::ServerInfo, node_manager::ParsedReadValueId};
 use opcua_types::{
-    match_extension_object_owned, DataChangeFilter, DataValue, DateTime, EventFieldList,
-    EventFilter, EventFilterResult, ExtensionObject, MonitoredItemCreateRequest,
+    match_extension_object_owned, DataChangeFilter, DataValue, DateTime, DateTimeUtc,
+    EventFieldList, EventFilter, EventFilterResult, ExtensionObject, MonitoredItemCreateRequest,
     MonitoredItemModifyRequest, MonitoredItemNotification, MonitoringMode, NumericRange,
     ParsedDataChangeFilter, StatusCode, TimestampsToReturn, Variant,
 };
@@ -281,6 +281,9 @@ pub struct MonitoredItem {
     queue_overflow: bool,
     timestamps_to_return: TimestampsToReturn,
     last_data_value: Option<DataValue>,
+    // Latest change within the sampling interval of the last reported value,
+    // it is reported once the interval has elapsed.
+    pending_data_value: Option<DataValue>,
     any_new_notification: bool,
     eu_range: Option<(f64, f64)>,
 }
@@ -298,6 +301,7 @@ impl MonitoredItem {
             discard_oldest: request.discard_oldest,
             timestamps_to_return: request.timestamps_to_return,
             last_data_value: None,
+            pending_data_value: None,
             queue_size: request.queue_size,
             notification_queue: VecDeque::new(),
             queue_overflow: false,
@@ -397,22 +401,72 @@ impl MonitoredItem {
             }
         }
 
-        let data_change = match (&self.last_data_value, &self.filter) {
-            (Some(last_dv), FilterType::DataChangeFilter(filter)) => {
-                filter.is_changed(&value, last_dv)
-                    && self.filter_by_sampling_interval(last_dv, &value)
-            }
-            (Some(last_dv), FilterType::None) => {
-                value.value != last_dv.value && self.filter_by_sampling_interval(last_dv, &value)
-            }
-            (None, _) => true,
-            _ => false,
+        let (data_change, sampled) = match (&self.last_data_value, &self.filter) {
+            (Some(last_dv), FilterType::DataChangeFilter(filter)) => (
+                filter.is_changed(&value, last_dv),
+                self.filter_by_sampling_interval(last_dv, &value),
+            ),
+            (Some(last_dv), FilterType::None) => (
+                value.value != last_dv.value,
+                self.filter_by_sampling_interval(last_dv, &value),
+            ),
+            (None, _) => (true, true),
+            _ => (false, true),
         };
 
         if !data_change {
+            // Back to the reported value, nothing left to report.
+            self.pending_data_value = None;
+            return false;
+        }
+        if !sampled {
+            // Hold the change back instead of dropping it, otherwise the last
+            // value of a burst of changes would never be reported.
+            self.pending_data_value = Some(value);
+            return false;
+        }
+        self.pending_data_value = None;
+        self.report_data_value(value);
+
+        true
+    }
+
+    /// Return `true` if this item holds back a change within its sampling interval.
+    pub fn has_pending_value(&self) -> bool {
+        self.pending_data_value.is_some()
+    }
+
+    /// Report the change held back by the sampling interval, if the interval has
+    /// elapsed since the last reported value at `now`. Returns `true` if it was reported.
+    pub(super) fn sample_pending_value(&mut self, now: &DateTimeUtc) -> bool {
+        if !self.is_sampling() {
+            self.pending_data_value = None;
+            return false;
+        }
+        let Some(last) = self
+            .last_data_value
+            .as_ref()
+            .and_then(|dv| dv.source_timestamp.as_ref())
+        else {
+            return false;
+        };
+        let sampling_interval =
+            std::time::Duration::from_micros((self.sampling_interval * 1000f64) as u64);
+        let due = now
+            .signed_duration_since(last.as_chrono())
+            .to_std()
+            .map_or(true, |elapsed| elapsed >= sampling_interval);
+        if !due {
             return false;
         }
+        let Some(value) = self.pending_data_value.take() else {
+            return false;
+        };
+        self.report_data_value(value);
+        true
+    }
 
+    fn report_data_value(&mut self, mut value: DataValue) {
         self.last_data_value = Some(value.clone());
 
         match self.timestamps_to_return {
@@ -440,8 +494,6 @@ impl MonitoredItem {
             client_handle,
             value,
         });
-
-        true
     }
 
     pub(super) fn notify_event(&mut self, event: &dyn Event) -> bool {
@@ -638,6 +690,7 @@ pub(super) mod tests {
             queue_overflow: false,
             timestamps_to_return: opcua_types::TimestampsToReturn::Both,
             last_data_value: None,
+            pending_data_value: None,
             any_new_notification: false,
             eu_range: None,
         };
@@ -818,6 +871,58 @@ pub(super) mod tests {
         assert_eq!(item.notification_queue.len(), 3);
     }
 
+    #[test]
+    fn monitored_item_pending_value() {
+        let start = Utc::now();
+        let mut item = new_monitored_item(
+            1,
+            ReadValueId {
+                node_id: NodeId::null(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            },
+            MonitoringMode::Reporting,
+            FilterType::None,
+            100.0,
+            true,
+            Some(DataValue::new_at(1.0, start.into())),
+        );
+        // Within the sampling interval, held back
+        assert!(!item.notify_data_value(DataValue::new_at(
+            2.0,
+            (start + Duration::try_milliseconds(20).unwrap()).into()
+        )));
+        assert!(!item.notify_data_value(DataValue::new_at(
+            3.0,
+            (start + Duration::try_milliseconds(40).unwrap()).into()
+        )));
+        assert!(item.has_pending_value());
+        // Interval not elapsed yet
+        assert!(!item.sample_pending_value(&(start + Duration::try_milliseconds(60).unwrap())));
+        // The latest change is reported once the interval has elapsed
+        assert!(item.sample_pending_value(&(start + Duration::try_milliseconds(100).unwrap())));
+        assert!(!item.has_pending_value());
+        assert_eq!(item.notification_queue.len(), 2);
+        let Some(Notification::MonitoredItemNotification(n)) = item.notification_queue.back()
+        else {
+            panic!("Expected a data change notification");
+        };
+        assert_eq!(n.value.value, Some(Variant::Double(3.0)));
+
+        // Back to the reported value within the interval, nothing to report
+        assert!(!item.notify_data_value(DataValue::new_at(
+            4.0,
+            (start + Duration::try_milliseconds(120).unwrap()).into()
+        )));
+        assert!(!item.notify_data_value(DataValue::new_at(
+            3.0,
+            (start + Duration::try_milliseconds(130).unwrap()).into()
+        )));
+        assert!(!item.has_pending_value());
+        assert!(!item.sample_pending_value(&(start + Duration::try_milliseconds(300).unwrap())));
+        assert_eq!(item.notification_queue.len(), 2);
+    }
+
     #[test]
     fn monitored_item_overflow() {
         let start = Utc::now();
@@ -99,6 +99,8 @@ pub struct Subscription {
     monitored_items: HashMap<u32, MonitoredItem>,
     /// Monitored items that have seen notifications.
     notified_monitored_items: HashSet<u32>,
+    /// Monitored items holding back a change within their sampling interval.
+    pending_monitored_items: HashSet<u32>,
     /// State of the subscription
     state: SubscriptionState,
     /// A value that contains the number of consecutive publishing timer expirations without Client
@@ -159,6 +161,7 @@ impl Subscription {
             priority,
             monitored_items: HashMap::new(),
             notified_monitored_items: HashSet::new(),
+            pending_monitored_items: HashSet::new(),
             // State variables
             state: SubscriptionState::Creating,
             lifetime_counter,
@@ -230,6 +233,9 @@ impl Subscription {
             if item.notify_data_value(value) {
                 self.notified_monitored_items.insert(*id);
             }
+            if item.has_pending_value() {
+                self.pending_monitored_items.insert(*id);
+            }
         }
     }
 
@@ -490,6 +496,7 @@ impl Subscription {
         if matches!(tick_reason, TickReason::TickTimerFired) && !publishing_interval_elapsed {
             return TickResult::None;
         }
+        self.sample_pending_values(now);
         // First, get the actual state transition we're in.
         let transition = self.get_state_transition(
             tick_reason,
@@ -534,6 +541,20 @@ impl Subscription {
         }
     }
 
+    /// Report the changes held back by the sampling intervals of the monitored items
+    /// once their intervals have elapsed.
+    fn sample_pending_values(&mut self, now: &DateTimeUtc) {
+        self.pending_monitored_items.retain(|id| {
+            let Some(item) = self.monitored_items.get_mut(id) else {
+                return false;
+            };
+            if item.sample_pending_value(now) {
+                self.notified_monitored_items.insert(*id);
+            }
+            item.has_pending_value()
+        });
+    }
+
     fn enqueue_notification(&mut self, notification: NotificationMessage) {
         // For sanity, check the sequence number is the expected sequence number.
         let expected_sequence_number = if self.last_sequence_number == u32::MAX {
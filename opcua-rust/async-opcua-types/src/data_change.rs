@@ -132,8 +132,9 @@ impl ParsedDataChangeFilter {
                 if filter.deadband_value < 0.0 || filter.deadband_value > 100.0 {
                     return Err(StatusCode::BadDeadbandFilterInvalid);
                 }
+                // Percent is only defined for nodes with an EURange (analog items)
                 let Some((low, high)) = eu_range else {
-                    return Err(StatusCode::BadDeadbandFilterInvalid);
+                    return Err(StatusCode::BadFilterNotAllowed);
                 };
                 if low >= high {
                     return Err(StatusCode::BadDeadbandFilterInvalid);
@@ -537,7 +537,10 @@ impl BinaryDecodable for ExtensionObject {
                 if size <= 0 {
                     None
                 } else {
-                    Some(ctx.load_from_binary(&node_id, &mut stream)?)
+                    // Limit the stream to the body, so that a type loader can never
+                    // read past the end of the extension object.
+                    let mut body_stream = (&mut stream).take(size as u64);
+                    Some(ctx.load_from_binary(&node_id, &mut body_stream)?)
                 }
             }
             0x2 => {
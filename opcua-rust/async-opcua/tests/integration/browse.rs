@@ -1,7 +1,9 @@
 use super::utils::setup;
 use opcua::{
     nodes::TypeTree,
-    server::address_space::{ObjectBuilder, ReferenceDirection, VariableBuilder},
+    server::address_space::{
+        DataTypeBuilder, ObjectBuilder, ReferenceDirection, VariableBuilder, ViewBuilder,
+    },
     types::{
         BrowseDescription, BrowseDirection, BrowsePath, BrowseResultMask, ByteString, DataTypeId,
         NodeClass, NodeClassMask, NodeId, ObjectId, ObjectTypeId, ReferenceTypeId, RelativePath,
@@ -10,7 +12,10 @@ use opcua::{
 };
 use opcua_client::browser::BrowseFilter;
 use opcua_nodes::DefaultTypeTree;
-use opcua_types::{AttributeId, ReadValueId, TimestampsToReturn, VariableId, Variant};
+use opcua_types::{
+    AttributeId, ExtensionObject, Range, ReadValueId, TimestampsToReturn, VariableId, Variant,
+};
+use std::collections::HashSet;
 
 fn hierarchical_desc(node_id: NodeId) -> BrowseDescription {
     BrowseDescription {
@@ -245,6 +250,215 @@ async fn browse_cross_node_manager() {
     assert_eq!(rf.display_name, "TestObj1".into());
 }
 
+#[tokio::test]
+async fn browse_properties() {
+    let (tester, nm, session) = setup().await;
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "Pressure", "Pressure")
+            .data_type(DataTypeId::Double)
+            .value(0.0)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    // EURange property, as described in Part 8.
+    let range_id = nm.inner().next_node_id();
+    {
+        let mut sp = nm.address_space().write();
+        VariableBuilder::new(&range_id, "EURange", "EURange")
+            .data_type(DataTypeId::Range)
+            .value(ExtensionObject::from_message(Range {
+                low: 0.0,
+                high: 10.0,
+            }))
+            .has_type_definition(VariableTypeId::PropertyType)
+            .property_of(&id)
+            .insert(&mut *sp);
+    }
+
+    let mut desc = hierarchical_desc(id.clone());
+    desc.reference_type_id = ReferenceTypeId::HasProperty.into();
+    let r = session.browse(&[desc], 1000, None).await.unwrap();
+    let refs = r[0].references.clone().unwrap_or_default();
+    assert_eq!(1, refs.len());
+    let rf = &refs[0];
+    assert_eq!(rf.node_id.node_id, range_id);
+    assert_eq!(rf.browse_name, "EURange".into());
+    assert_eq!(rf.type_definition, VariableTypeId::PropertyType.into());
+
+    let r = session
+        .read(
+            &[ReadValueId::new_value(range_id)],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    let Some(Variant::ExtensionObject(obj)) = &r[0].value else {
+        panic!("Expected extension object");
+    };
+    let range = obj.inner_as::<Range>().unwrap();
+    assert_eq!(range.low, 0.0);
+    assert_eq!(range.high, 10.0);
+}
+
+#[tokio::test]
+async fn browse_added_reference() {
+    let (tester, nm, session) = setup().await;
+    let machine_id = nm.inner().next_node_id();
+    let motor_id = nm.inner().next_node_id();
+    for (id, name) in [(&machine_id, "Machine"), (&motor_id, "Motor")] {
+        nm.inner().add_node(
+            nm.address_space(),
+            tester.handle.type_tree(),
+            ObjectBuilder::new(id, name, name).build().into(),
+            &ObjectId::ObjectsFolder.into(),
+            &ReferenceTypeId::Organizes.into(),
+            Some(&ObjectTypeId::BaseObjectType.into()),
+            Vec::new(),
+        );
+    }
+
+    // Reference added at runtime, after both nodes exist.
+    {
+        let mut sp = nm.address_space().write();
+        sp.insert_reference(&machine_id, &motor_id, ReferenceTypeId::HasComponent);
+    }
+
+    let mut desc = hierarchical_desc(machine_id.clone());
+    desc.reference_type_id = ReferenceTypeId::HasComponent.into();
+    desc.include_subtypes = false;
+    let r = session.browse(&[desc], 1000, None).await.unwrap();
+    let refs = r[0].references.clone().unwrap_or_default();
+    assert_eq!(1, refs.len());
+    assert_eq!(refs[0].node_id.node_id, motor_id);
+    assert!(refs[0].is_forward);
+
+    // The inverse reference is browsable from the target.
+    let mut desc = hierarchical_desc(motor_id.clone());
+    desc.reference_type_id = ReferenceTypeId::HasComponent.into();
+    desc.browse_direction = BrowseDirection::Inverse;
+    let r = session.browse(&[desc], 1000, None).await.unwrap();
+    let refs = r[0].references.clone().unwrap_or_default();
+    assert_eq!(1, refs.len());
+    assert_eq!(refs[0].node_id.node_id, machine_id);
+
+    {
+        let mut sp = nm.address_space().write();
+        assert!(sp.delete_reference(&machine_id, &motor_id, ReferenceTypeId::HasComponent));
+    }
+    let mut desc = hierarchical_desc(machine_id.clone());
+    desc.reference_type_id = ReferenceTypeId::HasComponent.into();
+    let r = session.browse(&[desc], 1000, None).await.unwrap();
+    assert!(r[0].references.clone().unwrap_or_default().is_empty());
+}
+
+#[tokio::test]
+async fn browse_view() {
+    let (tester, nm, session) = setup().await;
+    let view_id = nm.inner().next_node_id();
+    let var_id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&var_id, "Speed", "Speed")
+            .data_type(DataTypeId::Double)
+            .value(0.0)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+    {
+        let mut sp = nm.address_space().write();
+        ViewBuilder::new(&view_id, "Operator", "Operator")
+            .contains_no_loops(true)
+            .organized_by(ObjectId::ViewsFolder)
+            .insert(&mut *sp);
+        sp.insert_reference(&view_id, &var_id, ReferenceTypeId::Organizes);
+    }
+
+    // The view is found under the Views folder.
+    let r = session
+        .browse(
+            &[hierarchical_desc(ObjectId::ViewsFolder.into())],
+            1000,
+            None,
+        )
+        .await
+        .unwrap();
+    let refs = r[0].references.clone().unwrap_or_default();
+    let view = refs.iter().find(|r| r.node_id.node_id == view_id).unwrap();
+    assert_eq!(view.node_class, NodeClass::View);
+
+    let r = session
+        .browse(&[hierarchical_desc(view_id.clone())], 1000, None)
+        .await
+        .unwrap();
+    let refs = r[0].references.clone().unwrap_or_default();
+    assert_eq!(1, refs.len());
+    assert_eq!(refs[0].node_id.node_id, var_id);
+    assert_eq!(refs[0].browse_name, "Speed".into());
+}
+
+#[tokio::test]
+async fn browse_data_types() {
+    let (_tester, nm, session) = setup().await;
+    let type_id = nm.inner().next_node_id();
+    {
+        let mut sp = nm.address_space().write();
+        DataTypeBuilder::new(&type_id, "MyStructure", "MyStructure")
+            .subtype_of(DataTypeId::Structure)
+            .insert(&mut *sp);
+    }
+
+    // Walk the hierarchical references from the DataTypes folder,
+    // the custom type is found as a subtype of Structure.
+    let mut visited = HashSet::new();
+    let mut data_types = Vec::new();
+    let mut level: Vec<NodeId> = vec![ObjectId::DataTypesFolder.into()];
+    while !level.is_empty() {
+        let descs: Vec<BrowseDescription> = level
+            .drain(..)
+            .map(|node_id| BrowseDescription {
+                node_class_mask: (NodeClassMask::OBJECT | NodeClassMask::DATA_TYPE).bits(),
+                ..hierarchical_desc(node_id)
+            })
+            .collect();
+        for r in session.browse(&descs, 0, None).await.unwrap() {
+            assert!(r.continuation_point.is_null());
+            for rf in r.references.unwrap_or_default() {
+                if visited.insert(rf.node_id.node_id.clone()) {
+                    level.push(rf.node_id.node_id.clone());
+                    if rf.node_class == NodeClass::DataType {
+                        data_types.push(rf);
+                    }
+                }
+            }
+        }
+    }
+
+    assert!(data_types
+        .iter()
+        .any(|r| r.node_id.node_id == NodeId::from(DataTypeId::Double)));
+    let custom: Vec<_> = data_types
+        .iter()
+        .filter(|r| r.node_id.node_id.namespace != 0)
+        .collect();
+    assert_eq!(1, custom.len());
+    assert_eq!(custom[0].node_id.node_id, type_id);
+    assert_eq!(custom[0].browse_name, "MyStructure".into());
+}
+
 #[tokio::test]
 async fn browse_continuation_point() {
     let (tester, nm, session) = setup().await;
@@ -1,13 +1,14 @@
-use std::{sync::atomic::Ordering, time::Duration};
+use std::{path::PathBuf, sync::atomic::Ordering, time::Duration};
 
 use super::utils::hostname;
 use bytes::BytesMut;
 use log::debug;
 use opcua::{
-    client::IdentityToken,
+    client::{ClientBuilder, IdentityToken},
     core::comms::tcp_codec::{Message, TcpCodec},
     core::config::Config,
-    crypto::SecurityPolicy,
+    crypto::{CertificateStore, SecurityPolicy, X509Data},
+    server::{diagnostics::NamespaceMetadata, node_manager::memory::simple_node_manager},
     types::{
         ApplicationType, DecodingOptions, MessageSecurityMode, NodeId, ReadValueId, StatusCode,
         TimestampsToReturn, VariableId, Variant,
@@ -20,8 +21,8 @@ use tokio::{
 use tokio_util::codec::Decoder;
 
 use crate::utils::{
-    client_user_token, client_x509_token, copy_shared_certs, default_server, test_server, Tester,
-    CLIENT_USERPASS_ID, TEST_COUNTER,
+    client_user_token, client_x509_token, copy_shared_certs, default_client, default_server,
+    test_server, Tester, CLIENT_USERPASS_ID, TEST_COUNTER,
 };
 
 #[tokio::test]
@@ -38,10 +39,10 @@ async fn hello_timeout() {
         .hello_timeout(1);
     copy_shared_certs(test_id, &server.config().application_description());
 
-    let (server, handle) = server.build().unwrap();
+    let (mut server, handle) = server.build().unwrap();
     let addr = listener.local_addr().unwrap();
 
-    tokio::task::spawn(server.run_with(listener));
+    tokio::task::spawn(async move { server.run_with(listener).await });
 
     let _guard = handle.token().clone().drop_guard();
 
@@ -98,6 +99,70 @@ async fn get_endpoints() {
     assert_eq!(endpoints.len(), tester.handle.info().config.endpoints.len());
 }
 
+#[tokio::test]
+async fn session_endpoint() {
+    let mut tester = Tester::new_default_server(false).await;
+    let (session, handle) = tester
+        .connect(
+            SecurityPolicy::None,
+            MessageSecurityMode::None,
+            IdentityToken::Anonymous,
+        )
+        .await
+        .unwrap();
+    let _h = handle.spawn();
+
+    let server = &session.endpoint().server;
+    assert_eq!(server.application_name.text.as_ref(), "integration_server");
+    assert_eq!(server.application_uri.as_ref(), "urn:integration_server");
+    assert_eq!(
+        server.product_uri.as_ref(),
+        "urn:integration_server Testkit"
+    );
+    assert_eq!(server.application_type, ApplicationType::Server);
+}
+
+#[tokio::test]
+async fn wait_for_connection_lost() {
+    let mut tester = Tester::new_default_server(false).await;
+    let (session, handle) = tester.connect_default().await.unwrap();
+    let _h = handle.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    // Still connected
+    assert!(tokio::time::timeout(
+        Duration::from_millis(200),
+        session.wait_for_connection_lost()
+    )
+    .await
+    .is_err());
+
+    session.disconnect().await.unwrap();
+    assert!(
+        tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection_lost())
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn get_namespace_index() {
+    let server = default_server().with_node_manager(simple_node_manager(
+        NamespaceMetadata {
+            namespace_uri: "urn:TestNS".to_owned(),
+            ..Default::default()
+        },
+        "test",
+    ));
+    let tester = Tester::new(server, false).await;
+
+    let ns = tester.handle.get_namespace_index("urn:TestNS").unwrap();
+    assert!(ns > 0);
+    assert_eq!(tester.handle.get_namespace_index("urn:Unknown"), None);
+}
+
 async fn conn_test(policy: SecurityPolicy, mode: MessageSecurityMode, token: IdentityToken) {
     let mut tester = Tester::new_default_server(false).await;
     let (session, handle) = tester.connect(policy, mode, token).await.unwrap();
@@ -295,6 +360,131 @@ async fn discovery_test() {
     assert_eq!(endpoints.len(), 11);
 }
 
+#[tokio::test]
+async fn connect_with_custom_pki_dir() {
+    let mut tester = Tester::new_default_server(true).await;
+
+    // Own certificate generated up front in a PKI dir of its own
+    let pki_dir = PathBuf::from(format!("./pki-client/custom-{}", tester.test_id));
+    let config = tester.client.config().clone();
+    let (cert, _) = CertificateStore::create_certificate_and_key(
+        &X509Data::from(config.application_description()),
+        true,
+        &pki_dir.join("own/cert.der"),
+        &pki_dir.join("private/private.pem"),
+    )
+    .unwrap();
+
+    tester.client = ClientBuilder::from(config)
+        .pki_dir(&pki_dir)
+        .create_sample_keypair(false)
+        .client()
+        .unwrap();
+    let own_cert = tester
+        .client
+        .certificate_store()
+        .read()
+        .read_own_cert()
+        .unwrap();
+    assert_eq!(own_cert.thumbprint(), cert.thumbprint());
+
+    let session = tester
+        .connect_and_wait(
+            SecurityPolicy::Basic256Sha256,
+            MessageSecurityMode::SignAndEncrypt,
+            IdentityToken::Anonymous,
+        )
+        .await
+        .unwrap();
+    session
+        .read(
+            &[ReadValueId::from(<VariableId as Into<NodeId>>::into(
+                VariableId::Server_ServiceLevel,
+            ))],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn server_with_custom_pki_dir() {
+    let _ = env_logger::try_init();
+
+    let test_id = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let listener = TcpListener::bind(format!("{}:0", hostname()))
+        .await
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // Server with its own certificate created in a PKI dir of its own, which
+    // only accepts trusted client certificates
+    let pki_dir = PathBuf::from(format!("./pki-server/custom-{test_id}"));
+    let server = default_server()
+        .discovery_urls(vec![format!("opc.tcp://{}:{}", hostname(), addr.port())])
+        .pki_dir(&pki_dir)
+        .trust_client_certs(false);
+    copy_shared_certs(test_id, &server.config().application_description());
+
+    let mut client = default_client(test_id, true)
+        .pki_dir(format!("./pki-client/{test_id}"))
+        .client()
+        .unwrap();
+    let client_cert = client.certificate_store().read().read_own_cert().unwrap();
+    let store = CertificateStore::new(&pki_dir);
+    store.ensure_pki_path().unwrap();
+    std::fs::write(
+        store
+            .trusted_certs_dir()
+            .join(CertificateStore::cert_file_name(&client_cert)),
+        client_cert.to_der().unwrap(),
+    )
+    .unwrap();
+
+    let (mut server, handle) = server.build().unwrap();
+    let _guard = handle.token().clone().drop_guard();
+    let server_cert = store.read_own_cert().unwrap();
+    assert_eq!(
+        handle
+            .info()
+            .server_certificate
+            .load()
+            .as_ref()
+            .unwrap()
+            .thumbprint(),
+        server_cert.thumbprint()
+    );
+
+    tokio::task::spawn(async move { server.run_with(listener).await });
+
+    let (session, event_loop) = client
+        .connect_to_matching_endpoint(
+            (
+                format!("opc.tcp://{}:{}/", hostname(), addr.port()).as_str(),
+                SecurityPolicy::Basic256Sha256.to_str(),
+                MessageSecurityMode::SignAndEncrypt,
+            ),
+            IdentityToken::Anonymous,
+        )
+        .await
+        .unwrap();
+    event_loop.spawn();
+    tokio::time::timeout(Duration::from_secs(20), session.wait_for_connection())
+        .await
+        .unwrap();
+    session
+        .read(
+            &[ReadValueId::from(<VariableId as Into<NodeId>>::into(
+                VariableId::Server_ServiceLevel,
+            ))],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+}
+
 #[tokio::test]
 async fn multi_client_test() {
     // Simple multi-client test, checking that we can send and receive requests with multiple clients
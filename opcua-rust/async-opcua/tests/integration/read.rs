@@ -11,10 +11,10 @@ use opcua::{
         ObjectTypeBuilder, ReferenceTypeBuilder, VariableBuilder, VariableTypeBuilder, ViewBuilder,
     },
     types::{
-        AttributeId, DataTypeId, DataValue, DateTime, HistoryData, HistoryReadValueId, NodeClass,
-        NodeId, ObjectId, ObjectTypeId, QualifiedName, ReadRawModifiedDetails, ReadValueId,
-        ReferenceTypeId, StatusCode, TimestampsToReturn, VariableId, VariableTypeId, Variant,
-        WriteMask,
+        AttributeId, DataTypeId, DataValue, DateTime, HistoryData, HistoryReadValueId,
+        LocalizedText, NodeClass, NodeId, ObjectId, ObjectTypeId, QualifiedName,
+        ReadRawModifiedDetails, ReadValueId, ReferenceTypeId, StatusCode, TimestampsToReturn,
+        VariableId, VariableTypeId, Variant, WriteMask,
     },
 };
 use opcua_client::{services::Read, DefaultRetryPolicy, ExponentialBackoff};
@@ -122,6 +122,54 @@ async fn read_variable() {
     assert_eq!(r[11].value, Some(Variant::NodeId(Box::new(id))));
 }
 
+#[tokio::test]
+async fn read_changed_description() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(0)
+            .data_type(DataTypeId::Int32)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    {
+        let mut sp = nm.address_space().write();
+        sp.find_node_mut(&id)
+            .unwrap()
+            .as_mut_node()
+            .set_attribute(
+                AttributeId::Description,
+                Variant::LocalizedText(Box::new(LocalizedText::new("en", "Pump pressure"))),
+            )
+            .unwrap();
+    }
+
+    let r = session
+        .read(
+            &[read_value_id(AttributeId::Description, &id)],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        r[0].value,
+        Some(Variant::LocalizedText(Box::new(LocalizedText::new(
+            "en",
+            "Pump pressure"
+        ))))
+    );
+}
+
 #[tokio::test]
 async fn read_object() {
     let (tester, nm, session) = setup().await;
@@ -8,10 +8,14 @@ use opcua::{
     types::{
         AttributeId, DataTypeId, DataValue, MonitoredItemCreateRequest, MonitoredItemModifyRequest,
         MonitoringMode, MonitoringParameters, NodeId, ObjectId, ReadValueId, ReferenceTypeId,
-        StatusCode, TimestampsToReturn, VariableTypeId, Variant,
+        StatusCode, TimestampsToReturn, VariableId, VariableTypeId, Variant,
     },
 };
-use opcua_client::{services::TransferSubscriptions, IdentityToken, Subscription, UARequest};
+use opcua_client::{
+    services::{ModifySubscription, TransferSubscriptions},
+    IdentityToken, MonitoredItem, OnSubscriptionNotification, Subscription,
+    SubscriptionTransferResult, UARequest,
+};
 use opcua_crypto::SecurityPolicy;
 use opcua_types::{
     DataChangeFilter, DataChangeTrigger, DeadbandType, ExtensionObject, MessageSecurityMode, Range,
@@ -109,6 +113,97 @@ async fn simple_subscriptions() {
     session.delete_subscription(sub_id).await.unwrap();
 }
 
+// Collects the values of one notification message
+struct CycleNotifications {
+    values: Vec<i32>,
+    cycles: tokio::sync::mpsc::UnboundedSender<Vec<i32>>,
+}
+
+impl OnSubscriptionNotification for CycleNotifications {
+    fn on_data_value(&mut self, notification: DataValue, _item: &MonitoredItem) {
+        if let Some(Variant::Int32(v)) = notification.value {
+            self.values.push(v);
+        }
+    }
+
+    fn on_notification_end(&mut self) {
+        if !self.values.is_empty() {
+            let _ = self.cycles.send(std::mem::take(&mut self.values));
+        }
+    }
+}
+
+#[tokio::test]
+async fn notification_end() {
+    let (tester, nm, session) = setup().await;
+
+    let mut ids = Vec::new();
+    for value in [-1, -2] {
+        let id = nm.inner().next_node_id();
+        nm.inner().add_node(
+            nm.address_space(),
+            tester.handle.type_tree(),
+            VariableBuilder::new(&id, "TestVar", "TestVar")
+                .value(value)
+                .data_type(DataTypeId::Int32)
+                .access_level(AccessLevel::CURRENT_READ)
+                .user_access_level(AccessLevel::CURRENT_READ)
+                .build()
+                .into(),
+            &ObjectId::ObjectsFolder.into(),
+            &ReferenceTypeId::Organizes.into(),
+            Some(&VariableTypeId::BaseDataVariableType.into()),
+            Vec::new(),
+        );
+        ids.push(id);
+    }
+
+    let (cycles, mut cycles_recv) = tokio::sync::mpsc::unbounded_channel();
+    let notifs = CycleNotifications {
+        values: Vec::new(),
+        cycles,
+    };
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            ids.iter()
+                .map(|id| MonitoredItemCreateRequest {
+                    item_to_monitor: ReadValueId {
+                        node_id: id.clone(),
+                        attribute_id: AttributeId::Value as u32,
+                        ..Default::default()
+                    },
+                    monitoring_mode: MonitoringMode::Reporting,
+                    requested_parameters: MonitoringParameters {
+                        sampling_interval: 0.0,
+                        queue_size: 10,
+                        discard_oldest: true,
+                        ..Default::default()
+                    },
+                })
+                .collect(),
+        )
+        .await
+        .unwrap();
+    assert!(res.iter().all(|r| r.status_code.is_good()));
+
+    // The initial values of both items arrive in one notification message
+    let mut values = timeout(Duration::from_millis(500), cycles_recv.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    values.sort();
+    assert_eq!(values, vec![-2, -1]);
+
+    session.delete_subscription(sub_id).await.unwrap();
+}
+
 async fn recv_n<T>(recv: &mut UnboundedReceiver<T>, n: usize) -> Vec<T> {
     let mut res = Vec::with_capacity(n);
     for _ in 0..n {
@@ -600,6 +695,226 @@ async fn transfer_subscriptions() {
     assert_eq!(-1, val);
 }
 
+#[tokio::test]
+async fn take_subscriptions() {
+    let server = test_server();
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+    // Need to use an encrypted connection, or transfer won't work.
+    let mut sessions = Vec::new();
+    for _ in 0..2 {
+        let (session, lp) = tester
+            .connect(
+                SecurityPolicy::Aes256Sha256RsaPss,
+                MessageSecurityMode::SignAndEncrypt,
+                IdentityToken::Anonymous,
+            )
+            .await
+            .unwrap();
+        lp.spawn();
+        tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+            .await
+            .unwrap();
+        sessions.push(session);
+    }
+    let (old_session, new_session) = (&sessions[0], &sessions[1]);
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(-1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = old_session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    old_session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: id.clone(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    let (_, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(v.value, Some(Variant::Int32(-1)));
+
+    let res = new_session
+        .take_subscriptions(old_session, &[sub_id, sub_id + 1000])
+        .await
+        .unwrap();
+    assert_eq!(
+        res,
+        vec![
+            SubscriptionTransferResult::Transferred,
+            SubscriptionTransferResult::Failed(StatusCode::BadSubscriptionIdInvalid)
+        ]
+    );
+    assert!(!old_session
+        .subscription_state()
+        .lock()
+        .subscription_exists(sub_id));
+    assert!(new_session
+        .subscription_state()
+        .lock()
+        .subscription_exists(sub_id));
+
+    // The callback of the subscription moved along with it.
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(1),
+    )
+    .unwrap();
+    loop {
+        let (r, v) = timeout(Duration::from_millis(500), data.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(r.node_id, id);
+        if v.value == Some(Variant::Int32(1)) {
+            break;
+        }
+    }
+}
+
+#[tokio::test]
+async fn transfer_subscriptions_from() {
+    let server = test_server();
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+    // Need to use an encrypted connection, or transfer won't work.
+    let mut sessions = Vec::new();
+    for _ in 0..2 {
+        let (session, lp) = tester
+            .connect(
+                SecurityPolicy::Aes256Sha256RsaPss,
+                MessageSecurityMode::SignAndEncrypt,
+                IdentityToken::Anonymous,
+            )
+            .await
+            .unwrap();
+        lp.spawn();
+        tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+            .await
+            .unwrap();
+        sessions.push(session);
+    }
+    let (old_session, new_session) = (&sessions[0], &sessions[1]);
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(-1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = old_session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    old_session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: id.clone(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    let (_, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(v.value, Some(Variant::Int32(-1)));
+
+    let res = new_session
+        .transfer_subscriptions_from(old_session, &[sub_id, sub_id + 1000], true)
+        .await
+        .unwrap();
+    assert_eq!(
+        res,
+        vec![StatusCode::Good, StatusCode::BadSubscriptionIdInvalid]
+    );
+    assert!(!old_session
+        .subscription_state()
+        .lock()
+        .subscription_exists(sub_id));
+    assert!(new_session
+        .subscription_state()
+        .lock()
+        .subscription_exists(sub_id));
+
+    // The initial value is sent again, to the callback which moved along.
+    let (r, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(r.node_id, id);
+    assert_eq!(v.value, Some(Variant::Int32(-1)));
+}
+
 #[tokio::test]
 async fn test_data_change_filters() {
     let (tester, nm, session) = setup().await;
@@ -791,4 +1106,477 @@ async fn test_data_change_filters() {
     assert_eq!(v.value.unwrap(), Variant::Double(9.0));
 }
 
+fn deadband_filter(deadband_type: DeadbandType, deadband_value: f64) -> ExtensionObject {
+    ExtensionObject::from_message(DataChangeFilter {
+        trigger: DataChangeTrigger::StatusValue,
+        deadband_type: deadband_type as u32,
+        deadband_value,
+    })
+}
+
+#[tokio::test]
+async fn percent_deadband_without_eu_range() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(0.0f64)
+            .data_type(DataTypeId::Double)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let (notifs, _data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: id.clone(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    filter: deadband_filter(DeadbandType::Percent, 10.0),
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].status_code, StatusCode::BadFilterNotAllowed);
+}
+
+#[tokio::test]
+async fn modify_monitored_item_filter() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(0.0f64)
+            .data_type(DataTypeId::Double)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: id.clone(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    filter: deadband_filter(DeadbandType::Absolute, 5.0),
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].status_code, StatusCode::Good);
+    let item_id = res[0].monitored_item_id;
+    // Initial value
+    timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let client_handle = {
+        let state = session.subscription_state().lock();
+        let item = &state.get(sub_id).unwrap().monitored_items()[&item_id];
+        item.client_handle()
+    };
+    let new_filter = deadband_filter(DeadbandType::Absolute, 0.5);
+    let res = session
+        .modify_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            &[MonitoredItemModifyRequest {
+                monitored_item_id: item_id,
+                requested_parameters: MonitoringParameters {
+                    client_handle,
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    filter: new_filter.clone(),
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].status_code, StatusCode::Good);
+
+    // The local state follows the accepted filter.
+    {
+        let state = session.subscription_state().lock();
+        let item = &state.get(sub_id).unwrap().monitored_items()[&item_id];
+        assert_eq!(item.filter(), &new_filter);
+    }
+
+    // A change of 1 passes the new deadband, it wouldn't have passed the old one.
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(1.0),
+    )
+    .unwrap();
+    let (r, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(r.node_id, id);
+    assert_eq!(v.value.unwrap(), Variant::Double(1.0));
+
+    // A rejected filter leaves the local state alone.
+    let res = session
+        .modify_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            &[MonitoredItemModifyRequest {
+                monitored_item_id: item_id,
+                requested_parameters: MonitoringParameters {
+                    client_handle,
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    filter: deadband_filter(DeadbandType::Percent, 10.0),
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].status_code, StatusCode::BadFilterNotAllowed);
+    {
+        let state = session.subscription_state().lock();
+        let item = &state.get(sub_id).unwrap().monitored_items()[&item_id];
+        assert_eq!(item.filter(), &new_filter);
+    }
+}
+
+#[tokio::test]
+async fn modify_subscription_interval() {
+    let (_tester, _nm, session) = setup().await;
+
+    let (notifs, _data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(500), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let r = ModifySubscription::new(sub_id, &session)
+        .publishing_interval(Duration::from_millis(100))
+        .max_lifetime_count(100)
+        .max_keep_alive_count(20)
+        .max_notifications_per_publish(1000)
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert!(r.revised_publishing_interval <= 100.0);
+
+    // The local subscription state is updated with the revised values.
+    {
+        let state = session.subscription_state().lock();
+        let sub = state.get(sub_id).unwrap();
+        assert!(sub.publishing_interval() <= Duration::from_millis(100));
+        assert_eq!(sub.max_keep_alive_count(), r.revised_max_keep_alive_count);
+    }
+
+    session.delete_subscription(sub_id).await.unwrap();
+}
+
+#[tokio::test]
+async fn set_publishing_mode() {
+    let (_tester, _nm, session) = setup().await;
+    assert!(session.is_connected());
+
+    let (notifs, _data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let res = session
+        .set_publishing_mode(&[sub_id, sub_id + 1000], false)
+        .await
+        .unwrap();
+    assert_eq!(
+        res,
+        vec![StatusCode::Good, StatusCode::BadSubscriptionIdInvalid]
+    );
+
+    session.disconnect().await.unwrap();
+    assert!(!session.is_connected());
+}
+
+#[tokio::test]
+async fn new_event_loop_after_disconnect() {
+    let (_tester, _nm, session) = setup().await;
+
+    let (notifs, _data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    // The event loop of the session is still running.
+    assert_eq!(
+        session.new_event_loop().err(),
+        Some(StatusCode::BadInvalidState)
+    );
+
+    session
+        .disconnect_without_delete_subscriptions()
+        .await
+        .unwrap();
+    let event_loop = timeout(Duration::from_secs(2), async {
+        loop {
+            if let Ok(event_loop) = session.new_event_loop() {
+                break event_loop;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    event_loop.spawn();
+    timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+    assert!(session.is_connected());
+
+    // The subscription was transferred to the new server session, or created again.
+    let recreated = session.take_recreated_subscriptions();
+    let new_sub_id = match recreated.as_slice() {
+        [] => sub_id,
+        [(old_id, new_id)] => {
+            assert_eq!(*old_id, sub_id);
+            *new_id
+        }
+        _ => panic!("Unexpected recreated subscriptions {recreated:?}"),
+    };
+    assert_eq!(
+        session.subscription_state().lock().subscription_ids(),
+        Some(vec![new_sub_id])
+    );
+    assert!(session.take_recreated_subscriptions().is_empty());
+
+    session.delete_subscription(new_sub_id).await.unwrap();
+}
+
+#[tokio::test]
+async fn service_level_subscription() {
+    let (tester, _nm, session) = setup().await;
+    tester.handle.set_service_level(255);
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let id: NodeId = VariableId::Server_ServiceLevel.into();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: id.clone(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].status_code, StatusCode::Good);
+
+    // Initial value
+    let (r, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(r.node_id, id);
+    assert_eq!(v.value, Some(Variant::Byte(255)));
+
+    // Signal clients to switch to another server
+    tester.handle.set_service_level(100);
+    let (r, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(r.node_id, id);
+    assert_eq!(v.value, Some(Variant::Byte(100)));
+}
+
+#[tokio::test]
+async fn concurrent_set_value_with_subscription() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "StressVar", "StressVar")
+            .value(0.0)
+            .data_type(DataTypeId::Double)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(50), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: id.clone(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 100,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].status_code, StatusCode::Good);
+    let (_, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(v.value, Some(Variant::Double(0.0)));
+
+    // Two writers from plain threads, like a LabVIEW loop calling into the server.
+    const WRITES: i32 = 1000;
+    let writers: Vec<_> = [1.0, -1.0]
+        .into_iter()
+        .map(|sign| {
+            let nm = nm.clone();
+            let subs = tester.handle.subscriptions().clone();
+            let id = id.clone();
+            std::thread::spawn(move || {
+                for i in 1..=WRITES {
+                    nm.set_value(&subs, &id, None, DataValue::new_now(sign * i as f64))
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+    timeout(
+        Duration::from_secs(10),
+        tokio::task::spawn_blocking(move || {
+            for w in writers {
+                w.join().unwrap();
+            }
+        }),
+    )
+    .await
+    .expect("Writers deadlocked")
+    .unwrap();
+
+    // The final value is the last write of one of the writers,
+    // and the subscription catches up to it.
+    let last = session
+        .read(
+            &[ReadValueId {
+                node_id: id.clone(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            }],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap()
+        .remove(0)
+        .value;
+    assert!(
+        last == Some(Variant::Double(WRITES as f64))
+            || last == Some(Variant::Double(-WRITES as f64))
+    );
+    // Changes within the sampling interval are held back, not dropped,
+    // so the last value arrives however the writes were interleaved.
+    loop {
+        let (r, v) = timeout(Duration::from_millis(2000), data.recv())
+            .await
+            .expect("Last value of the burst not notified")
+            .unwrap();
+        assert_eq!(r.node_id, id);
+        if v.value == last {
+            break;
+        }
+    }
+
+    session.delete_subscription(sub_id).await.unwrap();
+}
+
 // TODO: Add more detailed high level tests on subscriptions.
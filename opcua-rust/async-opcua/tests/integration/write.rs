@@ -1,21 +1,35 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
 use chrono::TimeDelta;
 use opcua::{
     client::{HistoryReadAction, HistoryUpdateAction, Session},
-    server::address_space::{
-        AccessLevel, DataTypeBuilder, EventNotifier, MethodBuilder, NodeType, ObjectBuilder,
-        ObjectTypeBuilder, ReferenceTypeBuilder, VariableBuilder, VariableTypeBuilder, ViewBuilder,
+    crypto::Thumbprint,
+    server::{
+        address_space::{
+            AccessLevel, DataTypeBuilder, EventNotifier, MethodBuilder, NodeType, ObjectBuilder,
+            ObjectTypeBuilder, ReferenceTypeBuilder, VariableBuilder, VariableTypeBuilder,
+            ViewBuilder,
+        },
+        authenticator::{AuthManager, DefaultAuthenticator, Password, UserToken},
+        diagnostics::NamespaceMetadata,
+        node_manager::memory::{simple_node_manager, SimpleNodeManager},
+        ServerEndpoint,
     },
     types::{
-        AttributeId, ByteString, DataTypeId, DataValue, DateTime, HistoryData, HistoryReadValueId,
-        LocalizedText, NodeId, ObjectId, ObjectTypeId, QualifiedName, ReadRawModifiedDetails,
-        ReferenceTypeId, StatusCode, TimestampsToReturn, UpdateDataDetails, VariableTypeId,
-        Variant, WriteMask, WriteValue,
+        AttributeId, ByteString, DataTypeId, DataValue, DateTime, Error, HistoryData,
+        HistoryReadValueId, LocalizedText, NodeId, ObjectId, ObjectTypeId, QualifiedName,
+        ReadRawModifiedDetails, ReferenceTypeId, StatusCode, TimestampsToReturn, UpdateDataDetails,
+        UserTokenPolicy, VariableTypeId, Variant, WriteMask, WriteValue,
     },
 };
 use opcua_types::NumericRange;
 // Write is not implemented in the core library itself, only in the test node manager,
 // we still test here to test write functionality in the address space.
-use super::utils::{array_value, read_value_id, setup};
+use super::utils::{array_value, read_value_id, setup, test_server, TestNodeManager, Tester};
 
 fn write_value(
     attribute_id: AttributeId,
@@ -534,7 +548,227 @@ async fn write_invalid() {
     assert_eq!(r[0], StatusCode::BadTypeMismatch);
     assert_eq!(r[1], StatusCode::BadNotWritable);
     assert_eq!(r[2], StatusCode::BadNotWritable);
-    assert_eq!(r[3], StatusCode::BadUserAccessDenied);
+    assert_eq!(r[3], StatusCode::BadNotWritable);
+}
+
+#[tokio::test]
+async fn write_read_only_variable() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .data_type(DataTypeId::Int32)
+            .value(1)
+            .access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+            .user_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let r = session
+        .write(&[write_value(AttributeId::Value, 2, &id)])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::Good);
+
+    // Make the variable read-only at runtime
+    {
+        let mut sp = nm.address_space().write();
+        let node = sp.find_node_mut(&id).unwrap().as_mut_node();
+        for attribute_id in [AttributeId::AccessLevel, AttributeId::UserAccessLevel] {
+            node.set_attribute(
+                attribute_id,
+                Variant::Byte(AccessLevel::CURRENT_READ.bits()),
+            )
+            .unwrap();
+        }
+    }
+
+    let r = session
+        .write(&[write_value(AttributeId::Value, 3, &id)])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::BadNotWritable);
+}
+
+// Denies writes to one node and records the denied accesses
+struct ProtectedNodeAuthenticator {
+    inner: DefaultAuthenticator,
+    protected: Mutex<Option<NodeId>>,
+    denied: Mutex<Vec<(NodeId, AccessLevel)>>,
+}
+
+#[async_trait]
+impl AuthManager for ProtectedNodeAuthenticator {
+    async fn authenticate_anonymous_token(&self, endpoint: &ServerEndpoint) -> Result<(), Error> {
+        self.inner.authenticate_anonymous_token(endpoint).await
+    }
+
+    async fn authenticate_username_identity_token(
+        &self,
+        endpoint: &ServerEndpoint,
+        username: &str,
+        password: &Password,
+    ) -> Result<UserToken, Error> {
+        self.inner
+            .authenticate_username_identity_token(endpoint, username, password)
+            .await
+    }
+
+    async fn authenticate_x509_identity_token(
+        &self,
+        endpoint: &ServerEndpoint,
+        signing_thumbprint: &Thumbprint,
+    ) -> Result<UserToken, Error> {
+        self.inner
+            .authenticate_x509_identity_token(endpoint, signing_thumbprint)
+            .await
+    }
+
+    fn effective_user_access_level(
+        &self,
+        _token: &UserToken,
+        user_access_level: AccessLevel,
+        node_id: &NodeId,
+    ) -> AccessLevel {
+        if self.protected.lock().unwrap().as_ref() == Some(node_id) {
+            user_access_level - AccessLevel::CURRENT_WRITE
+        } else {
+            user_access_level
+        }
+    }
+
+    fn access_denied(&self, _token: &UserToken, node_id: &NodeId, access: AccessLevel) {
+        self.denied.lock().unwrap().push((node_id.clone(), access));
+    }
+
+    fn user_token_policies(&self, endpoint: &ServerEndpoint) -> Vec<UserTokenPolicy> {
+        self.inner.user_token_policies(endpoint)
+    }
+}
+
+#[tokio::test]
+async fn write_denied_by_authenticator() {
+    let server = test_server();
+    let authenticator = Arc::new(ProtectedNodeAuthenticator {
+        inner: DefaultAuthenticator::new(server.config().user_tokens.clone()),
+        protected: Mutex::new(None),
+        denied: Mutex::new(Vec::new()),
+    });
+    let mut tester = Tester::new(server.with_authenticator(authenticator.clone()), false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .data_type(DataTypeId::Int32)
+            .value(1)
+            .access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+            .user_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let r = session
+        .write(&[write_value(AttributeId::Value, 2, &id)])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::Good);
+    assert!(authenticator.denied.lock().unwrap().is_empty());
+
+    *authenticator.protected.lock().unwrap() = Some(id.clone());
+    let r = session
+        .write(&[write_value(AttributeId::Value, 3, &id)])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::BadUserAccessDenied);
+    let denied = authenticator.denied.lock().unwrap();
+    assert_eq!(denied.len(), 1);
+    assert_eq!(denied[0].0, id);
+    assert_eq!(denied[0].1.bits(), AccessLevel::CURRENT_WRITE.bits());
+}
+
+#[tokio::test]
+async fn write_observer() {
+    let server = test_server().with_node_manager(simple_node_manager(
+        NamespaceMetadata {
+            namespace_uri: "urn:SimpleNS".to_owned(),
+            ..Default::default()
+        },
+        "simple",
+    ));
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<SimpleNodeManager>()
+        .unwrap();
+    let ns = tester.handle.get_namespace_index("urn:SimpleNS").unwrap();
+    let id = NodeId::new(ns, "Observed");
+    VariableBuilder::new(&id, "Observed", "Observed")
+        .data_type(DataTypeId::Int32)
+        .value(1)
+        .writable()
+        .organized_by(ObjectId::ObjectsFolder)
+        .insert(&mut *nm.address_space().write());
+
+    let observed = Arc::new(Mutex::new(Vec::new()));
+    let observed_ref = observed.clone();
+    nm.inner()
+        .set_write_observer(move |context, node_id, old, new| {
+            assert!(context.token.is_anonymous());
+            observed_ref
+                .lock()
+                .unwrap()
+                .push((node_id.clone(), old.value, new.value.clone()));
+        });
+
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let r = session
+        .write(&[write_value(AttributeId::Value, 2, &id)])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::Good);
+    assert_eq!(
+        *observed.lock().unwrap(),
+        vec![(id.clone(), Some(Variant::Int32(1)), Some(Variant::Int32(2)))]
+    );
+
+    // No writes are observed after the observer is cleared
+    nm.inner().clear_write_observer();
+    let r = session
+        .write(&[write_value(AttributeId::Value, 3, &id)])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::Good);
+    assert_eq!(observed.lock().unwrap().len(), 1);
 }
 
 #[tokio::test]
@@ -308,10 +308,10 @@ impl Tester {
 
         copy_shared_certs(test_id, &server.config().application_description());
 
-        let (server, handle) = server.build().unwrap();
+        let (mut server, handle) = server.build().unwrap();
         let token = CancellationToken::new();
 
-        tokio::task::spawn(server.run_with(listener));
+        tokio::task::spawn(async move { server.run_with(listener).await });
 
         let client = default_client(test_id, quick_timeout).client().unwrap();
 
@@ -338,9 +338,9 @@ impl Tester {
 
         copy_shared_certs(test_id, &server.config().application_description());
 
-        let (server, handle) = server.build().unwrap();
+        let (mut server, handle) = server.build().unwrap();
 
-        tokio::task::spawn(server.run_with(listener));
+        tokio::task::spawn(async move { server.run_with(listener).await });
 
         let client = default_client(test_id, quick_timeout).client().unwrap();
 
@@ -370,9 +370,9 @@ impl Tester {
 
         let client = client.pki_dir(format!("./pki-client/{test_id}"));
 
-        let (server, handle) = server.build().unwrap();
+        let (mut server, handle) = server.build().unwrap();
 
-        tokio::task::spawn(server.run_with(listener));
+        tokio::task::spawn(async move { server.run_with(listener).await });
 
         let client = client.client().unwrap();
 